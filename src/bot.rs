@@ -1,556 +1,6596 @@
 /// Telegram bot command handlers and message processing.
-use crate::config::Config;
+use crate::audit::{format_audit_line, AuditRecord};
+use crate::config::{
+    describe_window, Config, RateLimitStrategy, DEFAULT_RATE_LIMIT_WINDOW, DELETABLE_WINDOW,
+};
+use crate::dedup::{duplicate_warning, DedupTags};
+use crate::encoding::{base58_encode, base64_encode, hex_encode};
 use crate::error::{BotError, Result};
+use crate::help::{help_keyboard, help_pages, render_help_page, HelpNav, HelpNavigator};
+use crate::locale::{Locales, FALLBACK_LANGUAGE};
 use crate::password::{
-    estimate_strength, format_metadata, generate_password, PasswordConfig, PasswordStrength,
+    config_for_password, entropy_bits, estimate_strength, format_metadata, generate_passphrase,
+    generate_password_avoiding, generate_passwords, group_password, improvement_suggestion,
+    length_for_entropy, min_length_for_strength, parse_pattern, parse_symbol_set,
+    passphrase_entropy_bits, pattern_length_range, phonetic, pin_config, policy_aware_entropy,
+    strength_for_entropy, wifi_config, wordlist,
+    CharClass, CharSets, PassphraseTransforms, PasswordConfig, PasswordMetadata,
+    PasswordStrength, StrengthThresholds,
+    DEFAULT_PASSPHRASE_SEPARATOR, DEFAULT_PASSPHRASE_WORDS, MAX_PASSPHRASE_WORDS,
+    PIN_DEFAULT_LENGTH, WIFI_DEFAULT_LENGTH,
 };
-use rand::rngs::OsRng;
-use std::collections::HashMap;
+use crate::preferences::UserPreferences;
+use crate::qr::render_png;
+use crate::template::{password_positions, render_template, ReplyContext};
+use crate::totp::{generate_totp_secret, otpauth_uri};
+use rand::rngs::{OsRng, StdRng};
+use rand::SeedableRng;
+use rand_core::RngCore;
+use std::collections::{HashMap, HashSet, VecDeque};
+use serde_json::{json, Value};
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::slice;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
 use teloxide::prelude::*;
-use teloxide::types::CallbackQuery;
+use teloxide::types::{
+    CallbackQuery, ForceReply, InlineKeyboardButton, InlineKeyboardMarkup, InputFile,
+    KeyboardButton, KeyboardMarkup, KeyboardRemove, MessageEntity, MessageEntityKind, MessageId,
+    ReplyMarkup, User,
+};
 use tokio::sync::Mutex;
 use tracing::{info, warn};
+use zeroize::Zeroizing;
 
-/// Rate limiter tracking password generation requests per chat.
-#[derive(Debug, Default)]
+/// Rolling window for the per-user daily quota.
+const DAILY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Remaining daily quota at or below which users are told how much is left.
+const QUOTA_WARNING_REMAINING: usize = 3;
+
+/// Rate limiter tracking password generation requests per chat, optionally per
+/// member of each chat, and per user overall.
+#[derive(Debug)]
 pub struct RateLimiter {
     /// Map of chat_id to timestamps of recent requests.
     requests: HashMap<i64, Vec<Instant>>,
+    /// Map of (chat_id, user_id) to timestamps of recent requests, kept when
+    /// `user_limit` is set.
+    user_requests: HashMap<(i64, u64), Vec<Instant>>,
+    /// Map of user_id to timestamps of requests in the last 24 hours.
+    daily: HashMap<u64, Vec<Instant>>,
+    /// Length of the window `limit` and `user_limit` apply to.
+    window: Duration,
+    /// Requests each member may make per window in a chat, on top of the chat's limit.
+    user_limit: Option<usize>,
+    /// Map of chat_id to the time of its last accepted request.
+    last_request: HashMap<i64, Instant>,
+    /// Shortest gap allowed between accepted requests in a chat; zero disables it.
+    min_interval: Duration,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATE_LIMIT_WINDOW)
+    }
 }
 
 impl RateLimiter {
-    /// Check if a request from the given chat is allowed.
-    /// Returns Ok(()) if allowed, Err if rate limit exceeded.
-    pub fn check_rate_limit(&mut self, chat_id: i64, limit: usize) -> Result<()> {
-        let now = Instant::now();
-        let one_minute_ago = now - Duration::from_secs(60);
+    /// An empty limiter applying per-chat limits over `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            requests: HashMap::new(),
+            user_requests: HashMap::new(),
+            daily: HashMap::new(),
+            window,
+            user_limit: None,
+            last_request: HashMap::new(),
+            min_interval: Duration::ZERO,
+        }
+    }
+
+    /// Also hold each member of a chat to `limit` requests per window, so one busy
+    /// member can't use up a group's allowance.
+    pub fn with_user_limit(mut self, limit: Option<usize>) -> Self {
+        self.user_limit = limit;
+        self
+    }
+
+    /// Also require `interval` between accepted requests in each chat, so mashing an
+    /// inline button can't spend the whole window allowance in a second.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
 
+    /// Single-request shorthand for [`check_requests`](Self::check_requests).
+    #[cfg(test)]
+    pub fn check_request(
+        &mut self,
+        chat_id: i64,
+        user_id: u64,
+        limit: usize,
+        daily_quota: Option<usize>,
+        now: Instant,
+    ) -> Result<Option<usize>> {
+        self.check_requests(chat_id, user_id, 1, limit, daily_quota, now)
+    }
+
+    /// Check if `cost` requests from the given chat and user are allowed at `now`.
+    ///
+    /// Enforces `limit` requests per window per chat, the per-member limit if one is
+    /// set, and, if `daily_quota` is set, that many requests per rolling 24 hours
+    /// per user. The batch is accepted or rejected as a whole, and a rejected one
+    /// counts against none of the limits. The window slides: a request stops
+    /// counting once it is older than the window. Returns the user's remaining
+    /// daily quota, if any.
+    pub fn check_requests(
+        &mut self,
+        chat_id: i64,
+        user_id: u64,
+        cost: usize,
+        limit: usize,
+        daily_quota: Option<usize>,
+        now: Instant,
+    ) -> Result<Option<usize>> {
+        self.check_interval(chat_id, now)?;
+        let window = self.window;
         // Get or create the request history for this chat
-        let requests = self.requests.entry(chat_id).or_insert_with(Vec::new);
+        let requests = self.requests.entry(chat_id).or_default();
+        check_sliding_window(requests, cost, limit, window, now, "")?;
+        let member = match self.user_limit {
+            Some(user_limit) => {
+                let member = self.user_requests.entry((chat_id, user_id)).or_default();
+                check_sliding_window(member, cost, user_limit, window, now, " per member")?;
+                Some(member)
+            }
+            None => None,
+        };
 
-        // Remove requests older than 1 minute
-        requests.retain(|&timestamp| timestamp > one_minute_ago);
+        let remaining = charge_daily(&mut self.daily, user_id, cost, daily_quota, now)?;
 
-        // Check if limit is exceeded
-        if requests.len() >= limit {
-            return Err(BotError::RateLimit(format!(
-                "Too many requests. Maximum {} password generations per minute. Please wait.",
-                limit
-            )));
+        // Add the current request(s)
+        requests.extend(std::iter::repeat_n(now, cost));
+        if let Some(member) = member {
+            member.extend(std::iter::repeat_n(now, cost));
         }
+        self.record_interval(chat_id, now);
+        Ok(remaining)
+    }
 
-        // Add the current request
-        requests.push(now);
-        Ok(())
+    /// Like [`check_requests`](Self::check_requests), but with fixed windows.
+    ///
+    /// A window opens with the first request in a chat (or of a member, for the
+    /// per-member limit) and lasts the configured length; everything in it counts
+    /// until it closes, however early it was made.
+    pub fn check_requests_fixed(
+        &mut self,
+        chat_id: i64,
+        user_id: u64,
+        cost: usize,
+        limit: usize,
+        daily_quota: Option<usize>,
+        now: Instant,
+    ) -> Result<Option<usize>> {
+        self.check_interval(chat_id, now)?;
+        let window = self.window;
+        let requests = self.requests.entry(chat_id).or_default();
+        check_fixed_window(requests, cost, limit, window, now, "")?;
+        let member = match self.user_limit {
+            Some(user_limit) => {
+                let member = self.user_requests.entry((chat_id, user_id)).or_default();
+                check_fixed_window(member, cost, user_limit, window, now, " per member")?;
+                Some(member)
+            }
+            None => None,
+        };
+
+        let remaining = charge_daily(&mut self.daily, user_id, cost, daily_quota, now)?;
+        requests.extend(std::iter::repeat_n(now, cost));
+        if let Some(member) = member {
+            member.extend(std::iter::repeat_n(now, cost));
+        }
+        self.record_interval(chat_id, now);
+        Ok(remaining)
     }
-}
 
-/// Shared bot state including configuration and rate limiter.
-#[derive(Clone)]
-pub struct BotState {
-    pub config: Arc<Config>,
-    pub rate_limiter: Arc<Mutex<RateLimiter>>,
-}
+    /// Refuse a request that comes less than the minimum interval after the chat's
+    /// last accepted one.
+    fn check_interval(&self, chat_id: i64, now: Instant) -> Result<()> {
+        let Some(&last) = self.last_request.get(&chat_id) else {
+            return Ok(());
+        };
+        let wait = (last + self.min_interval).saturating_duration_since(now);
+        if wait.is_zero() {
+            return Ok(());
+        }
+        Err(BotError::RateLimit(format!(
+            "Slow down: wait at least {} between password generations. Try again in {}.",
+            format_wait(self.min_interval),
+            format_wait(wait)
+        )))
+    }
 
-impl BotState {
-    pub fn new(config: Config) -> Self {
-        Self {
-            config: Arc::new(config),
-            rate_limiter: Arc::new(Mutex::new(RateLimiter::default())),
+    /// Start the chat's minimum interval at `now`, if one is configured.
+    fn record_interval(&mut self, chat_id: i64, now: Instant) {
+        if !self.min_interval.is_zero() {
+            self.last_request.insert(chat_id, now);
         }
     }
-}
 
-/// Handler for the /start command.
-pub async fn handle_start(bot: Bot, msg: Message) -> ResponseResult<()> {
-    let welcome_text = "🔐 Secure Password Generator Bot\n\n\
-        Welcome! I generate strong, random passwords using cryptographically secure randomness.\n\n\
-        🔒 Privacy Notice:\n\
-        • Passwords are generated using OS-level secure randomness\n\
-        • Passwords are NOT logged or stored on the server\n\
-        • However, Telegram messages are not end-to-end encrypted\n\
-        • Use this bot as a convenience tool, but be aware of inherent risks\n\n\
-        📝 Quick Start:\n\
-        Use /pass to generate a password with default settings, or customize it:\n\
-        • /pass - Default 16-character password\n\
-        • /pass 24 - 24-character password\n\
-        • /pass 20 --symbols - Include symbols\n\
-        • /pass 16 --no-ambiguous - Exclude ambiguous characters\n\n\
-        Type /help for detailed usage information.";
-
-    use teloxide::types::InlineKeyboardButton;
-    let keyboard = teloxide::types::InlineKeyboardMarkup::new(vec![
-        vec![
-            InlineKeyboardButton::callback("📋 Default (16)", "pass_default"),
-            InlineKeyboardButton::callback("🔒 Strong (24)", "pass_24"),
-        ],
-        vec![
-            InlineKeyboardButton::callback("📖 Help", "show_help"),
-        ],
-    ]);
+    /// Serialize the tracked requests for `RATE_LIMIT_STATE_PATH`.
+    ///
+    /// `Instant`s only mean something within one process, so each is stored as Unix
+    /// milliseconds by measuring its age at `now` back from the wall-clock `wall`.
+    pub fn to_json(&self, now: Instant, wall: SystemTime) -> Value {
+        let unix_ms = |at: &Instant| {
+            wall.checked_sub(now.saturating_duration_since(*at))
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_millis() as u64)
+        };
+        let requests: Vec<Value> = self
+            .requests
+            .iter()
+            .map(|(chat_id, times)| {
+                json!({ "chat_id": chat_id, "at": times.iter().map(unix_ms).collect::<Vec<_>>() })
+            })
+            .collect();
+        let user_requests: Vec<Value> = self
+            .user_requests
+            .iter()
+            .map(|((chat_id, user_id), times)| {
+                json!({
+                    "chat_id": chat_id,
+                    "user_id": user_id,
+                    "at": times.iter().map(unix_ms).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let daily: Vec<Value> = self
+            .daily
+            .iter()
+            .map(|(user_id, times)| {
+                json!({ "user_id": user_id, "at": times.iter().map(unix_ms).collect::<Vec<_>>() })
+            })
+            .collect();
+        json!({ "requests": requests, "user_requests": user_requests, "daily": daily })
+    }
 
-    bot.send_message(msg.chat.id, welcome_text)
-        .reply_markup(keyboard)
-        .await?;
+    /// Restore a limiter saved by [`to_json`](Self::to_json), with limits over `window`.
+    ///
+    /// The per-member limit isn't part of the state; set it again with
+    /// [`with_user_limit`](Self::with_user_limit).
+    ///
+    /// Requests that have left their window by `wall` are dropped.
+    pub fn from_json(json: &str, window: Duration, now: Instant, wall: SystemTime) -> Result<Self> {
+        let invalid = |msg: &str| BotError::Config(format!("invalid rate limit state: {}", msg));
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(&e.to_string()))?;
 
-    info!(
-        "User {} started the bot",
-        msg.chat.id
-    );
+        // Timestamps within `window` of `wall`, as instants of this process
+        let restore = |entry: &Value, window: Duration| -> Result<Vec<Instant>> {
+            let times = entry["at"].as_array().ok_or_else(|| invalid("missing \"at\""))?;
+            let mut restored = Vec::new();
+            for ms in times {
+                let ms = ms.as_u64().ok_or_else(|| invalid("timestamps must be integers"))?;
+                let at = UNIX_EPOCH + Duration::from_millis(ms);
+                let age = wall.duration_since(at).unwrap_or_default();
+                if age < window {
+                    restored.extend(now.checked_sub(age));
+                }
+            }
+            Ok(restored)
+        };
+        let entries = |key: &str| -> Result<Vec<Value>> {
+            match &value[key] {
+                Value::Null => Ok(Vec::new()),
+                Value::Array(entries) => Ok(entries.clone()),
+                _ => Err(invalid(&format!("\"{}\" must be an array", key))),
+            }
+        };
+
+        let mut limiter = Self::new(window);
+        for entry in entries("requests")? {
+            let chat_id = entry["chat_id"].as_i64().ok_or_else(|| invalid("bad chat_id"))?;
+            let times = restore(&entry, window)?;
+            if !times.is_empty() {
+                limiter.requests.insert(chat_id, times);
+            }
+        }
+        for entry in entries("user_requests")? {
+            let chat_id = entry["chat_id"].as_i64().ok_or_else(|| invalid("bad chat_id"))?;
+            let user_id = entry["user_id"].as_u64().ok_or_else(|| invalid("bad user_id"))?;
+            let times = restore(&entry, window)?;
+            if !times.is_empty() {
+                limiter.user_requests.insert((chat_id, user_id), times);
+            }
+        }
+        for entry in entries("daily")? {
+            let user_id = entry["user_id"].as_u64().ok_or_else(|| invalid("bad user_id"))?;
+            let times = restore(&entry, DAILY_WINDOW)?;
+            if !times.is_empty() {
+                limiter.daily.insert(user_id, times);
+            }
+        }
+        Ok(limiter)
+    }
+}
+
+/// Count `cost` requests against `user_id`'s daily quota, if there is one.
+///
+/// Returns the quota left afterwards; a request that doesn't fit is not counted.
+fn charge_daily(
+    daily: &mut HashMap<u64, Vec<Instant>>,
+    user_id: u64,
+    cost: usize,
+    daily_quota: Option<usize>,
+    now: Instant,
+) -> Result<Option<usize>> {
+    let Some(quota) = daily_quota else {
+        return Ok(None);
+    };
+    let daily = daily.entry(user_id).or_default();
+    daily.retain(|&timestamp| now.duration_since(timestamp) < DAILY_WINDOW);
+    if daily.len() + cost > quota {
+        // As with the window limit, wait for the oldest requests in the way to expire
+        let blocking = (daily.len() + cost - quota).min(daily.len());
+        let retry = match blocking.checked_sub(1).and_then(|i| daily.get(i)) {
+            Some(&at) => format!(
+                "Try again in {}.",
+                format_wait((at + DAILY_WINDOW).saturating_duration_since(now))
+            ),
+            None => "Please try again later.".to_string(),
+        };
+        return Err(BotError::RateLimit(format!(
+            "Daily quota of {} password generations reached. {}",
+            quota, retry
+        )));
+    }
+    daily.extend(std::iter::repeat_n(now, cost));
+    Ok(Some(quota - daily.len()))
+}
 
+/// Drop the `requests` that have left the sliding `window`, then check that `cost`
+/// more fit under `limit`. `scope` qualifies the limit in the error (" per member").
+fn check_sliding_window(
+    requests: &mut Vec<Instant>,
+    cost: usize,
+    limit: usize,
+    window: Duration,
+    now: Instant,
+    scope: &str,
+) -> Result<()> {
+    requests.retain(|&timestamp| now.duration_since(timestamp) < window);
+    if requests.len() + cost > limit {
+        // Enough room opens once the oldest requests in the way have expired
+        let blocking = (requests.len() + cost - limit).min(requests.len());
+        let reset = blocking
+            .checked_sub(1)
+            .and_then(|i| requests.get(i))
+            .map(|&at| (at + window).saturating_duration_since(now));
+        return Err(window_limit_error(limit, window, scope, cost, requests.len(), reset));
+    }
     Ok(())
 }
 
-/// Handler for the /help command.
-pub async fn handle_help(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
-    let help_text = format!(
-        "🔐 Password Generator - Help\n\n\
-        Available Commands:\n\
-        • /start - Welcome message\n\
-        • /help - Show this help message\n\
-        • /pass or /password - Generate a secure password\n\n\
-        Password Generation Syntax:\n\
-        /pass [length] [options]\n\n\
-        Examples:\n\
-        • /pass - Default password (length: {})\n\
-        • /pass 24 - 24-character password\n\
-        • /pass 20 --symbols - Include symbols\n\
-        • /pass 16 --no-symbols - No symbols\n\
-        • /pass 18 --no-ambiguous - Exclude ambiguous chars (0,O,o,1,l,I)\n\
-        • /pass 20 --no-digits --symbols - No digits, with symbols\n\n\
-        Available Options:\n\
-        • --symbols / --no-symbols\n\
-        • --digits / --no-digits\n\
-        • --uppercase / --no-uppercase\n\
-        • --lowercase / --no-lowercase\n\
-        • --no-ambiguous - Exclude confusing characters\n\n\
-        Constraints:\n\
-        • Min length: {} characters\n\
-        • Max length: {} characters\n\
-        • At least one character type must be enabled\n\
-        • Rate limit: {} passwords per minute per chat\n\n\
-        Security Recommendations:\n\
-        ✅ Use long passwords (16+ characters)\n\
-        ✅ Use unique passwords for each account\n\
-        ✅ Store passwords in a secure password manager\n\
-        ⚠️ Remember: Telegram is not end-to-end encrypted\n\
-        ⚠️ This bot doesn't log passwords, but they travel through Telegram's servers",
-        state.config.default_password_length,
-        state.config.min_password_length,
-        state.config.max_password_length,
-        state.config.rate_limit_per_minute
+/// Like [`check_sliding_window`], but the window opens with the first request kept
+/// and all of them expire together when it closes.
+fn check_fixed_window(
+    requests: &mut Vec<Instant>,
+    cost: usize,
+    limit: usize,
+    window: Duration,
+    now: Instant,
+    scope: &str,
+) -> Result<()> {
+    if requests.first().is_some_and(|&start| now.duration_since(start) >= window) {
+        requests.clear();
+    }
+    if requests.len() + cost > limit {
+        let reset = requests
+            .first()
+            .map(|&start| (start + window).saturating_duration_since(now));
+        return Err(window_limit_error(limit, window, scope, cost, requests.len(), reset));
+    }
+    Ok(())
+}
+
+/// The error for a request costing `cost` over a window limit, with `used` of
+/// `limit` already spent and room opening up again after `reset`.
+///
+/// A batch costing more than the whole limit never fits, so it isn't told to wait.
+fn window_limit_error(
+    limit: usize,
+    window: Duration,
+    scope: &str,
+    cost: usize,
+    used: usize,
+    reset: Option<Duration>,
+) -> BotError {
+    if cost > limit {
+        return BotError::RateLimit(format!(
+            "Too many passwords at once. Maximum {} password generations per {}{}; \
+            ask for {} or fewer.",
+            limit,
+            describe_window(window),
+            scope,
+            limit
+        ));
+    }
+    let mut message = format!(
+        "Too many requests. Maximum {} password generations per {}{}; {} left.",
+        limit,
+        describe_window(window),
+        scope,
+        limit.saturating_sub(used)
     );
+    if let Some(reset) = reset {
+        message.push_str(&format!(" Try again in {}.", format_wait(reset)));
+    }
+    BotError::RateLimit(message)
+}
 
-    use teloxide::types::InlineKeyboardButton;
-    let keyboard = teloxide::types::InlineKeyboardMarkup::new(vec![
-        vec![
-            InlineKeyboardButton::callback("📋 Default", "pass_default"),
-            InlineKeyboardButton::callback("🔒 Strong (24)", "pass_24"),
-        ],
-        vec![
-            InlineKeyboardButton::callback("🔤 No Symbols", "pass_no_symbols"),
-            InlineKeyboardButton::callback("🚫 Ambiguous", "pass_no_ambiguous"),
-        ],
-        vec![
-            InlineKeyboardButton::callback("🔐 Very Strong (32)", "pass_32"),
-            InlineKeyboardButton::callback("📏 Custom Length", "pass_custom"),
-        ],
-    ]);
+/// Format a wait for users: "23s", "4m 10s" or "5h 3m".
+///
+/// Rounds up, so "0s" is never shown while a request would still be refused.
+fn format_wait(wait: Duration) -> String {
+    let secs = (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1);
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => {
+            let minutes = secs.div_ceil(60);
+            format!("{}h {}m", minutes / 60, minutes % 60)
+        }
+    }
+}
 
-    bot.send_message(msg.chat.id, help_text)
-        .reply_markup(keyboard)
-        .await?;
+/// Load the limiter saved at `path`, or start empty.
+///
+/// A missing file is normal on first start; an unreadable one is logged and
+/// ignored rather than keeping the bot from starting.
+fn load_rate_limiter(path: Option<&Path>, window: Duration) -> RateLimiter {
+    let Some(path) = path else {
+        return RateLimiter::new(window);
+    };
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return RateLimiter::new(window),
+        Err(e) => {
+            warn!("Could not read rate limit state {}: {}", path.display(), e);
+            return RateLimiter::new(window);
+        }
+    };
+    RateLimiter::from_json(&json, window, Instant::now(), SystemTime::now()).unwrap_or_else(|e| {
+        warn!("Ignoring rate limit state {}: {}", path.display(), e);
+        RateLimiter::new(window)
+    })
+}
 
-    Ok(())
+/// Tell the user how much daily quota is left once it runs low.
+fn quota_notice(remaining: Option<usize>) -> Option<String> {
+    match remaining {
+        Some(left) if left <= QUOTA_WARNING_REMAINING => Some(format!(
+            "ℹ️ {} password generation{} left in your daily quota.",
+            left,
+            if left == 1 { "" } else { "s" }
+        )),
+        _ => None,
+    }
 }
 
-/// Parse password generation command arguments.
-///
-/// Expected format: /pass [length] [--option1] [--option2] ...
-pub fn parse_password_args(args: &str, default_length: usize) -> Result<PasswordConfig> {
-    let mut config = PasswordConfig::default();
-    config.length = default_length;
+/// Maximum number of password messages remembered per chat for /panic.
+const MAX_TRACKED_MESSAGES: usize = 100;
 
-    let parts: Vec<&str> = args.split_whitespace().collect();
+/// Delay between deletions so /panic stays within Telegram's outbound limits.
+const PANIC_DELETE_INTERVAL: Duration = Duration::from_millis(40);
 
-    for part in parts {
-        if part.starts_with("--") {
-            // Parse options
-            match part {
-                "--symbols" => config.use_symbols = true,
-                "--no-symbols" => config.use_symbols = false,
-                "--digits" => config.use_digits = true,
-                "--no-digits" => config.use_digits = false,
-                "--uppercase" => config.use_uppercase = true,
-                "--no-uppercase" => config.use_uppercase = false,
-                "--lowercase" => config.use_lowercase = true,
-                "--no-lowercase" => config.use_lowercase = false,
-                "--no-ambiguous" => config.exclude_ambiguous = true,
-                _ => {
-                    return Err(BotError::PasswordGeneration(format!(
-                        "Unknown option: {}",
-                        part
-                    )))
-                }
+/// Password messages sent per chat, remembered so /panic can delete them.
+#[derive(Debug, Default)]
+pub struct SentMessages {
+    /// Map of chat_id to (message id, send time), oldest first.
+    messages: HashMap<i64, VecDeque<(MessageId, Instant)>>,
+}
+
+/// Messages /panic should try to delete, and how many are past the deletable window.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PanicPlan {
+    pub deletable: Vec<MessageId>,
+    pub too_old: usize,
+}
+
+impl SentMessages {
+    /// Remember a message the bot sent to `chat_id`.
+    pub fn record(&mut self, chat_id: i64, id: MessageId, sent_at: Instant) {
+        let sent = self.messages.entry(chat_id).or_default();
+        sent.push_back((id, sent_at));
+        if sent.len() > MAX_TRACKED_MESSAGES {
+            sent.pop_front();
+        }
+    }
+
+    /// Forget every tracked message for `chat_id`, splitting them into those that
+    /// can still be deleted at `now` and those that are too old.
+    pub fn take_for_panic(&mut self, chat_id: i64, now: Instant) -> PanicPlan {
+        let mut plan = PanicPlan::default();
+        for (id, sent_at) in self.messages.remove(&chat_id).unwrap_or_default() {
+            if now.duration_since(sent_at) < DELETABLE_WINDOW {
+                plan.deletable.push(id);
+            } else {
+                plan.too_old += 1;
             }
-        } else {
-            // Try to parse as length
-            match part.parse::<usize>() {
-                Ok(len) => config.length = len,
-                Err(_) => {
-                    return Err(BotError::PasswordGeneration(format!(
-                        "Invalid length: '{}'. Expected a number.",
-                        part
-                    )))
-                }
+        }
+        plan
+    }
+}
+
+/// Tracks when each chat last received the full /start welcome.
+#[derive(Debug, Default)]
+pub struct StartCooldown {
+    last_start: HashMap<i64, Instant>,
+}
+
+impl StartCooldown {
+    /// Whether `chat_id` should get the full welcome at `now`.
+    ///
+    /// Returns `false` while the chat is still within `cooldown` of its last full
+    /// welcome. Only full welcomes reset the timer.
+    pub fn allow_full_welcome(&mut self, chat_id: i64, now: Instant, cooldown: Duration) -> bool {
+        match self.last_start.get(&chat_id) {
+            Some(&last) if now.duration_since(last) < cooldown => false,
+            _ => {
+                self.last_start.insert(chat_id, now);
+                true
             }
         }
     }
+}
 
-    Ok(config)
+/// When each user last sent /feedback, to keep one user from flooding the admins.
+#[derive(Debug, Default)]
+pub struct FeedbackCooldown {
+    last_sent: HashMap<u64, Instant>,
 }
 
-/// Handler for the /pass and /password commands.
-pub async fn handle_password(
-    bot: Bot,
-    msg: Message,
-    state: BotState,
-    args: String,
-) -> ResponseResult<()> {
-    let chat_id = msg.chat.id.0;
+impl FeedbackCooldown {
+    /// Let `user_id` send feedback at `now`, or return how long they still have to wait.
+    ///
+    /// Only accepted feedback restarts the cooldown.
+    pub fn try_send(
+        &mut self,
+        user_id: u64,
+        now: Instant,
+        cooldown: Duration,
+    ) -> std::result::Result<(), Duration> {
+        if let Some(&last) = self.last_sent.get(&user_id) {
+            let wait = (last + cooldown).saturating_duration_since(now);
+            if !wait.is_zero() {
+                return Err(wait);
+            }
+        }
+        self.last_sent.insert(user_id, now);
+        Ok(())
+    }
 
-    // Check rate limit
-    {
-        let mut rate_limiter = state.rate_limiter.lock().await;
-        if let Err(e) = rate_limiter.check_rate_limit(chat_id, state.config.rate_limit_per_minute)
-        {
-            bot.send_message(msg.chat.id, e.to_string()).await?;
-            warn!(
-                "Rate limit exceeded for chat {}: {}",
-                chat_id, e
-            );
-            return Ok(());
+    /// Undo the cooldown a [`try_send`](Self::try_send) at `sent_at` started, when the
+    /// feedback couldn't be delivered after all.
+    pub fn cancel(&mut self, user_id: u64, sent_at: Instant) {
+        if self.last_sent.get(&user_id) == Some(&sent_at) {
+            self.last_sent.remove(&user_id);
         }
     }
+}
 
-    // Parse arguments
-    let password_config = match parse_password_args(&args, state.config.default_password_length)
-    {
-        Ok(config) => config,
-        Err(e) => {
-            let error_msg = format!(
-                "❌ Error: {}\n\nUsage: `/pass [length] [options]`\n\
-                 Example: `/pass 20 --symbols --no-ambiguous`\n\n\
-                 Type `/help` for detailed usage.",
-                e
-            );
-            bot.send_message(msg.chat.id, error_msg)
-                .await?;
-            return Ok(());
+/// Maximum number of generations remembered per chat for /history.
+const MAX_HISTORY_ENTRIES: usize = 10;
+
+/// Metadata about one generated password. The password itself is never kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub at: Instant,
+    pub length: usize,
+    pub strength: PasswordStrength,
+}
+
+/// Recent generations per chat, for /history.
+#[derive(Debug, Default)]
+pub struct GenerationHistory {
+    entries: HashMap<i64, VecDeque<HistoryEntry>>,
+}
+
+impl GenerationHistory {
+    /// Remember a generation for `chat_id`, dropping the oldest beyond the limit.
+    pub fn record(&mut self, chat_id: i64, entry: HistoryEntry) {
+        let entries = self.entries.entry(chat_id).or_default();
+        entries.push_back(entry);
+        if entries.len() > MAX_HISTORY_ENTRIES {
+            entries.pop_front();
         }
-    };
+    }
 
-    // Validate length bounds
-    if password_config.length < state.config.min_password_length {
-        let error_msg = format!(
-            "❌ Password length too short. Minimum: {} characters.",
-            state.config.min_password_length
+    /// Recent generations for `chat_id`, oldest first.
+    pub fn recent(&self, chat_id: i64) -> Vec<HistoryEntry> {
+        self.entries
+            .get(&chat_id)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget every generation for `chat_id`, returning how many were removed.
+    pub fn clear(&mut self, chat_id: i64) -> usize {
+        self.entries.remove(&chat_id).map_or(0, |e| e.len())
+    }
+}
+
+/// Usage counters for the operators' /stats command and the metrics endpoint.
+///
+/// Only counts are kept: nothing about the passwords, users or chats involved.
+#[derive(Debug)]
+pub struct UsageStats {
+    started: Instant,
+    passwords_generated: u64,
+    generation_errors: u64,
+    rate_limit_rejections: u64,
+    commands: HashMap<&'static str, u64>,
+}
+
+impl UsageStats {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            passwords_generated: 0,
+            generation_errors: 0,
+            rate_limit_rejections: 0,
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Count one use of `/command`.
+    pub fn record_command(&mut self, command: &'static str) {
+        *self.commands.entry(command).or_default() += 1;
+    }
+
+    /// Count `count` generated passwords or passphrases.
+    pub fn record_passwords(&mut self, count: usize) {
+        self.passwords_generated += count as u64;
+    }
+
+    /// Count a password request that failed or timed out.
+    pub fn record_error(&mut self) {
+        self.generation_errors += 1;
+    }
+
+    /// Count a request turned away by the rate limit or daily quota.
+    pub fn record_rejection(&mut self) {
+        self.rate_limit_rejections += 1;
+    }
+
+    /// Build the /stats reply, most used commands first.
+    pub fn format(&self, now: Instant) -> String {
+        let mut commands: Vec<_> = self.commands.iter().collect();
+        commands.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let mut text = format!(
+            "📊 Usage since the bot started {}\n\n\
+            Passwords generated: {}\n\
+            Generation errors: {}\n\
+            Rate-limit rejections: {}\n\n\
+            Commands:",
+            format_age(now.duration_since(self.started)),
+            self.passwords_generated,
+            self.generation_errors,
+            self.rate_limit_rejections
         );
-        bot.send_message(msg.chat.id, error_msg).await?;
-        return Ok(());
+        if commands.is_empty() {
+            text.push_str(" none yet");
+        }
+        for (command, count) in commands {
+            text.push_str(&format!("\n  /{}: {}", command, count));
+        }
+        text
     }
 
-    if password_config.length > state.config.max_password_length {
-        let error_msg = format!(
-            "❌ Password length too long. Maximum: {} characters.",
-            state.config.max_password_length
+    /// Log the counters as one structured event, e.g. at shutdown.
+    pub fn log_summary(&self, now: Instant) {
+        info!(
+            uptime_secs = now.duration_since(self.started).as_secs(),
+            passwords_generated = self.passwords_generated,
+            generation_errors = self.generation_errors,
+            rate_limit_rejections = self.rate_limit_rejections,
+            commands = self.commands.values().sum::<u64>(),
+            "Usage since start"
         );
-        bot.send_message(msg.chat.id, error_msg).await?;
-        return Ok(());
     }
 
-    // Validate configuration
-    if let Err(e) = password_config.validate() {
-        let error_msg = format!(
-            "❌ Configuration error: {}\n\n\
-             Make sure at least one character type is enabled.",
-            e
+    /// Render the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self, now: Instant) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, u64)]| {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for (labels, value) in samples {
+                text.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        metric(
+            "password_bot_passwords_generated_total",
+            "counter",
+            "Passwords and passphrases generated.",
+            &[(String::new(), self.passwords_generated)],
         );
-        bot.send_message(msg.chat.id, error_msg).await?;
-        return Ok(());
+        metric(
+            "password_bot_generation_errors_total",
+            "counter",
+            "Password requests that failed or timed out.",
+            &[(String::new(), self.generation_errors)],
+        );
+        metric(
+            "password_bot_rate_limit_rejections_total",
+            "counter",
+            "Requests turned away by the rate limit or daily quota.",
+            &[(String::new(), self.rate_limit_rejections)],
+        );
+        let mut commands: Vec<_> = self
+            .commands
+            .iter()
+            .map(|(command, count)| (format!("{{command=\"{}\"}}", command), *count))
+            .collect();
+        commands.sort();
+        metric(
+            "password_bot_commands_total",
+            "counter",
+            "Commands received, by command.",
+            &commands,
+        );
+        metric(
+            "password_bot_uptime_seconds",
+            "gauge",
+            "Seconds since the bot started.",
+            &[(String::new(), now.duration_since(self.started).as_secs())],
+        );
+        text
     }
+}
 
-    // Generate password using cryptographically secure RNG
-    let mut rng = OsRng;
-    let password = match generate_password(&password_config, &mut rng) {
-        Ok(pwd) => pwd,
-        Err(e) => {
-            let error_msg = format!("❌ Failed to generate password: {}", e);
-            bot.send_message(msg.chat.id, error_msg).await?;
-            return Ok(());
-        }
-    };
+/// Per-chat rate limits set by group admins with /setlimit.
+#[derive(Debug, Default)]
+pub struct ChatLimits {
+    limits: HashMap<i64, usize>,
+}
 
-    // Estimate strength
-    let strength = estimate_strength(&password_config);
-    let metadata = format_metadata(&password_config, strength);
+impl ChatLimits {
+    /// Set `chat_id`'s per-minute limit to `requested`, clamped to `1..=max`.
+    ///
+    /// Returns the limit actually stored.
+    pub fn set(&mut self, chat_id: i64, requested: usize, max: usize) -> usize {
+        let limit = requested.clamp(1, max.max(1));
+        self.limits.insert(chat_id, limit);
+        limit
+    }
 
-    // Format response (send password in monospace for better readability)
-    let strength_emoji = match strength {
-        PasswordStrength::Strong => "💪",
-        PasswordStrength::Medium => "👍",
-        PasswordStrength::Weak => "⚠️",
-    };
+    /// The per-minute limit for `chat_id`, falling back to `default`.
+    pub fn limit_for(&self, chat_id: i64, default: usize) -> usize {
+        self.limits.get(&chat_id).copied().unwrap_or(default)
+    }
+}
 
-    let response = format!(
-        "🔐 Your Secure Password:\n\n`{}`\n\n{} {}\n\n⚠️ Security reminder: Copy this password immediately and store it securely. This message will remain in your chat history.",
-        password, strength_emoji, metadata
-    );
+/// How long a length confirmation button stays valid.
+const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
 
-    bot.send_message(msg.chat.id, response)
-        .await?;
+/// A long-password request waiting for the user to confirm it.
+#[derive(Debug, Clone)]
+pub struct PendingRequest {
+    pub chat_id: ChatId,
+    pub config: PasswordConfig,
+    pub display: DisplayOptions,
+    pub count: usize,
+    pub created: Instant,
+}
 
-    // Log metadata only (never log the actual password)
-    info!(
-        "Generated password for chat {}: {}",
-        chat_id, metadata
-    );
+/// Requests above `CONFIRM_LENGTH_THRESHOLD`, one per user, awaiting confirmation.
+#[derive(Debug, Default)]
+pub struct PendingConfirmations {
+    pending: HashMap<u64, PendingRequest>,
+}
 
-    Ok(())
+impl PendingConfirmations {
+    /// Remember `request` for `user_id`, replacing any earlier one.
+    pub fn insert(&mut self, user_id: u64, request: PendingRequest) {
+        self.pending.insert(user_id, request);
+    }
+
+    /// Take `user_id`'s pending request if it hasn't expired by `now`.
+    pub fn take(&mut self, user_id: u64, now: Instant) -> Option<PendingRequest> {
+        self.pending
+            .remove(&user_id)
+            .filter(|request| now.duration_since(request.created) < CONFIRMATION_TTL)
+    }
 }
 
-/// Handler for inline button callbacks.
-pub async fn handle_callback(
+/// Button configs kept for callback data too long to carry them; the oldest go first.
+const MAX_STORED_CONFIGS: usize = 1000;
+
+/// Password configs behind `cfg:<id>` buttons, for settings that don't fit in
+/// Telegram's 64 bytes of callback data.
+#[derive(Debug, Default)]
+pub struct StoredConfigs {
+    configs: HashMap<u64, PasswordConfig>,
+    /// Ids oldest first, for eviction.
+    order: VecDeque<u64>,
+}
+
+impl StoredConfigs {
+    /// Keep `config` and return the id its button refers to.
+    ///
+    /// Ids are random, so one user can't guess the button data for another's settings.
+    pub fn insert(&mut self, config: PasswordConfig, rng: &mut dyn RngCore) -> u64 {
+        let mut id = rng.next_u64();
+        while self.configs.contains_key(&id) {
+            id = rng.next_u64();
+        }
+        if self.order.len() == MAX_STORED_CONFIGS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.configs.remove(&oldest);
+            }
+        }
+        self.configs.insert(id, config);
+        self.order.push_back(id);
+        id
+    }
+
+    /// The config stored under `id`; it stays for later presses of the same button.
+    pub fn get(&self, id: u64) -> Option<&PasswordConfig> {
+        self.configs.get(&id)
+    }
+}
+
+/// How long a /verify flow waits for the user's replies.
+const VERIFICATION_TTL: Duration = Duration::from_secs(2 * 60);
+
+/// Compare two secrets without revealing where they first differ.
+///
+/// Only the lengths are compared in variable time.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.as_bytes().ct_eq(b.as_bytes()).into()
+}
+
+/// A /verify flow waiting for a reply to `prompt`.
+struct PendingVerification {
+    /// The first copy of the password, wiped when dropped.
+    first: Option<Zeroizing<String>>,
+    prompt: MessageId,
+    created: Instant,
+}
+
+/// What a /verify reply led to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyStep {
+    /// The first copy was stored; ask for the second.
+    Second,
+    /// Both copies were compared.
+    Finished { matched: bool },
+}
+
+/// Open /verify flows, one per user in each chat.
+#[derive(Default)]
+pub struct PendingVerifications {
+    pending: HashMap<(i64, u64), PendingVerification>,
+}
+
+impl PendingVerifications {
+    /// Start (or restart) a flow for `key` expecting a reply to `prompt`.
+    pub fn start(&mut self, key: (i64, u64), prompt: MessageId, now: Instant) {
+        self.expire(now);
+        self.pending.insert(
+            key,
+            PendingVerification {
+                first: None,
+                prompt,
+                created: now,
+            },
+        );
+    }
+
+    /// Expect the second copy as a reply to `prompt`.
+    pub fn await_second(&mut self, key: (i64, u64), prompt: MessageId) {
+        if let Some(flow) = self.pending.get_mut(&key) {
+            flow.prompt = prompt;
+        }
+    }
+
+    /// Feed `value`, sent in reply to `reply_to`, into `key`'s flow.
+    ///
+    /// Returns `None` if there is no live flow waiting for that reply.
+    pub fn submit(
+        &mut self,
+        key: (i64, u64),
+        reply_to: MessageId,
+        value: Zeroizing<String>,
+        now: Instant,
+    ) -> Option<VerifyStep> {
+        self.expire(now);
+        let flow = self.pending.get_mut(&key)?;
+        if flow.prompt != reply_to {
+            return None;
+        }
+
+        match &flow.first {
+            None => {
+                flow.first = Some(value);
+                Some(VerifyStep::Second)
+            }
+            Some(first) => {
+                let matched = constant_time_eq(first, &value);
+                self.pending.remove(&key);
+                Some(VerifyStep::Finished { matched })
+            }
+        }
+    }
+
+    /// Forget every flow older than [`VERIFICATION_TTL`], first copies included,
+    /// so abandoned ones don't linger until the next reply to them.
+    fn expire(&mut self, now: Instant) {
+        self.pending.retain(|_, flow| now.duration_since(flow.created) < VERIFICATION_TTL);
+    }
+}
+
+/// Where password generation gets its randomness.
+///
+/// Production uses [`OsRng`]; tests inject a seeded source so the generated
+/// passwords, and so whole replies, are reproducible.
+#[derive(Clone)]
+pub struct RngSource(Arc<dyn Fn() -> Box<dyn RngCore + Send> + Send + Sync>);
+
+impl RngSource {
+    /// The operating system's CSPRNG.
+    pub fn os() -> Self {
+        Self(Arc::new(|| Box::new(OsRng)))
+    }
+
+    /// A deterministic stream from `seed`; successive draws differ but repeat per seed.
+    ///
+    /// For tests only: the output is predictable to anyone who knows the seed.
+    pub fn seeded(seed: u64) -> Self {
+        let parent = Arc::new(std::sync::Mutex::new(StdRng::seed_from_u64(seed)));
+        Self(Arc::new(move || {
+            let mut parent = parent.lock().unwrap_or_else(|e| e.into_inner());
+            Box::new(StdRng::seed_from_u64(parent.next_u64()))
+        }))
+    }
+
+    /// A generator for one request.
+    pub fn rng(&self) -> Box<dyn RngCore + Send> {
+        (self.0)()
+    }
+}
+
+impl Default for RngSource {
+    fn default() -> Self {
+        Self::os()
+    }
+}
+
+/// Shared bot state including configuration and rate limiter.
+#[derive(Clone)]
+pub struct BotState {
+    pub config: Arc<Config>,
+    pub rate_limiter: Arc<Mutex<RateLimiter>>,
+    pub sent_messages: Arc<Mutex<SentMessages>>,
+    pub start_cooldown: Arc<Mutex<StartCooldown>>,
+    pub feedback_cooldown: Arc<Mutex<FeedbackCooldown>>,
+    pub help_navigator: Arc<Mutex<HelpNavigator>>,
+    pub locales: Arc<Locales>,
+    pub history: Arc<Mutex<GenerationHistory>>,
+    pub confirmations: Arc<Mutex<PendingConfirmations>>,
+    pub chat_limits: Arc<Mutex<ChatLimits>>,
+    pub verifications: Arc<Mutex<PendingVerifications>>,
+    /// Chats showing a `--reply-keyboard` password button that still needs removing.
+    pub reply_keyboards: Arc<Mutex<HashSet<i64>>>,
+    pub dedup: Arc<Mutex<DedupTags>>,
+    pub stats: Arc<Mutex<UsageStats>>,
+    pub preferences: Arc<Mutex<UserPreferences>>,
+    /// Languages picked with `/lang`, overriding each user's Telegram language.
+    pub chat_languages: Arc<Mutex<HashMap<i64, String>>>,
+    /// Regenerate settings too long to encode in a button's callback data.
+    pub stored_configs: Arc<Mutex<StoredConfigs>>,
+    /// Randomness for /pass and its buttons; replaced with a seeded source in tests.
+    pub rng: RngSource,
+    /// HaveIBeenPwned client for /check, built once when `ENABLE_HIBP` is set.
+    #[cfg(feature = "hibp")]
+    pub hibp: Option<crate::hibp::HibpClient>,
+}
+
+impl BotState {
+    /// Apply the per-minute and daily limits to a generation request.
+    ///
+    /// Returns a notice to show the user when their daily quota is running low.
+    pub async fn check_limits(&self, chat_id: i64, user_id: u64) -> Result<Option<String>> {
+        self.check_limits_for(chat_id, user_id, 1).await
+    }
+
+    /// Apply the limits to a request that counts as `cost` generations.
+    pub async fn check_limits_for(
+        &self,
+        chat_id: i64,
+        user_id: u64,
+        cost: usize,
+    ) -> Result<Option<String>> {
+        let limit = self
+            .chat_limits
+            .lock()
+            .await
+            .limit_for(chat_id, self.config.rate_limit_per_minute);
+        let checked = {
+            let mut rate_limiter = self.rate_limiter.lock().await;
+            let check = match self.config.rate_limit_strategy {
+                RateLimitStrategy::Sliding => RateLimiter::check_requests,
+                RateLimitStrategy::Fixed => RateLimiter::check_requests_fixed,
+            };
+            check(
+                &mut rate_limiter,
+                chat_id,
+                user_id,
+                cost,
+                limit,
+                self.config.daily_quota_per_user,
+                Instant::now(),
+            )
+        };
+        if checked.is_err() {
+            self.stats.lock().await.record_rejection();
+        }
+        Ok(quota_notice(checked?))
+    }
+
+    /// Tag `passwords` for `user_id` and count how many were generated for them before.
+    ///
+    /// Always 0 unless `DEDUP_TAGGING` is on; only salted hashes are kept, never passwords.
+    pub async fn count_repeats(&self, user_id: u64, passwords: &[String]) -> usize {
+        let Some(salt) = self.config.dedup_salt.as_deref().filter(|_| self.config.dedup_tagging)
+        else {
+            return 0;
+        };
+        let mut dedup = self.dedup.lock().await;
+        passwords
+            .iter()
+            .filter(|password| dedup.check_and_record(salt.as_bytes(), user_id, password))
+            .count()
+    }
+
+    /// Save the rate limiter to `RATE_LIMIT_STATE_PATH`; a no-op when it is unset.
+    ///
+    /// The state is written to a temporary file first and renamed over the old one,
+    /// so a crash mid-write can't leave a truncated file behind.
+    pub async fn persist_rate_limits(&self) -> Result<()> {
+        let Some(path) = &self.config.rate_limit_state_path else {
+            return Ok(());
+        };
+        let json = self
+            .rate_limiter
+            .lock()
+            .await
+            .to_json(Instant::now(), SystemTime::now())
+            .to_string();
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
+    /// Save the `/setdefault` options to `PREFERENCES_PATH`; a no-op when it is unset.
+    ///
+    /// Written through a temporary file like [`persist_rate_limits`](Self::persist_rate_limits).
+    pub async fn persist_preferences(&self) -> Result<()> {
+        let Some(path) = &self.config.preferences_path else {
+            return Ok(());
+        };
+        let json = self.preferences.lock().await.to_json().to_string();
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+
+    /// The language to reply in: the chat's `/lang` choice, else the user's own.
+    pub async fn language(&self, chat_id: i64, from: Option<&User>) -> Option<String> {
+        let chosen = self.chat_languages.lock().await.get(&chat_id).cloned();
+        chosen.or_else(|| from.and_then(|u| u.language_code.clone()))
+    }
+
+    pub fn new(config: Config, locales: Locales) -> Self {
+        let rate_limiter = load_rate_limiter(
+            config.rate_limit_state_path.as_deref(),
+            config.rate_limit_window(),
+        )
+        .with_user_limit(config.rate_limit_per_user)
+        .with_min_interval(Duration::from_secs(config.min_interval_secs));
+        let preferences = UserPreferences::load(config.preferences_path.as_deref());
+        #[cfg(feature = "hibp")]
+        let hibp = if config.enable_hibp {
+            crate::hibp::HibpClient::new(crate::hibp::HIBP_RANGE_URL)
+                .map_err(|e| warn!("Breach checks will be skipped: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        Self {
+            config: Arc::new(config),
+            rate_limiter: Arc::new(Mutex::new(rate_limiter)),
+            sent_messages: Arc::new(Mutex::new(SentMessages::default())),
+            start_cooldown: Arc::new(Mutex::new(StartCooldown::default())),
+            feedback_cooldown: Arc::new(Mutex::new(FeedbackCooldown::default())),
+            help_navigator: Arc::new(Mutex::new(HelpNavigator::default())),
+            locales: Arc::new(locales),
+            history: Arc::new(Mutex::new(GenerationHistory::default())),
+            confirmations: Arc::new(Mutex::new(PendingConfirmations::default())),
+            chat_limits: Arc::new(Mutex::new(ChatLimits::default())),
+            verifications: Arc::new(Mutex::new(PendingVerifications::default())),
+            reply_keyboards: Arc::new(Mutex::new(HashSet::new())),
+            dedup: Arc::new(Mutex::new(DedupTags::default())),
+            stats: Arc::new(Mutex::new(UsageStats::new(Instant::now()))),
+            preferences: Arc::new(Mutex::new(preferences)),
+            chat_languages: Arc::new(Mutex::new(HashMap::new())),
+            stored_configs: Arc::new(Mutex::new(StoredConfigs::default())),
+            rng: RngSource::os(),
+            #[cfg(feature = "hibp")]
+            hibp,
+        }
+    }
+}
+
+/// Build the /start welcome text.
+///
+/// When `first_name` is present it is used to greet the user. The message is sent
+/// as plain text (no parse mode), so the name needs no markup escaping; control
+/// characters are stripped so a crafted name can't break the layout.
+pub fn build_greeting(locales: &Locales, lang: Option<&str>, first_name: Option<&str>) -> String {
+    let welcome = welcome_line(locales, lang, first_name);
+    locales.get(lang, "start").replace("{welcome}", &welcome)
+}
+
+/// The translated "Welcome!" line, naming the user if `first_name` has anything printable.
+fn welcome_line(locales: &Locales, lang: Option<&str>, first_name: Option<&str>) -> String {
+    let name: Option<String> = first_name
+        .map(|n| n.chars().filter(|c| !c.is_control()).collect::<String>())
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    match name {
+        Some(name) => locales.get(lang, "welcome_named").replace("{name}", &name),
+        None => locales.get(lang, "welcome").to_string(),
+    }
+}
+
+/// Name standing in for `{bot_name}` in a `WELCOME_MESSAGE_PATH` text when
+/// `BOT_NAME` is unset.
+const DEFAULT_BOT_NAME: &str = "Secure Password Generator Bot";
+
+/// Build the /start text with the operator's branding.
+///
+/// A `WELCOME_MESSAGE_PATH` text replaces the built-in one, with `{welcome}` and
+/// `{bot_name}` filled in; otherwise `BOT_NAME`, if set, replaces the built-in
+/// title line. `{bot_name}` is filled first so a user's name is never expanded.
+fn build_branded_greeting(
+    welcome_message: Option<&str>,
+    bot_name: Option<&str>,
+    locales: &Locales,
+    lang: Option<&str>,
+    first_name: Option<&str>,
+) -> String {
+    if let Some(template) = welcome_message {
+        return template
+            .replace("{bot_name}", bot_name.unwrap_or(DEFAULT_BOT_NAME))
+            .replace("{welcome}", &welcome_line(locales, lang, first_name));
+    }
+
+    let text = build_greeting(locales, lang, first_name);
+    match bot_name {
+        Some(name) => {
+            let body = text.find('\n').map_or("", |i| &text[i..]);
+            format!("🔐 {}{}", name, body)
+        }
+        None => text,
+    }
+}
+
+/// Handler for the /start command.
+pub async fn handle_start(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let cooldown = Duration::from_secs(state.config.start_cooldown_secs);
+    let full_welcome = state
+        .start_cooldown
+        .lock()
+        .await
+        .allow_full_welcome(msg.chat.id.0, Instant::now(), cooldown);
+    let lang = state.language(msg.chat.id.0, msg.from()).await;
+    let lang = lang.as_deref();
+    if !full_welcome {
+        bot.send_message(msg.chat.id, state.locales.get(lang, "already_started"))
+            .await?;
+        return Ok(());
+    }
+
+    let first_name = msg
+        .from()
+        .filter(|_| state.config.personalize_greeting)
+        .map(|user| user.first_name.as_str());
+    let welcome_text = build_branded_greeting(
+        state.config.welcome_message.as_deref(),
+        state.config.bot_name.as_deref(),
+        &state.locales,
+        lang,
+        first_name,
+    );
+
+    let button = |key| state.locales.get(lang, key);
+    let keyboard = InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback(button("button_default"), "pass_default"),
+            InlineKeyboardButton::callback(button("button_strong"), "pass_24"),
+        ],
+        vec![
+            InlineKeyboardButton::callback(button("button_help"), "show_help"),
+        ],
+    ]);
+
+    bot.send_message(msg.chat.id, welcome_text)
+        .reply_markup(keyboard)
+        .await?;
+
+    info!(
+        "User {} started the bot",
+        msg.chat.id
+    );
+
+    Ok(())
+}
+
+/// Handler for the /help command.
+pub async fn handle_help(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let user_id = msg.from().map(|u| u.id.0).unwrap_or(msg.chat.id.0 as u64);
+    let page = state.help_navigator.lock().await.open(user_id);
+
+    // The option reference itself is English only; say so in the user's language
+    let lang = state.language(msg.chat.id.0, msg.from()).await;
+    let mut text = render_help_page(page, &state.config);
+    if state.locales.resolve(lang.as_deref()) != FALLBACK_LANGUAGE {
+        text = format!("{}\n\n{}", state.locales.get(lang.as_deref(), "help_in_english"), text);
+    }
+
+    bot.send_message(msg.chat.id, text)
+        .reply_markup(help_keyboard())
+        .await?;
+
+    Ok(())
+}
+
+/// Presentation options that change how a password is shown, not how it is generated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Style each character class differently using message entities.
+    pub annotate: bool,
+    /// Add a hyphenated copy, split every this many characters, for manual entry.
+    pub dashes: Option<usize>,
+    /// Add a grouped, phonetically spelled version to help memorization.
+    pub memorize: bool,
+    /// Add the entropy left for an attacker who knows the generation rules.
+    pub explain: bool,
+    /// Offer the password as a reply keyboard button, for clients without tap-to-copy.
+    pub reply_keyboard: bool,
+    /// Also send the password as a QR code photo, to scan onto another device.
+    pub qr: bool,
+    /// Follow up with the metadata as a JSON object, for tools reading the chat.
+    pub json: bool,
+    /// Hide the password, and any copy of it, behind a spoiler until tapped.
+    ///
+    /// Set from `USE_SPOILER_FORMATTING` when the reply is sent, not by a flag.
+    pub spoiler: bool,
+}
+
+/// A parsed password request: generation settings plus display options.
+#[derive(Debug, Clone)]
+pub struct PasswordRequest {
+    pub config: PasswordConfig,
+    pub display: DisplayOptions,
+    /// Use the requested length as-is, even if `AUTO_STRENGTHEN` would bump it.
+    pub exact: bool,
+    /// How many independent passwords to generate (`--count`); 1 unless requested.
+    pub count: usize,
+    /// Entropy target from `--bits`; `config.length` has already been set to meet it.
+    pub bits: Option<u32>,
+    /// Set when the length was given more than once; the last one was used.
+    pub length_warning: Option<String>,
+    /// Describe the settings (`--preview`) instead of generating anything.
+    pub preview: bool,
+}
+
+/// Sent with every `--emoji` password.
+const EMOJI_WARNING: &str = "⚠️ Many sites and systems reject emoji in passwords, or mangle them \
+     on some keyboards. Check that yours accepts them before relying on this one.";
+
+/// Largest entropy target `--bits` accepts.
+const MAX_TARGET_BITS: u32 = 4096;
+
+/// Fit a `--bits` length into `min..=max`, with a warning if the target is out of reach.
+///
+/// Lengths below `min` are raised silently: that only adds entropy.
+fn fit_bits_length(
+    config: &mut PasswordConfig,
+    bits: u32,
+    min: usize,
+    max: usize,
+) -> Option<String> {
+    if config.length < min {
+        config.length = min;
+        return None;
+    }
+    if config.length <= max {
+        return None;
+    }
+    let needed = config.length;
+    config.length = max;
+    Some(format!(
+        "⚠️ {} bits would need {} characters with these settings. Capped at the maximum of {}, \
+        which gives {:.1} bits.",
+        bits,
+        if needed == usize::MAX {
+            "unlimited".to_string()
+        } else {
+            needed.to_string()
+        },
+        max,
+        entropy_bits(config)
+    ))
+}
+
+/// Error for a `--pattern` whose passwords can fall outside `min..=max` characters.
+///
+/// Words differ in length, so both the shortest and the longest possible result
+/// must fit.
+fn pattern_length_error(config: &PasswordConfig, min: usize, max: usize) -> Option<String> {
+    let tokens = parse_pattern(config.pattern.as_deref()?).ok()?;
+    let (shortest, longest) = pattern_length_range(&tokens);
+    if shortest >= min && longest <= max {
+        return None;
+    }
+    Some(format!(
+        "❌ This pattern makes passwords of {} to {} characters, but they must be \
+         between {} and {}.",
+        shortest, longest, min, max
+    ))
+}
+
+/// Reject `args` longer than `max_len` bytes, before any parsing work is done.
+fn check_args_len(args: &str, max_len: usize) -> Result<()> {
+    if args.len() > max_len {
+        return Err(BotError::PasswordGeneration("Arguments too long.".to_string()));
+    }
+    Ok(())
+}
+
+/// Parse `/pass` arguments, refusing over-long input up front.
+fn parse_bounded_password_args(
+    args: &str,
+    base: &PasswordConfig,
+    max_len: usize,
+) -> Result<PasswordRequest> {
+    check_args_len(args, max_len)?;
+    parse_password_args(args, base)
+}
+
+/// Parse password generation command arguments.
+///
+/// Expected format: /pass [length] [--option1] [--option2] ...
+///
+/// Options are applied on top of `base`, normally the operator-configured
+/// defaults from [`Config::base_password_config`].
+pub fn parse_password_args(args: &str, base: &PasswordConfig) -> Result<PasswordRequest> {
+    let mut config = base.clone();
+    let mut display = DisplayOptions::default();
+    let mut exact = false;
+    let mut preview = false;
+    let mut count = 1;
+    let mut bits = None;
+    let mut explicit_length = false;
+    let mut length_warning = None;
+
+    // Explicitly requested on/off state per character class, to detect conflicts
+    let mut symbols = None;
+    let mut digits = None;
+    let mut uppercase = None;
+    let mut lowercase = None;
+
+    let mut parts = args.split_whitespace();
+
+    while let Some(part) = parts.next() {
+        if part.starts_with("--") {
+            // Parse options
+            match part {
+                "--symbols" => set_toggle(&mut config.use_symbols, &mut symbols, true, "symbols")?,
+                "--no-symbols" => {
+                    set_toggle(&mut config.use_symbols, &mut symbols, false, "symbols")?
+                }
+                "--digits" => set_toggle(&mut config.use_digits, &mut digits, true, "digits")?,
+                "--no-digits" => set_toggle(&mut config.use_digits, &mut digits, false, "digits")?,
+                "--uppercase" => {
+                    set_toggle(&mut config.use_uppercase, &mut uppercase, true, "uppercase")?
+                }
+                "--no-uppercase" => {
+                    set_toggle(&mut config.use_uppercase, &mut uppercase, false, "uppercase")?
+                }
+                "--lowercase" => {
+                    set_toggle(&mut config.use_lowercase, &mut lowercase, true, "lowercase")?
+                }
+                "--no-lowercase" => {
+                    set_toggle(&mut config.use_lowercase, &mut lowercase, false, "lowercase")?
+                }
+                "--no-ambiguous" => config.exclude_ambiguous = true,
+                "--ambiguous" => config.exclude_ambiguous = false,
+                "--no-confusable-symbols" => config.exclude_confusable_symbols = true,
+                "--csv-safe" => config.exclude_csv_unsafe = true,
+                "--interior-special" => config.interior_special = true,
+                "--no-repeat" => config.no_repeat = true,
+                "--pronounceable" => config.pronounceable = true,
+                "--emoji" => config.use_emoji = true,
+                "--annotate" => display.annotate = true,
+                "--memorize" => display.memorize = true,
+                "--explain" => display.explain = true,
+                "--json" => display.json = true,
+                "--reply-keyboard" => display.reply_keyboard = true,
+                "--qr" => display.qr = true,
+                "--dashes" => {
+                    let size = parts.next().map(normalize_digits);
+                    match size.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(size)) if size > 0 => display.dashes = Some(size),
+                        _ => {
+                            return Err(BotError::PasswordGeneration(
+                                "--dashes needs a group size, e.g. --dashes 4".to_string(),
+                            ))
+                        }
+                    }
+                }
+                "--exact" => exact = true,
+                "--preview" => preview = true,
+                _ if part.starts_with("--length=") || part.starts_with("--len=") => {
+                    let (name, value) = part.split_once('=').expect("matched on '='");
+                    match normalize_digits(value).parse::<usize>() {
+                        Ok(len) => {
+                            set_length(&mut config, len, &mut explicit_length, &mut length_warning)
+                        }
+                        Err(_) => {
+                            return Err(BotError::PasswordGeneration(format!(
+                                "{}= needs a number, e.g. {}=24",
+                                name, name
+                            )))
+                        }
+                    }
+                }
+                _ if part.starts_with("--exclude=") => {
+                    let chars = &part["--exclude=".len()..];
+                    if chars.is_empty() {
+                        return Err(BotError::PasswordGeneration(
+                            "--exclude= needs the characters to leave out, e.g. --exclude=<>\""
+                                .to_string(),
+                        ));
+                    }
+                    for c in chars.chars() {
+                        if !config.exclude_chars.contains(&c) {
+                            config.exclude_chars.push(c);
+                        }
+                    }
+                }
+                _ if part.starts_with("--symbols=") => {
+                    let chars = parse_symbol_set(&part["--symbols=".len()..])?;
+                    set_toggle(&mut config.use_symbols, &mut symbols, true, "symbols")?;
+                    config.charsets = config.charsets.with_symbols(chars);
+                }
+                "--bits" => {
+                    let n = parts.next().map(normalize_digits);
+                    match n.as_deref().map(str::parse::<u32>) {
+                        Some(Ok(n)) if (1..=MAX_TARGET_BITS).contains(&n) => bits = Some(n),
+                        _ => {
+                            return Err(BotError::PasswordGeneration(format!(
+                                "--bits needs an entropy target from 1 to {}, e.g. --bits 128",
+                                MAX_TARGET_BITS
+                            )))
+                        }
+                    }
+                }
+                "--min-lowercase" | "--min-uppercase" | "--min-digits" | "--min-symbols" => {
+                    let class = CharClass::from_name(&part["--min-".len()..])
+                        .expect("option names match class names");
+                    let n = parts.next().map(normalize_digits);
+                    match n.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(n)) => *config.class_minimum_mut(class) = n,
+                        _ => {
+                            return Err(BotError::PasswordGeneration(format!(
+                                "{} needs a count, e.g. {} 2",
+                                part, part
+                            )))
+                        }
+                    }
+                }
+                "--pattern" => match parts.next() {
+                    Some(template) => {
+                        parse_pattern(template)?;
+                        config.pattern = Some(template.to_string());
+                    }
+                    None => {
+                        return Err(BotError::PasswordGeneration(
+                            "--pattern needs a template, e.g. --pattern {Word}-{digit}{digit}"
+                                .to_string(),
+                        ))
+                    }
+                },
+                "--max-symbols" => {
+                    let n = parts.next().map(normalize_digits);
+                    match n.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(n)) => config.max_symbols = Some(n),
+                        _ => {
+                            return Err(BotError::PasswordGeneration(
+                                "--max-symbols needs a count, e.g. --max-symbols 2".to_string(),
+                            ))
+                        }
+                    }
+                }
+                "--count" => {
+                    let n = parts.next().map(normalize_digits);
+                    match n.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(n)) if n > 0 => count = n,
+                        _ => {
+                            return Err(BotError::PasswordGeneration(
+                                "--count needs a number of passwords, e.g. --count 5".to_string(),
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(BotError::PasswordGeneration(format!(
+                        "Unknown option: {}",
+                        part
+                    )))
+                }
+            }
+        } else {
+            // Try to parse as length
+            match normalize_digits(part).parse::<usize>() {
+                Ok(len) => set_length(&mut config, len, &mut explicit_length, &mut length_warning),
+                Err(_) => {
+                    return Err(BotError::PasswordGeneration(format!(
+                        "Invalid length: '{}'. Expected a number.",
+                        part
+                    )))
+                }
+            }
+        }
+    }
+
+    // Per-password display extras would repeat for every entry of a batch
+    if count > 1
+        && (display.annotate
+            || display.memorize
+            || display.reply_keyboard
+            || display.qr
+            || display.dashes.is_some())
+    {
+        return Err(BotError::PasswordGeneration(
+            "--count can't be combined with --annotate, --dashes, --memorize, --qr or \
+             --reply-keyboard"
+                .to_string(),
+        ));
+    }
+
+    // A pattern spells out the whole password, leaving nothing for these to shape
+    if let Some(template) = &config.pattern {
+        let shaped = explicit_length
+            || bits.is_some()
+            || [symbols, digits, uppercase, lowercase].iter().any(Option::is_some)
+            || config.pronounceable != base.pronounceable
+            || config.max_symbols != base.max_symbols
+            || config.use_emoji != base.use_emoji
+            || config.no_repeat != base.no_repeat
+            || config.exclude_chars != base.exclude_chars
+            || CharClass::ALL
+                .iter()
+                .any(|&class| config.class_minimum(class) != base.class_minimum(class));
+        if shaped {
+            return Err(BotError::PasswordGeneration(
+                "--pattern can't be combined with a length, --bits, --pronounceable, --emoji, \
+                 --no-repeat, --exclude or character class options"
+                    .to_string(),
+            ));
+        }
+        // Bounds checks and the long-password confirmation see the longest possible result
+        config.length = pattern_length_range(&parse_pattern(template)?).1;
+    }
+
+    // Options can follow --bits, so the length is worked out once they are all applied
+    if let Some(bits) = bits {
+        if explicit_length {
+            return Err(BotError::PasswordGeneration(
+                "Give either a length or --bits, not both".to_string(),
+            ));
+        }
+        config.length = length_for_entropy(&config, bits as f64);
+    }
+
+    Ok(PasswordRequest {
+        config,
+        display,
+        exact,
+        count,
+        bits,
+        length_warning,
+        preview,
+    })
+}
+
+/// Apply a positional or `--length=` length; a repeated one replaces the earlier value.
+fn set_length(
+    config: &mut PasswordConfig,
+    length: usize,
+    explicit_length: &mut bool,
+    warning: &mut Option<String>,
+) {
+    if *explicit_length && config.length != length {
+        *warning = Some(format!(
+            "⚠️ Length given more than once; using the last one ({}).",
+            length
+        ));
+    }
+    config.length = length;
+    *explicit_length = true;
+}
+
+/// Handler for the /wifi command.
+pub async fn handle_wifi(
     bot: Bot,
-    q: CallbackQuery,
+    msg: Message,
     state: BotState,
+    args: String,
 ) -> ResponseResult<()> {
-    use teloxide::types::InlineKeyboardButton;
-    
-    if let Some(ref data) = q.data {
-        // Handle different button callbacks
-        let message = match data.as_str() {
-            "pass_default" => "/pass".to_string(),
-            "pass_24" => "/pass 24".to_string(),
-            "pass_32" => "/pass 32".to_string(),
-            "pass_no_symbols" => "/pass 16 --no-symbols".to_string(),
-            "pass_no_ambiguous" => "/pass 18 --no-ambiguous".to_string(),
-            "pass_custom" => {
-                bot.answer_callback_query(&q.id).await?;
-                bot.send_message(
-                    q.from.id,
-                    "📝 Please type your custom password command:\nExample: /pass 20 --symbols --no-digits",
-                )
-                .await?;
-                return Ok(());
-            }
-            "show_help" => {
-                // Re-send help with buttons
-                let help_text = format!(
-                    "🔐 Password Generator - Help\n\n\
-                    Available Commands:\n\
-                    • /start - Welcome message\n\
-                    • /help - Show this help message\n\
-                    • /pass or /password - Generate a secure password\n\n\
-                    Password Generation Syntax:\n\
-                    /pass [length] [options]\n\n\
-                    Examples:\n\
-                    • /pass - Default password (length: {})\n\
-                    • /pass 24 - 24-character password\n\
-                    • /pass 20 --symbols - Include symbols\n\
-                    • /pass 16 --no-symbols - No symbols\n\
-                    • /pass 18 --no-ambiguous - Exclude ambiguous chars (0,O,o,1,l,I)\n\
-                    • /pass 20 --no-digits --symbols - No digits, with symbols\n\n\
-                    Available Options:\n\
-                    • --symbols / --no-symbols\n\
-                    • --digits / --no-digits\n\
-                    • --uppercase / --no-uppercase\n\
-                    • --lowercase / --no-lowercase\n\
-                    • --no-ambiguous - Exclude confusing characters\n\n\
-                    Constraints:\n\
-                    • Min length: {} characters\n\
-                    • Max length: {} characters\n\
-                    • At least one character type must be enabled\n\
-                    • Rate limit: {} passwords per minute per chat\n\n\
-                    Security Recommendations:\n\
-                    ✅ Use long passwords (16+ characters)\n\
-                    ✅ Use unique passwords for each account\n\
-                    ✅ Store passwords in a secure password manager\n\
-                    ⚠️ Remember: Telegram is not end-to-end encrypted\n\
-                    ⚠️ This bot doesn't log passwords, but they travel through Telegram's servers",
-                    state.config.default_password_length,
-                    state.config.min_password_length,
-                    state.config.max_password_length,
-                    state.config.rate_limit_per_minute
-                );
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let length = match args.trim() {
+        "" => Ok(WIFI_DEFAULT_LENGTH),
+        arg => normalize_digits(arg).parse::<usize>().map_err(|_| {
+            BotError::PasswordGeneration(format!("Invalid length: '{}'. Expected a number.", arg))
+        }),
+    };
+
+    let config = match length.and_then(wifi_config) {
+        Ok(config) => config,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+    let config = PasswordConfig {
+        strength_thresholds: state.config.strength_thresholds(),
+        ..config
+    };
+
+    let display = DisplayOptions::default();
+    send_password_with_timeout(&bot, msg.chat.id, &state, config, display, 1, msg.from()).await
+}
+
+/// Handler for the /pin command: a numeric-only code.
+pub async fn handle_pin(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let length = match args.trim() {
+        "" => Ok(PIN_DEFAULT_LENGTH.max(state.config.min_pin_length)),
+        arg => normalize_digits(arg).parse::<usize>().map_err(|_| {
+            BotError::PasswordGeneration(format!("Invalid length: '{}'. Expected a number.", arg))
+        }),
+    };
+
+    let config = match length.and_then(|length| {
+        pin_config(length, state.config.min_pin_length, state.config.max_password_length)
+    }) {
+        Ok(config) => config,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+    let config = PasswordConfig {
+        strength_thresholds: state.config.strength_thresholds(),
+        ..config
+    };
+
+    let display = DisplayOptions::default();
+    send_password_with_timeout(&bot, msg.chat.id, &state, config, display, 1, msg.from()).await
+}
+
+/// Account label used in the /totp URI when none is given.
+const DEFAULT_TOTP_ACCOUNT: &str = "account";
+
+/// Build the /totp reply and the code entities covering the secret and URI.
+fn build_totp_reply(secret: &str, uri: &str) -> (String, Vec<MessageEntity>) {
+    let intro = "🔑 TOTP secret (base32):\n";
+    let middle = "\n\nEnrollment URI:\n";
+    let outro = "\n\nEnter the secret in your authenticator app, or open the URI on the \
+        device that runs it.\n\n\
+        ⚠️ This secret travels through Telegram's servers. Anyone who sees it can \
+        generate your codes, so delete this message once you have enrolled.";
+
+    let text = format!("{}{}{}{}{}", intro, secret, middle, uri, outro);
+    let secret_offset = utf16_len(intro);
+    let uri_offset = secret_offset + utf16_len(secret) + utf16_len(middle);
+    let entities = vec![
+        MessageEntity::code(secret_offset, utf16_len(secret)),
+        MessageEntity::code(uri_offset, utf16_len(uri)),
+    ];
+    (text, entities)
+}
+
+/// Handler for the /totp command.
+pub async fn handle_totp(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let account = match args.trim() {
+        "" => DEFAULT_TOTP_ACCOUNT,
+        account => account,
+    };
+    let secret = generate_totp_secret(&mut OsRng);
+    let (text, entities) = build_totp_reply(&secret, &otpauth_uri(&secret, account));
+
+    let sent = bot
+        .send_message(msg.chat.id, text)
+        .entities(entities)
+        .reply_markup(password_keyboard())
+        .await?;
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id, sent.id, Instant::now());
+
+    // Never log the secret itself
+    info!(chat_id, "Generated TOTP secret");
+    Ok(())
+}
+
+/// Seed size used when /seed is given no byte count.
+const DEFAULT_SEED_BYTES: usize = 32;
+/// Largest seed /seed will generate.
+const MAX_SEED_BYTES: usize = 64;
+
+/// A parsed /seed request.
+#[derive(Debug, PartialEq, Eq)]
+struct SeedRequest {
+    bytes: usize,
+    /// Show hex, base64 and base58 instead of hex alone.
+    all: bool,
+}
+
+/// Parse `/seed [bytes] [--all]`.
+fn parse_seed_args(args: &str) -> Result<SeedRequest> {
+    let mut request = SeedRequest {
+        bytes: DEFAULT_SEED_BYTES,
+        all: false,
+    };
+    for part in args.split_whitespace() {
+        match part {
+            "--all" => request.all = true,
+            _ => {
+                request.bytes = normalize_digits(part).parse::<usize>().map_err(|_| {
+                    BotError::PasswordGeneration(format!("Unknown option: {}", part))
+                })?;
+            }
+        }
+    }
+
+    if !(1..=MAX_SEED_BYTES).contains(&request.bytes) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Seed size must be between 1 and {} bytes",
+            MAX_SEED_BYTES
+        )));
+    }
+    Ok(request)
+}
+
+/// Render `seed` in each requested encoding, with code entities over the values.
+///
+/// Every encoding is computed from the same bytes.
+fn build_seed_reply(seed: &[u8], all: bool) -> (String, Vec<MessageEntity>) {
+    let mut encodings = vec![("Hex", hex_encode(seed))];
+    if all {
+        encodings.push(("Base64", base64_encode(seed)));
+        encodings.push(("Base58", base58_encode(seed)));
+    }
+
+    let mut text = format!(
+        "🌱 Random seed: {} bytes ({} bits of entropy)\n",
+        seed.len(),
+        seed.len() * 8
+    );
+    let mut entities = Vec::new();
+    for (label, value) in encodings {
+        text.push_str(&format!("\n{}:\n", label));
+        entities.push(MessageEntity::code(utf16_len(&text), utf16_len(&value)));
+        text.push_str(&value);
+        text.push('\n');
+    }
+    if all {
+        text.push_str("\nAll three encode the same bytes; use whichever your tool accepts.");
+    }
+    (text.trim_end().to_string(), entities)
+}
+
+/// Handler for the /seed command.
+pub async fn handle_seed(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    let request = match parse_seed_args(&args) {
+        Ok(request) => request,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let mut seed = Zeroizing::new(vec![0u8; request.bytes]);
+    OsRng.fill_bytes(&mut seed);
+    let (text, entities) = build_seed_reply(&seed, request.all);
+
+    let sent = bot
+        .send_message(msg.chat.id, text)
+        .entities(entities)
+        .reply_markup(password_keyboard())
+        .await?;
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id, sent.id, Instant::now());
+
+    info!(chat_id, bytes = request.bytes, "Generated seed");
+    Ok(())
+}
+
+/// Token size used when /token is given no byte count.
+const DEFAULT_TOKEN_BYTES: usize = 32;
+
+/// Encodings /token can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenFormat {
+    Hex,
+    Base64,
+}
+
+impl TokenFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenFormat::Hex => "hex",
+            TokenFormat::Base64 => "base64",
+        }
+    }
+
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            TokenFormat::Hex => hex_encode(bytes),
+            TokenFormat::Base64 => base64_encode(bytes),
+        }
+    }
+}
+
+/// A parsed /token request.
+#[derive(Debug, PartialEq, Eq)]
+struct TokenRequest {
+    bytes: usize,
+    format: TokenFormat,
+}
+
+/// Parse `/token [bytes] [--hex|--base64]`, allowing at most `max_bytes`.
+fn parse_token_args(args: &str, max_bytes: usize) -> Result<TokenRequest> {
+    let mut bytes = DEFAULT_TOKEN_BYTES.min(max_bytes);
+    let mut format = None;
+    for part in args.split_whitespace() {
+        let chosen = match part {
+            "--hex" => TokenFormat::Hex,
+            "--base64" => TokenFormat::Base64,
+            _ => {
+                bytes = normalize_digits(part).parse::<usize>().map_err(|_| {
+                    BotError::PasswordGeneration(format!("Unknown option: {}", part))
+                })?;
+                continue;
+            }
+        };
+        if format.is_some_and(|f| f != chosen) {
+            return Err(BotError::PasswordGeneration(
+                "Choose one of --hex or --base64".to_string(),
+            ));
+        }
+        format = Some(chosen);
+    }
+
+    if !(1..=max_bytes).contains(&bytes) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Token size must be between 1 and {} bytes",
+            max_bytes
+        )));
+    }
+    Ok(TokenRequest {
+        bytes,
+        format: format.unwrap_or(TokenFormat::Hex),
+    })
+}
+
+/// Metadata line for a token: every byte is uniformly random, so entropy is `bytes × 8`.
+fn format_token_metadata(
+    bytes: usize,
+    format: TokenFormat,
+    thresholds: StrengthThresholds,
+) -> String {
+    let bits = bytes * 8;
+    format!(
+        "Bytes: {} | Format: {} | Entropy: {} bits | Strength: {}",
+        bytes,
+        format.as_str(),
+        bits,
+        strength_for_entropy(bits as f64, thresholds).as_str()
+    )
+}
+
+/// Build the /token reply, with the token in a monospace block.
+fn build_token_reply(token: &str, metadata: &str) -> (String, Vec<MessageEntity>) {
+    let header = "🔑 Your API token:\n\n";
+    let text = format!(
+        "{}{}\n\n{}\n\n⚠️ Store it in your secret manager now; this message stays in your \
+        chat history.",
+        header, token, metadata
+    );
+    let entities = vec![MessageEntity::pre(None, utf16_len(header), utf16_len(token))];
+    (text, entities)
+}
+
+/// Handler for the /token command: random bytes encoded for use as an API key.
+pub async fn handle_token(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    let request = match parse_token_args(&args, state.config.max_token_bytes) {
+        Ok(request) => request,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let mut bytes = Zeroizing::new(vec![0u8; request.bytes]);
+    OsRng.fill_bytes(&mut bytes);
+    let token = Zeroizing::new(request.format.encode(&bytes));
+    let metadata = format_token_metadata(
+        request.bytes,
+        request.format,
+        state.config.strength_thresholds(),
+    );
+    let (text, entities) = build_token_reply(&token, &metadata);
+
+    let sent = bot
+        .send_message(msg.chat.id, text)
+        .entities(entities)
+        .reply_markup(password_keyboard())
+        .await?;
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id, sent.id, Instant::now());
+
+    // Log metadata only (never log the token)
+    info!(
+        chat_id,
+        bytes = request.bytes,
+        format = request.format.as_str(),
+        "Generated token"
+    );
+    Ok(())
+}
+
+/// First code point of each Unicode decimal digit block we accept in lengths.
+const DIGIT_BLOCK_STARTS: &[u32] = &[
+    0xFF10, // Full-width
+    0x0660, // Arabic-Indic
+    0x06F0, // Extended Arabic-Indic
+    0x0966, // Devanagari
+    0x09E6, // Bengali
+];
+
+/// Map full-width and other common Unicode decimal digits to ASCII.
+///
+/// Other characters are kept as-is, so non-numeric tokens still fail to parse.
+fn normalize_digits(token: &str) -> String {
+    token
+        .chars()
+        .map(|c| {
+            let code = c as u32;
+            DIGIT_BLOCK_STARTS
+                .iter()
+                .find(|&&start| (start..start + 10).contains(&code))
+                .and_then(|&start| char::from_digit(code - start, 10))
+                .unwrap_or(c)
+        })
+        .collect()
+}
+
+/// Apply an explicit `--<class>` / `--no-<class>` toggle.
+///
+/// Repeating the same flag is fine, but combining a flag with its negation is
+/// rejected instead of letting the last one silently win.
+fn set_toggle(
+    target: &mut bool,
+    explicit: &mut Option<bool>,
+    value: bool,
+    class: &str,
+) -> Result<()> {
+    if explicit.is_some_and(|previous| previous != value) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Conflicting flags: --{} and --no-{}",
+            class, class
+        )));
+    }
+
+    *explicit = Some(value);
+    *target = value;
+    Ok(())
+}
+
+/// Length of a string in UTF-16 code units, as required by Telegram entity offsets.
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+/// Build message entities that style each character class of `password`.
+///
+/// `offset` is the UTF-16 offset of the password within the message text.
+/// Consecutive characters of the same class are merged into a single entity:
+/// digits are bold, symbols italic and uppercase letters underlined, while
+/// lowercase letters are left plain.
+pub fn annotation_entities(password: &str, offset: usize) -> Vec<MessageEntity> {
+    fn style(c: char) -> Option<MessageEntityKind> {
+        if c.is_ascii_digit() {
+            Some(MessageEntityKind::Bold)
+        } else if c.is_uppercase() {
+            Some(MessageEntityKind::Underline)
+        } else if c.is_lowercase() {
+            None
+        } else {
+            Some(MessageEntityKind::Italic)
+        }
+    }
+
+    let mut entities: Vec<MessageEntity> = Vec::new();
+    let mut previous: Option<MessageEntityKind> = None;
+    let mut pos = offset;
+
+    for c in password.chars() {
+        let width = c.len_utf16();
+        let kind = style(c);
+        match (&kind, entities.last_mut()) {
+            (Some(kind), Some(last)) if previous.as_ref() == Some(kind) => last.length += width,
+            (Some(kind), _) => entities.push(MessageEntity::new(kind.clone(), pos, width)),
+            (None, _) => {}
+        }
+        previous = kind;
+        pos += width;
+    }
+
+    entities
+}
+
+/// Supplementary strength figure shown after the entropy-based metadata.
+#[cfg(feature = "zxcvbn")]
+fn supplementary_metadata(password: &str) -> Option<String> {
+    Some(format!(
+        "zxcvbn: 10^{:.1} guesses",
+        crate::password::zxcvbn_guesses_log10(password)
+    ))
+}
+
+/// Supplementary strength figure shown after the entropy-based metadata.
+#[cfg(not(feature = "zxcvbn"))]
+fn supplementary_metadata(_password: &str) -> Option<String> {
+    None
+}
+
+/// Size of the groups shown by `--memorize`.
+const MEMORIZE_GROUP_SIZE: usize = 4;
+
+/// Build the `--memorize` aid: one line per group with its phonetic readout.
+fn memorize_aid(password: &str) -> String {
+    let lines: Vec<String> = group_password(password, MEMORIZE_GROUP_SIZE, " ")
+        .split(' ')
+        .map(|group| {
+            let spoken: Vec<String> = group.chars().map(phonetic).collect();
+            format!("{} — {}", group, spoken.join(" · "))
+        })
+        .collect();
+
+    format!("🧠 Memorize in groups:\n{}", lines.join("\n"))
+}
+
+/// Separator for the `--dashes` copy: `-` unless the password itself contains one.
+fn typing_separator(password: &str) -> &'static str {
+    if password.contains('-') {
+        " "
+    } else {
+        "-"
+    }
+}
+
+/// Build the reply text (and entities, if any) for a generated password.
+fn build_password_reply(
+    password: &str,
+    strength: PasswordStrength,
+    metadata: &str,
+    display: &DisplayOptions,
+) -> (String, Vec<MessageEntity>) {
+    let strength_emoji = match strength {
+        PasswordStrength::Strong => "💪",
+        PasswordStrength::Medium => "👍",
+        PasswordStrength::Weak => "⚠️",
+    };
+
+    let header = "🔐 Your Secure Password:\n\n";
+    let summary = format!("\n\n{} {}", strength_emoji, metadata);
+
+    let offset = utf16_len(header);
+    let mut entities = if display.annotate {
+        annotation_entities(password, offset)
+    } else {
+        vec![code_entity(header, password)]
+    };
+    let extras_offset = offset + utf16_len(password) + utf16_len(&summary);
+    let (extras, extras_entities) = display_extras(password, display, extras_offset);
+    entities.extend(extras_entities);
+    if display.spoiler {
+        entities.push(MessageEntity::spoiler(offset, utf16_len(password)));
+        entities.extend(extras_spoiler(extras_offset, &extras));
+    }
+
+    let text = format!(
+        "{}{}{}{}\n\n⚠️ Security reminder: Copy this password immediately and store it securely. This message will remain in your chat history.",
+        header, password, summary, extras
+    );
+    (text, entities)
+}
+
+/// Code entity over `password` placed right after `before` in the message text.
+///
+/// Passwords are styled with entities rather than a parse mode, so backticks and
+/// other markup characters in them need no escaping.
+fn code_entity(before: &str, password: &str) -> MessageEntity {
+    MessageEntity::code(utf16_len(before), utf16_len(password))
+}
+
+/// Spoiler over the [`display_extras`] at `offset`, as `--dashes` and `--memorize`
+/// spell the password out again; `None` if there are none.
+fn extras_spoiler(offset: usize, extras: &str) -> Option<MessageEntity> {
+    (!extras.is_empty()).then(|| MessageEntity::spoiler(offset, utf16_len(extras)))
+}
+
+/// The optional `--dashes` and `--memorize` sections appended to a reply, with
+/// the entities styling them; `offset` is where they start in the message.
+fn display_extras(
+    password: &str,
+    display: &DisplayOptions,
+    offset: usize,
+) -> (String, Vec<MessageEntity>) {
+    let mut entities = Vec::new();
+    let dashes = match display.dashes {
+        Some(size) => {
+            let separator = typing_separator(password);
+            let label = "\n\n⌨️ For typing: ";
+            let typed = group_password(password, size, separator);
+            let entity = code_entity(label, &typed);
+            entities.push(MessageEntity::code(offset + entity.offset, entity.length));
+            format!(
+                "{}{}\n(separators are not part of the password and add no entropy)",
+                label, typed
+            )
+        }
+        None => String::new(),
+    };
+    let memorize = if display.memorize {
+        format!("\n\n{}", memorize_aid(password))
+    } else {
+        String::new()
+    };
+    let keyboard = if display.reply_keyboard {
+        "\n\n📲 Tap the keyboard button below to send the password as your own message, \
+        which you can then copy. The bot removes the keyboard after that; /panic does too."
+    } else {
+        ""
+    };
+    (format!("{}{}{}", dashes, memorize, keyboard), entities)
+}
+
+/// The `--json` follow-up: `config`'s metadata as a JSON object in a code block.
+///
+/// Built from the settings only, so the password never appears in it.
+fn metadata_json(config: &PasswordConfig, strength: PasswordStrength) -> (String, MessageEntity) {
+    let json = serde_json::to_string_pretty(&PasswordMetadata::new(config, strength))
+        .expect("metadata serializes");
+    let entity = MessageEntity::pre(Some("json".to_string()), 0, utf16_len(&json));
+    (json, entity)
+}
+
+/// Send the [`metadata_json`] follow-up for `config`.
+async fn send_metadata_json(
+    bot: &Bot,
+    chat_id: ChatId,
+    config: &PasswordConfig,
+    strength: PasswordStrength,
+) -> ResponseResult<()> {
+    let (json, entity) = metadata_json(config, strength);
+    bot.send_message(chat_id, json).entities([entity]).await?;
+    Ok(())
+}
+
+/// The `--explain` note comparing brute-force and policy-aware entropy.
+fn explain_entropy(config: &PasswordConfig) -> String {
+    let naive = entropy_bits(config);
+    let aware = policy_aware_entropy(config);
+    format!(
+        "🧮 Policy-aware entropy: {:.1} bits. An attacker who knows this bot's rules \
+        (length, character types, required classes) only has to search passwords \
+        that follow them, {:.1} bits fewer than the {:.1}-bit brute-force estimate.",
+        aware,
+        naive - aware,
+        naive
+    )
+}
+
+/// Build the reply from an operator `REPLY_TEMPLATE`.
+///
+/// Every `{password}` is formatted as code, or styled per class with `--annotate`,
+/// and spoilered with `USE_SPOILER_FORMATTING`.
+fn build_templated_reply(
+    tmpl: &str,
+    ctx: &ReplyContext,
+    display: &DisplayOptions,
+) -> (String, Vec<MessageEntity>) {
+    let mut entities = Vec::new();
+    for position in password_positions(tmpl) {
+        let offset = utf16_len(&render_template(&tmpl[..position], ctx));
+        if display.annotate {
+            entities.extend(annotation_entities(ctx.password, offset));
+        } else {
+            entities.push(MessageEntity::code(offset, utf16_len(ctx.password)));
+        }
+        if display.spoiler {
+            entities.push(MessageEntity::spoiler(offset, utf16_len(ctx.password)));
+        }
+    }
+
+    let rendered = render_template(tmpl, ctx);
+    let (extras, extras_entities) = display_extras(ctx.password, display, utf16_len(&rendered));
+    entities.extend(extras_entities);
+    if display.spoiler {
+        entities.extend(extras_spoiler(utf16_len(&rendered), &extras));
+    }
+    (format!("{}{}", rendered, extras), entities)
+}
+
+/// Build the reply for a `config` whose length is outside `min..=max`, or `None` if
+/// it is in bounds.
+///
+/// Given `stored` (when `SUGGEST_NEAREST_LENGTH` is on), the reply carries a button
+/// that generates a password with the same options at the nearest valid length
+/// instead. It uses the 🔄 Regenerate callback data, so the options travel with it.
+fn length_bounds_reply(
+    config: &PasswordConfig,
+    min: usize,
+    max: usize,
+    stored: Option<&mut StoredConfigs>,
+) -> Option<(String, Option<InlineKeyboardMarkup>)> {
+    let length = config.length;
+    let (text, nearest) = if length < min {
+        (
+            format!("❌ Password length too short. Minimum: {} characters.", min),
+            min,
+        )
+    } else if length > max {
+        (
+            format!("❌ Password length too long. Maximum: {} characters.", max),
+            max,
+        )
+    } else {
+        return None;
+    };
+
+    let clamped = PasswordConfig {
+        length: nearest,
+        ..config.clone()
+    };
+    let keyboard = stored
+        .and_then(|stored| regenerate_data(&clamped, stored, &mut OsRng))
+        .map(|data| {
+            InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+                format!("Generate with {} instead?", nearest),
+                data,
+            )]])
+        });
+
+    Some((text, keyboard))
+}
+
+/// Build the confirmation prompt for a length above `threshold`, or `None` to
+/// generate immediately.
+fn confirmation_prompt(
+    length: usize,
+    threshold: Option<usize>,
+) -> Option<(String, InlineKeyboardMarkup)> {
+    if length <= threshold? {
+        return None;
+    }
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            format!("✅ Generate {}-char password", length),
+            "confirm_length",
+        ),
+        InlineKeyboardButton::callback("✖ Cancel", "cancel_length"),
+    ]]);
+    Some((
+        format!("Generate a {}-character password? That's unusually long.", length),
+        keyboard,
+    ))
+}
+
+/// Handler for the /pass and /password commands.
+pub async fn handle_password(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    // A bare /pass uses the options saved with /setdefault, if any
+    let args = if args.trim().is_empty() {
+        state.preferences.lock().await.get(chat_id).map_or(args, str::to_string)
+    } else {
+        args
+    };
+
+    let lang = state.language(chat_id, msg.from()).await;
+
+    // Parse arguments
+    let PasswordRequest {
+        config: mut password_config,
+        display,
+        exact,
+        count,
+        bits,
+        length_warning,
+        preview,
+    } = match parse_bounded_password_args(
+        &args,
+        &state.config.base_password_config(),
+        state.config.max_args_len,
+    ) {
+        Ok(request) => request,
+        Err(e) => {
+            let error_msg = state
+                .locales
+                .get(lang.as_deref(), "pass_usage")
+                .replace("{error}", &e.to_string());
+            bot.send_message(msg.chat.id, error_msg)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if let Some(warning) = length_warning {
+        bot.send_message(msg.chat.id, warning).await?;
+    }
+    if password_config.use_emoji {
+        bot.send_message(msg.chat.id, EMOJI_WARNING).await?;
+    }
+
+    // A reply keyboard would be shown to everyone in a group
+    if display.reply_keyboard && !msg.chat.is_private() {
+        bot.send_message(msg.chat.id, state.locales.get(lang.as_deref(), "reply_keyboard_private"))
+            .await?;
+        return Ok(());
+    }
+
+    if count > state.config.max_batch_count {
+        let error_msg = state
+            .locales
+            .get(lang.as_deref(), "too_many_passwords")
+            .replace("{max}", &state.config.max_batch_count.to_string());
+        bot.send_message(msg.chat.id, error_msg).await?;
+        return Ok(());
+    }
+
+    // A --bits target picks the length, so it is fitted to the bounds instead of rejected
+    if let Some(bits) = bits {
+        if let Some(warning) = fit_bits_length(
+            &mut password_config,
+            bits,
+            state.config.min_password_length,
+            state.config.max_password_length,
+        ) {
+            bot.send_message(msg.chat.id, warning).await?;
+        }
+    }
+
+    if let Some(error_msg) = pattern_length_error(
+        &password_config,
+        state.config.min_password_length,
+        state.config.max_password_length,
+    ) {
+        bot.send_message(msg.chat.id, error_msg).await?;
+        return Ok(());
+    }
+
+    // Validate length bounds
+    let bounds_reply = {
+        let mut stored = state.stored_configs.lock().await;
+        length_bounds_reply(
+            &password_config,
+            state.config.min_password_length,
+            state.config.max_password_length,
+            state.config.suggest_nearest_length.then_some(&mut *stored),
+        )
+    };
+    if let Some((error_msg, keyboard)) = bounds_reply {
+        let mut request = bot.send_message(msg.chat.id, error_msg);
+        if let Some(keyboard) = keyboard {
+            request = request.reply_markup(keyboard);
+        }
+        request.await?;
+        return Ok(());
+    }
+
+    // Validate configuration
+    if let Err(e) = password_config.validate() {
+        let error_msg = state
+            .locales
+            .get(lang.as_deref(), "config_error")
+            .replace("{error}", &e.to_string());
+        bot.send_message(msg.chat.id, error_msg).await?;
+        return Ok(());
+    }
+
+    // An explicit --bits target is honored as given, like --exact
+    if state.config.auto_strengthen && !exact && bits.is_none() {
+        if let Some(notice) = auto_strengthen(&mut password_config, state.config.max_password_length)
+        {
+            bot.send_message(msg.chat.id, notice).await?;
+        }
+    }
+
+    if preview {
+        bot.send_message(msg.chat.id, preview_reply(&password_config, count)).await?;
+        return Ok(());
+    }
+
+    // Ask before generating unusually long passwords; the limits are charged on confirming
+    if let Some((prompt, keyboard)) = confirmation_prompt(
+        password_config.length,
+        state.config.confirm_length_threshold,
+    ) {
+        state.confirmations.lock().await.insert(
+            user_id,
+            PendingRequest {
+                chat_id: msg.chat.id,
+                config: password_config,
+                display,
+                count,
+                created: Instant::now(),
+            },
+        );
+        bot.send_message(msg.chat.id, prompt)
+            .reply_markup(keyboard)
+            .await?;
+        return Ok(());
+    }
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, count).await? {
+        return Ok(());
+    }
+    let from = msg.from();
+    send_password_with_timeout(&bot, msg.chat.id, &state, password_config, display, count, from)
+        .await
+}
+
+/// Charge a batch of `count` passwords against the rate limit and daily quota: one
+/// generation per password with `BATCH_COUNTS_EACH`, otherwise one for the batch.
+///
+/// Tells the user about a refusal or a low quota; returns whether to go ahead.
+/// Single-result commands charge a batch of 1.
+async fn charge_batch(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    user_id: u64,
+    count: usize,
+) -> ResponseResult<bool> {
+    let cost = if state.config.batch_counts_each {
+        count
+    } else {
+        1
+    };
+    match state.check_limits_for(chat_id.0, user_id, cost).await {
+        Ok(Some(notice)) => {
+            bot.send_message(chat_id, notice).await?;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            bot.send_message(chat_id, e.to_string()).await?;
+            warn!("Rate limit exceeded for chat {}: {}", chat_id, e);
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The `--preview` reply: what `config` would generate, without generating it.
+fn preview_reply(config: &PasswordConfig, count: usize) -> String {
+    let mut lines = vec![
+        "🔍 Preview, no password generated:".to_string(),
+        format_metadata(config, estimate_strength(config)),
+    ];
+    if config.pattern.is_none() && !config.pronounceable {
+        let pool: String = config.build_char_pool().into_iter().collect();
+        lines.push(format!("Pool: {}", pool));
+    }
+    let minimums: Vec<String> = CharClass::ALL
+        .iter()
+        .filter(|&&class| config.class_minimum(class) > 0)
+        .map(|&class| format!("{} {}", config.class_minimum(class), class.name()))
+        .collect();
+    if !minimums.is_empty() {
+        lines.push(format!("At least: {}", minimums.join(", ")));
+    }
+    if let Some(max) = config.max_symbols {
+        lines.push(format!("At most {} symbols", max));
+    }
+    let rules: Vec<&str> = [
+        (config.exclude_ambiguous, "no ambiguous characters"),
+        (config.exclude_confusable_symbols, "no confusable symbols"),
+        (config.exclude_csv_unsafe, "CSV-safe"),
+        (config.interior_special, "letters on both edges"),
+        (config.no_repeat, "no repeated neighbours"),
+    ]
+    .into_iter()
+    .filter_map(|(on, rule)| on.then_some(rule))
+    .collect();
+    if !rules.is_empty() {
+        lines.push(format!("Rules: {}", rules.join(", ")));
+    }
+    if count > 1 {
+        lines.push(format!("Count: {}", count));
+    }
+    lines.push("Send the command again without --preview to generate.".to_string());
+    lines.join("\n")
+}
+
+/// Raise a Weak `config` to the shortest length that reaches Medium, up to `max_length`.
+///
+/// Returns a notice for the user if the length was changed.
+fn auto_strengthen(config: &mut PasswordConfig, max_length: usize) -> Option<String> {
+    // A pattern fixes the shape of the password; its length isn't ours to change
+    if config.pattern.is_some() || estimate_strength(config) != PasswordStrength::Weak {
+        return None;
+    }
+    let length = min_length_for_strength(config, PasswordStrength::Medium, max_length)?;
+    let notice = format!(
+        "📈 Length bumped {}→{} for a stronger result; use --exact to override.",
+        config.length, length
+    );
+    config.length = length;
+    Some(notice)
+}
+
+/// Run `fut` to completion unless it exceeds `limit`.
+pub async fn with_timeout<T>(limit: Duration, fut: impl Future<Output = T>) -> Result<T> {
+    tokio::time::timeout(limit, fut).await.map_err(|_| {
+        BotError::Timeout(format!(
+            "Request did not complete within {}s",
+            limit.as_secs_f64()
+        ))
+    })
+}
+
+/// Generate a password on the blocking thread pool.
+///
+/// Running generation off the async worker lets the request timeout fire even
+/// if a constraint-heavy configuration keeps the generator busy.
+async fn generate_off_thread(
+    config: PasswordConfig,
+    forbidden: Vec<String>,
+    mut rng: Box<dyn RngCore + Send>,
+) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        generate_password_avoiding(&config, &forbidden, &mut rng)
+    })
+    .await
+    .map_err(|e| BotError::PasswordGeneration(format!("Generation task failed: {}", e)))?
+}
+
+/// Draws allowed per requested password before a batch gives up on finding distinct ones.
+const BATCH_ATTEMPTS_PER_PASSWORD: usize = 10;
+
+/// Generate `count` distinct passwords, each from its own draw of `rng`.
+fn generate_distinct(
+    config: &PasswordConfig,
+    forbidden: &[String],
+    count: usize,
+    rng: &mut impl RngCore,
+) -> Result<Vec<String>> {
+    let mut passwords: Vec<String> = Vec::with_capacity(count);
+    for _ in 0..count * BATCH_ATTEMPTS_PER_PASSWORD {
+        if passwords.len() == count {
+            break;
+        }
+        let password = generate_password_avoiding(config, forbidden, rng)?;
+        if !passwords.contains(&password) {
+            passwords.push(password);
+        }
+    }
+    if passwords.len() < count {
+        return Err(BotError::PasswordGeneration(format!(
+            "Couldn't generate {} distinct passwords with these settings. \
+             Try a longer length or more character types.",
+            count
+        )));
+    }
+    Ok(passwords)
+}
+
+/// Generate a `--count` batch on the blocking thread pool.
+async fn generate_batch_off_thread(
+    config: PasswordConfig,
+    forbidden: Vec<String>,
+    count: usize,
+    mut rng: Box<dyn RngCore + Send>,
+) -> Result<Vec<String>> {
+    tokio::task::spawn_blocking(move || {
+        generate_distinct(&config, &forbidden, count, &mut rng)
+    })
+    .await
+    .map_err(|e| BotError::PasswordGeneration(format!("Generation task failed: {}", e)))?
+}
+
+/// Generate a password and send it, bounded by the configured request timeout.
+async fn send_password_with_timeout(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    config: PasswordConfig,
+    display: DisplayOptions,
+    count: usize,
+    from: Option<&User>,
+) -> ResponseResult<()> {
+    let limit = Duration::from_secs(state.config.request_timeout_secs);
+    let send = async {
+        if count > 1 {
+            send_password_batch(bot, chat_id, state, config, display, count, from).await
+        } else {
+            send_password(bot, chat_id, state, config, display, from).await
+        }
+    };
+    match with_timeout(limit, send).await {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Password request for chat {} timed out: {}", chat_id, e);
+            state.stats.lock().await.record_error();
+            let lang = state.language(chat_id.0, from).await;
+            bot.send_message(chat_id, state.locales.get(lang.as_deref(), "request_timed_out"))
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Generate a password for `config` and send it to `chat_id`.
+async fn send_password(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    config: PasswordConfig,
+    display: DisplayOptions,
+    from: Option<&User>,
+) -> ResponseResult<()> {
+    let lang = state.language(chat_id.0, from).await;
+    let display = DisplayOptions {
+        spoiler: state.config.use_spoiler_formatting,
+        ..display
+    };
+    // Generate password using cryptographically secure RNG
+    let forbidden = state.config.forbidden_substrings.clone();
+    let password =
+        match generate_off_thread(config.clone(), forbidden, state.rng.rng()).await {
+            Ok(pwd) => pwd,
+            Err(e) => {
+                state.stats.lock().await.record_error();
+                let error_msg = state
+                    .locales
+                    .get(lang.as_deref(), "generation_failed")
+                    .replace("{error}", &e.to_string());
+                bot.send_message(chat_id, error_msg).await?;
+                return Ok(());
+            }
+        };
+
+    // Estimate strength
+    let strength = estimate_strength(&config);
+    let metadata = format_metadata(&config, strength);
+
+    // The naive entropy estimate stays primary; zxcvbn (if enabled) is appended
+    let mut shown_metadata = match supplementary_metadata(&password) {
+        Some(extra) => format!("{} | {}", metadata, extra),
+        None => metadata.clone(),
+    };
+    if let Some(tip) = improvement_suggestion(&config) {
+        shown_metadata.push_str(&format!("\n💡 {}", tip));
+    }
+    if display.explain {
+        shown_metadata.push_str(&format!("\n{}", explain_entropy(&config)));
+    }
+    let user_id = from.map_or(chat_id.0 as u64, |u| u.id.0);
+    let repeats = state.count_repeats(user_id, slice::from_ref(&password)).await;
+    if let Some(warning) = duplicate_warning(repeats, 1) {
+        shown_metadata.push_str(&format!("\n{}", warning));
+    }
+
+    // Format response (send password in monospace for better readability)
+    let (mut response, entities) = match &state.config.reply_template {
+        Some(tmpl) => {
+            let ctx = ReplyContext {
+                password: &password,
+                strength,
+                entropy: policy_aware_entropy(&config),
+                metadata: &shown_metadata,
+            };
+            build_templated_reply(tmpl, &ctx, &display)
+        }
+        None => build_password_reply(&password, strength, &shown_metadata, &display),
+    };
+    if let Some(secs) = state.config.auto_delete_seconds {
+        response.push_str(&self_destruct_note(secs));
+    }
+
+    // A message carries one keyboard, so the reply keyboard replaces the Clear button
+    let markup = if display.reply_keyboard {
+        ReplyMarkup::Keyboard(password_reply_keyboard(&password))
+    } else {
+        let data = regenerate_data(&config, &mut *state.stored_configs.lock().await, &mut OsRng);
+        ReplyMarkup::InlineKeyboard(regenerate_keyboard(data))
+    };
+    let mut request = bot.send_message(chat_id, response).reply_markup(markup);
+    if !entities.is_empty() {
+        request = request.entities(entities);
+    }
+    let sent = request.await?;
+    if display.reply_keyboard {
+        state.reply_keyboards.lock().await.insert(chat_id.0);
+    }
+    if let Some(secs) = state.config.auto_delete_seconds {
+        schedule_delete(bot, chat_id, sent.id, Duration::from_secs(secs));
+    }
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id.0, sent.id, Instant::now());
+    if display.qr {
+        send_password_qr(bot, chat_id, state, &password).await?;
+    }
+    if display.json {
+        send_metadata_json(bot, chat_id, &config, strength).await?;
+    }
+    state.history.lock().await.record(
+        chat_id.0,
+        HistoryEntry {
+            at: Instant::now(),
+            length: password.chars().count(),
+            strength,
+        },
+    );
+    state.stats.lock().await.record_passwords(1);
+
+    // Log metadata only (never log the actual password)
+    info!(
+        chat_id = chat_id.0,
+        length = password.chars().count(),
+        strength = strength.as_str(),
+        pool_size = logged_pool_size(&config),
+        entropy_bits = entropy_bits(&config),
+        "Generated password"
+    );
+    let record = AuditRecord {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        chat_id: chat_id.0,
+        length: password.chars().count(),
+        strength: strength.as_str(),
+    };
+    let key = state.config.audit_hmac_key.as_deref().map(str::as_bytes);
+    info!(target: "audit", "{}", format_audit_line(&record, key));
+
+    Ok(())
+}
+
+/// Generate `count` passwords for `config` and send them as one numbered list.
+///
+/// The metadata is shared by the whole batch, so it is shown once below the list.
+async fn send_password_batch(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    config: PasswordConfig,
+    display: DisplayOptions,
+    count: usize,
+    from: Option<&User>,
+) -> ResponseResult<()> {
+    let lang = state.language(chat_id.0, from).await;
+    let forbidden = state.config.forbidden_substrings.clone();
+    let generate = generate_batch_off_thread(config.clone(), forbidden, count, state.rng.rng());
+    let passwords = match generate.await {
+        Ok(passwords) => passwords,
+        Err(e) => {
+            state.stats.lock().await.record_error();
+            let error_msg = state
+                .locales
+                .get(lang.as_deref(), "generation_failed")
+                .replace("{error}", &e.to_string());
+            bot.send_message(chat_id, error_msg).await?;
+            return Ok(());
+        }
+    };
+
+    let strength = estimate_strength(&config);
+    let metadata = format_metadata(&config, strength);
+    let mut shown_metadata = metadata.clone();
+    if let Some(tip) = improvement_suggestion(&config) {
+        shown_metadata.push_str(&format!("\n💡 {}", tip));
+    }
+    if display.explain {
+        shown_metadata.push_str(&format!("\n{}", explain_entropy(&config)));
+    }
+    let user_id = from.map_or(chat_id.0 as u64, |u| u.id.0);
+    let repeats = state.count_repeats(user_id, &passwords).await;
+    if let Some(warning) = duplicate_warning(repeats, passwords.len()) {
+        shown_metadata.push_str(&format!("\n{}", warning));
+    }
+
+    let (mut response, entities) = build_batch_reply(
+        &passwords,
+        strength,
+        &shown_metadata,
+        state.config.use_spoiler_formatting,
+    );
+    if let Some(secs) = state.config.auto_delete_seconds {
+        response.push_str(&self_destruct_note(secs));
+    }
+    let sent = bot
+        .send_message(chat_id, response)
+        .entities(entities)
+        .reply_markup(password_keyboard())
+        .await?;
+    if let Some(secs) = state.config.auto_delete_seconds {
+        schedule_delete(bot, chat_id, sent.id, Duration::from_secs(secs));
+    }
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id.0, sent.id, Instant::now());
+
+    record_batch(state, chat_id, &passwords, strength).await;
+    if display.json {
+        send_metadata_json(bot, chat_id, &config, strength).await?;
+    }
+
+    // Log metadata only (never log the actual passwords)
+    info!(
+        chat_id = chat_id.0,
+        count = passwords.len(),
+        length = config.length,
+        strength = strength.as_str(),
+        pool_size = logged_pool_size(&config),
+        entropy_bits = entropy_bits(&config),
+        "Generated passwords"
+    );
+
+    Ok(())
+}
+
+/// Add each password of a batch to the chat's history, audit log and usage stats.
+async fn record_batch(
+    state: &BotState,
+    chat_id: ChatId,
+    passwords: &[String],
+    strength: PasswordStrength,
+) {
+    let now = Instant::now();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let key = state.config.audit_hmac_key.as_deref().map(str::as_bytes);
+    let mut history = state.history.lock().await;
+    for password in passwords {
+        // Pattern passwords vary in length, so record what was actually sent
+        let length = password.chars().count();
+        history.record(
+            chat_id.0,
+            HistoryEntry {
+                at: now,
+                length,
+                strength,
+            },
+        );
+        let record = AuditRecord {
+            timestamp,
+            chat_id: chat_id.0,
+            length,
+            strength: strength.as_str(),
+        };
+        info!(target: "audit", "{}", format_audit_line(&record, key));
+    }
+    drop(history);
+    state.stats.lock().await.record_passwords(passwords.len());
+}
+
+/// Build the reply for a `--count` batch: a numbered list in one monospace block.
+fn build_batch_reply(
+    passwords: &[String],
+    strength: PasswordStrength,
+    metadata: &str,
+    spoiler: bool,
+) -> (String, Vec<MessageEntity>) {
+    let strength_emoji = match strength {
+        PasswordStrength::Strong => "💪",
+        PasswordStrength::Medium => "👍",
+        PasswordStrength::Weak => "⚠️",
+    };
+
+    let header = format!("🔐 Your {} Secure Passwords:\n\n", passwords.len());
+    let width = passwords.len().to_string().len();
+    let list = passwords
+        .iter()
+        .enumerate()
+        .map(|(i, password)| format!("{:>width$}. {}", i + 1, password, width = width))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let footer = format!(
+        "\n\n{} {}\n\n⚠️ Security reminder: Copy these passwords immediately and store them \
+        securely. This message will remain in your chat history.",
+        strength_emoji, metadata
+    );
+
+    let mut entities = vec![MessageEntity::pre(None, utf16_len(&header), utf16_len(&list))];
+    if spoiler {
+        entities.push(MessageEntity::spoiler(utf16_len(&header), utf16_len(&list)));
+    }
+    (format!("{}{}{}", header, list, footer), entities)
+}
+
+/// A parsed `/bulk` request.
+#[derive(Debug, Clone)]
+struct BulkRequest {
+    count: usize,
+    config: PasswordConfig,
+    /// Notes for the user about how the options were interpreted.
+    warnings: Vec<String>,
+}
+
+/// Parse `/bulk <count> [length] [options]` and validate it against the bot's limits.
+///
+/// The options are those of /pass, minus `--count` and the display options: a
+/// file has nothing to annotate, spoiler or render as a QR code.
+fn parse_bulk_args(
+    args: &str,
+    base: &PasswordConfig,
+    min: usize,
+    max: usize,
+    max_args_len: usize,
+    max_count: usize,
+) -> Result<BulkRequest> {
+    let args = args.trim();
+    let (count, options) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let count = match normalize_digits(count).parse::<usize>() {
+        Ok(n) if (1..=max_count).contains(&n) => n,
+        _ => {
+            return Err(BotError::PasswordGeneration(format!(
+                "Usage: /bulk <count> [length] [options], with a count from 1 to {}",
+                max_count
+            )))
+        }
+    };
+
+    let request = parse_bounded_password_args(options, base, max_args_len)?;
+    if request.count != 1 {
+        return Err(BotError::PasswordGeneration(
+            "Give the number of passwords as the first argument of /bulk, not with --count"
+                .to_string(),
+        ));
+    }
+    if request.display != DisplayOptions::default() {
+        return Err(BotError::PasswordGeneration(
+            "/bulk sends a plain text file, so display options don't apply".to_string(),
+        ));
+    }
+    if request.preview {
+        return Err(BotError::PasswordGeneration(
+            "--preview works with /pass, not /bulk".to_string(),
+        ));
+    }
+
+    let mut config = request.config;
+    let mut warnings: Vec<String> = request.length_warning.into_iter().collect();
+    if let Some(bits) = request.bits {
+        warnings.extend(fit_bits_length(&mut config, bits, min, max));
+    }
+    if let Some(error) = pattern_length_error(&config, min, max) {
+        return Err(BotError::PasswordGeneration(error));
+    }
+    if config.length < min || config.length > max {
+        return Err(BotError::PasswordGeneration(format!(
+            "Length must be between {} and {}",
+            min, max
+        )));
+    }
+    config.validate()?;
+    Ok(BulkRequest {
+        count,
+        config,
+        warnings,
+    })
+}
+
+/// Caption of a `/bulk` file: the shared metadata and a warning that the file is plaintext.
+fn bulk_caption(count: usize, metadata: &str) -> String {
+    format!(
+        "📄 {} passwords, one per line\n{}\n\n⚠️ This file is not encrypted: anyone who can \
+        read this chat can download it. Import the passwords into your password manager, then \
+        delete the file from your device and clear this message with /panic.",
+        count, metadata
+    )
+}
+
+/// Handler for the /bulk command: send many passwords at once as a `.txt` file.
+pub async fn handle_bulk(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+    let lang = state.language(chat_id, msg.from()).await;
+
+    let request = match parse_bulk_args(
+        &args,
+        &state.config.base_password_config(),
+        state.config.min_password_length,
+        state.config.max_password_length,
+        state.config.max_args_len,
+        state.config.max_bulk_count,
+    ) {
+        Ok(request) => request,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    // Charged like a --count batch of the same size
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, request.count).await? {
+        return Ok(());
+    }
+
+    for warning in &request.warnings {
+        bot.send_message(msg.chat.id, warning).await?;
+    }
+    if request.config.use_emoji {
+        bot.send_message(msg.chat.id, EMOJI_WARNING).await?;
+    }
+
+    let BulkRequest { count, config, .. } = request;
+    let limit = Duration::from_secs(state.config.request_timeout_secs);
+    let forbidden = state.config.forbidden_substrings.clone();
+    let generate = generate_batch_off_thread(config.clone(), forbidden, count, state.rng.rng());
+    let passwords = match with_timeout(limit, generate).await {
+        Ok(Ok(passwords)) => passwords,
+        Ok(Err(e)) => {
+            state.stats.lock().await.record_error();
+            let error_msg = state
+                .locales
+                .get(lang.as_deref(), "generation_failed")
+                .replace("{error}", &e.to_string());
+            bot.send_message(msg.chat.id, error_msg).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            warn!("Bulk request for chat {} timed out: {}", chat_id, e);
+            state.stats.lock().await.record_error();
+            bot.send_message(msg.chat.id, state.locales.get(lang.as_deref(), "request_timed_out"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let strength = estimate_strength(&config);
+    let mut caption = bulk_caption(count, &format_metadata(&config, strength));
+    if let Some(secs) = state.config.auto_delete_seconds {
+        caption.push_str(&self_destruct_note(secs));
+    }
+    let mut contents = Zeroizing::new(String::new());
+    for password in &passwords {
+        contents.push_str(password);
+        contents.push('\n');
+    }
+    let file = InputFile::memory(contents.as_bytes().to_vec()).file_name("passwords.txt");
+    let sent = bot
+        .send_document(msg.chat.id, file)
+        .caption(caption)
+        .reply_markup(password_keyboard())
+        .await?;
+    if let Some(secs) = state.config.auto_delete_seconds {
+        schedule_delete(&bot, msg.chat.id, sent.id, Duration::from_secs(secs));
+    }
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id, sent.id, Instant::now());
+    record_batch(&state, msg.chat.id, &passwords, strength).await;
+
+    // Log metadata only (never log the actual passwords)
+    info!(
+        chat_id,
+        count,
+        length = config.length,
+        strength = strength.as_str(),
+        pool_size = logged_pool_size(&config),
+        entropy_bits = entropy_bits(&config),
+        "Generated bulk password file"
+    );
+
+    Ok(())
+}
+
+/// Send `password` as a QR code photo below its text message.
+///
+/// The photo exposes the password like the text does, so it is spoilered, cleared
+/// by /panic and auto-deleted the same way. If rendering fails the text message
+/// already carries the password, so the user only gets a note.
+async fn send_password_qr(
+    bot: &Bot,
+    chat_id: ChatId,
+    state: &BotState,
+    password: &str,
+) -> ResponseResult<()> {
+    let png = match render_png(password) {
+        Ok(png) => png,
+        Err(e) => {
+            warn!("QR code for chat {} failed: {}", chat_id, e);
+            bot.send_message(
+                chat_id,
+                "⚠️ Couldn't render a QR code for this password; copy it from the message above.",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut caption = "📷 Scan to copy the password to another device.".to_string();
+    if let Some(secs) = state.config.auto_delete_seconds {
+        caption.push_str(&self_destruct_note(secs));
+    }
+    let sent = bot
+        .send_photo(chat_id, InputFile::memory(png).file_name("password.png"))
+        .caption(caption)
+        .has_spoiler(true)
+        .reply_markup(password_keyboard())
+        .await?;
+    if let Some(secs) = state.config.auto_delete_seconds {
+        schedule_delete(bot, chat_id, sent.id, Duration::from_secs(secs));
+    }
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id.0, sent.id, Instant::now());
+    Ok(())
+}
+
+/// Countdown note appended to password messages when `AUTO_DELETE_SECONDS` is set.
+fn self_destruct_note(secs: u64) -> String {
+    format!("\n\n⏳ This message self-destructs in {}s", secs)
+}
+
+/// Delete message `id` in `chat_id` once `after` has passed.
+///
+/// The user may have deleted it already, so a failed deletion is expected and ignored.
+fn schedule_delete(bot: &Bot, chat_id: ChatId, id: MessageId, after: Duration) {
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(after).await;
+        if bot.delete_message(chat_id, id).await.is_err() {
+            info!("Auto-delete in chat {}: message already gone", chat_id);
+        }
+    });
+}
+
+/// Pool size for the generation logs; `None` for patterns and pronounceable
+/// passwords, whose positions each draw from their own set.
+fn logged_pool_size(config: &PasswordConfig) -> Option<usize> {
+    (config.pattern.is_none() && !config.pronounceable).then(|| config.build_char_pool().len())
+}
+
+/// Inline keyboard attached to every password message.
+fn password_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "🧹 Clear",
+        "panic",
+    )]])
+}
+
+/// Telegram's limit on callback data, in bytes.
+const MAX_CALLBACK_DATA: usize = 64;
+
+/// Callback data prefix of the 🔄 Regenerate button.
+const REGENERATE_PREFIX: &str = "regen:";
+
+/// Callback data prefix of a 🔄 Regenerate button whose config is in [`StoredConfigs`].
+const STORED_CONFIG_PREFIX: &str = "cfg:";
+
+/// Flag letters for the boolean settings encoded in regenerate callback data.
+const REGENERATE_FLAGS: [char; 11] = ['l', 'u', 'd', 's', 'a', 'c', 'v', 'i', 'p', 'e', 'r'];
+
+/// The boolean settings of `config`, in [`REGENERATE_FLAGS`] order.
+fn regenerate_flag_fields(config: &mut PasswordConfig) -> [&mut bool; 11] {
+    [
+        &mut config.use_lowercase,
+        &mut config.use_uppercase,
+        &mut config.use_digits,
+        &mut config.use_symbols,
+        &mut config.exclude_ambiguous,
+        &mut config.exclude_confusable_symbols,
+        &mut config.exclude_csv_unsafe,
+        &mut config.interior_special,
+        &mut config.pronounceable,
+        &mut config.use_emoji,
+        &mut config.no_repeat,
+    ]
+}
+
+/// Encode `config` as `regen:<length>[,<minimums>]:<flags>[:<symbols>]` callback data.
+///
+/// Per-class minimums, if any are set, follow the length as four comma-separated
+/// counts in [`CharClass::ALL`] order. A custom symbol set is carried along; other
+/// custom sets aren't, so their configs (and any that don't fit in 64 bytes) get
+/// `None` and no button. The ambiguous set is the deployment's `AMBIGUOUS_CHARS`,
+/// restored from the base config on decode, so it needn't travel.
+fn encode_regenerate_data(config: &PasswordConfig) -> Option<String> {
+    if config.pattern.is_some() || config.max_symbols.is_some() || !config.exclude_chars.is_empty()
+    {
+        return None;
+    }
+    let defaults = CharSets::default();
+    let sets = &config.charsets;
+    if sets.lowercase != defaults.lowercase
+        || sets.uppercase != defaults.uppercase
+        || sets.digits != defaults.digits
+    {
+        return None;
+    }
+
+    let mut config = config.clone();
+    let flags: String = REGENERATE_FLAGS
+        .iter()
+        .zip(regenerate_flag_fields(&mut config))
+        .filter(|(_, on)| **on)
+        .map(|(flag, _)| flag)
+        .collect();
+    let mut size = config.length.to_string();
+    if CharClass::ALL.iter().any(|&class| config.class_minimum(class) > 0) {
+        for class in CharClass::ALL {
+            size.push_str(&format!(",{}", config.class_minimum(class)));
+        }
+    }
+    let mut data = format!("{}{}:{}", REGENERATE_PREFIX, size, flags);
+    if sets.symbols != defaults.symbols {
+        data.push_str(&format!(":{}", sets.symbols));
+    }
+    (data.len() <= MAX_CALLBACK_DATA).then_some(data)
+}
+
+/// Decode regenerate callback data on top of `base`, or `None` if it is malformed.
+fn decode_regenerate_data(data: &str, base: &PasswordConfig) -> Option<PasswordConfig> {
+    let mut parts = data.strip_prefix(REGENERATE_PREFIX)?.splitn(3, ':');
+    let mut size = parts.next()?.split(',');
+    let length = size.next()?.parse().ok()?;
+    let minimums: Vec<usize> = size.map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    if !minimums.is_empty() && minimums.len() != CharClass::ALL.len() {
+        return None;
+    }
+    let flags = parts.next()?;
+    if flags.chars().any(|c| !REGENERATE_FLAGS.contains(&c)) {
+        return None;
+    }
+
+    let mut config = PasswordConfig {
+        length,
+        ..base.clone()
+    };
+    for (flag, field) in REGENERATE_FLAGS.iter().zip(regenerate_flag_fields(&mut config)) {
+        *field = flags.contains(*flag);
+    }
+    let minimums = minimums.into_iter().chain(std::iter::repeat(0));
+    for (class, min) in CharClass::ALL.into_iter().zip(minimums) {
+        *config.class_minimum_mut(class) = min;
+    }
+    // Start from the base sets so a deployment's AMBIGUOUS_CHARS still applies
+    config.charsets = match parts.next() {
+        Some(symbols) => base.charsets.clone().with_symbols(parse_symbol_set(symbols).ok()?),
+        None => base.charsets.clone(),
+    };
+    Some(config)
+}
+
+/// Callback data for a 🔄 Regenerate button that repeats `config`.
+///
+/// Encoded inline when [`encode_regenerate_data`] can; otherwise `config` goes into
+/// `stored` and the button carries a `cfg:<id>` token instead. Telegram rejects a
+/// whole message whose callback data exceeds 64 bytes, so the result is checked
+/// here rather than trusted to the callers.
+fn regenerate_data(
+    config: &PasswordConfig,
+    stored: &mut StoredConfigs,
+    rng: &mut dyn RngCore,
+) -> Option<String> {
+    let data = encode_regenerate_data(config).unwrap_or_else(|| {
+        let id = stored.insert(config.clone(), rng);
+        format!("{}{:x}", STORED_CONFIG_PREFIX, id)
+    });
+    (data.len() <= MAX_CALLBACK_DATA).then_some(data)
+}
+
+/// The config behind 🔄 Regenerate callback `data`, decoded on top of `base` or looked
+/// up in `stored`; `None` if it is malformed or has been evicted.
+fn resolve_regenerate_data(
+    data: &str,
+    base: &PasswordConfig,
+    stored: &StoredConfigs,
+) -> Option<PasswordConfig> {
+    match data.strip_prefix(STORED_CONFIG_PREFIX) {
+        Some(id) => stored.get(u64::from_str_radix(id, 16).ok()?).cloned(),
+        None => decode_regenerate_data(data, base),
+    }
+}
+
+/// Password message keyboard: 🔄 Regenerate (with callback `data`, if any) and 🧹 Clear.
+fn regenerate_keyboard(data: Option<String>) -> InlineKeyboardMarkup {
+    let clear = InlineKeyboardButton::callback("🧹 Clear", "panic");
+    let row = match data {
+        Some(data) => vec![InlineKeyboardButton::callback("🔄 Regenerate", data), clear],
+        None => vec![clear],
+    };
+    InlineKeyboardMarkup::new(vec![row])
+}
+
+/// Handle a 🔄 Regenerate press: a fresh password with the message's settings.
+async fn handle_regenerate(
+    bot: &Bot,
+    q: &CallbackQuery,
+    state: &BotState,
+    data: &str,
+) -> ResponseResult<()> {
+    let base = state.config.base_password_config();
+    let resolved = resolve_regenerate_data(data, &base, &*state.stored_configs.lock().await);
+    let Some(config) = resolved else {
+        let mut answer = bot.answer_callback_query(&q.id);
+        if data.starts_with(STORED_CONFIG_PREFIX) {
+            answer = answer.text("This button has expired; send /pass again");
+        }
+        answer.await?;
+        return Ok(());
+    };
+
+    // Callback data comes from the client, so the bounds are checked again
+    let min_length = if config.is_pin() {
+        state.config.min_pin_length
+    } else {
+        state.config.min_password_length
+    };
+    if config.length < min_length || config.length > state.config.max_password_length {
+        bot.answer_callback_query(&q.id)
+            .text("Invalid password length")
+            .await?;
+        return Ok(());
+    }
+    if let Err(e) = config.validate() {
+        bot.answer_callback_query(&q.id).text(e.to_string()).await?;
+        return Ok(());
+    }
+
+    let chat_id = q
+        .message
+        .as_ref()
+        .map(|m| m.chat.id)
+        .unwrap_or_else(|| ChatId::from(q.from.id));
+    match state.check_limits(chat_id.0, q.from.id.0).await {
+        Ok(Some(notice)) => {
+            bot.send_message(chat_id, notice).await?;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            bot.answer_callback_query(&q.id).text(e.to_string()).await?;
+            return Ok(());
+        }
+    }
+    bot.answer_callback_query(&q.id).await?;
+
+    let display = DisplayOptions::default();
+    send_password_with_timeout(bot, chat_id, state, config, display, 1, Some(&q.from)).await
+}
+
+/// One-button reply keyboard whose button sends `password` when tapped.
+///
+/// It hides itself after one tap; [`remove_keyboard`] gets rid of it for good.
+fn password_reply_keyboard(password: &str) -> KeyboardMarkup {
+    KeyboardMarkup::new(vec![vec![KeyboardButton::new(password)]])
+        .one_time_keyboard(true)
+        .resize_keyboard(true)
+}
+
+/// Markup that takes a `--reply-keyboard` password button off the user's screen.
+fn remove_keyboard() -> KeyboardRemove {
+    KeyboardRemove::new()
+}
+
+/// Remove the password reply keyboard after the user has tapped it.
+///
+/// Returns `true` if `msg` was the tap (or any message following the keyboard).
+async fn handle_keyboard_tap(bot: &Bot, msg: &Message, state: &BotState) -> ResponseResult<bool> {
+    if !state.reply_keyboards.lock().await.remove(&msg.chat.id.0) {
+        return Ok(false);
+    }
+    bot.send_message(
+        msg.chat.id,
+        "⌨️ Keyboard removed. Copy the password from your message, then delete that message.",
+    )
+    .reply_markup(remove_keyboard())
+    .await?;
+    Ok(true)
+}
+
+/// Delete every tracked password message in `chat_id` and report the outcome.
+async fn clear_password_messages(bot: &Bot, chat_id: ChatId, state: &BotState) -> ResponseResult<()> {
+    let plan = state
+        .sent_messages
+        .lock()
+        .await
+        .take_for_panic(chat_id.0, Instant::now());
+
+    let mut deleted = 0;
+    let mut failed = 0;
+    for id in plan.deletable {
+        match bot.delete_message(chat_id, id).await {
+            Ok(_) => deleted += 1,
+            // Already deleted by the user, or otherwise gone
+            Err(_) => failed += 1,
+        }
+        tokio::time::sleep(PANIC_DELETE_INTERVAL).await;
+    }
+
+    let mut report = format!("🧹 Deleted {} password message(s).", deleted);
+    if plan.too_old > 0 {
+        report.push_str(&format!(
+            "\n{} message(s) are older than 48 hours and can't be deleted by the bot.",
+            plan.too_old
+        ));
+    }
+    if failed > 0 {
+        report.push_str(&format!("\n{} message(s) were already gone.", failed));
+    }
+
+    info!(
+        "Panic clear in chat {}: {} deleted, {} too old, {} failed",
+        chat_id, deleted, plan.too_old, failed
+    );
+    let mut request = bot.send_message(chat_id, report);
+    if state.reply_keyboards.lock().await.remove(&chat_id.0) {
+        request = request.reply_markup(remove_keyboard());
+    }
+    request.await?;
+    Ok(())
+}
+
+/// Handler for the /panic command.
+pub async fn handle_panic(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    clear_password_messages(&bot, msg.chat.id, &state).await
+}
+
+/// Upper bound on the number of passwords a single /bench run may generate.
+pub const MAX_BENCH_COUNT: usize = 100_000;
+
+/// Outcome of a generation benchmark.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    /// Number of passwords actually generated (after capping).
+    pub count: usize,
+    /// Total time spent generating.
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    /// Generated passwords per second.
+    pub fn rate(&self) -> f64 {
+        self.count as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Generate `count` passwords (capped at [`MAX_BENCH_COUNT`]) and time it.
+///
+/// The passwords are discarded immediately; they are never sent or logged.
+pub fn run_benchmark(config: &PasswordConfig, count: usize) -> Result<BenchResult> {
+    let count = count.min(MAX_BENCH_COUNT);
+    let mut rng = OsRng;
+
+    let start = Instant::now();
+    let passwords = generate_passwords(config, count, &mut rng)?;
+    let elapsed = start.elapsed();
+    drop(passwords);
+
+    Ok(BenchResult { count, elapsed })
+}
+
+/// Whether the sender of `msg` is listed in `ADMIN_USER_IDS`.
+fn is_admin(msg: &Message, config: &Config) -> bool {
+    msg.from()
+        .map(|user| config.admin_user_ids.contains(&user.id.0))
+        .unwrap_or(false)
+}
+
+/// Handler for the admin-only /stats command: usage counts, never password data.
+pub async fn handle_stats(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    if !is_admin(&msg, &state.config) {
+        bot.send_message(msg.chat.id, "⛔ This command is restricted to bot operators.")
+            .await?;
+        return Ok(());
+    }
+
+    let reply = state.stats.lock().await.format(Instant::now());
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Handler for the admin-only /store command.
+///
+/// Generates a password and writes it to Vault instead of the chat; the reply
+/// only names the path.
+#[cfg(feature = "vault")]
+pub async fn handle_store(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    use crate::vault::VaultClient;
+
+    if !is_admin(&msg, &state.config) {
+        bot.send_message(msg.chat.id, "⛔ This command is restricted to bot operators.")
+            .await?;
+        return Ok(());
+    }
+
+    let (path, options) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+    if path.is_empty() {
+        bot.send_message(msg.chat.id, "Usage: /store <path> [length] [options]")
+            .await?;
+        return Ok(());
+    }
+
+    let result = async {
+        let request = parse_password_args(options, &state.config.base_password_config())?;
+        if request.preview {
+            return Err(BotError::PasswordGeneration(
+                "--preview works with /pass, not /store".to_string(),
+            ));
+        }
+        request.config.validate()?;
+        let client = VaultClient::from_config(&state.config)?;
+        let password = generate_off_thread(
+            request.config.clone(),
+            state.config.forbidden_substrings.clone(),
+            state.rng.rng(),
+        )
+        .await?;
+        client.write_secret(path, &password).await?;
+        Ok::<_, BotError>(request.config)
+    };
+
+    let reply = match result.await {
+        Ok(config) => {
+            let strength = estimate_strength(&config);
+            info!(
+                chat_id = msg.chat.id.0,
+                length = config.length,
+                strength = strength.as_str(),
+                pool_size = logged_pool_size(&config),
+                "Stored password in Vault"
+            );
+            format!("🔒 Stored at {}.", path)
+        }
+        Err(e) => {
+            warn!("Vault store for chat {} failed: {}", msg.chat.id, e);
+            format!("❌ {}", e)
+        }
+    };
+
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Handler for /store in builds without the `vault` feature.
+#[cfg(not(feature = "vault"))]
+pub async fn handle_store(
+    bot: Bot,
+    msg: Message,
+    _state: BotState,
+    _args: String,
+) -> ResponseResult<()> {
+    bot.send_message(
+        msg.chat.id,
+        "🔒 Vault support is not enabled in this build (compile with --features vault).",
+    )
+    .await?;
+    Ok(())
+}
+
+/// Handler for the admin-only /bench command.
+pub async fn handle_bench(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    if !is_admin(&msg, &state.config) {
+        bot.send_message(msg.chat.id, "⛔ This command is restricted to bot operators.")
+            .await?;
+        return Ok(());
+    }
+
+    let count = match args.trim() {
+        "" => 1_000,
+        arg => match arg.parse::<usize>() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                bot.send_message(msg.chat.id, "Usage: /bench <count>").await?;
+                return Ok(());
+            }
+        },
+    };
+
+    let config = PasswordConfig {
+        length: state.config.default_password_length,
+        ..Default::default()
+    };
+    let result = tokio::task::spawn_blocking(move || run_benchmark(&config, count)).await;
+
+    let reply = match result {
+        Ok(Ok(bench)) => {
+            info!(
+                "Benchmark: {} passwords in {:?} ({:.0}/s)",
+                bench.count,
+                bench.elapsed,
+                bench.rate()
+            );
+            format!(
+                "⏱ Generated {} passwords in {:.3}s ({:.0} passwords/s){}",
+                bench.count,
+                bench.elapsed.as_secs_f64(),
+                bench.rate(),
+                if count > MAX_BENCH_COUNT {
+                    format!("\nCount capped at {}.", MAX_BENCH_COUNT)
+                } else {
+                    String::new()
+                }
+            )
+        }
+        Ok(Err(e)) => format!("❌ Benchmark failed: {}", e),
+        Err(e) => format!("❌ Benchmark failed: {}", e),
+    };
+
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Strip a leading `/pass` or `/password` token, returning the trimmed arguments.
+///
+/// Only a whole leading token is removed; `pass` elsewhere in the string is kept.
+fn strip_command_prefix(s: &str) -> &str {
+    let s = s.trim_start();
+    let (head, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    match head {
+        "/pass" | "/password" => rest.trim(),
+        _ => s.trim(),
+    }
+}
+
+/// Format how long ago something happened, e.g. "5m ago".
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// Build the /history reply for `entries` (oldest first), listing newest first.
+fn format_history(entries: &[HistoryEntry], now: Instant) -> String {
+    if entries.is_empty() {
+        return "🕘 No generations recorded for this chat yet.".to_string();
+    }
+
+    let mut text =
+        String::from("🕘 Recent generations (metadata only — passwords are never stored):\n\n");
+    for (i, entry) in entries.iter().rev().enumerate() {
+        text.push_str(&format!(
+            "{}. {} — {} chars, {}\n",
+            i + 1,
+            format_age(now.duration_since(entry.at)),
+            entry.length,
+            entry.strength.as_str()
+        ));
+    }
+    text.push_str("\nUse /history clear to forget this list.");
+    text
+}
+
+/// Longest separator /passphrase accepts, in characters.
+const MAX_SEPARATOR_CHARS: usize = 8;
+
+/// A parsed /passphrase request.
+#[derive(Debug, PartialEq, Eq)]
+struct PassphraseRequest {
+    words: usize,
+    separator: String,
+    transforms: PassphraseTransforms,
+}
+
+/// Parse `/passphrase [words] [--separator S] [--title-case] [--all-caps] [--leet]`.
+///
+/// `--capitalize` is the original name of `--title-case`.
+fn parse_passphrase_args(args: &str) -> Result<PassphraseRequest> {
+    let mut request = PassphraseRequest {
+        words: DEFAULT_PASSPHRASE_WORDS,
+        separator: DEFAULT_PASSPHRASE_SEPARATOR.to_string(),
+        transforms: PassphraseTransforms::default(),
+    };
+
+    let mut parts = args.split_whitespace();
+    while let Some(part) = parts.next() {
+        match part {
+            "--capitalize" | "--title-case" => request.transforms.title_case = true,
+            "--all-caps" => request.transforms.all_caps = true,
+            "--leet" => request.transforms.leet = true,
+            "--separator" => {
+                let separator = parts.next().ok_or_else(|| {
+                    BotError::PasswordGeneration("--separator needs a value".to_string())
+                })?;
+                if separator.chars().count() > MAX_SEPARATOR_CHARS {
+                    return Err(BotError::PasswordGeneration(format!(
+                        "Separators can be at most {} characters",
+                        MAX_SEPARATOR_CHARS
+                    )));
+                }
+                request.separator = separator.to_string();
+            }
+            _ => {
+                request.words = normalize_digits(part).parse::<usize>().map_err(|_| {
+                    BotError::PasswordGeneration(format!("Unknown option: {}", part))
+                })?;
+            }
+        }
+    }
+
+    if !(1..=MAX_PASSPHRASE_WORDS).contains(&request.words) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Word count must be between 1 and {}",
+            MAX_PASSPHRASE_WORDS
+        )));
+    }
+    Ok(request)
+}
+
+/// Handler for the /passphrase command.
+pub async fn handle_passphrase(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let user_id = msg.from().map_or(chat_id as u64, |u| u.id.0);
+
+    let request = match parse_passphrase_args(&args) {
+        Ok(request) => request,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+        return Ok(());
+    }
+
+    let passphrase = match generate_passphrase(
+        request.words,
+        &request.separator,
+        request.transforms,
+        &mut OsRng,
+    ) {
+        Ok(passphrase) => passphrase,
+        Err(e) => {
+            bot.send_message(msg.chat.id, format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    let entropy = passphrase_entropy_bits(request.words);
+    let strength = strength_for_entropy(entropy, state.config.strength_thresholds());
+    let header = "🔐 Your Passphrase:\n\n";
+    // The transforms are the same for every word, so they don't change the entropy
+    let transforms = request
+        .transforms
+        .describe()
+        .map(|names| format!(" | Transforms: {} (add no entropy)", names))
+        .unwrap_or_default();
+    let text = format!(
+        "{}{}\n\nWords: {} | Wordlist: {} words{} | Entropy: {:.1} bits | Strength: {}\n\n\
+        ⚠️ Security reminder: Copy this passphrase immediately and store it securely. \
+        This message will remain in your chat history.",
+        header,
+        passphrase,
+        request.words,
+        wordlist().len(),
+        transforms,
+        entropy,
+        strength.as_str()
+    );
+    let entities = vec![MessageEntity::code(utf16_len(header), utf16_len(&passphrase))];
+
+    let sent = bot
+        .send_message(msg.chat.id, text)
+        .entities(entities)
+        .reply_markup(password_keyboard())
+        .await?;
+    state
+        .sent_messages
+        .lock()
+        .await
+        .record(chat_id, sent.id, Instant::now());
+    state.history.lock().await.record(
+        chat_id,
+        HistoryEntry {
+            at: Instant::now(),
+            length: passphrase.chars().count(),
+            strength,
+        },
+    );
+    state.stats.lock().await.record_passwords(1);
+
+    // Log metadata only (never the passphrase)
+    info!(
+        chat_id,
+        words = request.words,
+        length = passphrase.chars().count(),
+        strength = strength.as_str(),
+        entropy_bits = entropy,
+        "Generated passphrase"
+    );
+    Ok(())
+}
+
+/// Fewest words /explain compares.
+const EXPLAIN_MIN_WORDS: usize = 3;
+/// Most words /explain compares.
+const EXPLAIN_MAX_WORDS: usize = 12;
+/// Word count /explain uses when none is given.
+const EXPLAIN_DEFAULT_WORDS: usize = 5;
+
+/// Parse the /explain argument: `5words`, `5 words`, `5` or nothing for the default.
+fn parse_word_count(args: &str) -> Result<usize> {
+    let arg = args.trim();
+    if arg.is_empty() {
+        return Ok(EXPLAIN_DEFAULT_WORDS);
+    }
+
+    let number = arg
+        .strip_suffix("words")
+        .or_else(|| arg.strip_suffix("word"))
+        .unwrap_or(arg)
+        .trim();
+    let words = normalize_digits(number).parse::<usize>().map_err(|_| {
+        BotError::PasswordGeneration(format!(
+            "Invalid word count: '{}'. Try /explain 5words.",
+            arg
+        ))
+    })?;
+
+    if !(EXPLAIN_MIN_WORDS..=EXPLAIN_MAX_WORDS).contains(&words) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Word count must be between {} and {}",
+            EXPLAIN_MIN_WORDS, EXPLAIN_MAX_WORDS
+        )));
+    }
+    Ok(words)
+}
+
+/// Compare a `words`-word passphrase with a random password of the same printed length.
+///
+/// Both figures use the regular entropy helpers; the examples are shown only to
+/// illustrate the shapes and are not recorded anywhere.
+fn explain_comparison(words: usize, rng: &mut impl RngCore) -> Result<String> {
+    let passphrase = generate_passphrase(
+        words,
+        DEFAULT_PASSPHRASE_SEPARATOR,
+        PassphraseTransforms::default(),
+        rng,
+    )?;
+    let config = PasswordConfig {
+        length: passphrase.chars().count(),
+        ..PasswordConfig::default()
+    };
+    let password = generate_passwords(&config, 1, rng)?.remove(0);
+
+    let phrase_bits = passphrase_entropy_bits(words);
+    let password_bits = entropy_bits(&config);
+
+    Ok(format!(
+        "📚 Passphrase vs random password ({} characters each)\n\n\
+        🗣 {}-word passphrase: {:.1} bits\n\
+        Example: {}\n\n\
+        🎲 Random password: {:.1} bits\n\
+        Example: {}\n\n\
+        Each word is picked at random, so the passphrase's strength comes from the \
+        number of words, not from how unusual it looks. Per character a random password \
+        packs in more entropy, but the passphrase is far easier to remember and type; \
+        add words to make it stronger.",
+        config.length, words, phrase_bits, passphrase, password_bits, password
+    ))
+}
+
+/// Handler for the /explain command.
+pub async fn handle_explain(bot: Bot, msg: Message, args: String) -> ResponseResult<()> {
+    let reply = parse_word_count(&args).and_then(|words| explain_comparison(words, &mut OsRng));
+    match reply {
+        Ok(text) => bot.send_message(msg.chat.id, text).await?,
+        Err(e) => bot.send_message(msg.chat.id, format!("❌ {}", e)).await?,
+    };
+    Ok(())
+}
+
+/// Longest /feedback text forwarded, in characters; the header has to fit too.
+const MAX_FEEDBACK_CHARS: usize = 3500;
+
+/// The message forwarded to `FEEDBACK_CHAT_ID`, naming the sender so admins can follow up.
+///
+/// Sent as plain text, so nothing in the name or feedback needs escaping.
+fn feedback_forward_text(
+    username: Option<&str>,
+    user_id: u64,
+    chat_id: i64,
+    feedback: &str,
+) -> String {
+    let sender = match username {
+        Some(username) => format!("@{} (id {})", username, user_id),
+        None => format!("user id {}", user_id),
+    };
+    format!("📨 Feedback from {} in chat {}:\n\n{}", sender, chat_id, feedback)
+}
+
+/// Handler for the /feedback command: forward the user's message to the operators.
+pub async fn handle_feedback(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let Some(feedback_chat) = state.config.feedback_chat_id else {
+        bot.send_message(
+            msg.chat.id,
+            "Thanks for wanting to help! Feedback isn't set up for this bot, so please \
+            contact its operator another way.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let feedback = args.trim();
+    if feedback.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Usage: /feedback <message>\nExample: /feedback The --dashes option ignores --qr\n\n\
+            Please don't include passwords.",
+        )
+        .await?;
+        return Ok(());
+    }
+    if feedback.chars().count() > MAX_FEEDBACK_CHARS {
+        bot.send_message(
+            msg.chat.id,
+            format!("❌ Feedback can be at most {} characters.", MAX_FEEDBACK_CHARS),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let user_id = msg.from().map_or(msg.chat.id.0 as u64, |u| u.id.0);
+    let cooldown = Duration::from_secs(state.config.feedback_cooldown_secs);
+    let now = Instant::now();
+    let allowed = state
+        .feedback_cooldown
+        .lock()
+        .await
+        .try_send(user_id, now, cooldown);
+    if let Err(wait) = allowed {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "⏳ You've sent feedback recently. Try again in {}.",
+                format_wait(wait)
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let username = msg.from().and_then(|u| u.username.as_deref());
+    let text = feedback_forward_text(username, user_id, msg.chat.id.0, feedback);
+    let reply = match bot.send_message(ChatId(feedback_chat), text).await {
+        Ok(_) => {
+            info!(chat_id = msg.chat.id.0, "Forwarded feedback");
+            "✅ Thanks! Your feedback was sent to the bot's operators."
+        }
+        Err(e) => {
+            warn!("Could not forward feedback to chat {}: {}", feedback_chat, e);
+            // Nothing was delivered, so don't make the user wait out the cooldown
+            state.feedback_cooldown.lock().await.cancel(user_id, now);
+            "❌ Sorry, your feedback couldn't be delivered. Please try again later."
+        }
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Handler for the /check command: estimate the strength of a user's own password.
+///
+/// The message holding the password is deleted where possible, and the password
+/// itself is never logged.
+pub async fn handle_check(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let password = Zeroizing::new(args.trim().to_string());
+    if password.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "Usage: /check <password>\nExample: /check correct-horse-42\n\n\
+            I'll estimate its strength without storing it.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // Best effort: the bot may lack permission to delete in groups
+    if bot.delete_message(msg.chat.id, msg.id).await.is_err() {
+        warn!("Could not delete a /check message in chat {}", msg.chat.id);
+    }
+
+    // Each breach check is an outbound request, so it counts against the rate limit
+    if state.config.enable_hibp {
+        let user_id = msg.from().map_or(msg.chat.id.0 as u64, |u| u.id.0);
+        if !charge_batch(&bot, msg.chat.id, &state, user_id, 1).await? {
+            return Ok(());
+        }
+    }
+
+    let config = PasswordConfig {
+        strength_thresholds: state.config.strength_thresholds(),
+        ..config_for_password(&password)
+    };
+    let strength = estimate_strength(&config);
+    let breach = if state.config.enable_hibp {
+        Some(breach_note(&state, &password).await)
+    } else {
+        None
+    };
+    let reply = format_check_reply(&password, &config, strength, breach.as_deref());
+    bot.send_message(msg.chat.id, reply).await?;
+
+    // Log metadata only (never log the checked password)
+    info!(
+        chat_id = msg.chat.id.0,
+        length = config.length,
+        strength = strength.as_str(),
+        "Checked a password"
+    );
+    Ok(())
+}
+
+/// The /check line saying whether `password` appears in HaveIBeenPwned's breaches.
+///
+/// A failed lookup only skips the check: the strength estimate is still useful.
+#[cfg(feature = "hibp")]
+async fn breach_note(state: &BotState, password: &str) -> String {
+    let lookup = match &state.hibp {
+        Some(client) => client.breach_count(password).await,
+        None => Err(BotError::Hibp("no HTTP client".to_string())),
+    };
+    match lookup {
+        Ok(0) => "🛡️ Not found in HaveIBeenPwned's breached passwords.".to_string(),
+        Ok(count) => format!(
+            "🚨 Seen {} times in data breaches (HaveIBeenPwned). Attackers try these first: \
+            don't use it.",
+            count
+        ),
+        Err(e) => {
+            warn!("Breach check failed: {}", e);
+            "⚠️ Couldn't reach HaveIBeenPwned, so the breach check was skipped.".to_string()
+        }
+    }
+}
+
+/// The /check breach line in builds without the `hibp` feature; unreachable, as the
+/// config refuses `ENABLE_HIBP` there.
+#[cfg(not(feature = "hibp"))]
+async fn breach_note(_state: &BotState, _password: &str) -> String {
+    "⚠️ Breach checks are not enabled in this build (compile with --features hibp).".to_string()
+}
+
+/// Build the /check reply for `password`, judged as if generated from `config`.
+///
+/// `breach` is the HaveIBeenPwned result, when `ENABLE_HIBP` is set.
+fn format_check_reply(
+    password: &str,
+    config: &PasswordConfig,
+    strength: PasswordStrength,
+    breach: Option<&str>,
+) -> String {
+    let strength_emoji = match strength {
+        PasswordStrength::Strong => "💪",
+        PasswordStrength::Medium => "👍",
+        PasswordStrength::Weak => "⚠️",
+    };
+    let mut metadata = format_metadata(config, strength);
+    if let Some(extra) = supplementary_metadata(password) {
+        metadata.push_str(&format!(" | {}", extra));
+    }
+    let (breach, privacy) = match breach {
+        Some(note) => (
+            format!("{}\n\n", note),
+            " Only the first 5 characters of its SHA-1 hash were sent to HaveIBeenPwned.",
+        ),
+        None => (String::new(), ""),
+    };
+    format!(
+        "🔎 Strength check\n\n{} {}\n\nThis assumes every character was picked at random. \
+        Words, names, dates and keyboard patterns make a password much weaker than shown.\n\n\
+        {}🔒 Your password was not stored or logged.{}",
+        strength_emoji, metadata, breach, privacy
+    )
+}
+
+/// Handler for the /history command.
+pub async fn handle_history(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let reply = match args.trim() {
+        "" => format_history(&state.history.lock().await.recent(chat_id), Instant::now()),
+        "clear" => {
+            let removed = state.history.lock().await.clear(chat_id);
+            format!("🧹 Cleared {} history entries.", removed)
+        }
+        _ => "Usage: /history or /history clear".to_string(),
+    };
+
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Looks up whether a user administers a chat.
+///
+/// Abstracted over so the /setlimit permission gate can be tested without Telegram.
+trait ChatAdminLookup {
+    async fn is_chat_admin(&self, chat_id: ChatId, user_id: UserId) -> bool;
+}
+
+impl ChatAdminLookup for Bot {
+    async fn is_chat_admin(&self, chat_id: ChatId, user_id: UserId) -> bool {
+        match self.get_chat_member(chat_id, user_id).await {
+            Ok(member) => member.is_privileged(),
+            Err(e) => {
+                warn!("Could not look up chat member in {}: {}", chat_id, e);
+                false
+            }
+        }
+    }
+}
+
+/// Whether `user_id` may change the rate limit of `chat_id`.
+///
+/// Only administrators of group chats qualify; private chats are always refused.
+async fn can_set_chat_limit(
+    lookup: &impl ChatAdminLookup,
+    chat_id: ChatId,
+    is_group: bool,
+    user_id: Option<UserId>,
+) -> bool {
+    match user_id {
+        Some(user_id) if is_group => lookup.is_chat_admin(chat_id, user_id).await,
+        _ => false,
+    }
+}
+
+/// Handler for the /setlimit command (group admins only).
+pub async fn handle_setlimit(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    let user_id = msg.from().map(|u| u.id);
+    if !can_set_chat_limit(&bot, msg.chat.id, is_group, user_id).await {
+        bot.send_message(
+            msg.chat.id,
+            "⛔ Only group administrators can change this chat's rate limit.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let requested = match normalize_digits(args.trim()).parse::<usize>() {
+        Ok(n) if n > 0 => n,
+        _ => {
+            let usage = format!(
+                "Usage: /setlimit <requests per {}>",
+                describe_window(state.config.rate_limit_window())
+            );
+            bot.send_message(msg.chat.id, usage).await?;
+            return Ok(());
+        }
+    };
+
+    let max = state.config.max_chat_rate_limit;
+    let limit = state
+        .chat_limits
+        .lock()
+        .await
+        .set(msg.chat.id.0, requested, max);
+    info!("Rate limit for chat {} set to {}", msg.chat.id, limit);
+
+    let window = describe_window(state.config.rate_limit_window());
+    let reply = if limit < requested {
+        format!(
+            "✅ Rate limit set to {} per {} (the operator's maximum).",
+            limit, window
+        )
+    } else {
+        format!("✅ Rate limit set to {} per {}.", limit, window)
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Whether the sender may change `chat_id`'s `/setdefault` options.
+///
+/// Anyone can in a private chat; in a group the options apply to every member, so
+/// only administrators can.
+async fn can_set_chat_defaults(
+    lookup: &impl ChatAdminLookup,
+    chat_id: ChatId,
+    is_group: bool,
+    user_id: Option<UserId>,
+) -> bool {
+    !is_group || can_set_chat_limit(lookup, chat_id, is_group, user_id).await
+}
+
+/// Check `args` as `/pass` options worth saving: they must parse, validate and fit
+/// `min..=max` characters.
+fn check_default_options(
+    args: &str,
+    base: &PasswordConfig,
+    min: usize,
+    max: usize,
+    max_args_len: usize,
+) -> Result<()> {
+    let request = parse_bounded_password_args(args, base, max_args_len)?;
+    if request.preview {
+        return Err(BotError::PasswordGeneration(
+            "--preview can't be saved as a default".to_string(),
+        ));
+    }
+    if let Some(error) = pattern_length_error(&request.config, min, max) {
+        return Err(BotError::PasswordGeneration(error));
+    }
+    let length = request.config.length;
+    if length < min || length > max {
+        return Err(BotError::PasswordGeneration(format!(
+            "Length must be between {} and {}",
+            min, max
+        )));
+    }
+    request.config.validate()
+}
+
+/// Handler for the /setdefault command: save options for a bare /pass in this chat.
+pub async fn handle_setdefault(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    let user_id = msg.from().map(|u| u.id);
+    if !can_set_chat_defaults(&bot, msg.chat.id, is_group, user_id).await {
+        bot.send_message(
+            msg.chat.id,
+            "⛔ Only group administrators can change this chat's default options.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if args.trim().is_empty() {
+        let current = match state.preferences.lock().await.get(msg.chat.id.0) {
+            Some(options) => format!("Current defaults: {}", options),
+            None => "No defaults saved; /pass uses the bot's own.".to_string(),
+        };
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "Usage: /setdefault <options>, e.g. /setdefault 24 --no-ambiguous\n\n{}",
+                current
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if let Err(e) = check_default_options(
+        &args,
+        &state.config.base_password_config(),
+        state.config.min_password_length,
+        state.config.max_password_length,
+        state.config.max_args_len,
+    ) {
+        bot.send_message(msg.chat.id, format!("❌ Not saved: {}", e))
+            .await?;
+        return Ok(());
+    }
+
+    let saved = {
+        let mut preferences = state.preferences.lock().await;
+        preferences.set(msg.chat.id.0, &args);
+        preferences.get(msg.chat.id.0).unwrap_or_default().to_string()
+    };
+    if let Err(e) = state.persist_preferences().await {
+        warn!("Failed to save preferences: {}", e);
+    }
+    info!("Default options saved for chat {}", msg.chat.id);
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "✅ Saved. /pass with no options now uses: {}\n\
+             Use /resetdefault to go back to the bot's defaults.",
+            saved
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Handler for the /resetdefault command: forget this chat's /setdefault options.
+pub async fn handle_resetdefault(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    let user_id = msg.from().map(|u| u.id);
+    if !can_set_chat_defaults(&bot, msg.chat.id, is_group, user_id).await {
+        bot.send_message(
+            msg.chat.id,
+            "⛔ Only group administrators can change this chat's default options.",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let cleared = state.preferences.lock().await.clear(msg.chat.id.0);
+    let reply = if cleared {
+        if let Err(e) = state.persist_preferences().await {
+            warn!("Failed to save preferences: {}", e);
+        }
+        "✅ Default options cleared; /pass uses the bot's defaults again."
+    } else {
+        "No default options were saved for this chat."
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Handler for the /lang command: choose the language of this chat's replies.
+///
+/// `/lang auto` goes back to each user's Telegram language. Like /setdefault, only
+/// administrators can change it in a group.
+pub async fn handle_lang(
+    bot: Bot,
+    msg: Message,
+    state: BotState,
+    args: String,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id.0;
+    let code = args.trim().to_ascii_lowercase();
+    let lang = state.language(chat_id, msg.from()).await;
+    let languages = state.locales.languages().join(", ");
+
+    if code.is_empty() {
+        let reply = state
+            .locales
+            .get(lang.as_deref(), "lang_current")
+            .replace("{language}", &state.locales.resolve(lang.as_deref()))
+            .replace("{languages}", &languages);
+        bot.send_message(msg.chat.id, reply).await?;
+        return Ok(());
+    }
+
+    let is_group = msg.chat.is_group() || msg.chat.is_supergroup();
+    let user_id = msg.from().map(|u| u.id);
+    if !can_set_chat_defaults(&bot, msg.chat.id, is_group, user_id).await {
+        bot.send_message(msg.chat.id, state.locales.get(lang.as_deref(), "lang_not_admin"))
+            .await?;
+        return Ok(());
+    }
+
+    let reply = if code == "auto" {
+        state.chat_languages.lock().await.remove(&chat_id);
+        let lang = state.language(chat_id, msg.from()).await;
+        state.locales.get(lang.as_deref(), "lang_auto").to_string()
+    } else if state.locales.supports(&code) {
+        state.chat_languages.lock().await.insert(chat_id, code.clone());
+        state.locales.get(Some(&code), "lang_set").replace("{language}", &code)
+    } else {
+        state
+            .locales
+            .get(lang.as_deref(), "lang_unsupported")
+            .replace("{code}", args.trim())
+            .replace("{languages}", &languages)
+    };
+    bot.send_message(msg.chat.id, reply).await?;
+    Ok(())
+}
+
+/// Handler for inline button callbacks.
+pub async fn handle_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    state: BotState,
+) -> ResponseResult<()> {
+    if let Some(ref data) = q.data {
+        // Handle different button callbacks
+        let message = match data.as_str() {
+            "pass_default" => "/pass".to_string(),
+            "pass_24" => "/pass 24".to_string(),
+            "pass_32" => "/pass 32".to_string(),
+            "pass_no_symbols" => "/pass 16 --no-symbols".to_string(),
+            "pass_no_ambiguous" => "/pass 18 --no-ambiguous".to_string(),
+            regen
+                if regen.starts_with(REGENERATE_PREFIX)
+                    || regen.starts_with(STORED_CONFIG_PREFIX) =>
+            {
+                return handle_regenerate(&bot, &q, &state, regen).await;
+            }
+            "panic" => {
+                bot.answer_callback_query(&q.id).await?;
+                let chat_id = q
+                    .message
+                    .as_ref()
+                    .map(|m| m.chat.id)
+                    .unwrap_or_else(|| ChatId::from(q.from.id));
+                return clear_password_messages(&bot, chat_id, &state).await;
+            }
+            "confirm_length" => {
+                let pending = state
+                    .confirmations
+                    .lock()
+                    .await
+                    .take(q.from.id.0, Instant::now());
+                let Some(request) = pending else {
+                    bot.answer_callback_query(&q.id)
+                        .text("Nothing to confirm (it may have expired). Please request again.")
+                        .await?;
+                    return Ok(());
+                };
+                bot.answer_callback_query(&q.id).await?;
+                if !charge_batch(&bot, request.chat_id, &state, q.from.id.0, request.count).await?
+                {
+                    return Ok(());
+                }
+                return send_password_with_timeout(
+                    &bot,
+                    request.chat_id,
+                    &state,
+                    request.config,
+                    request.display,
+                    request.count,
+                    Some(&q.from),
+                )
+                .await;
+            }
+            "cancel_length" => {
+                state
+                    .confirmations
+                    .lock()
+                    .await
+                    .take(q.from.id.0, Instant::now());
+                bot.answer_callback_query(&q.id).text("Cancelled").await?;
+                return Ok(());
+            }
+            "pass_custom" => {
+                bot.answer_callback_query(&q.id).await?;
+                bot.send_message(
+                    q.from.id,
+                    "📝 Please type your custom password command:\nExample: /pass 20 --symbols --no-digits",
+                )
+                .await?;
+                return Ok(());
+            }
+            "show_help" => {
+                // Re-send help with buttons, starting from the first page
+                let page = state.help_navigator.lock().await.open(q.from.id.0);
+                bot.answer_callback_query(&q.id).await?;
+                bot.send_message(q.from.id, render_help_page(page, &state.config))
+                    .reply_markup(help_keyboard())
+                    .await?;
+                return Ok(());
+            }
+            nav @ ("help_prev" | "help_next") => {
+                let direction = if nav == "help_next" {
+                    HelpNav::Next
+                } else {
+                    HelpNav::Prev
+                };
+                let total = help_pages(state.config.help_options_per_page).len();
+                let page = state
+                    .help_navigator
+                    .lock()
+                    .await
+                    .navigate(q.from.id.0, direction, total);
+                bot.answer_callback_query(&q.id).await?;
+
+                let text = render_help_page(page, &state.config);
+                match q.message {
+                    Some(ref m) => {
+                        bot.edit_message_text(m.chat.id, m.id, text)
+                            .reply_markup(help_keyboard())
+                            .await?;
+                    }
+                    None => {
+                        bot.send_message(q.from.id, text)
+                            .reply_markup(help_keyboard())
+                            .await?;
+                    }
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        // Create a fake message for password generation
+        let chat_id = q.from.id;
+        match state.check_limits(chat_id.0 as i64, chat_id.0).await {
+            Ok(Some(notice)) => {
+                bot.send_message(chat_id, notice).await?;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                bot.answer_callback_query(&q.id)
+                    .text(e.to_string())
+                    .await?;
+                return Ok(());
+            }
+        }
+
+        // Parse and generate password
+        let PasswordRequest {
+            config: mut password_config,
+            display,
+            exact,
+            ..
+        } = match parse_password_args(
+            strip_command_prefix(&message),
+            &state.config.base_password_config(),
+        ) {
+            Ok(request) => request,
+            Err(e) => {
+                bot.answer_callback_query(&q.id)
+                    .text(format!("Error: {}", e))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        // Validate length bounds
+        if password_config.length < state.config.min_password_length
+            || password_config.length > state.config.max_password_length
+            || pattern_length_error(
+                &password_config,
+                state.config.min_password_length,
+                state.config.max_password_length,
+            )
+            .is_some()
+        {
+            bot.answer_callback_query(&q.id)
+                .text("Invalid password length")
+                .await?;
+            return Ok(());
+        }
+
+        // Validate configuration
+        if let Err(e) = password_config.validate() {
+            bot.answer_callback_query(&q.id)
+                .text(e.to_string())
+                .await?;
+            return Ok(());
+        }
+
+        let mut answer = bot.answer_callback_query(&q.id);
+        if state.config.auto_strengthen && !exact {
+            if let Some(notice) =
+                auto_strengthen(&mut password_config, state.config.max_password_length)
+            {
+                answer = answer.text(notice);
+            }
+        }
+        answer.await?;
+        send_password_with_timeout(
+            &bot,
+            ChatId::from(q.from.id),
+            &state,
+            password_config,
+            display,
+            1,
+            Some(&q.from),
+        )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Handler for the /verify command: start a two-step equality check.
+pub async fn handle_verify(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    let Some(user) = msg.from() else {
+        return Ok(());
+    };
+
+    let prompt = bot
+        .send_message(
+            msg.chat.id,
+            "🔍 Reply to this message with the password to check. I'll then ask for it \
+            a second time and tell you whether the two match.",
+        )
+        .reply_markup(ForceReply::new())
+        .await?;
+    state
+        .verifications
+        .lock()
+        .await
+        .start((msg.chat.id.0, user.id.0), prompt.id, Instant::now());
+    Ok(())
+}
+
+/// Handle a reply belonging to a /verify flow.
+///
+/// Returns `false` if `msg` is not part of one. The user's messages are deleted
+/// where possible, and neither value is ever logged.
+async fn handle_verify_reply(bot: &Bot, msg: &Message, state: &BotState) -> ResponseResult<bool> {
+    let (Some(user), Some(reply_to), Some(text)) =
+        (msg.from(), msg.reply_to_message(), msg.text())
+    else {
+        return Ok(false);
+    };
+
+    let key = (msg.chat.id.0, user.id.0);
+    let value = Zeroizing::new(text.to_string());
+    let step = state
+        .verifications
+        .lock()
+        .await
+        .submit(key, reply_to.id, value, Instant::now());
+    let Some(step) = step else {
+        return Ok(false);
+    };
+
+    // Best effort: the bot may lack permission to delete in groups
+    if bot.delete_message(msg.chat.id, msg.id).await.is_err() {
+        warn!("Could not delete a /verify reply in chat {}", msg.chat.id);
+    }
+
+    match step {
+        VerifyStep::Second => {
+            let prompt = bot
+                .send_message(msg.chat.id, "🔁 Now reply with the password once more.")
+                .reply_markup(ForceReply::new())
+                .await?;
+            state.verifications.lock().await.await_second(key, prompt.id);
+        }
+        VerifyStep::Finished { matched } => {
+            let verdict = if matched {
+                "✅ The passwords match."
+            } else {
+                "❌ The passwords do not match."
+            };
+            bot.send_message(msg.chat.id, verdict).await?;
+            info!("Completed /verify in chat {}", msg.chat.id);
+        }
+    }
+    Ok(true)
+}
+
+/// Handler for unknown commands.
+pub async fn handle_unknown(bot: Bot, msg: Message, state: BotState) -> ResponseResult<()> {
+    if handle_verify_reply(&bot, &msg, &state).await? {
+        return Ok(());
+    }
+    if handle_keyboard_tap(&bot, &msg, &state).await? {
+        return Ok(());
+    }
+
+    let lang = state.language(msg.chat.id.0, msg.from()).await;
+    let response = state.locales.get(lang.as_deref(), "unknown_command");
+    bot.send_message(msg.chat.id, response)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::MessageEntityRef;
+
+    /// Parse `args` on top of the built-in defaults with the given default length.
+    fn parse_with_length(args: &str, default_length: usize) -> Result<PasswordRequest> {
+        let base = PasswordConfig {
+            length: default_length,
+            ..Default::default()
+        };
+        parse_password_args(args, &base)
+    }
+
+    #[test]
+    fn test_parse_password_args_default() {
+        let config = parse_with_length("", 16).unwrap().config;
+        assert_eq!(config.length, 16);
+        assert!(config.use_lowercase);
+        assert!(config.use_uppercase);
+        assert!(config.use_digits);
+        assert!(config.use_symbols);
+    }
+
+    #[test]
+    fn test_parse_password_args_with_length() {
+        let config = parse_with_length("24", 16).unwrap().config;
+        assert_eq!(config.length, 24);
+    }
+
+    #[test]
+    fn test_parse_password_args_with_options() {
+        let config = parse_with_length("20 --no-symbols --no-ambiguous", 16)
+            .unwrap()
+            .config;
+        assert_eq!(config.length, 20);
+        assert!(!config.use_symbols);
+        assert!(config.exclude_ambiguous);
+    }
+
+    #[test]
+    fn test_parse_password_args_invalid_length() {
+        let result = parse_with_length("abc", 16);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_metadata_json() {
+        let request = parse_with_length("20 --no-symbols --json", 16).unwrap();
+        assert!(request.display.json);
+        let config = request.config;
+        let strength = estimate_strength(&config);
+        let (json, entity) = metadata_json(&config, strength);
+
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["length"], 20);
+        assert_eq!(value["pool_size"], 62);
+        assert_eq!(value["strength"], strength.as_str());
+        let bits = policy_aware_entropy(&config);
+        assert!((value["entropy_bits"].as_f64().unwrap() - bits).abs() < 1e-9);
+        assert_eq!(value.as_object().unwrap().len(), 4);
+        assert_eq!(entity, MessageEntity::pre(Some("json".to_string()), 0, utf16_len(&json)));
+
+        // A pattern has no single pool
+        let config = parse_with_length("--pattern {word}{digit}", 16).unwrap().config;
+        let (json, _) = metadata_json(&config, estimate_strength(&config));
+        assert!(json.contains("\"pool_size\": null"), "{}", json);
+        // nor a single length: words vary, so config.length is only the longest result
+        assert!(json.contains("\"length\": null"), "{}", json);
+        let config = parse_with_length("--pattern {upper}-{digit}{digit}", 16).unwrap().config;
+        let (json, _) = metadata_json(&config, estimate_strength(&config));
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["length"], 4);
+    }
+
+    #[test]
+    fn test_preview_reply() {
+        let request = parse_with_length("20 --no-symbols --min-digits 3 --no-repeat --preview", 16)
+            .unwrap();
+        assert!(request.preview);
+        let reply = preview_reply(&request.config, request.count);
+        assert!(reply.starts_with("🔍 Preview, no password generated:"), "{}", reply);
+        assert!(reply.contains("Length: 20 | Types: lowercase, uppercase, digits | Pool size: 62"));
+        assert!(reply.contains("\nPool: abc"), "{}", reply);
+        assert!(reply.contains("At least: 3 digits"), "{}", reply);
+        assert!(reply.contains("Rules: no repeated neighbours"), "{}", reply);
+        assert!(!reply.contains("Count:"));
+
+        let request = parse_with_length("--pronounceable --count 3 --preview", 16).unwrap();
+        let reply = preview_reply(&request.config, request.count);
+        assert!(!reply.contains("Pool:"), "{}", reply);
+        assert!(reply.contains("Count: 3"));
+
+        assert!(!parse_with_length("20", 16).unwrap().preview);
+    }
+
+    #[test]
+    fn test_parse_password_args_unknown_option() {
+        let result = parse_with_length("--invalid", 16);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::default();
+        let chat_id = 12345;
+
+        let now = Instant::now();
+
+        // Should allow up to the limit
+        for _ in 0..5 {
+            assert!(limiter.check_request(chat_id, 1, 5, None, now).is_ok());
+        }
+
+        // Should deny the next request, from anyone in the chat
+        assert!(limiter.check_request(chat_id, 1, 5, None, now).is_err());
+        assert!(limiter.check_request(chat_id, 2, 5, None, now).is_err());
+
+        // Other chats are unaffected
+        assert!(limiter.check_request(54321, 1, 5, None, now).is_ok());
+
+        // The window slides
+        let later = now + Duration::from_secs(60);
+        assert!(limiter.check_request(chat_id, 2, 5, None, later).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_per_member() {
+        let mut limiter = RateLimiter::default().with_user_limit(Some(2));
+        let now = Instant::now();
+
+        // One member spends their share of the chat's five...
+        assert!(limiter.check_request(1, 7, 5, None, now).is_ok());
+        assert!(limiter.check_request(1, 7, 5, None, now).is_ok());
+        let err = limiter.check_request(1, 7, 5, None, now).unwrap_err().to_string();
+        assert!(err.contains("per minute per member; 0 left"), "{}", err);
+
+        // ...without blocking the others, until the chat as a whole runs out
+        assert!(limiter.check_request(1, 8, 5, None, now).is_ok());
+        assert!(limiter.check_request(1, 8, 5, None, now).is_ok());
+        assert!(limiter.check_request(1, 9, 5, None, now).is_ok());
+        let err = limiter.check_request(1, 9, 5, None, now).unwrap_err().to_string();
+        assert!(err.contains("per minute; 0 left"), "{}", err);
+        assert_eq!(limiter.requests[&1].len(), 5);
+
+        // The member limit is per chat, and comes back with the window
+        assert!(limiter.check_request(2, 7, 5, None, now).is_ok());
+        let later = now + Duration::from_secs(60);
+        assert!(limiter.check_requests_fixed(1, 7, 2, 5, None, later).is_ok());
+        let err = limiter.check_requests_fixed(1, 7, 1, 5, None, later).unwrap_err();
+        assert!(err.to_string().contains("per member"), "{}", err);
+    }
+
+    #[test]
+    fn test_rate_limiter_custom_window() {
+        let mut limiter = RateLimiter::new(Duration::from_secs(10));
+        let now = Instant::now();
+        assert!(limiter.check_request(1, 7, 1, None, now).is_ok());
+        let err = limiter.check_request(1, 7, 1, None, now + Duration::from_secs(4)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Rate limit exceeded: Too many requests. Maximum 1 password generations \
+             per 10 seconds; 0 left. Try again in 6s."
+        );
+        assert!(limiter.check_request(1, 7, 1, None, now + Duration::from_secs(10)).is_ok());
+    }
+
+    #[test]
+    fn test_sliding_window_frees_one_slot_at_a_time() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        for i in 0..3 {
+            let at = start + Duration::from_secs(20 * i);
+            assert!(limiter.check_request(1, 7, 3, None, at).is_ok());
+        }
+
+        // The first request leaves the window at 60s, the second at 80s
+        let at = start + Duration::from_secs(50);
+        let err = limiter.check_requests(1, 7, 2, 3, None, at).unwrap_err().to_string();
+        assert!(err.ends_with("per minute; 0 left. Try again in 30s."), "{}", err);
+        let at = start + Duration::from_secs(60);
+        assert!(limiter.check_request(1, 7, 3, None, at).is_ok());
+        assert!(limiter.check_request(1, 7, 3, None, at).is_err());
+    }
+
+    #[test]
+    fn test_fixed_window_resets_all_at_once() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        for i in 0..3 {
+            let at = start + Duration::from_secs(20 * i);
+            assert!(limiter.check_requests_fixed(1, 7, 1, 3, None, at).is_ok());
+        }
+
+        let at = start + Duration::from_secs(45);
+        let err = limiter.check_requests_fixed(1, 7, 1, 3, None, at).unwrap_err().to_string();
+        assert!(err.ends_with("0 left. Try again in 15s."), "{}", err);
+
+        // The window opened by the first request closes at 60s, freeing every slot
+        let at = start + Duration::from_secs(60);
+        assert!(limiter.check_requests_fixed(1, 7, 3, 3, None, at).is_ok());
+        assert!(limiter.check_requests_fixed(1, 7, 1, 3, None, at).is_err());
+    }
+
+    #[test]
+    fn test_min_interval_between_requests() {
+        let mut limiter = RateLimiter::default().with_min_interval(Duration::from_secs(2));
+        let now = Instant::now();
+        assert!(limiter.check_request(1, 7, 5, None, now).is_ok());
+
+        // A second tap right away is refused with its own message, and isn't counted
+        let soon = now + Duration::from_millis(500);
+        let err = limiter.check_request(1, 7, 5, None, soon).unwrap_err().to_string();
+        assert!(err.contains("Slow down: wait at least 2s"), "{}", err);
+        assert!(err.ends_with("Try again in 2s."), "{}", err);
+        // The interval is per chat, so other members wait too; other chats don't
+        assert!(limiter.check_request(1, 8, 5, None, soon).is_err());
+        assert!(limiter.check_request(2, 7, 5, None, soon).is_ok());
+
+        let later = now + Duration::from_secs(2);
+        assert!(limiter.check_requests_fixed(1, 7, 1, 5, None, later).is_ok());
+        // Once the interval is met, the window limit still applies: 2 of 2 used
+        let later = later + Duration::from_secs(2);
+        let err = limiter.check_request(1, 7, 2, None, later).unwrap_err().to_string();
+        assert!(err.contains("0 left"), "{}", err);
+    }
+
+    #[test]
+    fn test_rate_limit_error_reports_remaining() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        assert!(limiter.check_requests(1, 7, 3, 5, None, now).is_ok());
+        let err = limiter.check_requests(1, 7, 4, 5, None, now).unwrap_err().to_string();
+        assert!(err.contains("; 2 left. Try again in 1m 0s."), "{}", err);
+
+        // A batch bigger than the whole limit can't be helped by waiting
+        for check in [RateLimiter::check_requests, RateLimiter::check_requests_fixed] {
+            let err = check(&mut limiter, 1, 7, 6, 5, None, now).unwrap_err().to_string();
+            assert!(err.contains("ask for 5 or fewer."), "{}", err);
+            assert!(!err.contains("Try again"), "{}", err);
+        }
+
+        assert_eq!(describe_window(Duration::from_secs(60)), "minute");
+        assert_eq!(describe_window(Duration::from_secs(300)), "5 minutes");
+        assert_eq!(describe_window(Duration::from_secs(90)), "90 seconds");
+        assert_eq!(describe_window(Duration::from_secs(1)), "1 second");
+        assert_eq!(RateLimitStrategy::from_name(" Fixed"), Some(RateLimitStrategy::Fixed));
+        assert_eq!(RateLimitStrategy::from_name("leaky"), None);
+    }
+
+    #[test]
+    fn test_rate_limiter_state_round_trips() {
+        let now = Instant::now();
+        let wall = SystemTime::now();
+        let mut limiter = RateLimiter::default().with_user_limit(Some(3));
+        for _ in 0..3 {
+            limiter.check_request(1, 7, 3, Some(5), now).unwrap();
+        }
+
+        // Restored a few seconds later, in a "new process", the limits still hold
+        let json = limiter.to_json(now, wall).to_string();
+        let later = now + Duration::from_secs(5);
+        let wall_later = wall + Duration::from_secs(5);
+        let mut restored =
+            RateLimiter::from_json(&json, DEFAULT_RATE_LIMIT_WINDOW, later, wall_later).unwrap();
+        assert!(restored.check_request(1, 7, 3, Some(5), later).is_err());
+        assert!(restored.check_request(2, 7, 3, Some(5), later).is_ok());
+        assert!(restored.check_request(3, 7, 3, Some(5), later).is_ok());
+        assert!(restored.check_request(4, 7, 3, Some(5), later).is_err());
+
+        // After a minute only the daily quota is left
+        let wall_minute_later = wall + Duration::from_secs(61);
+        let restored =
+            RateLimiter::from_json(&json, DEFAULT_RATE_LIMIT_WINDOW, later, wall_minute_later)
+                .unwrap();
+        assert!(restored.requests.is_empty());
+        assert!(restored.user_requests.is_empty());
+        assert_eq!(restored.daily[&7].len(), 3);
+    }
+
+    #[test]
+    fn test_rate_limiter_state_rejects_malformed_json() {
+        let now = Instant::now();
+        let wall = SystemTime::now();
+        let parse = |json| RateLimiter::from_json(json, DEFAULT_RATE_LIMIT_WINDOW, now, wall);
+        assert!(parse("not json").is_err());
+        assert!(parse(r#"{"requests": 5}"#).is_err());
+        assert!(parse(r#"{"daily": [{"user_id": 7}]}"#).is_err());
+        assert!(parse("{}").unwrap().daily.is_empty());
+    }
+
+    #[test]
+    fn test_load_rate_limiter_tolerates_missing_and_corrupt_files() {
+        let window = DEFAULT_RATE_LIMIT_WINDOW;
+        assert!(load_rate_limiter(None, window).requests.is_empty());
+        let dir = std::env::temp_dir();
+        let missing = dir.join(format!("ratelimit-missing-{}.json", std::process::id()));
+        assert!(load_rate_limiter(Some(&missing), window).requests.is_empty());
+
+        let corrupt = dir.join(format!("ratelimit-corrupt-{}.json", std::process::id()));
+        fs::write(&corrupt, "{").unwrap();
+        assert!(load_rate_limiter(Some(&corrupt), window).requests.is_empty());
+        fs::remove_file(&corrupt).unwrap();
+    }
+
+    #[test]
+    fn test_daily_quota_window_resets() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        // Spread requests out so the per-minute limit never triggers
+        for i in 0..3 {
+            let at = start + Duration::from_secs(120 * i);
+            let remaining = limiter.check_request(1, 7, 5, Some(3), at).unwrap();
+            assert_eq!(remaining, Some(2 - i as usize));
+        }
+        assert!(limiter
+            .check_request(1, 7, 5, Some(3), start + Duration::from_secs(600))
+            .is_err());
+
+        // Once the first request is 24 hours old, one slot frees up
+        let later = start + DAILY_WINDOW;
+        assert_eq!(limiter.check_request(1, 7, 5, Some(3), later).unwrap(), Some(0));
+        assert!(limiter.check_request(1, 7, 5, Some(3), later).is_err());
+    }
+
+    #[test]
+    fn test_daily_quota_error_says_when_to_retry() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.check_request(1, 7, 5, Some(2), start).is_ok());
+        let second = start + Duration::from_secs(3600);
+        assert!(limiter.check_request(1, 7, 5, Some(2), second).is_ok());
+
+        // The first request frees its slot 24 hours after it was made
+        let at = start + Duration::from_secs(5 * 3600);
+        let err = limiter.check_request(1, 7, 5, Some(2), at).unwrap_err().to_string();
+        assert!(err.ends_with("reached. Try again in 19h 0m."), "{}", err);
+        // A batch of two has to wait for the second as well
+        let err = limiter.check_requests(1, 7, 2, 5, Some(2), at).unwrap_err().to_string();
+        assert!(err.ends_with("Try again in 20h 0m."), "{}", err);
+    }
+
+    #[test]
+    fn test_format_wait() {
+        assert_eq!(format_wait(Duration::from_millis(200)), "1s");
+        assert_eq!(format_wait(Duration::from_millis(22_400)), "23s");
+        assert_eq!(format_wait(Duration::from_secs(250)), "4m 10s");
+        assert_eq!(format_wait(Duration::from_secs(5 * 3600 + 150)), "5h 3m");
+    }
+
+    #[test]
+    fn test_daily_quota_combined_with_per_minute() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+
+        // The per-minute limit is hit first and rejected requests don't use quota
+        assert!(limiter.check_request(1, 7, 2, Some(10), now).is_ok());
+        assert!(limiter.check_request(1, 7, 2, Some(10), now).is_ok());
+        assert!(limiter.check_request(1, 7, 2, Some(10), now).is_err());
+        assert_eq!(limiter.daily[&7].len(), 2);
+
+        // The daily quota follows the user across chats
+        assert!(limiter.check_request(2, 7, 2, Some(3), now).is_ok());
+        assert!(limiter.check_request(3, 7, 2, Some(3), now).is_err());
+        assert!(limiter.check_request(3, 8, 2, Some(3), now).is_ok());
+
+        assert_eq!(quota_notice(Some(10)), None);
+        assert!(quota_notice(Some(1)).unwrap().contains("1 password generation left"));
+    }
+
+    #[test]
+    fn test_batch_requests_accepted_or_rejected_whole() {
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+
+        assert!(limiter.check_requests(1, 7, 4, 5, Some(6), now).is_ok());
+        // Only one slot is left this minute, so a batch of two is refused outright
+        assert!(limiter.check_requests(1, 7, 2, 5, Some(6), now).is_err());
+        assert_eq!(limiter.requests[&1].len(), 4);
+        // Elsewhere, the daily quota has two left and a batch of three is refused
+        assert!(limiter.check_requests(2, 7, 3, 5, Some(6), now).is_err());
+        assert_eq!(limiter.check_requests(2, 7, 2, 5, Some(6), now).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_parse_password_args_annotate() {
+        let request = parse_with_length("20 --annotate", 16).unwrap();
+        assert!(request.display.annotate);
+        assert!(!parse_with_length("20", 16).unwrap().display.annotate);
+    }
+
+    #[test]
+    fn test_annotation_entities() {
+        let entities = annotation_entities("ab12!?Cd", 0);
+        assert_eq!(
+            entities,
+            vec![
+                MessageEntity::bold(2, 2),
+                MessageEntity::italic(4, 2),
+                MessageEntity::underline(6, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_annotation_entities_utf16_offsets() {
+        // "🔐 " is 3 UTF-16 code units (surrogate pair + space)
+        let prefix = "🔐 ";
+        let offset = utf16_len(prefix);
+        assert_eq!(offset, 3);
+
+        let entities = annotation_entities("x9", offset);
+        assert_eq!(entities, vec![MessageEntity::bold(4, 1)]);
+    }
+
+    #[test]
+    fn test_annotated_reply_entities_cover_password() {
+        let display = DisplayOptions {
+            annotate: true,
+            ..Default::default()
+        };
+        let (text, entities) =
+            build_password_reply("Ab1!", PasswordStrength::Weak, "meta", &display);
+        let parsed = MessageEntityRef::parse(&text, &entities);
+        let styled: Vec<&str> = parsed.iter().map(|e| e.text()).collect();
+        assert_eq!(styled, vec!["A", "1", "!"]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fires_for_slow_generator() {
+        let slow = tokio::task::spawn_blocking(|| {
+            std::thread::sleep(Duration::from_millis(500));
+            "slow".to_string()
+        });
+        let result = with_timeout(Duration::from_millis(20), slow).await;
+        assert!(matches!(result, Err(BotError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_passes_fast_generator() {
+        let config = PasswordConfig::default();
+        let result = with_timeout(
+            Duration::from_secs(5),
+            generate_off_thread(config, Vec::new(), RngSource::os().rng()),
+        )
+        .await;
+        let password = result.unwrap().unwrap();
+        assert_eq!(password.chars().count(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_seeded_rng_source_is_reproducible() {
+        let config = PasswordConfig::default();
+        let draw = |source: &RngSource| {
+            generate_off_thread(config.clone(), Vec::new(), source.rng())
+        };
+
+        let (a, b) = (RngSource::seeded(291), RngSource::seeded(291));
+        let first = draw(&a).await.unwrap();
+        assert_eq!(first, draw(&b).await.unwrap());
+        // Each request continues the stream instead of repeating the first password
+        let second = draw(&a).await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(second, draw(&b).await.unwrap());
+        assert_ne!(first, draw(&RngSource::seeded(292)).await.unwrap());
+
+        let batch = |source: &RngSource| {
+            generate_batch_off_thread(config.clone(), Vec::new(), 3, source.rng())
+        };
+        assert_eq!(
+            batch(&RngSource::seeded(7)).await.unwrap(),
+            batch(&RngSource::seeded(7)).await.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_length_bounds_reply_offers_clamp_to_max() {
+        let base = PasswordConfig::default();
+        let mut stored = StoredConfigs::default();
+        let request = parse_with_length("100 --no-symbols", 16).unwrap();
+        let (text, keyboard) =
+            length_bounds_reply(&request.config, 8, 64, Some(&mut stored)).unwrap();
+        assert!(text.contains("Maximum: 64"));
+
+        // The button keeps the request's options, only the length changes
+        let keyboard = keyboard.unwrap();
+        let button = &keyboard.inline_keyboard[0][0];
+        assert_eq!(button.text, "Generate with 64 instead?");
+        let teloxide::types::InlineKeyboardButtonKind::CallbackData(data) = &button.kind else {
+            panic!("expected callback data, got {:?}", button.kind);
+        };
+        assert_eq!(data, "regen:64:lud");
+        let clamped = resolve_regenerate_data(data, &base, &stored).unwrap();
+        assert_eq!(clamped.length, 64);
+        assert!(!clamped.use_symbols);
+    }
+
+    #[test]
+    fn test_length_bounds_reply_in_bounds_and_disabled() {
+        let mut stored = StoredConfigs::default();
+        let in_bounds = PasswordConfig::default();
+        assert!(length_bounds_reply(&in_bounds, 8, 64, Some(&mut stored)).is_none());
+
+        let short = PasswordConfig {
+            length: 4,
+            ..Default::default()
+        };
+        let (text, keyboard) = length_bounds_reply(&short, 8, 64, None).unwrap();
+        assert!(text.contains("Minimum: 8"));
+        assert!(keyboard.is_none());
+    }
+
+    #[test]
+    fn test_build_greeting_with_name() {
+        let locales = Locales::default();
+        let greeting = build_greeting(&locales, None, Some("Alex"));
+        assert!(greeting.contains("Welcome, Alex! I generate"));
+        assert!(greeting.contains("Privacy Notice"));
+    }
+
+    #[test]
+    fn test_build_greeting_without_name() {
+        let locales = Locales::default();
+        let generic = build_greeting(&locales, None, None);
+        assert!(generic.contains("Welcome! I generate"));
+        assert_eq!(build_greeting(&locales, None, Some("  \n")), generic);
+        assert!(build_greeting(&locales, None, Some("Al\nex")).contains("Welcome, Alex!"));
+    }
+
+    #[test]
+    fn test_build_greeting_translated() {
+        let locales = Locales::default();
+        let greeting = build_greeting(&locales, Some("de-DE"), Some("Alex"));
+        assert!(greeting.starts_with("🔐 Sicherer Passwort-Generator"));
+        assert!(greeting.contains("Willkommen, Alex! Ich erzeuge"));
+        let greeting = build_greeting(&locales, Some("ru"), None);
+        assert!(greeting.contains("Добро пожаловать! Я создаю"));
+        // Unsupported languages get English
+        assert_eq!(
+            build_greeting(&locales, Some("fr"), None),
+            build_greeting(&locales, None, None)
+        );
+    }
+
+    #[test]
+    fn test_branded_greeting() {
+        let locales = Locales::default();
+        // Unbranded, the built-in text is unchanged
+        assert_eq!(
+            build_branded_greeting(None, None, &locales, None, Some("Alex")),
+            build_greeting(&locales, None, Some("Alex"))
+        );
+
+        // BOT_NAME replaces the title line in every language
+        let named = build_branded_greeting(None, Some("Acme Vault"), &locales, None, None);
+        assert!(named.starts_with("🔐 Acme Vault\n\nWelcome! I generate"));
+        let named = build_branded_greeting(None, Some("Acme Vault"), &locales, Some("de"), None);
+        assert!(named.starts_with("🔐 Acme Vault\n\nWillkommen!"));
+
+        // A custom text gets the greeting and name filled in
+        let template = "{bot_name}: {welcome} Ask IT before sharing.";
+        assert_eq!(
+            build_branded_greeting(Some(template), Some("Acme"), &locales, None, Some("Alex")),
+            "Acme: Welcome, Alex! Ask IT before sharing."
+        );
+        assert_eq!(
+            build_branded_greeting(Some(template), None, &locales, Some("de"), None),
+            "Secure Password Generator Bot: Willkommen! Ask IT before sharing."
+        );
+        // A user's name is not expanded as a placeholder
+        let name = Some("{bot_name}");
+        let greeting = build_branded_greeting(Some(template), Some("Acme"), &locales, None, name);
+        assert!(greeting.contains("Welcome, {bot_name}!"));
+    }
+
+    #[test]
+    fn test_memorize_aid_keeps_clean_copy() {
+        let request = parse_with_length("--memorize", 16).unwrap();
+        assert!(request.display.memorize);
+
+        let password = "xK4f9pLm2Q";
+        let aid = memorize_aid(password);
+        assert!(aid.contains("xK4f — x-ray · KILO · four · foxtrot"));
+
+        let (text, entities) =
+            build_password_reply(password, PasswordStrength::Weak, "meta", &request.display);
+        assert_eq!(MessageEntityRef::parse(&text, &entities)[0].text(), password);
+
+        let groups: String = aid
+            .lines()
+            .skip(1)
+            .map(|line| line.split(" — ").next().unwrap())
+            .collect();
+        assert_eq!(groups, password);
+    }
+
+    #[test]
+    fn test_parse_no_confusable_symbols_combines() {
+        let config = parse_with_length("20 --no-ambiguous --no-confusable-symbols", 16)
+            .unwrap()
+            .config;
+        assert!(config.exclude_ambiguous);
+        assert!(config.exclude_confusable_symbols);
+    }
+
+    #[test]
+    fn test_parse_csv_safe() {
+        let config = parse_with_length("20 --csv-safe --no-ambiguous", 16)
+            .unwrap()
+            .config;
+        assert!(config.exclude_csv_unsafe);
+        assert!(config.exclude_ambiguous);
+        assert!(!parse_with_length("20", 16).unwrap().config.exclude_csv_unsafe);
+    }
+
+    #[test]
+    fn test_benchmark_reports_positive_rate() {
+        let bench = run_benchmark(&PasswordConfig::default(), 50).unwrap();
+        assert_eq!(bench.count, 50);
+        assert!(bench.rate() > 0.0);
+    }
+
+    #[test]
+    fn test_benchmark_respects_cap() {
+        let config = PasswordConfig {
+            length: 1,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
+            ..Default::default()
+        };
+        let bench = run_benchmark(&config, usize::MAX).unwrap();
+        assert_eq!(bench.count, MAX_BENCH_COUNT);
+    }
+
+    #[test]
+    fn test_parse_password_args_conflicting_flags() {
+        for class in ["symbols", "digits", "uppercase", "lowercase"] {
+            let args = format!("--{} --no-{}", class, class);
+            let err = parse_with_length(&args, 16).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains(&format!("Conflicting flags: --{} and --no-{}", class, class)),
+                "unexpected error for {}: {}",
+                class,
+                err
+            );
+
+            let reversed = format!("--no-{} 20 --{}", class, class);
+            assert!(parse_with_length(&reversed, 16).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_password_args_repeated_flags_tolerated() {
+        let config = parse_with_length("--symbols --symbols --no-digits --no-digits", 16)
+            .unwrap()
+            .config;
+        assert!(config.use_symbols);
+        assert!(!config.use_digits);
+    }
+
+    #[test]
+    fn test_sent_messages_panic_bookkeeping() {
+        let mut sent = SentMessages::default();
+        let start = Instant::now();
+        sent.record(1, MessageId(10), start);
+        sent.record(1, MessageId(11), start + Duration::from_secs(3600));
+        sent.record(2, MessageId(20), start);
+
+        // 47 hours after the second message, 48h+ after the first
+        let now = start + Duration::from_secs(3600) + Duration::from_secs(47 * 3600);
+        let plan = sent.take_for_panic(1, now);
+        assert_eq!(plan.deletable, vec![MessageId(11)]);
+        assert_eq!(plan.too_old, 1);
+
+        // Chat 1 is now empty, chat 2 untouched
+        assert_eq!(sent.take_for_panic(1, now), PanicPlan::default());
+        assert_eq!(sent.take_for_panic(2, start).deletable, vec![MessageId(20)]);
+    }
+
+    #[test]
+    fn test_sent_messages_bounded() {
+        let mut sent = SentMessages::default();
+        let start = Instant::now();
+        for i in 0..(MAX_TRACKED_MESSAGES as i32 + 5) {
+            sent.record(1, MessageId(i), start);
+        }
+        let plan = sent.take_for_panic(1, start);
+        assert_eq!(plan.deletable.len(), MAX_TRACKED_MESSAGES);
+        assert_eq!(plan.deletable[0], MessageId(5));
+    }
+
+    #[test]
+    fn test_parse_password_args_full_width_length() {
+        let config = parse_with_length("２４", 16).unwrap().config;
+        assert_eq!(config.length, 24);
+
+        let config = parse_with_length("٢٠ --no-symbols", 16).unwrap().config;
+        assert_eq!(config.length, 20);
+        assert!(!config.use_symbols);
+    }
+
+    #[test]
+    fn test_parse_password_args_still_rejects_non_numeric() {
+        assert!(parse_with_length("２x", 16).is_err());
+        assert!(parse_with_length("twenty", 16).is_err());
+    }
+
+    #[test]
+    fn test_feedback_cooldown() {
+        let mut cooldown = FeedbackCooldown::default();
+        let limit = Duration::from_secs(300);
+        let now = Instant::now();
+        assert!(cooldown.try_send(7, now, limit).is_ok());
+
+        let soon = now + Duration::from_secs(100);
+        assert_eq!(cooldown.try_send(7, soon, limit), Err(Duration::from_secs(200)));
+        // Refused attempts don't restart the wait, and other users aren't affected
+        assert!(cooldown.try_send(8, soon, limit).is_ok());
+        assert!(cooldown.try_send(7, now + limit, limit).is_ok());
+    }
+
+    #[test]
+    fn test_feedback_cooldown_cancelled_when_undelivered() {
+        let mut cooldown = FeedbackCooldown::default();
+        let limit = Duration::from_secs(300);
+        let now = Instant::now();
+        assert!(cooldown.try_send(7, now, limit).is_ok());
+
+        // The forward failed: the user can try again straight away
+        cooldown.cancel(7, now);
+        let retry = now + Duration::from_secs(1);
+        assert!(cooldown.try_send(7, retry, limit).is_ok());
+
+        // A stale cancel doesn't clear the cooldown of the feedback that got through
+        cooldown.cancel(7, now);
+        assert!(cooldown.try_send(7, retry + Duration::from_secs(1), limit).is_err());
+    }
+
+    #[test]
+    fn test_feedback_forward_text() {
+        assert_eq!(
+            feedback_forward_text(Some("alice"), 42, -100123, "The QR code is blurry"),
+            "📨 Feedback from @alice (id 42) in chat -100123:\n\nThe QR code is blurry"
+        );
+        let anonymous = feedback_forward_text(None, 42, 42, "hi");
+        assert!(anonymous.starts_with("📨 Feedback from user id 42 in chat 42:"), "{}", anonymous);
+    }
+
+    #[test]
+    fn test_start_cooldown() {
+        let mut cooldown = StartCooldown::default();
+        let window = Duration::from_secs(10);
+        let start = Instant::now();
+
+        assert!(cooldown.allow_full_welcome(1, start, window));
+        // Rapid repeat gets the short reply
+        assert!(!cooldown.allow_full_welcome(1, start + Duration::from_secs(2), window));
+        // Other chats are unaffected
+        assert!(cooldown.allow_full_welcome(2, start + Duration::from_secs(2), window));
+        // After the cooldown the full welcome is sent again
+        assert!(cooldown.allow_full_welcome(1, start + Duration::from_secs(11), window));
+    }
+
+    #[test]
+    fn test_parse_password_args_uses_base_config() {
+        let base = PasswordConfig {
+            length: 20,
+            use_symbols: false,
+            ..Default::default()
+        };
+        let config = parse_password_args("", &base).unwrap().config;
+        assert_eq!(config.length, 20);
+        assert!(!config.use_symbols);
+        assert!(config.use_digits);
+
+        // Flags still override the base
+        let config = parse_password_args("--symbols", &base).unwrap().config;
+        assert!(config.use_symbols);
+    }
+
+    #[test]
+    fn test_every_help_option_is_accepted() {
+        for option in crate::help::OPTION_HELP {
+            for flag in option.usage.split(" / ").filter(|f| f.starts_with("--")) {
+                // Fill in placeholder arguments such as `--dashes N`
+                let flag = flag
+                    .replace(" N", " 4")
+                    .replace("=N", "=4")
+                    .replace("<chars>", "!@#")
+                    .replace("<template>", "{Word}-{digit}");
+                assert!(
+                    parse_with_length(&flag, 16).is_ok(),
+                    "{} is documented but rejected",
+                    flag
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strip_command_prefix() {
+        assert_eq!(strip_command_prefix("/pass 24"), "24");
+        assert_eq!(strip_command_prefix("/password 24"), "24");
+        assert_eq!(strip_command_prefix("/pass"), "");
+        assert_eq!(
+            strip_command_prefix("/pass 20 --passphrase /pass"),
+            "20 --passphrase /pass"
+        );
+        assert_eq!(strip_command_prefix("/passage 24"), "/passage 24");
+    }
+
+    fn history_entry(at: Instant, length: usize) -> HistoryEntry {
+        HistoryEntry {
+            at,
+            length,
+            strength: PasswordStrength::Strong,
+        }
+    }
+
+    #[test]
+    fn test_history_is_bounded_per_chat() {
+        let mut history = GenerationHistory::default();
+        let now = Instant::now();
+        for length in 0..MAX_HISTORY_ENTRIES + 3 {
+            history.record(1, history_entry(now, length));
+        }
+        history.record(2, history_entry(now, 99));
+
+        let recent = history.recent(1);
+        assert_eq!(recent.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(recent[0].length, 3);
+        assert_eq!(recent.last().unwrap().length, MAX_HISTORY_ENTRIES + 2);
+        assert_eq!(history.recent(2).len(), 1);
+    }
+
+    #[test]
+    fn test_history_clear() {
+        let mut history = GenerationHistory::default();
+        let now = Instant::now();
+        history.record(1, history_entry(now, 16));
+        history.record(1, history_entry(now, 24));
+        history.record(2, history_entry(now, 32));
+
+        assert_eq!(history.clear(1), 2);
+        assert!(history.recent(1).is_empty());
+        assert_eq!(history.clear(1), 0);
+        assert_eq!(history.recent(2).len(), 1);
+    }
+
+    #[test]
+    fn test_format_history_newest_first() {
+        let now = Instant::now();
+        let entries = vec![history_entry(now, 16), history_entry(now, 24)];
+        let text = format_history(&entries, now + Duration::from_secs(120));
+        assert!(text.contains("1. 2m ago — 24 chars, Strong"));
+        assert!(text.contains("2. 2m ago — 16 chars, Strong"));
+        assert!(format_history(&[], now).contains("No generations"));
+    }
+
+    #[test]
+    fn test_auto_strengthen_bumps_weak_lengths() {
+        let mut config = parse_with_length("6", 16).unwrap().config;
+        let notice = auto_strengthen(&mut config, 64).unwrap();
+        assert_eq!(config.length, 8);
+        assert!(notice.contains("6→8"));
+
+        // Already Medium or better: untouched
+        let mut config = parse_with_length("16", 16).unwrap().config;
+        assert!(auto_strengthen(&mut config, 64).is_none());
+        assert_eq!(config.length, 16);
+
+        // No reachable Medium length within the maximum: untouched
+        let mut config = parse_with_length("6", 16).unwrap().config;
+        assert!(auto_strengthen(&mut config, 7).is_none());
+        assert_eq!(config.length, 6);
+    }
+
+    #[test]
+    fn test_parse_exact() {
+        assert!(parse_with_length("6 --exact", 16).unwrap().exact);
+        assert!(!parse_with_length("6", 16).unwrap().exact);
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_with_length("12 --count 5", 16).unwrap().count, 5);
+        assert_eq!(parse_with_length("12", 16).unwrap().count, 1);
+        assert!(parse_with_length("--count 0", 16).is_err());
+        assert!(parse_with_length("--count", 16).is_err());
+        assert!(parse_with_length("--count 3 --annotate", 16).is_err());
+        assert!(parse_with_length("--count 3 --dashes 4", 16).is_err());
+        // A single password keeps its display extras
+        assert!(parse_with_length("--count 1 --memorize", 16).is_ok());
+        assert!(parse_with_length("--count 3 --explain", 16).is_ok());
+    }
+
+    #[test]
+    fn test_generate_distinct() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let config = PasswordConfig::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let passwords = generate_distinct(&config, &[], 10, &mut rng).unwrap();
+        assert_eq!(passwords.len(), 10);
+        let unique: std::collections::HashSet<&String> = passwords.iter().collect();
+        assert_eq!(unique.len(), 10);
+
+        // Only ten one-digit passwords exist, so eleven distinct ones can't be found
+        let tiny = PasswordConfig {
+            length: 1,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_symbols: false,
+            ..Default::default()
+        };
+        assert!(generate_distinct(&tiny, &[], 11, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_batch_reply_lists_passwords_in_one_block() {
+        let passwords: Vec<String> = (0..10).map(|i| format!("pw{}", i)).collect();
+        let (text, entities) =
+            build_batch_reply(&passwords, PasswordStrength::Strong, "16 chars | 95.3 bits", false);
+
+        assert_eq!(text.matches("95.3 bits").count(), 1);
+        assert_eq!(entities.len(), 1);
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let block =
+            String::from_utf16(&units[entities[0].offset..][..entities[0].length]).unwrap();
+        assert!(block.starts_with(" 1. pw0\n 2. pw1\n"));
+        assert!(block.ends_with("10. pw9"));
+    }
+
+    #[test]
+    fn test_password_reply_keyboard() {
+        let keyboard = password_reply_keyboard("x7#Kq9!mP2");
+        assert_eq!(keyboard.keyboard.len(), 1);
+        assert_eq!(keyboard.keyboard[0].len(), 1);
+        assert_eq!(keyboard.keyboard[0][0].text, "x7#Kq9!mP2");
+        assert!(keyboard.one_time_keyboard.unwrap_or(false));
+
+        let request = parse_with_length("--reply-keyboard", 16).unwrap();
+        assert!(request.display.reply_keyboard);
+        assert!(parse_with_length("--count 2 --reply-keyboard", 16).is_err());
+
+        let (text, _) = build_password_reply(
+            "x7#Kq9!mP2",
+            PasswordStrength::Weak,
+            "meta",
+            &request.display,
+        );
+        assert!(text.contains("Tap the keyboard button"));
+    }
 
-                let keyboard = teloxide::types::InlineKeyboardMarkup::new(vec![
-                    vec![
-                        InlineKeyboardButton::callback("📋 Default", "pass_default"),
-                        InlineKeyboardButton::callback("🔒 Strong (24)", "pass_24"),
-                    ],
-                    vec![
-                        InlineKeyboardButton::callback("🔤 No Symbols", "pass_no_symbols"),
-                        InlineKeyboardButton::callback("🚫 Ambiguous", "pass_no_ambiguous"),
-                    ],
-                    vec![
-                        InlineKeyboardButton::callback("🔐 Very Strong (32)", "pass_32"),
-                        InlineKeyboardButton::callback("📏 Custom Length", "pass_custom"),
-                    ],
-                ]);
+    #[test]
+    fn test_parse_custom_symbols() {
+        let config = parse_with_length("20 --symbols=!@#$", 16).unwrap().config;
+        assert_eq!(config.length, 20);
+        assert!(config.use_symbols);
+        assert_eq!(config.charsets.symbols, "!@#$");
+        assert!(format_metadata(&config, estimate_strength(&config)).contains("Pool size: 66 "));
 
-                bot.answer_callback_query(&q.id).await?;
-                bot.send_message(q.from.id, help_text)
-                    .reply_markup(keyboard)
-                    .await?;
-                return Ok(());
-            }
-            _ => return Ok(()),
+        assert!(parse_with_length("--symbols=", 16).is_err());
+        assert!(parse_with_length("--symbols=!@ --no-symbols", 16).is_err());
+
+        // Multibyte symbols still give the requested number of characters
+        let config = parse_with_length("20 --symbols=§€", 16).unwrap().config;
+        let password = generate_passwords(&config, 1, &mut OsRng).unwrap().remove(0);
+        assert_eq!(password.chars().count(), 20);
+    }
+
+    #[test]
+    fn test_parse_exclude_chars() {
+        let config = parse_with_length("20 --symbols --exclude=<>\" --exclude=>;", 16)
+            .unwrap()
+            .config;
+        assert_eq!(config.exclude_chars, ['<', '>', '"', ';']);
+        assert!(config.validate().is_ok());
+        assert!(encode_regenerate_data(&config).is_none());
+
+        assert!(parse_with_length("--exclude=", 16).is_err());
+        // Every digit excluded with digits still on
+        let config = parse_with_length("--exclude=0123456789", 16).unwrap().config;
+        assert!(config.validate().is_err());
+        assert!(parse_with_length("--pattern {word}{digit} --exclude=1", 16).is_err());
+    }
+
+    #[test]
+    fn test_regenerate_data_round_trips() {
+        let base = PasswordConfig::default();
+        let configs = [
+            parse_with_length("20 --no-symbols --no-ambiguous", 16).unwrap().config,
+            parse_with_length("12 --symbols=!@#$ --csv-safe --interior-special", 16)
+                .unwrap()
+                .config,
+            pin_config(6, 4, 64).unwrap(),
+            parse_with_length("14 --pronounceable", 16).unwrap().config,
+            parse_with_length("18 --no-repeat", 16).unwrap().config,
+            wifi_config(63).unwrap(),
+        ];
+        for config in configs {
+            let data = encode_regenerate_data(&config).unwrap();
+            assert!(data.len() <= MAX_CALLBACK_DATA, "{}", data);
+            let decoded = decode_regenerate_data(&data, &base).unwrap();
+            assert_eq!(decoded.length, config.length);
+            assert_eq!(decoded.charsets, config.charsets);
+            assert_eq!(decoded.build_char_pool(), config.build_char_pool());
+            assert_eq!(decoded.interior_special, config.interior_special);
+            assert_eq!(decoded.pronounceable, config.pronounceable);
+            assert_eq!(decoded.no_repeat, config.no_repeat);
+        }
+        assert_eq!(
+            encode_regenerate_data(&PasswordConfig::default()).unwrap(),
+            "regen:16:luds"
+        );
+
+        // A deployment's AMBIGUOUS_CHARS comes back from the base config
+        let base = PasswordConfig {
+            charsets: CharSets::default().with_ambiguous("0Oo1lI5S".chars()),
+            ..Default::default()
         };
+        let config = parse_password_args("--no-ambiguous", &base).unwrap().config;
+        let data = encode_regenerate_data(&config).unwrap();
+        let decoded = decode_regenerate_data(&data, &base).unwrap();
+        assert_eq!(decoded.build_char_pool(), config.build_char_pool());
+    }
 
-        // Create a fake message for password generation
-        let chat_id = q.from.id;
-        {
-            let mut rate_limiter = state.rate_limiter.lock().await;
-            if let Err(e) = rate_limiter.check_rate_limit(chat_id.0 as i64, state.config.rate_limit_per_minute) {
-                bot.answer_callback_query(&q.id)
-                    .text(e.to_string())
-                    .await?;
-                return Ok(());
+    #[test]
+    fn test_stored_regenerate_data_round_trips() {
+        let base = PasswordConfig::default();
+        let mut stored = StoredConfigs::default();
+        let mut rng = StdRng::seed_from_u64(295);
+
+        // Short configs still travel inline, without touching the store
+        let data = regenerate_data(&base, &mut stored, &mut rng).unwrap();
+        assert_eq!(data, "regen:16:luds");
+        assert!(stored.order.is_empty());
+
+        // Too long for 64 bytes, and settings the inline format can't carry at all
+        let long = PasswordConfig {
+            charsets: CharSets::default().with_symbols(('←'..='↯').collect::<String>()),
+            ..Default::default()
+        };
+        let complex = parse_with_length("20 --symbols --exclude=<>\" --max-symbols 3", 16)
+            .unwrap()
+            .config;
+        for config in [long, complex] {
+            let data = regenerate_data(&config, &mut stored, &mut rng).unwrap();
+            assert!(data.starts_with(STORED_CONFIG_PREFIX), "{}", data);
+            assert!(data.len() <= MAX_CALLBACK_DATA, "{}", data);
+            assert_eq!(regenerate_keyboard(Some(data.clone())).inline_keyboard[0].len(), 2);
+
+            // Pressing the button again finds the same settings
+            for _ in 0..2 {
+                let decoded = resolve_regenerate_data(&data, &base, &stored).unwrap();
+                assert_eq!(decoded.length, config.length);
+                assert_eq!(decoded.charsets, config.charsets);
+                assert_eq!(decoded.exclude_chars, config.exclude_chars);
+                assert_eq!(decoded.max_symbols, config.max_symbols);
+                assert_eq!(decoded.build_char_pool(), config.build_char_pool());
             }
         }
 
-        // Parse and generate password
-        let mut password_config =
-            match parse_password_args(&message.replace("/pass", "").trim(), state.config.default_password_length)
-            {
-                Ok(config) => config,
-                Err(e) => {
-                    bot.answer_callback_query(&q.id)
-                        .text(format!("Error: {}", e))
-                        .await?;
-                    return Ok(());
-                }
-            };
+        assert!(resolve_regenerate_data("cfg:zz", &base, &stored).is_none());
+        assert!(resolve_regenerate_data("cfg:1", &base, &stored).is_none());
+    }
 
-        // Validate length bounds
-        if password_config.length < state.config.min_password_length
-            || password_config.length > state.config.max_password_length
-        {
-            bot.answer_callback_query(&q.id)
-                .text("Invalid password length")
-                .await?;
-            return Ok(());
+    #[test]
+    fn test_stored_configs_evict_oldest() {
+        let mut stored = StoredConfigs::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        let first = stored.insert(PasswordConfig::default(), &mut rng);
+        for _ in 1..MAX_STORED_CONFIGS {
+            stored.insert(PasswordConfig::default(), &mut rng);
         }
+        assert!(stored.get(first).is_some());
+        let newest = stored.insert(PasswordConfig::default(), &mut rng);
+        assert!(stored.get(first).is_none());
+        assert!(stored.get(newest).is_some());
+        assert_eq!(stored.configs.len(), MAX_STORED_CONFIGS);
+    }
 
-        // Validate configuration
-        if let Err(e) = password_config.validate() {
-            bot.answer_callback_query(&q.id)
-                .text(e.to_string())
-                .await?;
-            return Ok(());
-        }
+    #[test]
+    fn test_regenerate_data_limits() {
+        // Too many symbols to fit in 64 bytes (arrows are 3 bytes each): Clear only
+        let symbols: String = ('←'..='↯').collect();
+        let config = PasswordConfig {
+            charsets: CharSets::default().with_symbols(symbols),
+            ..Default::default()
+        };
+        assert!(encode_regenerate_data(&config).is_none());
+        assert_eq!(regenerate_keyboard(None).inline_keyboard[0].len(), 1);
+        let data = encode_regenerate_data(&PasswordConfig::default());
+        assert_eq!(regenerate_keyboard(data).inline_keyboard[0].len(), 2);
 
-        // Generate password
-        let mut rng = OsRng;
-        let password = match generate_password(&password_config, &mut rng) {
-            Ok(pwd) => pwd,
-            Err(e) => {
-                bot.answer_callback_query(&q.id)
-                    .text(format!("Failed to generate: {}", e))
-                    .await?;
-                return Ok(());
+        let base = PasswordConfig::default();
+        assert!(decode_regenerate_data("regen:x:luds", &base).is_none());
+        assert!(decode_regenerate_data("regen:16:luqs", &base).is_none());
+        assert!(decode_regenerate_data("regen:16", &base).is_none());
+        assert!(decode_regenerate_data("regen:16:s:ab", &base).is_none());
+    }
+
+    #[test]
+    fn test_self_destruct_note() {
+        assert_eq!(self_destruct_note(60), "\n\n⏳ This message self-destructs in 60s");
+    }
+
+    #[test]
+    fn test_parse_token_args() {
+        assert_eq!(
+            parse_token_args("", 64).unwrap(),
+            TokenRequest {
+                bytes: 32,
+                format: TokenFormat::Hex
+            }
+        );
+        assert_eq!(
+            parse_token_args("48 --base64", 64).unwrap(),
+            TokenRequest {
+                bytes: 48,
+                format: TokenFormat::Base64
             }
+        );
+        assert!(parse_token_args("0", 64).is_err());
+        assert!(parse_token_args("65", 64).is_err());
+        assert!(parse_token_args("--hex --base64", 64).is_err());
+        assert!(parse_token_args("--base32", 64).is_err());
+        // A small operator cap lowers the default too
+        assert_eq!(parse_token_args("", 16).unwrap().bytes, 16);
+    }
+
+    #[test]
+    fn test_token_reply() {
+        assert_eq!(TokenFormat::Hex.encode(&[0xde, 0xad]), "dead");
+        assert_eq!(TokenFormat::Base64.encode(&[0xde, 0xad]), "3q0=");
+
+        let metadata = format_token_metadata(32, TokenFormat::Base64, Default::default());
+        assert!(metadata.contains("Entropy: 256 bits"));
+        assert!(metadata.contains("Strength: Strong"));
+
+        let token = "3q2+7w==";
+        let (text, entities) = build_token_reply(token, &metadata);
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let covered =
+            String::from_utf16(&units[entities[0].offset..][..entities[0].length]).unwrap();
+        assert_eq!(covered, token);
+        assert!(text.contains(&metadata));
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let start = Instant::now();
+        let mut stats = UsageStats::new(start);
+        assert!(stats.format(start).contains("Commands: none yet"));
+
+        stats.record_command("pass");
+        stats.record_command("help");
+        stats.record_command("pass");
+        stats.record_passwords(1);
+        stats.record_passwords(5);
+        stats.record_rejection();
+        let text = stats.format(start + Duration::from_secs(7200));
+        assert!(text.contains("started 2h ago"));
+        assert!(text.contains(
+            "Passwords generated: 6\nGeneration errors: 0\nRate-limit rejections: 1"
+        ));
+        assert!(text.ends_with("Commands:\n  /pass: 2\n  /help: 1"), "{}", text);
+
+        stats.record_error();
+        let metrics = stats.to_prometheus(start + Duration::from_secs(90));
+        assert!(metrics.contains(
+            "# TYPE password_bot_passwords_generated_total counter\n\
+            password_bot_passwords_generated_total 6\n"
+        ));
+        assert!(metrics.contains("password_bot_generation_errors_total 1\n"));
+        assert!(metrics.contains("password_bot_rate_limit_rejections_total 1\n"));
+        assert!(metrics.contains(
+            "password_bot_commands_total{command=\"help\"} 1\n\
+            password_bot_commands_total{command=\"pass\"} 2\n"
+        ));
+        assert!(metrics.ends_with("password_bot_uptime_seconds 90\n"));
+    }
+
+    #[test]
+    fn test_format_check_reply() {
+        let config = config_for_password("hunter2");
+        let reply = format_check_reply("hunter2", &config, estimate_strength(&config), None);
+        assert!(reply.contains("Length: 7 | Types: lowercase, digits | Pool size: 36"));
+        assert!(reply.contains("Strength: Weak"));
+        assert!(reply.contains("not stored or logged"));
+        assert!(!reply.contains("HaveIBeenPwned"));
+        // Only the metadata is echoed back, never the password
+        assert!(!reply.contains("hunter2"));
+
+        let reply =
+            format_check_reply("hunter2", &config, estimate_strength(&config), Some("🚨 Seen"));
+        assert!(reply.contains("\n\n🚨 Seen\n\n🔒"));
+        assert!(reply.ends_with("sent to HaveIBeenPwned."));
+        assert!(!reply.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_parse_class_minimums() {
+        let config = parse_with_length("--min-digits 3 --min-symbols 2", 16).unwrap().config;
+        assert_eq!((config.min_digits, config.min_symbols), (3, 2));
+        assert_eq!((config.min_lowercase, config.min_uppercase), (0, 0));
+        assert!(parse_with_length("--min-digits", 16).is_err());
+        assert!(parse_with_length("--min-digits two", 16).is_err());
+
+        let data = encode_regenerate_data(&config).unwrap();
+        assert_eq!(data, "regen:16,0,0,3,2:luds");
+        let decoded = decode_regenerate_data(&data, &PasswordConfig::default()).unwrap();
+        assert_eq!((decoded.min_digits, decoded.min_symbols), (3, 2));
+        assert!(decode_regenerate_data("regen:16,1,2:luds", &PasswordConfig::default()).is_none());
+    }
+
+    #[test]
+    fn test_parse_qr() {
+        let request = parse_with_length("--qr", 16).unwrap();
+        assert!(request.display.qr);
+        assert!(!parse_with_length("", 16).unwrap().display.qr);
+        assert!(parse_with_length("--count 3 --qr", 16).is_err());
+    }
+
+    #[test]
+    fn test_parse_bits() {
+        let request = parse_with_length("--bits 128", 16).unwrap();
+        assert_eq!(request.bits, Some(128));
+        assert_eq!(request.config.length, 20);
+        // Later options change the pool, and so the length
+        let request = parse_with_length("--bits 128 --no-symbols --no-uppercase", 16).unwrap();
+        assert_eq!(request.config.length, 25);
+
+        assert!(parse_with_length("20 --bits 128", 16).is_err());
+        assert!(parse_with_length("--bits 0", 16).is_err());
+        assert!(parse_with_length("--bits", 16).is_err());
+    }
+
+    #[test]
+    fn test_parse_emoji() {
+        let request = parse_with_length("12 --emoji", 16).unwrap();
+        assert!(request.config.use_emoji);
+        let data = encode_regenerate_data(&request.config).unwrap();
+        assert_eq!(data, "regen:12:ludse");
+        let decoded = decode_regenerate_data(&data, &PasswordConfig::default()).unwrap();
+        assert!(decoded.use_emoji);
+        assert!(parse_with_length("--pattern {word} --emoji", 16).is_err());
+    }
+
+    #[test]
+    fn test_parse_max_symbols() {
+        let request = parse_with_length("20 --max-symbols 2", 16).unwrap();
+        assert_eq!(request.config.max_symbols, Some(2));
+        assert!(encode_regenerate_data(&request.config).is_none());
+        assert!(parse_with_length("--max-symbols", 16).is_err());
+        assert!(parse_with_length("--max-symbols lots", 16).is_err());
+    }
+
+    #[test]
+    fn test_parse_named_length() {
+        let request = parse_with_length("--length=30 --symbols", 16).unwrap();
+        assert_eq!(request.config.length, 30);
+        assert!(request.config.use_symbols);
+        assert!(request.length_warning.is_none());
+        assert_eq!(parse_with_length("--len=24", 16).unwrap().config.length, 24);
+
+        // Mixed forms: the last one wins, with a warning
+        let request = parse_with_length("20 --length=30", 16).unwrap();
+        assert_eq!(request.config.length, 30);
+        assert!(request.length_warning.unwrap().contains("(30)"));
+        let request = parse_with_length("--len=30 20", 16).unwrap();
+        assert_eq!(request.config.length, 20);
+        assert!(request.length_warning.is_some());
+        // Repeating the same value is harmless
+        assert!(parse_with_length("20 --length=20", 16).unwrap().length_warning.is_none());
+
+        assert!(parse_with_length("--length=", 16).is_err());
+        assert!(parse_with_length("--len=abc", 16).is_err());
+        assert!(parse_with_length("--length=20 --bits 128", 16).is_err());
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        let request = parse_with_length("--pattern {Word}-{digit}{digit} --qr", 16).unwrap();
+        assert_eq!(request.config.pattern.as_deref(), Some("{Word}-{digit}{digit}"));
+        // The longest possible result stands in for the length
+        assert_eq!(request.config.length, 11);
+        assert!(request.display.qr);
+
+        assert!(parse_with_length("--pattern", 16).is_err());
+        assert!(parse_with_length("--pattern {colour}", 16).is_err());
+        assert!(parse_with_length("20 --pattern {word}{digit}", 16).is_err());
+        assert!(parse_with_length("--pattern {word} --no-symbols", 16).is_err());
+        assert!(parse_with_length("--pattern {word} --bits 40", 16).is_err());
+        assert!(parse_with_length("--pattern {word} --min-digits 2", 16).is_err());
+        assert!(parse_with_length("--pattern {word} --max-symbols 1", 16).is_err());
+        assert!(encode_regenerate_data(&request.config).is_none());
+    }
+
+    #[test]
+    fn test_pattern_length_error() {
+        let config = parse_with_length("--pattern {word}{word}", 16).unwrap().config;
+        assert!(pattern_length_error(&config, 8, 16).is_none());
+        assert!(pattern_length_error(&config, 10, 64).is_some());
+        assert!(pattern_length_error(&config, 8, 12).is_some());
+        assert!(pattern_length_error(&PasswordConfig::default(), 20, 64).is_none());
+    }
+
+    #[test]
+    fn test_fit_bits_length() {
+        let mut config = parse_with_length("--bits 1000", 16).unwrap().config;
+        let warning = fit_bits_length(&mut config, 1000, 8, 64).unwrap();
+        assert_eq!(config.length, 64);
+        assert!(warning.contains("1000 bits would need 156 characters"));
+
+        let mut config = parse_with_length("--bits 20", 16).unwrap().config;
+        assert!(fit_bits_length(&mut config, 20, 8, 64).is_none());
+        assert_eq!(config.length, 8);
+    }
+
+    #[test]
+    fn test_parse_dashes() {
+        let request = parse_with_length("20 --dashes 4 --no-symbols", 16).unwrap();
+        assert_eq!(request.display.dashes, Some(4));
+        assert_eq!(request.config.length, 20);
+        assert!(!request.config.use_symbols);
+
+        assert!(parse_with_length("20 --dashes", 16).is_err());
+        assert!(parse_with_length("20 --dashes 0", 16).is_err());
+        assert!(parse_with_length("--dashes --no-symbols", 16).is_err());
+    }
+
+    #[test]
+    fn test_dashes_copy_strips_to_password() {
+        let display = DisplayOptions {
+            dashes: Some(4),
+            ..Default::default()
         };
+        for password in ["xK4f9pLm2Qvz", "ab-cd!efgh"] {
+            let (reply, entities) =
+                build_password_reply(password, PasswordStrength::Strong, "meta", &display);
+            let parsed = MessageEntityRef::parse(&reply, &entities);
+            // The clean copy is still shown on its own
+            assert_eq!(parsed[0].text(), password);
 
-        // Estimate strength
-        let strength = estimate_strength(&password_config);
-        let metadata = format_metadata(&password_config, strength);
+            let typed = parsed[1].text();
+            assert!(reply.contains(&format!("For typing: {}\n", typed)));
+            let separator = typing_separator(password);
+            assert_eq!(typed.replace(separator, ""), password);
+            assert_eq!(typed.replace(separator, "").chars().count(), password.chars().count());
+        }
+
+        let (reply, entities) = build_password_reply(
+            "xK4f9pLm2Qvz",
+            PasswordStrength::Strong,
+            "meta",
+            &display,
+        );
+        assert_eq!(MessageEntityRef::parse(&reply, &entities)[1].text(), "xK4f-9pLm-2Qvz");
+    }
+
+    #[test]
+    fn test_code_entity_needs_no_escaping() {
+        for password in ["ab`cd", "*_[x]`", "🐶`🐱"] {
+            let (text, entities) = build_password_reply(
+                password,
+                PasswordStrength::Weak,
+                "meta",
+                &DisplayOptions::default(),
+            );
+            assert!(!text.contains(&format!("`{}`", password)));
+            let parsed = MessageEntityRef::parse(&text, &entities);
+            assert_eq!(parsed.len(), 1);
+            assert_eq!(parsed[0].kind(), &MessageEntityKind::Code);
+            assert_eq!(parsed[0].text(), password);
+        }
+    }
+
+    #[test]
+    fn test_templated_reply_formats_password_as_code() {
+        let ctx = ReplyContext {
+            password: "ab12",
+            strength: PasswordStrength::Weak,
+            entropy: 20.0,
+            metadata: "meta",
+        };
+        let (text, entities) =
+            build_templated_reply("🔑 {password} ({strength})", &ctx, &DisplayOptions::default());
+        assert_eq!(text, "🔑 ab12 (Weak)");
+        // "🔑 " is three UTF-16 units
+        assert_eq!(entities, vec![MessageEntity::code(3, 4)]);
+    }
+
+    #[test]
+    fn test_spoiler_reply_covers_password_without_escaping() {
+        let display = DisplayOptions {
+            spoiler: true,
+            ..Default::default()
+        };
+        // Markdown-special characters and a surrogate pair are sent as-is
+        for password in ["a*_`[b]\\~", "🐶x*y"] {
+            let (text, entities) =
+                build_password_reply(password, PasswordStrength::Weak, "meta", &display);
+            assert!(text.contains(&format!("\n\n{}\n\n", password)));
+            let parsed = MessageEntityRef::parse(&text, &entities);
+            let styled: Vec<(&MessageEntityKind, &str)> =
+                parsed.iter().map(|e| (e.kind(), e.text())).collect();
+            assert_eq!(
+                styled,
+                vec![(&MessageEntityKind::Code, password), (&MessageEntityKind::Spoiler, password)]
+            );
+        }
 
-        let strength_emoji = match strength {
-            PasswordStrength::Strong => "💪",
-            PasswordStrength::Medium => "👍",
-            PasswordStrength::Weak => "⚠️",
+        // The copy for typing spells the password out, so it is hidden too
+        let display = DisplayOptions {
+            dashes: Some(4),
+            ..display
         };
+        let (text, entities) =
+            build_password_reply("xK4f9pLm2Qvz", PasswordStrength::Weak, "meta", &display);
+        let hidden: Vec<&str> = MessageEntityRef::parse(&text, &entities)
+            .iter()
+            .filter(|e| e.kind() == &MessageEntityKind::Spoiler)
+            .map(|e| e.text())
+            .collect();
+        assert_eq!(hidden.len(), 2);
+        assert!(hidden[1].contains("xK4f-9pLm-2Qvz"));
+        assert!(!hidden[1].contains("meta"));
+    }
 
-        let response = format!(
-            "🔐 Your Secure Password:\n\n`{}`\n\n{} {}\n\n⚠️ Security reminder: Copy this password immediately and store it securely. This message will remain in your chat history.",
-            password, strength_emoji, metadata
+    #[test]
+    fn test_spoiler_templated_and_batch_replies() {
+        let ctx = ReplyContext {
+            password: "ab*_",
+            strength: PasswordStrength::Weak,
+            entropy: 20.0,
+            metadata: "meta",
+        };
+        let display = DisplayOptions {
+            spoiler: true,
+            ..Default::default()
+        };
+        let (_, entities) = build_templated_reply("🔑 {password} ({strength})", &ctx, &display);
+        assert_eq!(
+            entities,
+            vec![MessageEntity::code(3, 4), MessageEntity::spoiler(3, 4)]
         );
 
-        bot.answer_callback_query(&q.id).await?;
-        bot.send_message(q.from.id, response).await?;
+        let passwords = vec!["a*b".to_string(), "c_d".to_string()];
+        let (text, entities) = build_batch_reply(&passwords, PasswordStrength::Weak, "meta", true);
+        let parsed = MessageEntityRef::parse(&text, &entities);
+        assert_eq!(parsed[1].kind(), &MessageEntityKind::Spoiler);
+        assert_eq!(parsed[1].text(), "1. a*b\n2. c_d");
+    }
 
-        info!(
-            "Generated password via button for user {}: {}",
-            q.from.id, metadata
+    #[test]
+    fn test_confirmation_prompt_threshold() {
+        // Below or at the threshold, or with no threshold: generate immediately
+        assert!(confirmation_prompt(64, Some(128)).is_none());
+        assert!(confirmation_prompt(128, Some(128)).is_none());
+        assert!(confirmation_prompt(500, None).is_none());
+
+        let (text, keyboard) = confirmation_prompt(256, Some(128)).unwrap();
+        assert!(text.contains("256-character"));
+        assert_eq!(keyboard.inline_keyboard[0].len(), 2);
+        assert_eq!(
+            keyboard.inline_keyboard[0][0].kind,
+            teloxide::types::InlineKeyboardButtonKind::CallbackData("confirm_length".to_string())
         );
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_pending_confirmations() {
+        let mut confirmations = PendingConfirmations::default();
+        let now = Instant::now();
+        let request = |length| PendingRequest {
+            chat_id: ChatId(1),
+            config: PasswordConfig {
+                length,
+                ..Default::default()
+            },
+            display: DisplayOptions::default(),
+            count: 1,
+            created: now,
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // A newer request replaces the older one, and taking it consumes it
+        confirmations.insert(7, request(200));
+        confirmations.insert(7, request(300));
+        assert_eq!(confirmations.take(7, now).unwrap().config.length, 300);
+        assert!(confirmations.take(7, now).is_none());
+
+        // Expired requests can't be confirmed
+        confirmations.insert(7, request(200));
+        assert!(confirmations.take(7, now + CONFIRMATION_TTL).is_none());
+    }
 
     #[test]
-    fn test_parse_password_args_default() {
-        let config = parse_password_args("", 16).unwrap();
-        assert_eq!(config.length, 16);
-        assert!(config.use_lowercase);
-        assert!(config.use_uppercase);
-        assert!(config.use_digits);
-        assert!(config.use_symbols);
+    fn test_chat_limit_clamping() {
+        let mut limits = ChatLimits::default();
+        assert_eq!(limits.limit_for(1, 10), 10);
+
+        assert_eq!(limits.set(1, 30, 50), 30);
+        assert_eq!(limits.limit_for(1, 10), 30);
+        assert_eq!(limits.set(1, 500, 50), 50);
+        assert_eq!(limits.set(1, 0, 50), 1);
+        assert_eq!(limits.limit_for(2, 10), 10);
+    }
+
+    struct MockAdmins(Vec<u64>);
+
+    impl ChatAdminLookup for MockAdmins {
+        async fn is_chat_admin(&self, _chat_id: ChatId, user_id: UserId) -> bool {
+            self.0.contains(&user_id.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setlimit_requires_group_admin() {
+        let admins = MockAdmins(vec![7]);
+        let chat = ChatId(-100);
+
+        assert!(can_set_chat_limit(&admins, chat, true, Some(UserId(7))).await);
+        assert!(!can_set_chat_limit(&admins, chat, true, Some(UserId(8))).await);
+        assert!(!can_set_chat_limit(&admins, chat, true, None).await);
+        // Private chats never qualify, even for a known admin
+        assert!(!can_set_chat_limit(&admins, ChatId(7), false, Some(UserId(7))).await);
+    }
+
+    #[tokio::test]
+    async fn test_setdefault_permissions() {
+        let admins = MockAdmins(vec![7]);
+
+        // Anyone in a private chat; only admins in a group
+        assert!(can_set_chat_defaults(&admins, ChatId(8), false, Some(UserId(8))).await);
+        assert!(can_set_chat_defaults(&admins, ChatId(-100), true, Some(UserId(7))).await);
+        assert!(!can_set_chat_defaults(&admins, ChatId(-100), true, Some(UserId(8))).await);
     }
 
     #[test]
-    fn test_parse_password_args_with_length() {
-        let config = parse_password_args("24", 16).unwrap();
-        assert_eq!(config.length, 24);
+    fn test_check_default_options() {
+        let base = PasswordConfig::default();
+        let check = |args| check_default_options(args, &base, 8, 64, 512);
+
+        assert!(check("24 --no-ambiguous").is_ok());
+        assert!(check("--pattern {Word}-{word}").is_ok());
+        assert!(check("--no-such-option").is_err());
+        assert!(check("4").is_err());
+        assert!(check("100").is_err());
+        assert!(check("--pattern {word}").is_err());
+        assert!(check("--no-lowercase --no-uppercase --no-digits --no-symbols").is_err());
     }
 
     #[test]
-    fn test_parse_password_args_with_options() {
-        let config = parse_password_args("20 --no-symbols --no-ambiguous", 16).unwrap();
-        assert_eq!(config.length, 20);
-        assert!(!config.use_symbols);
-        assert!(config.exclude_ambiguous);
+    fn test_parse_bulk_args() {
+        let base = PasswordConfig::default();
+        let parse = |args| parse_bulk_args(args, &base, 8, 64, 512, 100);
+
+        let request = parse("50").unwrap();
+        assert_eq!(request.count, 50);
+        assert_eq!(request.config.length, base.length);
+        let request = parse("20 24 --no-ambiguous").unwrap();
+        assert_eq!((request.count, request.config.length), (20, 24));
+        assert!(request.config.exclude_ambiguous);
+        assert!(parse("100").is_ok());
+
+        assert!(parse("").is_err());
+        assert!(parse("0").is_err());
+        assert!(parse("101").is_err());
+        assert!(parse("24 --count 3").is_err());
+        assert!(parse("10 --qr").is_err());
+        assert!(parse("10 4").is_err());
+        assert!(parse("10 --no-such-option").is_err());
     }
 
     #[test]
-    fn test_parse_password_args_invalid_length() {
-        let result = parse_password_args("abc", 16);
-        assert!(result.is_err());
+    fn test_bulk_caption_warns_file_is_unencrypted() {
+        let caption = bulk_caption(20, "Strength: Strong");
+        assert!(caption.starts_with("📄 20 passwords"));
+        assert!(caption.contains("Strength: Strong"));
+        assert!(caption.contains("not encrypted"));
     }
 
     #[test]
-    fn test_parse_password_args_unknown_option() {
-        let result = parse_password_args("--invalid", 16);
-        assert!(result.is_err());
+    fn test_parse_word_count() {
+        assert_eq!(parse_word_count("").unwrap(), EXPLAIN_DEFAULT_WORDS);
+        assert_eq!(parse_word_count("5words").unwrap(), 5);
+        assert_eq!(parse_word_count("6 words").unwrap(), 6);
+        assert_eq!(parse_word_count("4").unwrap(), 4);
+        assert!(parse_word_count("lots").is_err());
+        assert!(parse_word_count("50words").is_err());
     }
 
     #[test]
-    fn test_rate_limiter() {
-        let mut limiter = RateLimiter::default();
-        let chat_id = 12345;
+    fn test_explain_shows_both_entropies() {
+        use rand::{rngs::StdRng, SeedableRng};
 
-        // Should allow up to the limit
-        for _ in 0..5 {
-            assert!(limiter.check_rate_limit(chat_id, 5).is_ok());
-        }
+        let mut rng = StdRng::seed_from_u64(7);
+        let text = explain_comparison(5, &mut rng).unwrap();
 
-        // Should deny the next request
-        assert!(limiter.check_rate_limit(chat_id, 5).is_err());
+        let expected = 5.0 * (wordlist().len() as f64).log2();
+        assert!(text.contains(&format!("5-word passphrase: {:.1} bits", expected)), "{}", text);
+
+        // The random password matches the passphrase's printed length
+        let example = text.split("Example: ").nth(1).unwrap().lines().next().unwrap();
+        let length = example.chars().count();
+        let config = PasswordConfig {
+            length,
+            ..PasswordConfig::default()
+        };
+        assert!(text.contains(&format!("({} characters each)", length)));
+        assert!(text.contains(&format!("Random password: {:.1} bits", entropy_bits(&config))));
     }
-}
 
-/// Handler for unknown commands.
-pub async fn handle_unknown(bot: Bot, msg: Message) -> ResponseResult<()> {
-    let response = "❓ Unknown command. Type /help to see available commands.";
-    bot.send_message(msg.chat.id, response)
-        .await?;
-    Ok(())
+    #[test]
+    fn test_totp_reply_entities() {
+        let secret = "MZXW6YTBOI";
+        let uri = otpauth_uri(secret, "me");
+        let (text, entities) = build_totp_reply(secret, &uri);
+
+        assert!(text.contains("travels through Telegram"));
+        assert_eq!(entities.len(), 2);
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let covered = |e: &MessageEntity| {
+            String::from_utf16(&units[e.offset..e.offset + e.length]).unwrap()
+        };
+        assert_eq!(covered(&entities[0]), secret);
+        assert_eq!(covered(&entities[1]), uri);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("Tr0ub4dor&3", "Tr0ub4dor&3"));
+        assert!(constant_time_eq("", ""));
+        assert!(!constant_time_eq("Tr0ub4dor&3", "Tr0ub4dor&4"));
+        assert!(!constant_time_eq("Tr0ub4dor&3", "Tr0ub4dor"));
+        assert!(!constant_time_eq("password", "PASSWORD"));
+    }
+
+    #[test]
+    fn test_verification_flow() {
+        let mut flows = PendingVerifications::default();
+        let now = Instant::now();
+        let key = (1, 7);
+        let secret = || Zeroizing::new("hunter2".to_string());
+
+        // Replies outside a flow, or to the wrong message, are not consumed
+        assert_eq!(flows.submit(key, MessageId(10), secret(), now), None);
+        flows.start(key, MessageId(10), now);
+        assert_eq!(flows.submit(key, MessageId(99), secret(), now), None);
+
+        assert_eq!(flows.submit(key, MessageId(10), secret(), now), Some(VerifyStep::Second));
+        flows.await_second(key, MessageId(12));
+        assert_eq!(
+            flows.submit(key, MessageId(12), secret(), now),
+            Some(VerifyStep::Finished { matched: true })
+        );
+        // The flow is over once both copies were compared
+        assert_eq!(flows.submit(key, MessageId(12), secret(), now), None);
+
+        flows.start(key, MessageId(20), now);
+        flows.submit(key, MessageId(20), secret(), now);
+        let typo = Zeroizing::new("hunter3".to_string());
+        assert_eq!(
+            flows.submit(key, MessageId(20), typo, now),
+            Some(VerifyStep::Finished { matched: false })
+        );
+
+        flows.start(key, MessageId(30), now);
+        let later = now + VERIFICATION_TTL;
+        assert_eq!(flows.submit(key, MessageId(30), secret(), later), None);
+
+        // An abandoned flow holding its first copy is dropped by anyone else's /verify
+        flows.start(key, MessageId(40), now);
+        flows.submit(key, MessageId(40), secret(), now);
+        flows.start((2, 8), MessageId(41), later);
+        assert!(!flows.pending.contains_key(&key));
+        assert_eq!(flows.pending.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_seed_args() {
+        assert_eq!(
+            parse_seed_args("").unwrap(),
+            SeedRequest {
+                bytes: DEFAULT_SEED_BYTES,
+                all: false
+            }
+        );
+        assert_eq!(parse_seed_args("16 --all").unwrap(), SeedRequest { bytes: 16, all: true });
+        assert!(parse_seed_args("0").is_err());
+        assert!(parse_seed_args("65").is_err());
+        assert!(parse_seed_args("16 --hex").is_err());
+    }
+
+    #[test]
+    fn test_seed_encodings_share_bytes() {
+        use crate::encoding::{base58_decode, base64_decode, hex_decode};
+
+        let seed: Vec<u8> = (0u8..24).map(|b| b.wrapping_mul(37)).collect();
+        let (text, entities) = build_seed_reply(&seed, true);
+        assert!(text.contains("24 bytes (192 bits of entropy)"));
+        assert_eq!(entities.len(), 3);
+
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let value = |i: usize| {
+            let e = &entities[i];
+            String::from_utf16(&units[e.offset..e.offset + e.length]).unwrap()
+        };
+        assert_eq!(hex_decode(&value(0)).unwrap(), seed);
+        assert_eq!(base64_decode(&value(1)).unwrap(), seed);
+        assert_eq!(base58_decode(&value(2)).unwrap(), seed);
+
+        let (_, entities) = build_seed_reply(&seed, false);
+        assert_eq!(entities.len(), 1);
+    }
+
+    #[test]
+    fn test_explain_flag_reports_both_entropies() {
+        let request = parse_with_length("8 --explain", 16).unwrap();
+        assert!(request.display.explain);
+
+        let note = explain_entropy(&request.config);
+        assert!(note.contains(&format!("{:.1} bits.", policy_aware_entropy(&request.config))));
+        assert!(note.contains(&format!("{:.1}-bit", entropy_bits(&request.config))));
+    }
+
+    #[test]
+    fn test_parse_passphrase_args() {
+        assert_eq!(
+            parse_passphrase_args("").unwrap(),
+            PassphraseRequest {
+                words: DEFAULT_PASSPHRASE_WORDS,
+                separator: "-".to_string(),
+                transforms: PassphraseTransforms::default(),
+            }
+        );
+        let title_case = PassphraseTransforms {
+            title_case: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_passphrase_args("5 --separator · --capitalize").unwrap(),
+            PassphraseRequest {
+                words: 5,
+                separator: "·".to_string(),
+                transforms: title_case,
+            }
+        );
+        assert_eq!(
+            parse_passphrase_args("--leet --title-case --all-caps").unwrap().transforms,
+            PassphraseTransforms {
+                title_case: true,
+                all_caps: true,
+                leet: true,
+            }
+        );
+        assert!(parse_passphrase_args("0").is_err());
+        assert!(parse_passphrase_args("5 --separator").is_err());
+        assert!(parse_passphrase_args("--separator 123456789").is_err());
+        assert!(parse_passphrase_args("5 --symbols").is_err());
+    }
+
+    #[test]
+    fn test_overlong_args_rejected_before_parsing() {
+        let base = PasswordConfig::default();
+
+        // Junk that would otherwise fail as an unknown option is caught by the length check
+        let junk = "--junk ".repeat(100);
+        let err = parse_bounded_password_args(&junk, &base, 256).unwrap_err();
+        assert!(err.to_string().contains("Arguments too long."), "{}", err);
+
+        let request = parse_bounded_password_args("20 --no-symbols", &base, 256).unwrap();
+        assert_eq!(request.config.length, 20);
+        assert!(check_args_len(&"x".repeat(256), 256).is_ok());
+        assert!(check_args_len(&"x".repeat(257), 256).is_err());
+    }
 }
@@ -1,6 +1,60 @@
 /// Configuration management for the Telegram password bot.
 use crate::error::{BotError, Result};
+use crate::password::{
+    CharSets, PasswordConfig, StrengthThresholds, AMBIGUOUS, DEFAULT_MEDIUM_ENTROPY_MAX,
+    DEFAULT_WEAK_ENTROPY_MAX, MAX_ENTROPY_THRESHOLD,
+};
+use crate::policy::GenerationPolicy;
+use crate::template::validate_template;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Default length of the rate limit window.
+pub const DEFAULT_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// How the rate limit window is measured (`RATE_LIMIT_STRATEGY`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitStrategy {
+    /// Count requests in the window ending now, so capacity frees up one request at a time.
+    #[default]
+    Sliding,
+    /// Count requests since the window opened with the first request; the whole
+    /// allowance comes back at once when it closes.
+    Fixed,
+}
+
+impl RateLimitStrategy {
+    /// Parse a `RATE_LIMIT_STRATEGY` value.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sliding" => Some(Self::Sliding),
+            "fixed" => Some(Self::Fixed),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sliding => "sliding",
+            Self::Fixed => "fixed",
+        }
+    }
+}
+
+/// Describe a rate limit window for users: "minute", "5 minutes" or "90 seconds".
+pub fn describe_window(window: Duration) -> String {
+    match window.as_secs() {
+        1 => "1 second".to_string(),
+        60 => "minute".to_string(),
+        secs if secs % 60 == 0 => format!("{} minutes", secs / 60),
+        secs => format!("{} seconds", secs),
+    }
+}
+
+/// Telegram only lets bots delete messages younger than 48 hours.
+pub const DELETABLE_WINDOW: Duration = Duration::from_secs(48 * 60 * 60);
 
 /// Main application configuration loaded from environment variables.
 #[derive(Debug, Clone)]
@@ -13,8 +67,124 @@ pub struct Config {
     pub max_password_length: usize,
     /// Minimum allowed password length.
     pub min_password_length: usize,
-    /// Maximum password generation requests per chat per minute.
+    /// Maximum password generation requests per chat per rate limit window.
     pub rate_limit_per_minute: usize,
+    /// Abort startup if the Telegram command menu cannot be set.
+    pub require_command_menu: bool,
+    /// Commands, without the slash, that reply `command_disabled` instead.
+    pub disabled_commands: Vec<String>,
+    /// Substrings no generated password may contain (case-insensitive).
+    pub forbidden_substrings: Vec<String>,
+    /// Maximum time in seconds to spend generating and sending one password.
+    pub request_timeout_secs: u64,
+    /// Offer a button to retry at the nearest valid length on out-of-bounds requests.
+    pub suggest_nearest_length: bool,
+    /// Greet users by their first name in /start.
+    pub personalize_greeting: bool,
+    /// Telegram user IDs allowed to run operator commands.
+    pub admin_user_ids: Vec<u64>,
+    /// Seconds during which a repeated /start gets a short reply instead of the welcome.
+    pub start_cooldown_secs: u64,
+    /// Chat that /feedback messages are forwarded to; `None` turns /feedback off.
+    pub feedback_chat_id: Option<i64>,
+    /// Seconds each user must wait between /feedback messages.
+    pub feedback_cooldown_secs: u64,
+    /// Include lowercase letters unless the user opts out.
+    pub default_use_lowercase: bool,
+    /// Include uppercase letters unless the user opts out.
+    pub default_use_uppercase: bool,
+    /// Include digits unless the user opts out.
+    pub default_use_digits: bool,
+    /// Include symbols unless the user opts out.
+    pub default_use_symbols: bool,
+    /// Exclude ambiguous characters by default.
+    pub default_exclude_ambiguous: bool,
+    /// Maximum number of options listed on one /help page.
+    pub help_options_per_page: usize,
+    /// Secret used to HMAC-sign audit log lines, if set.
+    pub audit_hmac_key: Option<String>,
+    /// Directory of `<lang>.json` language packs merged over the built-ins.
+    pub locale_dir: Option<PathBuf>,
+    /// Name shown in the /start title in place of the built-in one.
+    pub bot_name: Option<String>,
+    /// File the /start text was loaded from.
+    pub welcome_message_path: Option<PathBuf>,
+    /// Operator /start text replacing the built-in welcome, if set.
+    pub welcome_message: Option<String>,
+    /// Lengthen requests that would be Weak up to the shortest Medium length.
+    pub auto_strengthen: bool,
+    /// Maximum generations per user per rolling 24 hours; `None` for unlimited.
+    pub daily_quota_per_user: Option<usize>,
+    /// Vault server address for /store (requires the `vault` feature).
+    pub vault_addr: Option<String>,
+    /// Vault token used by /store.
+    pub vault_token: Option<String>,
+    /// KV v2 mount /store writes under.
+    pub vault_mount: String,
+    /// Look /check passwords up in HaveIBeenPwned (requires the `hibp` feature).
+    pub enable_hibp: bool,
+    /// Operator layout for the password reply; built-in format if unset.
+    pub reply_template: Option<String>,
+    /// Hide passwords behind a spoiler that is revealed by tapping.
+    pub use_spoiler_formatting: bool,
+    /// Lengths above this need a button-tap confirmation; `None` to never ask.
+    pub confirm_length_threshold: Option<usize>,
+    /// Highest per-minute limit group admins may set with /setlimit.
+    pub max_chat_rate_limit: usize,
+    /// Extra attempts for startup calls to Telegram before giving up.
+    pub startup_retries: u32,
+    /// Delay before the first startup retry; doubles on each further retry.
+    pub startup_retry_delay_secs: u64,
+    /// JSON file the generation policy was loaded from.
+    pub policy_file: Option<PathBuf>,
+    /// Per-class proportions `/pass` honors; `None` for plain random generation.
+    pub policy: Option<GenerationPolicy>,
+    /// Handler panics within the window that restart the dispatcher; 0 never restarts.
+    pub panic_restart_threshold: usize,
+    /// Window, in seconds, over which handler panics are counted.
+    pub panic_window_secs: u64,
+    /// Fail generation rather than ever output non-ASCII characters.
+    pub strict_ascii: bool,
+    /// Entropy, in bits, below which a password is rated Weak.
+    pub weak_entropy_max: f64,
+    /// Entropy, in bits, below which a password is rated Medium rather than Strong.
+    pub medium_entropy_max: f64,
+    /// Longest `/pass` argument string accepted, in bytes.
+    pub max_args_len: usize,
+    /// Most passwords a single `/pass --count` request may generate.
+    pub max_batch_count: usize,
+    /// Most passwords a single `/bulk` file may contain.
+    pub max_bulk_count: usize,
+    /// Charge a `--count` batch as one rate-limit request per password, not one in total.
+    pub batch_counts_each: bool,
+    /// Shortest `/pin` accepted; separate from `min_password_length` as PINs are short.
+    pub min_pin_length: usize,
+    /// Warn users when a password repeats one generated for them before.
+    pub dedup_tagging: bool,
+    /// Per-deployment secret keying the duplicate-detection tags.
+    pub dedup_salt: Option<String>,
+    /// Requests each member may make per window in a chat, on top of the chat's limit.
+    pub rate_limit_per_user: Option<usize>,
+    /// JSON file the rate limiter is saved to and restored from; `None` keeps it in memory.
+    pub rate_limit_state_path: Option<PathBuf>,
+    /// Seconds between saves of the rate limiter state.
+    pub rate_limit_flush_secs: u64,
+    /// Length of the window the per-user rate limit counts requests over, in seconds.
+    pub rate_limit_window_secs: u64,
+    /// Whether that window slides or resets all at once.
+    pub rate_limit_strategy: RateLimitStrategy,
+    /// Shortest gap between generations in a chat, in seconds; 0 disables it.
+    pub min_interval_secs: u64,
+    /// Delete password messages this many seconds after sending them, if set.
+    pub auto_delete_seconds: Option<u64>,
+    /// Largest random token `/token` will generate, in bytes.
+    pub max_token_bytes: usize,
+    /// Characters `--no-ambiguous` (and `DEFAULT_EXCLUDE_AMBIGUOUS`) strip from the pool.
+    pub ambiguous_chars: String,
+    /// Port to serve Prometheus metrics on; `None` runs no listener.
+    pub metrics_port: Option<u16>,
+    /// File the per-chat `/setdefault` options are saved to; `None` keeps them in memory.
+    pub preferences_path: Option<PathBuf>,
 }
 
 impl Config {
@@ -27,7 +197,62 @@ impl Config {
     /// - `DEFAULT_PASSWORD_LENGTH`: Default password length (default: 16).
     /// - `MAX_PASSWORD_LENGTH`: Maximum password length (default: 64).
     /// - `MIN_PASSWORD_LENGTH`: Minimum password length (default: 8).
-    /// - `RATE_LIMIT_PER_MINUTE`: Max requests per chat per minute (default: 10).
+    /// - `RATE_LIMIT_PER_MINUTE`: Max requests per chat per window (default: 10).
+    /// - `REQUIRE_COMMAND_MENU`: Fail startup if the command menu can't be set (default: false).
+    /// - `DISABLED_COMMANDS`: Comma-separated commands to turn off, e.g. `password,check`
+    ///   (default: none).
+    /// - `FORBIDDEN_SUBSTRINGS`: Comma-separated substrings passwords must avoid (default: none).
+    /// - `REQUEST_TIMEOUT_SECS`: Time budget for generating and sending a password (default: 10).
+    /// - `SUGGEST_NEAREST_LENGTH`: Offer the nearest valid length on bad lengths (default: true).
+    /// - `PERSONALIZE_GREETING`: Greet users by first name in /start (default: false).
+    /// - `ADMIN_USER_IDS`: Comma-separated user IDs allowed to run operator commands (default: none).
+    /// - `START_COOLDOWN_SECS`: Cooldown between full /start welcomes per chat (default: 10).
+    /// - `FEEDBACK_CHAT_ID`: Chat /feedback is forwarded to (default: none, /feedback off).
+    /// - `FEEDBACK_COOLDOWN_SECS`: Wait between /feedback messages per user (default: 300).
+    /// - `DEFAULT_LOWERCASE`, `DEFAULT_UPPERCASE`, `DEFAULT_DIGITS`, `DEFAULT_SYMBOLS`:
+    ///   Character classes enabled when the user doesn't say otherwise (default: true).
+    /// - `DEFAULT_EXCLUDE_AMBIGUOUS`: Exclude ambiguous characters by default (default: false).
+    /// - `HELP_OPTIONS_PER_PAGE`: Options listed per /help page (default: 6).
+    /// - `AUDIT_HMAC_KEY`: Secret for signing audit log lines (default: unsigned).
+    /// - `LOCALE_DIR`: Directory of JSON language packs (default: built-in English only).
+    /// - `BOT_NAME`: Name shown in the /start title (default: built-in).
+    /// - `WELCOME_MESSAGE_PATH`: UTF-8 file replacing the /start text (default: built-in).
+    /// - `AUTO_STRENGTHEN`: Bump Weak requests to the shortest Medium length (default: false).
+    /// - `DAILY_QUOTA_PER_USER`: Max generations per user per 24 hours (default: unlimited).
+    /// - `VAULT_ADDR`, `VAULT_TOKEN`: Vault server and token for /store (default: unset).
+    /// - `VAULT_MOUNT`: KV v2 mount /store writes under (default: secret).
+    /// - `ENABLE_HIBP`: Check /check passwords against HaveIBeenPwned (default: false).
+    /// - `REPLY_TEMPLATE`: Password reply layout with `{password}`, `{strength}`,
+    ///   `{entropy}` and `{metadata}` placeholders; `\n` for newlines (default: built-in).
+    /// - `USE_SPOILER_FORMATTING`: Hide passwords until tapped (default: false).
+    /// - `CONFIRM_LENGTH_THRESHOLD`: Ask for confirmation above this length (default: never).
+    /// - `MAX_CHAT_RATE_LIMIT`: Highest per-minute limit /setlimit may set (default: 60).
+    /// - `STARTUP_RETRIES`: Retries when Telegram is unreachable at startup (default: 3).
+    /// - `STARTUP_RETRY_DELAY_SECS`: Initial delay between startup retries (default: 2).
+    /// - `POLICY_FILE`: JSON generation policy with per-class proportions (default: none).
+    /// - `PANIC_RESTART_THRESHOLD`: Handler panics that restart the dispatcher (default: 5).
+    /// - `PANIC_WINDOW_SECS`: Window over which handler panics are counted (default: 60).
+    /// - `STRICT_ASCII`: Refuse to generate non-ASCII passwords (default: false).
+    /// - `WEAK_ENTROPY_MAX`: Bits below which a password is Weak (default: 50).
+    /// - `MEDIUM_ENTROPY_MAX`: Bits below which a password is Medium (default: 80).
+    /// - `MAX_ARGS_LEN`: Longest `/pass` arguments accepted, in bytes (default: 256).
+    /// - `MAX_BATCH_COUNT`: Most passwords one `/pass --count` may generate (default: 10).
+    /// - `MAX_BULK_COUNT`: Most passwords one `/bulk` file may contain (default: 100).
+    /// - `BATCH_COUNTS_EACH`: Rate-limit a batch per password, not as one (default: false).
+    /// - `MIN_PIN_LENGTH`: Shortest numeric PIN `/pin` generates (default: 4).
+    /// - `DEDUP_TAGGING`: Warn when a user gets a repeated password (default: false).
+    /// - `DEDUP_SALT`: Secret keying the duplicate tags; required with `DEDUP_TAGGING`.
+    /// - `RATE_LIMIT_PER_USER`: Max requests per member of a chat per window (default: none).
+    /// - `RATE_LIMIT_STATE_PATH`: File that keeps rate limits across restarts (default: none).
+    /// - `RATE_LIMIT_FLUSH_SECS`: Seconds between saves of that file (default: 30).
+    /// - `RATE_LIMIT_WINDOW_SECS`: Length of the rate limit window (default: 60).
+    /// - `RATE_LIMIT_STRATEGY`: `sliding` or `fixed` rate limit window (default: sliding).
+    /// - `MIN_INTERVAL_SECS`: Shortest gap between generations in a chat (default: 0, off).
+    /// - `AUTO_DELETE_SECONDS`: Delete password messages after this long (default: never).
+    /// - `MAX_TOKEN_BYTES`: Largest `/token` in random bytes (default: 64).
+    /// - `AMBIGUOUS_CHARS`: Characters treated as ambiguous (default: `0Oo1lI`).
+    /// - `METRICS_PORT`: Serve Prometheus metrics at `/metrics` on this port (default: off).
+    /// - `PREFERENCES_PATH`: File keeping `/setdefault` options across restarts (default: none).
     pub fn from_env() -> Result<Self> {
         // Required: bot token
         let bot_token = env::var("TELEGRAM_BOT_TOKEN").map_err(|_| {
@@ -44,6 +269,16 @@ impl Config {
             ));
         }
 
+        // Catch copy-paste mistakes here rather than as an opaque API error later
+        if !is_bot_token(&bot_token) {
+            return Err(BotError::Config(
+                "TELEGRAM_BOT_TOKEN doesn't look like a bot token: expected the bot id, \
+                 a colon and 35 or more letters, digits, '_' or '-' (like 123456:ABC-...). \
+                 Copy it again from @BotFather, without quotes or spaces."
+                    .to_string(),
+            ));
+        }
+
         // Optional: default password length
         let default_password_length = env::var("DEFAULT_PASSWORD_LENGTH")
             .ok()
@@ -68,36 +303,782 @@ impl Config {
             .and_then(|s| s.parse::<usize>().ok())
             .unwrap_or(10);
 
-        // Validate configuration
-        if min_password_length == 0 {
+        // Optional: whether the command menu is mandatory at startup
+        let require_command_menu = env::var("REQUIRE_COMMAND_MENU")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: commands a locked-down deployment doesn't offer
+        let disabled_commands = env::var("DISABLED_COMMANDS")
+            .map(|s| parse_command_list(&s))
+            .unwrap_or(Ok(Vec::new()))?;
+
+        // Optional: substrings generated passwords must not contain
+        let forbidden_substrings = env::var("FORBIDDEN_SUBSTRINGS")
+            .map(|s| parse_list(&s))
+            .unwrap_or_default();
+
+        // Optional: per-request timeout
+        let request_timeout_secs = env::var("REQUEST_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(10);
+
+        // Optional: offer a clamped length when a request is out of bounds
+        let suggest_nearest_length = env::var("SUGGEST_NEAREST_LENGTH")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(true);
+
+        // Optional: personalize the /start greeting
+        let personalize_greeting = env::var("PERSONALIZE_GREETING")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: operator user IDs
+        let admin_user_ids = match env::var("ADMIN_USER_IDS") {
+            Ok(s) => parse_list(&s)
+                .iter()
+                .map(|id| id.parse::<u64>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| BotError::Config(format!("ADMIN_USER_IDS must be numeric: {}", e)))?,
+            Err(_) => Vec::new(),
+        };
+
+        // Optional: cooldown between full /start welcomes
+        let start_cooldown_secs = env::var("START_COOLDOWN_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(10);
+
+        // Optional: where /feedback goes, and how often each user may send it
+        let feedback_chat_id = match env::var("FEEDBACK_CHAT_ID") {
+            Ok(s) => Some(s.trim().parse::<i64>().map_err(|e| {
+                BotError::Config(format!("FEEDBACK_CHAT_ID must be a numeric chat ID: {}", e))
+            })?),
+            Err(_) => None,
+        };
+        let feedback_cooldown_secs = env::var("FEEDBACK_COOLDOWN_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(300);
+
+        // Optional: character class defaults applied before user options
+        let class_default = |name: &str, default: bool| {
+            env::var(name)
+                .ok()
+                .and_then(|s| parse_bool(&s))
+                .unwrap_or(default)
+        };
+        let default_use_lowercase = class_default("DEFAULT_LOWERCASE", true);
+        let default_use_uppercase = class_default("DEFAULT_UPPERCASE", true);
+        let default_use_digits = class_default("DEFAULT_DIGITS", true);
+        let default_use_symbols = class_default("DEFAULT_SYMBOLS", true);
+        let default_exclude_ambiguous = class_default("DEFAULT_EXCLUDE_AMBIGUOUS", false);
+
+        // Optional: /help pagination
+        let help_options_per_page = env::var("HELP_OPTIONS_PER_PAGE")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(6);
+
+        // Optional: audit log signing key
+        let audit_hmac_key = env::var("AUDIT_HMAC_KEY").ok().filter(|k| !k.is_empty());
+
+        // Optional: extra language packs
+        let locale_dir = env::var("LOCALE_DIR").ok().map(PathBuf::from);
+
+        // Optional: white-label branding for /start
+        let bot_name = env::var("BOT_NAME")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let welcome_message_path = env::var("WELCOME_MESSAGE_PATH")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from);
+        let welcome_message = welcome_message_path
+            .as_deref()
+            .map(load_welcome_message)
+            .transpose()?;
+
+        // Optional: bump Weak requests to Medium
+        let auto_strengthen = env::var("AUTO_STRENGTHEN")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: per-user daily quota (0 or unset means unlimited)
+        let daily_quota_per_user = env::var("DAILY_QUOTA_PER_USER")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&quota| quota > 0);
+
+        // Optional: Vault secrets backend for /store
+        let vault_addr = env::var("VAULT_ADDR").ok().filter(|s| !s.is_empty());
+        let vault_token = env::var("VAULT_TOKEN").ok().filter(|s| !s.is_empty());
+        let vault_mount = env::var("VAULT_MOUNT").unwrap_or_else(|_| "secret".to_string());
+
+        // Optional: breach lookups for /check
+        let enable_hibp = env::var("ENABLE_HIBP")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: custom reply layout
+        let reply_template = env::var("REPLY_TEMPLATE")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.replace("\\n", "\n"));
+        let use_spoiler_formatting = env::var("USE_SPOILER_FORMATTING")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: confirm unusually long requests
+        let confirm_length_threshold = env::var("CONFIRM_LENGTH_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+
+        // Optional: ceiling for per-chat limits set by group admins
+        let max_chat_rate_limit = env::var("MAX_CHAT_RATE_LIMIT")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(60);
+
+        // Optional: ride out Telegram being briefly unreachable at boot
+        let startup_retries = env::var("STARTUP_RETRIES")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(3);
+        let startup_retry_delay_secs = env::var("STARTUP_RETRY_DELAY_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(2);
+
+        // Optional: declarative class proportions for /pass
+        let policy_file = env::var("POLICY_FILE")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from);
+        let policy = policy_file
+            .as_deref()
+            .map(GenerationPolicy::load)
+            .transpose()?;
+
+        // Optional: restart the dispatcher when handlers keep panicking
+        let panic_restart_threshold = env::var("PANIC_RESTART_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(5);
+        let panic_window_secs = env::var("PANIC_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(60);
+
+        // Optional: guarantee ASCII-only output
+        let strict_ascii = env::var("STRICT_ASCII")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: organization-specific strength cutoffs
+        let weak_entropy_max = env::var("WEAK_ENTROPY_MAX")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_WEAK_ENTROPY_MAX);
+        let medium_entropy_max = env::var("MEDIUM_ENTROPY_MAX")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_MEDIUM_ENTROPY_MAX);
+
+        // Optional: bound the work done parsing /pass arguments
+        let max_args_len = env::var("MAX_ARGS_LEN")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(256);
+
+        // Optional: cap and rate-limit accounting for /pass --count batches
+        let max_batch_count = env::var("MAX_BATCH_COUNT")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(10);
+        let max_bulk_count = env::var("MAX_BULK_COUNT")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(100);
+        let batch_counts_each = env::var("BATCH_COUNTS_EACH")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+
+        // Optional: PINs bypass MIN_PASSWORD_LENGTH and use their own minimum
+        let min_pin_length = env::var("MIN_PIN_LENGTH")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(4);
+
+        // Optional: salted-hash duplicate detection (passwords themselves are never kept)
+        let dedup_tagging = env::var("DEDUP_TAGGING")
+            .ok()
+            .and_then(|s| parse_bool(&s))
+            .unwrap_or(false);
+        let dedup_salt = env::var("DEDUP_SALT").ok().filter(|s| !s.is_empty());
+
+        // Optional: a separate per-user limit within each chat
+        let rate_limit_per_user = env::var("RATE_LIMIT_PER_USER")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+
+        // Optional: persist rate limits across restarts
+        let rate_limit_state_path = env::var("RATE_LIMIT_STATE_PATH")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+        let rate_limit_flush_secs = env::var("RATE_LIMIT_FLUSH_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(30);
+
+        // Optional: how the per-user rate limit window is measured
+        let rate_limit_window_secs = env::var("RATE_LIMIT_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_RATE_LIMIT_WINDOW.as_secs());
+        let rate_limit_strategy = match env::var("RATE_LIMIT_STRATEGY") {
+            Ok(name) => RateLimitStrategy::from_name(&name).ok_or_else(|| {
+                BotError::Config(format!(
+                    "RATE_LIMIT_STRATEGY must be 'sliding' or 'fixed', not '{}'",
+                    name
+                ))
+            })?,
+            Err(_) => RateLimitStrategy::default(),
+        };
+        let min_interval_secs = env::var("MIN_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+
+        // Optional: self-destructing password messages
+        let auto_delete_seconds = env::var("AUTO_DELETE_SECONDS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok());
+
+        // Optional: cap /token sizes
+        let max_token_bytes = env::var("MAX_TOKEN_BYTES")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(64);
+
+        // Optional: which characters count as ambiguous
+        let ambiguous_chars = env::var("AMBIGUOUS_CHARS")
+            .unwrap_or_else(|_| AMBIGUOUS.iter().collect());
+
+        // Optional: Prometheus listener alongside the long-poll dispatcher
+        let metrics_port = env::var("METRICS_PORT")
+            .ok()
+            .and_then(|s| s.parse::<u16>().ok());
+
+        // Optional: keep /setdefault options across restarts
+        let preferences_path = env::var("PREFERENCES_PATH")
+            .ok()
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from);
+
+        let config = Config {
+            bot_token,
+            default_password_length,
+            max_password_length,
+            min_password_length,
+            rate_limit_per_minute,
+            require_command_menu,
+            disabled_commands,
+            forbidden_substrings,
+            request_timeout_secs,
+            suggest_nearest_length,
+            personalize_greeting,
+            admin_user_ids,
+            start_cooldown_secs,
+            feedback_chat_id,
+            feedback_cooldown_secs,
+            default_use_lowercase,
+            default_use_uppercase,
+            default_use_digits,
+            default_use_symbols,
+            default_exclude_ambiguous,
+            help_options_per_page,
+            audit_hmac_key,
+            locale_dir,
+            bot_name,
+            welcome_message_path,
+            welcome_message,
+            auto_strengthen,
+            daily_quota_per_user,
+            vault_addr,
+            vault_token,
+            vault_mount,
+            enable_hibp,
+            reply_template,
+            use_spoiler_formatting,
+            confirm_length_threshold,
+            max_chat_rate_limit,
+            startup_retries,
+            startup_retry_delay_secs,
+            policy_file,
+            policy,
+            panic_restart_threshold,
+            panic_window_secs,
+            strict_ascii,
+            weak_entropy_max,
+            medium_entropy_max,
+            max_args_len,
+            max_batch_count,
+            max_bulk_count,
+            batch_counts_each,
+            min_pin_length,
+            dedup_tagging,
+            dedup_salt,
+            rate_limit_per_user,
+            rate_limit_state_path,
+            rate_limit_flush_secs,
+            rate_limit_window_secs,
+            rate_limit_strategy,
+            min_interval_secs,
+            auto_delete_seconds,
+            max_token_bytes,
+            ambiguous_chars,
+            metrics_port,
+            preferences_path,
+        };
+        config.validate_invariants()?;
+        Ok(config)
+    }
+
+    /// Check the relationships between settings that `from_env` can't enforce per variable.
+    pub fn validate_invariants(&self) -> Result<()> {
+        if self.min_password_length == 0 {
             return Err(BotError::Config(
                 "MIN_PASSWORD_LENGTH must be greater than 0".to_string(),
             ));
         }
 
-        if max_password_length < min_password_length {
+        if self.help_options_per_page == 0 {
+            return Err(BotError::Config(
+                "HELP_OPTIONS_PER_PAGE must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_args_len == 0 {
+            return Err(BotError::Config(
+                "MAX_ARGS_LEN must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_token_bytes == 0 {
+            return Err(BotError::Config(
+                "MAX_TOKEN_BYTES must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.ambiguous_chars.is_empty() {
+            return Err(BotError::Config(
+                "AMBIGUOUS_CHARS must list at least one character".to_string(),
+            ));
+        }
+        if let Some(c) = self
+            .ambiguous_chars
+            .chars()
+            .find(|c| c.is_whitespace() || c.is_control())
+        {
+            return Err(BotError::Config(format!(
+                "AMBIGUOUS_CHARS can't contain whitespace or control characters, found {:?}",
+                c
+            )));
+        }
+
+        if self.metrics_port == Some(0) {
+            return Err(BotError::Config(
+                "METRICS_PORT must be between 1 and 65535".to_string(),
+            ));
+        }
+
+        if self.max_batch_count == 0 {
+            return Err(BotError::Config(
+                "MAX_BATCH_COUNT must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.max_bulk_count == 0 {
+            return Err(BotError::Config(
+                "MAX_BULK_COUNT must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.panic_window_secs == 0 {
+            return Err(BotError::Config(
+                "PANIC_WINDOW_SECS must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.request_timeout_secs == 0 {
+            return Err(BotError::Config(
+                "REQUEST_TIMEOUT_SECS must be greater than 0".to_string(),
+            ));
+        }
+
+        if !(self.default_use_lowercase
+            || self.default_use_uppercase
+            || self.default_use_digits
+            || self.default_use_symbols)
+        {
+            return Err(BotError::Config(
+                "At least one DEFAULT_* character class must be enabled".to_string(),
+            ));
+        }
+
+        if self.max_password_length < self.min_password_length {
             return Err(BotError::Config(format!(
                 "MAX_PASSWORD_LENGTH ({}) must be >= MIN_PASSWORD_LENGTH ({})",
-                max_password_length, min_password_length
+                self.max_password_length, self.min_password_length
             )));
         }
 
-        if default_password_length < min_password_length
-            || default_password_length > max_password_length
+        if self.default_password_length < self.min_password_length
+            || self.default_password_length > self.max_password_length
         {
             return Err(BotError::Config(format!(
                 "DEFAULT_PASSWORD_LENGTH ({}) must be between {} and {}",
-                default_password_length, min_password_length, max_password_length
+                self.default_password_length, self.min_password_length, self.max_password_length
             )));
         }
 
-        Ok(Config {
-            bot_token,
-            default_password_length,
-            max_password_length,
-            min_password_length,
-            rate_limit_per_minute,
+        if self.min_pin_length == 0 || self.min_pin_length > self.max_password_length {
+            return Err(BotError::Config(format!(
+                "MIN_PIN_LENGTH ({}) must be between 1 and MAX_PASSWORD_LENGTH ({})",
+                self.min_pin_length, self.max_password_length
+            )));
+        }
+
+        // Telegram refuses to let bots delete messages older than 48 hours
+        if let Some(secs) = self.auto_delete_seconds {
+            if secs == 0 || secs >= DELETABLE_WINDOW.as_secs() {
+                return Err(BotError::Config(format!(
+                    "AUTO_DELETE_SECONDS ({}) must be between 1 and {}",
+                    secs,
+                    DELETABLE_WINDOW.as_secs() - 1
+                )));
+            }
+        }
+
+        if self.rate_limit_flush_secs == 0 {
+            return Err(BotError::Config(
+                "RATE_LIMIT_FLUSH_SECS must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.rate_limit_window_secs == 0 {
+            return Err(BotError::Config(
+                "RATE_LIMIT_WINDOW_SECS must be greater than 0".to_string(),
+            ));
+        }
+
+        let (weak, medium) = (self.weak_entropy_max, self.medium_entropy_max);
+        if !(weak > 0.0 && weak < medium && medium <= MAX_ENTROPY_THRESHOLD) {
+            return Err(BotError::Config(format!(
+                "WEAK_ENTROPY_MAX ({}) must be above 0 and below MEDIUM_ENTROPY_MAX ({}), \
+                 which can be at most {}",
+                self.weak_entropy_max, self.medium_entropy_max, MAX_ENTROPY_THRESHOLD
+            )));
+        }
+
+        if self.rate_limit_per_user == Some(0) {
+            return Err(BotError::Config(
+                "RATE_LIMIT_PER_USER must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.dedup_tagging && self.dedup_salt.is_none() {
+            return Err(BotError::Config(
+                "DEDUP_TAGGING requires a DEDUP_SALT".to_string(),
+            ));
+        }
+
+        if self.enable_hibp && !cfg!(feature = "hibp") {
+            return Err(BotError::Config(
+                "ENABLE_HIBP needs a build with --features hibp".to_string(),
+            ));
+        }
+
+        if let Some(tmpl) = &self.reply_template {
+            validate_template(tmpl)?;
+        }
+
+        if let Some(policy) = &self.policy {
+            policy.seed_plan(&self.base_password_config()).map_err(|e| {
+                BotError::Config(format!(
+                    "POLICY_FILE cannot be met at DEFAULT_PASSWORD_LENGTH: {}",
+                    e
+                ))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Human-readable effective configuration, with secrets redacted.
+    pub fn describe(&self) -> String {
+        let redact = |secret: &str| if secret.is_empty() { "(unset)" } else { "<redacted>" };
+        let list = |items: Vec<String>| {
+            if items.is_empty() {
+                "(none)".to_string()
+            } else {
+                items.join(",")
+            }
+        };
+
+        [
+            format!("TELEGRAM_BOT_TOKEN={}", redact(&self.bot_token)),
+            format!("DEFAULT_PASSWORD_LENGTH={}", self.default_password_length),
+            format!("MIN_PASSWORD_LENGTH={}", self.min_password_length),
+            format!("MAX_PASSWORD_LENGTH={}", self.max_password_length),
+            format!("RATE_LIMIT_PER_MINUTE={}", self.rate_limit_per_minute),
+            format!("REQUIRE_COMMAND_MENU={}", self.require_command_menu),
+            format!("DISABLED_COMMANDS={}", list(self.disabled_commands.clone())),
+            format!("FORBIDDEN_SUBSTRINGS={}", list(self.forbidden_substrings.clone())),
+            format!("REQUEST_TIMEOUT_SECS={}", self.request_timeout_secs),
+            format!("SUGGEST_NEAREST_LENGTH={}", self.suggest_nearest_length),
+            format!("PERSONALIZE_GREETING={}", self.personalize_greeting),
+            format!(
+                "ADMIN_USER_IDS={}",
+                list(self.admin_user_ids.iter().map(u64::to_string).collect())
+            ),
+            format!("START_COOLDOWN_SECS={}", self.start_cooldown_secs),
+            format!(
+                "FEEDBACK_CHAT_ID={}",
+                self.feedback_chat_id.map_or("(off)".to_string(), |id| id.to_string())
+            ),
+            format!("FEEDBACK_COOLDOWN_SECS={}", self.feedback_cooldown_secs),
+            format!("DEFAULT_LOWERCASE={}", self.default_use_lowercase),
+            format!("DEFAULT_UPPERCASE={}", self.default_use_uppercase),
+            format!("DEFAULT_DIGITS={}", self.default_use_digits),
+            format!("DEFAULT_SYMBOLS={}", self.default_use_symbols),
+            format!("DEFAULT_EXCLUDE_AMBIGUOUS={}", self.default_exclude_ambiguous),
+            format!("HELP_OPTIONS_PER_PAGE={}", self.help_options_per_page),
+            format!(
+                "AUDIT_HMAC_KEY={}",
+                redact(self.audit_hmac_key.as_deref().unwrap_or_default())
+            ),
+            format!(
+                "LOCALE_DIR={}",
+                self.locale_dir
+                    .as_ref()
+                    .map_or("(none)".to_string(), |d| d.display().to_string())
+            ),
+            format!("BOT_NAME={}", self.bot_name.as_deref().unwrap_or("(built-in)")),
+            format!(
+                "WELCOME_MESSAGE_PATH={}",
+                self.welcome_message_path
+                    .as_ref()
+                    .map_or("(built-in)".to_string(), |f| f.display().to_string())
+            ),
+            format!("AUTO_STRENGTHEN={}", self.auto_strengthen),
+            format!(
+                "DAILY_QUOTA_PER_USER={}",
+                self.daily_quota_per_user
+                    .map_or("(unlimited)".to_string(), |q| q.to_string())
+            ),
+            format!(
+                "VAULT_ADDR={}",
+                self.vault_addr.as_deref().unwrap_or("(none)")
+            ),
+            format!(
+                "VAULT_TOKEN={}",
+                redact(self.vault_token.as_deref().unwrap_or_default())
+            ),
+            format!("VAULT_MOUNT={}", self.vault_mount),
+            format!("ENABLE_HIBP={}", self.enable_hibp),
+            format!(
+                "REPLY_TEMPLATE={}",
+                self.reply_template
+                    .as_deref()
+                    .map_or("(built-in)".to_string(), |t| t.replace('\n', "\\n"))
+            ),
+            format!("USE_SPOILER_FORMATTING={}", self.use_spoiler_formatting),
+            format!(
+                "CONFIRM_LENGTH_THRESHOLD={}",
+                self.confirm_length_threshold
+                    .map_or("(never)".to_string(), |t| t.to_string())
+            ),
+            format!("MAX_CHAT_RATE_LIMIT={}", self.max_chat_rate_limit),
+            format!("STARTUP_RETRIES={}", self.startup_retries),
+            format!("STARTUP_RETRY_DELAY_SECS={}", self.startup_retry_delay_secs),
+            format!(
+                "POLICY_FILE={}",
+                self.policy_file
+                    .as_ref()
+                    .map_or("(none)".to_string(), |f| f.display().to_string())
+            ),
+            format!("PANIC_RESTART_THRESHOLD={}", self.panic_restart_threshold),
+            format!("PANIC_WINDOW_SECS={}", self.panic_window_secs),
+            format!("STRICT_ASCII={}", self.strict_ascii),
+            format!("WEAK_ENTROPY_MAX={}", self.weak_entropy_max),
+            format!("MEDIUM_ENTROPY_MAX={}", self.medium_entropy_max),
+            format!("MAX_ARGS_LEN={}", self.max_args_len),
+            format!("MAX_BATCH_COUNT={}", self.max_batch_count),
+            format!("MAX_BULK_COUNT={}", self.max_bulk_count),
+            format!("BATCH_COUNTS_EACH={}", self.batch_counts_each),
+            format!("MIN_PIN_LENGTH={}", self.min_pin_length),
+            format!("DEDUP_TAGGING={}", self.dedup_tagging),
+            format!(
+                "DEDUP_SALT={}",
+                redact(self.dedup_salt.as_deref().unwrap_or_default())
+            ),
+            format!(
+                "RATE_LIMIT_PER_USER={}",
+                self.rate_limit_per_user
+                    .map_or("(none)".to_string(), |n| n.to_string())
+            ),
+            format!(
+                "RATE_LIMIT_STATE_PATH={}",
+                self.rate_limit_state_path
+                    .as_ref()
+                    .map_or("(none)".to_string(), |p| p.display().to_string())
+            ),
+            format!("RATE_LIMIT_FLUSH_SECS={}", self.rate_limit_flush_secs),
+            format!("RATE_LIMIT_WINDOW_SECS={}", self.rate_limit_window_secs),
+            format!("RATE_LIMIT_STRATEGY={}", self.rate_limit_strategy.name()),
+            format!("MIN_INTERVAL_SECS={}", self.min_interval_secs),
+            format!(
+                "AUTO_DELETE_SECONDS={}",
+                self.auto_delete_seconds
+                    .map_or("(never)".to_string(), |n| n.to_string())
+            ),
+            format!("MAX_TOKEN_BYTES={}", self.max_token_bytes),
+            format!("AMBIGUOUS_CHARS={}", self.ambiguous_chars),
+            format!(
+                "METRICS_PORT={}",
+                self.metrics_port.map_or("(off)".to_string(), |p| p.to_string())
+            ),
+            format!(
+                "PREFERENCES_PATH={}",
+                self.preferences_path
+                    .as_ref()
+                    .map_or("(none)".to_string(), |p| p.display().to_string())
+            ),
+        ]
+        .join("\n")
+    }
+
+    /// Whether `DISABLED_COMMANDS` turns off the command `name` (without the slash).
+    pub fn is_command_disabled(&self, name: &str) -> bool {
+        self.disabled_commands.iter().any(|disabled| disabled == name)
+    }
+
+    /// The window the rate limits apply to.
+    pub fn rate_limit_window(&self) -> Duration {
+        Duration::from_secs(self.rate_limit_window_secs)
+    }
+
+    /// The `WEAK_ENTROPY_MAX` and `MEDIUM_ENTROPY_MAX` cutoffs strength is rated against.
+    pub fn strength_thresholds(&self) -> StrengthThresholds {
+        StrengthThresholds {
+            weak_max: self.weak_entropy_max,
+            medium_max: self.medium_entropy_max,
+        }
+    }
+
+    /// The password settings `/pass` starts from before applying user options.
+    pub fn base_password_config(&self) -> PasswordConfig {
+        PasswordConfig {
+            length: self.default_password_length,
+            use_lowercase: self.default_use_lowercase,
+            use_uppercase: self.default_use_uppercase,
+            use_digits: self.default_use_digits,
+            use_symbols: self.default_use_symbols,
+            exclude_ambiguous: self.default_exclude_ambiguous,
+            policy: self.policy.clone(),
+            strict_ascii: self.strict_ascii,
+            strength_thresholds: self.strength_thresholds(),
+            charsets: CharSets::default().with_ambiguous(self.ambiguous_chars.chars()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether `token` has the shape of a BotFather token: `\d+:[A-Za-z0-9_-]{35,}`.
+fn is_bot_token(token: &str) -> bool {
+    let Some((id, secret)) = token.split_once(':') else {
+        return false;
+    };
+    !id.is_empty()
+        && id.bytes().all(|b| b.is_ascii_digit())
+        && secret.len() >= 35
+        && secret
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+/// Parse a comma-separated list, trimming entries and dropping empty ones.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse `DISABLED_COMMANDS`: command names, with or without the leading slash,
+/// matched case-insensitively like Telegram does.
+fn parse_command_list(value: &str) -> Result<Vec<String>> {
+    parse_list(value)
+        .into_iter()
+        .map(|name| {
+            let name = name.trim_start_matches('/').to_ascii_lowercase();
+            if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+                return Err(BotError::Config(format!(
+                    "DISABLED_COMMANDS entries must be command names like 'check', not '{}'",
+                    name
+                )));
+            }
+            Ok(name)
         })
+        .collect()
+}
+
+/// Telegram's limit on the text of one message, in characters.
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+/// Read the `WELCOME_MESSAGE_PATH` file: non-empty UTF-8 that fits in one message.
+fn load_welcome_message(path: &Path) -> Result<String> {
+    let invalid = |msg: String| {
+        BotError::Config(format!("WELCOME_MESSAGE_PATH {}: {}", path.display(), msg))
+    };
+    let bytes = fs::read(path).map_err(|e| invalid(e.to_string()))?;
+    let text = String::from_utf8(bytes).map_err(|_| invalid("not valid UTF-8".to_string()))?;
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        return Err(invalid("file is empty".to_string()));
+    }
+    if text.chars().count() > MAX_MESSAGE_CHARS {
+        return Err(invalid(format!(
+            "longer than Telegram's {}-character message limit",
+            MAX_MESSAGE_CHARS
+        )));
+    }
+    Ok(text.to_string())
+}
+
+/// Parse a boolean flag from an environment variable value.
+///
+/// Accepts `true`/`false`, `1`/`0`, `yes`/`no` and `on`/`off` (case-insensitive).
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
     }
 }
 
@@ -114,10 +1095,198 @@ mod tests {
             max_password_length: 64,
             min_password_length: 8,
             rate_limit_per_minute: 10,
+            require_command_menu: false,
+            disabled_commands: Vec::new(),
+            forbidden_substrings: Vec::new(),
+            request_timeout_secs: 10,
+            suggest_nearest_length: true,
+            personalize_greeting: false,
+            admin_user_ids: Vec::new(),
+            start_cooldown_secs: 10,
+            feedback_chat_id: None,
+            feedback_cooldown_secs: 300,
+            default_use_lowercase: true,
+            default_use_uppercase: true,
+            default_use_digits: true,
+            default_use_symbols: false,
+            default_exclude_ambiguous: false,
+            help_options_per_page: 6,
+            audit_hmac_key: None,
+            locale_dir: None,
+            bot_name: None,
+            welcome_message_path: None,
+            welcome_message: None,
+            auto_strengthen: false,
+            daily_quota_per_user: None,
+            vault_addr: None,
+            vault_token: None,
+            vault_mount: "secret".to_string(),
+            enable_hibp: false,
+            reply_template: None,
+            use_spoiler_formatting: false,
+            confirm_length_threshold: None,
+            max_chat_rate_limit: 60,
+            startup_retries: 3,
+            startup_retry_delay_secs: 2,
+            policy_file: None,
+            policy: None,
+            panic_restart_threshold: 5,
+            panic_window_secs: 60,
+            strict_ascii: false,
+            weak_entropy_max: 50.0,
+            medium_entropy_max: 80.0,
+            max_args_len: 256,
+            max_batch_count: 10,
+            max_bulk_count: 100,
+            batch_counts_each: false,
+            min_pin_length: 4,
+            dedup_tagging: false,
+            dedup_salt: None,
+            rate_limit_per_user: None,
+            rate_limit_state_path: None,
+            rate_limit_flush_secs: 30,
+            rate_limit_window_secs: 60,
+            rate_limit_strategy: RateLimitStrategy::Sliding,
+            min_interval_secs: 0,
+            auto_delete_seconds: None,
+            max_token_bytes: 64,
+            ambiguous_chars: "0Oo1lI5S2ZB8".to_string(),
+            metrics_port: None,
+            preferences_path: None,
         };
 
         assert!(config.default_password_length >= config.min_password_length);
         assert!(config.default_password_length <= config.max_password_length);
         assert!(config.max_password_length >= config.min_password_length);
+
+        let base = config.base_password_config();
+        assert_eq!(base.length, config.default_password_length);
+        assert!(!base.use_symbols);
+        assert!(base.use_lowercase);
+        assert_eq!(base.charsets.ambiguous.len(), 12);
+
+        let strict = PasswordConfig {
+            exclude_ambiguous: true,
+            ..base
+        };
+        assert!(!strict.build_char_pool().iter().any(|c| "5S2ZB8".contains(*c)));
+        assert!(config.validate_invariants().is_ok());
+        assert!(Config {
+            ambiguous_chars: String::new(),
+            ..config.clone()
+        }
+        .validate_invariants()
+        .is_err());
+
+        assert_eq!(base.strength_thresholds, StrengthThresholds::default());
+        let invalid = [
+            (80.0, 80.0),
+            (0.0, 80.0),
+            (60.0, f64::INFINITY),
+            (60.0, 1e15),
+            (f64::NAN, 80.0),
+        ];
+        for (weak, medium) in invalid {
+            let thresholds = Config {
+                weak_entropy_max: weak,
+                medium_entropy_max: medium,
+                ..config.clone()
+            };
+            assert!(thresholds.validate_invariants().is_err(), "{} {}", weak, medium);
+        }
+        assert!(Config {
+            weak_entropy_max: 100.0,
+            medium_entropy_max: MAX_ENTROPY_THRESHOLD,
+            ..config.clone()
+        }
+        .validate_invariants()
+        .is_ok());
+
+        // Breach checks can only be turned on in builds that have them
+        let hibp = Config {
+            enable_hibp: true,
+            ..config.clone()
+        };
+        assert_eq!(hibp.validate_invariants().is_ok(), cfg!(feature = "hibp"));
+
+        // Deletion has to happen while Telegram still allows it
+        let last = DELETABLE_WINDOW.as_secs() - 1;
+        for (secs, ok) in [(0, false), (1, true), (last, true), (last + 1, false)] {
+            let auto_delete = Config {
+                auto_delete_seconds: Some(secs),
+                ..config.clone()
+            };
+            assert_eq!(auto_delete.validate_invariants().is_ok(), ok, "{}", secs);
+        }
+    }
+
+    #[test]
+    fn test_load_welcome_message() {
+        let dir = std::env::temp_dir().join(format!("welcome-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, bytes: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).unwrap();
+            path
+        };
+
+        let ok = write("ok.txt", "🔐 {bot_name}\n\n{welcome} Acme passwords.\n".as_bytes());
+        assert_eq!(
+            load_welcome_message(&ok).unwrap(),
+            "🔐 {bot_name}\n\n{welcome} Acme passwords."
+        );
+
+        let missing = load_welcome_message(&dir.join("missing.txt")).unwrap_err();
+        assert!(matches!(missing, BotError::Config(_)));
+        let latin1 = load_welcome_message(&write("latin1.txt", b"Gr\xfc\xdfe")).unwrap_err();
+        assert!(latin1.to_string().contains("not valid UTF-8"));
+        let empty = load_welcome_message(&write("empty.txt", b" \n\n")).unwrap_err();
+        assert!(empty.to_string().contains("empty"));
+        let long = write("long.txt", "x".repeat(MAX_MESSAGE_CHARS + 1).as_bytes());
+        assert!(load_welcome_message(&long).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_bool() {
+        assert_eq!(parse_bool("true"), Some(true));
+        assert_eq!(parse_bool("ON"), Some(true));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("no"), Some(false));
+        assert_eq!(parse_bool("maybe"), None);
+    }
+
+    #[test]
+    fn test_is_bot_token() {
+        assert!(is_bot_token("123456789:AAHfiqksKZ8WmR2zSjiQ7_v4TMAKdiHm9T0"));
+        assert!(is_bot_token("42:abcdefghij-klmnopqrst_uvwxyz0123456789"));
+        // Too short, missing id, stray characters or quotes
+        assert!(!is_bot_token("123456789:AAHfiqksKZ8WmR2zSjiQ7"));
+        assert!(!is_bot_token(":AAHfiqksKZ8WmR2zSjiQ7_v4TMAKdiHm9T0"));
+        assert!(!is_bot_token("bot123:AAHfiqksKZ8WmR2zSjiQ7_v4TMAKdiHm9T0"));
+        assert!(!is_bot_token("123456789:AAHfiqksKZ8WmR2zSjiQ7_v4TMAKdiHm9T0 "));
+        assert!(!is_bot_token("\"123456789:AAHfiqksKZ8WmR2zSjiQ7_v4TMAKdiHm9T0\""));
+        assert!(!is_bot_token("test_token"));
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list("acme, password,,admin "),
+            vec!["acme", "password", "admin"]
+        );
+        assert!(parse_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_command_list() {
+        assert_eq!(
+            parse_command_list("password, /Check,,").unwrap(),
+            vec!["password", "check"]
+        );
+        assert!(parse_command_list("").unwrap().is_empty());
+        assert!(parse_command_list("pass word").is_err());
+        assert!(parse_command_list("check, /").is_err());
     }
 }
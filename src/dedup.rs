@@ -0,0 +1,99 @@
+/// Duplicate detection for generated passwords, without keeping the passwords.
+///
+/// Only a keyed hash (HMAC-SHA256 under the deployment's `DEDUP_SALT`) of each
+/// password is remembered. The salt keeps tags from being checked against a
+/// dictionary by anyone who sees them but not the configuration.
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Tags remembered per user; the oldest are forgotten first.
+pub const MAX_TAGS_PER_USER: usize = 1000;
+
+/// Verification tag for `password` generated for `user_id`.
+///
+/// The user id is part of the MAC input, so the same password has different tags
+/// for different users.
+pub fn tag(salt: &[u8], user_id: u64, password: &str) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(salt).expect("HMAC accepts keys of any length");
+    mac.update(&user_id.to_be_bytes());
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Recent password tags per user.
+#[derive(Debug, Default)]
+pub struct DedupTags {
+    tags: HashMap<u64, VecDeque<[u8; 32]>>,
+}
+
+impl DedupTags {
+    /// Remember `password` for `user_id` and report whether it was generated before.
+    pub fn check_and_record(&mut self, salt: &[u8], user_id: u64, password: &str) -> bool {
+        let tag = tag(salt, user_id, password);
+        let tags = self.tags.entry(user_id).or_default();
+        if tags.contains(&tag) {
+            return true;
+        }
+        if tags.len() >= MAX_TAGS_PER_USER {
+            tags.pop_front();
+        }
+        tags.push_back(tag);
+        false
+    }
+}
+
+/// Warning shown when `repeats` of the passwords just sent were generated before.
+pub fn duplicate_warning(repeats: usize, total: usize) -> Option<String> {
+    match repeats {
+        0 => None,
+        _ if total == 1 => Some(
+            "♻️ This exact password was generated for you before. Don't reuse it \
+            across accounts; generate another."
+                .to_string(),
+        ),
+        n => Some(format!(
+            "♻️ {} of these passwords were generated for you before. Don't reuse them \
+            across accounts.",
+            n
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password::{generate_password, PasswordConfig};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_repeated_password_is_flagged() {
+        let config = PasswordConfig::default();
+        let generate = |seed| generate_password(&config, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let mut tags = DedupTags::default();
+
+        assert!(!tags.check_and_record(b"salt", 7, &generate(1)));
+        // The same seed reproduces the same password, which is caught
+        assert!(tags.check_and_record(b"salt", 7, &generate(1)));
+        assert!(duplicate_warning(1, 1).is_some());
+        // A different password, or the same one for another user, is not
+        assert!(!tags.check_and_record(b"salt", 7, &generate(2)));
+        assert!(!tags.check_and_record(b"salt", 8, &generate(1)));
+        assert!(duplicate_warning(0, 1).is_none());
+    }
+
+    #[test]
+    fn test_tags_are_salted_and_bounded() {
+        assert_ne!(tag(b"one", 7, "hunter2"), tag(b"two", 7, "hunter2"));
+
+        let mut tags = DedupTags::default();
+        tags.check_and_record(b"salt", 7, "first");
+        for i in 0..MAX_TAGS_PER_USER {
+            tags.check_and_record(b"salt", 7, &i.to_string());
+        }
+        assert_eq!(tags.tags[&7].len(), MAX_TAGS_PER_USER);
+        assert!(!tags.check_and_record(b"salt", 7, "first"));
+    }
+}
@@ -0,0 +1,151 @@
+/// Text encodings for raw random bytes.
+///
+/// Hex, base64 and base58 cover what most tools accept for keys and seeds.
+/// The bot only encodes; the test-only decoders sit next to each encoder so
+/// round trips can be checked.
+use std::iter;
+
+/// Standard base64 alphabet (RFC 4648).
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Bitcoin base58 alphabet: no 0, O, I or l.
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Lowercase hex.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex (either case); `None` if it is malformed.
+#[cfg(test)]
+pub fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => u8::from_str_radix(std::str::from_utf8(&[*hi, *lo]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Standard base64 with `=` padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode padded standard base64; `None` if it is malformed.
+#[cfg(test)]
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        if chunk.len() != 4 {
+            return None;
+        }
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)?;
+            n = (n << 6) | value as u32;
+        }
+        n <<= 6 * padding;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+/// Base58 with the Bitcoin alphabet; leading zero bytes become leading `1`s.
+pub fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|&&b| b == 0).count();
+
+    // Repeatedly divide the big-endian number by 58, collecting remainders
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &bytes[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in digits.iter_mut() {
+            carry += u32::from(*digit) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    iter::repeat_n('1', zeros)
+        .chain(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize] as char))
+        .collect()
+}
+
+/// Decode Bitcoin-alphabet base58; `None` if it contains other characters.
+#[cfg(test)]
+pub fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in text.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += u32::from(*byte) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    Some(iter::repeat_n(0, zeros).chain(bytes.into_iter().rev()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_vectors() {
+        assert_eq!(hex_encode(b"\x00\xffab"), "00ff6162");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_encode(&[]), "");
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected() {
+        assert_eq!(hex_decode("abc"), None);
+        assert_eq!(hex_decode("zz"), None);
+        assert_eq!(base64_decode("Zg="), None);
+        assert_eq!(base64_decode("Z==="), None);
+        assert_eq!(base58_decode("0OIl"), None);
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let samples: [&[u8]; 5] = [b"", b"\x00", b"\x00\x00\x07", b"seed", &[0xff; 33]];
+        for bytes in samples {
+            assert_eq!(hex_decode(&hex_encode(bytes)).unwrap(), bytes);
+            assert_eq!(base64_decode(&base64_encode(bytes)).unwrap(), bytes);
+            assert_eq!(base58_decode(&base58_encode(bytes)).unwrap(), bytes);
+        }
+    }
+}
@@ -16,6 +16,24 @@ pub enum BotError {
     #[error("Rate limit exceeded: {0}")]
     RateLimit(String),
 
+    /// A request exceeded its time budget.
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// QR code rendering errors (password too long to encode).
+    #[error("QR code error: {0}")]
+    Qr(String),
+
+    /// Secrets backend errors (Vault unreachable, auth failure, bad path).
+    #[cfg(feature = "vault")]
+    #[error("Vault error: {0}")]
+    Vault(String),
+
+    /// Breach lookup errors (HaveIBeenPwned unreachable or returning an error).
+    #[cfg(feature = "hibp")]
+    #[error("Breach check error: {0}")]
+    Hibp(String),
+
     /// Telegram API errors.
     #[error("Telegram error: {0}")]
     Telegram(#[from] teloxide::RequestError),
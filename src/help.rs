@@ -0,0 +1,412 @@
+/// Paginated /help content.
+///
+/// The options listing is built from [`OPTION_HELP`] rather than one large string,
+/// grouped into sections and split into pages of at most `HELP_OPTIONS_PER_PAGE`
+/// options so the help stays readable as options are added.
+use crate::config::{describe_window, Config};
+use std::collections::HashMap;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+/// Help sections, in page order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpSection {
+    Basic,
+    Classes,
+    Exclusions,
+    Formats,
+    Advanced,
+}
+
+impl HelpSection {
+    pub const ALL: [HelpSection; 5] = [
+        HelpSection::Basic,
+        HelpSection::Classes,
+        HelpSection::Exclusions,
+        HelpSection::Formats,
+        HelpSection::Advanced,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            HelpSection::Basic => "📘 Basics",
+            HelpSection::Classes => "🔤 Character Classes",
+            HelpSection::Exclusions => "🚫 Exclusions",
+            HelpSection::Formats => "🎨 Display Formats",
+            HelpSection::Advanced => "⚙️ Advanced",
+        }
+    }
+}
+
+/// Documentation for one `/pass` option.
+#[derive(Debug)]
+pub struct OptionHelp {
+    pub section: HelpSection,
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+/// Every `/pass` option, grouped by section.
+pub const OPTION_HELP: &[OptionHelp] = &[
+    OptionHelp {
+        section: HelpSection::Basic,
+        usage: "[length]",
+        description: "Number of characters to generate",
+    },
+    OptionHelp {
+        section: HelpSection::Basic,
+        usage: "--length=N / --len=N",
+        description: "Same as [length], as a named option",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--symbols / --no-symbols",
+        description: "Include or exclude symbols",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--symbols=<chars>",
+        description: "Use only these symbols, e.g. --symbols=!@#$",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--digits / --no-digits",
+        description: "Include or exclude digits",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--uppercase / --no-uppercase",
+        description: "Include or exclude uppercase letters",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--lowercase / --no-lowercase",
+        description: "Include or exclude lowercase letters",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--min-digits N / --min-symbols N / --min-uppercase N / --min-lowercase N",
+        description: "Require at least N of a type (1 by default)",
+    },
+    OptionHelp {
+        section: HelpSection::Classes,
+        usage: "--max-symbols N",
+        description: "Allow at most N symbols, for sites that cap them",
+    },
+    OptionHelp {
+        section: HelpSection::Exclusions,
+        usage: "--exclude=<chars>",
+        description: "Never use these characters, e.g. --exclude=<>\"",
+    },
+    OptionHelp {
+        section: HelpSection::Exclusions,
+        usage: "--no-ambiguous / --ambiguous",
+        description: "Exclude (or allow) confusing characters (0,O,o,1,l,I)",
+    },
+    OptionHelp {
+        section: HelpSection::Exclusions,
+        usage: "--no-confusable-symbols",
+        description: "Drop one of each look-alike symbol pair (, . ; : - _ ...)",
+    },
+    OptionHelp {
+        section: HelpSection::Exclusions,
+        usage: "--csv-safe",
+        description: "Exclude characters that break CSV/TSV exports (, \" ;)",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--annotate",
+        description: "Style digits, symbols and capitals differently",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--memorize",
+        description: "Add a grouped, spelled-out version to help memorize",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--dashes N",
+        description: "Also show a copy with a dash every N characters for typing",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--count N",
+        description: "Generate N independent passwords in one numbered list",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--reply-keyboard",
+        description: "Offer the password as a keyboard button (private chats)",
+    },
+    OptionHelp {
+        section: HelpSection::Formats,
+        usage: "--qr",
+        description: "Also send a QR code to scan the password onto another device",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--explain",
+        description: "Show entropy against an attacker who knows the bot's rules",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--json",
+        description: "Also send length, entropy, strength and pool size as JSON",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--interior-special",
+        description: "Never start or end with a digit or symbol",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--no-repeat",
+        description: "Never put the same character twice in a row",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--pronounceable",
+        description: "Syllables like bakudo-fepi42: easier to type, lower entropy",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--emoji",
+        description: "Mix 32 emoji into the pool; many sites reject them",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--exact",
+        description: "Keep the requested length even if it is weak",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--preview",
+        description: "Show the pool and strength without generating a password",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--bits N",
+        description: "Pick the shortest length that reaches N bits of entropy",
+    },
+    OptionHelp {
+        section: HelpSection::Advanced,
+        usage: "--pattern <template>",
+        description: "Fill in {Word}, {word}, {digit}, {symbol}, {upper}, {lower}, \
+                      e.g. {Word}-{word}-{digit}{digit}",
+    },
+];
+
+/// One page of help: a section and the options shown on it.
+#[derive(Debug)]
+pub struct HelpPage {
+    pub section: HelpSection,
+    pub options: Vec<&'static OptionHelp>,
+}
+
+/// Split [`OPTION_HELP`] into pages of at most `per_page` options.
+///
+/// Every section gets at least one page, even if it has no options.
+pub fn help_pages(per_page: usize) -> Vec<HelpPage> {
+    let per_page = per_page.max(1);
+    let mut pages = Vec::new();
+
+    for section in HelpSection::ALL {
+        let options: Vec<&OptionHelp> = OPTION_HELP
+            .iter()
+            .filter(|o| o.section == section)
+            .collect();
+
+        if options.is_empty() {
+            pages.push(HelpPage {
+                section,
+                options: Vec::new(),
+            });
+        }
+        for chunk in options.chunks(per_page) {
+            pages.push(HelpPage {
+                section,
+                options: chunk.to_vec(),
+            });
+        }
+    }
+
+    pages
+}
+
+/// Render help page `index` (clamped to the last page).
+pub fn render_help_page(index: usize, config: &Config) -> String {
+    let pages = help_pages(config.help_options_per_page);
+    let index = index.min(pages.len() - 1);
+    let page = &pages[index];
+
+    let mut text = format!(
+        "🔐 Password Generator - Help ({}/{})\n\n{}\n\n",
+        index + 1,
+        pages.len(),
+        page.section.title()
+    );
+
+    if page.section == HelpSection::Basic && index == 0 {
+        text.push_str(&format!(
+            "Available Commands:\n\
+            • /start - Welcome message\n\
+            • /help - Show this help message\n\
+            • /pass or /password - Generate a secure password\n\
+            • /passphrase [words] [--separator S] [--title-case] [--all-caps] [--leet] - \
+            Diceware-style passphrase\n\
+            • /wifi [length] - WPA2 Wi-Fi passphrase (8-63, default 20)\n\
+            • /pin [length] - Numeric PIN (default 4 digits)\n\
+            • /bulk <count> [length] [options] - Many passwords as a .txt file\n\
+            • /totp [account] - Base32 TOTP secret for 2FA enrollment\n\
+            • /seed [bytes] [--all] - Random bytes as hex (--all: also base64, base58)\n\
+            • /token [bytes] [--hex|--base64] - Random API token (default 32 bytes, hex)\n\
+            • /verify - Check that two copies of a password match\n\
+            • /check <password> - Estimate the strength of a password you have\n\
+            • /explain [N]words - Passphrase vs random password entropy\n\
+            • /history - Recent generation metadata (/history clear to forget)\n\
+            • /panic - Delete the password messages sent in this chat\n\
+            • /setlimit <n> - Set this group's rate limit (group admins only)\n\
+            • /setdefault <options> - Make a bare /pass use these options here\n\
+            • /resetdefault - Forget this chat's saved /pass options\n\
+            • /lang [code] - Choose the language of this chat's replies (en, de, ru)\n\
+            • /feedback <message> - Report a problem or idea to the bot's operators\n\n\
+            Password Generation Syntax:\n\
+            /pass [length] [options]\n\n\
+            Examples:\n\
+            • /pass - Default password (length: {})\n\
+            • /pass 24 - 24-character password\n\
+            • /pass 20 --symbols - Include symbols\n\
+            • /pass 16 --no-symbols - No symbols\n\
+            • /pass 18 --no-ambiguous - Exclude ambiguous chars (0,O,o,1,l,I)\n\
+            • /pass 20 --no-digits --symbols - No digits, with symbols\n\n\
+            Constraints:\n\
+            • Min length: {} characters\n\
+            • Max length: {} characters\n\
+            • At least one character type must be enabled\n\
+            • Rate limit: {} passwords per {} per chat\n\n",
+            config.default_password_length,
+            config.min_password_length,
+            config.max_password_length,
+            config.rate_limit_per_minute,
+            describe_window(config.rate_limit_window())
+        ));
+    }
+
+    if !page.options.is_empty() {
+        text.push_str("Options:\n");
+        for option in &page.options {
+            text.push_str(&format!("• {} - {}\n", option.usage, option.description));
+        }
+        text.push('\n');
+    }
+
+    if page.section == HelpSection::Advanced {
+        text.push_str(
+            "Security Recommendations:\n\
+            ✅ Use long passwords (16+ characters)\n\
+            ✅ Use unique passwords for each account\n\
+            ✅ Store passwords in a secure password manager\n\
+            ⚠️ Remember: Telegram is not end-to-end encrypted\n\
+            ⚠️ This bot doesn't log passwords, but they travel through Telegram's servers\n",
+        );
+    }
+
+    text.trim_end().to_string()
+}
+
+/// Keyboard shown under every help page: quick presets plus page navigation.
+pub fn help_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback("📋 Default", "pass_default"),
+            InlineKeyboardButton::callback("🔒 Strong (24)", "pass_24"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("🔤 No Symbols", "pass_no_symbols"),
+            InlineKeyboardButton::callback("🚫 Ambiguous", "pass_no_ambiguous"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("🔐 Very Strong (32)", "pass_32"),
+            InlineKeyboardButton::callback("📏 Custom Length", "pass_custom"),
+        ],
+        vec![
+            InlineKeyboardButton::callback("◀ Prev", "help_prev"),
+            InlineKeyboardButton::callback("Next ▶", "help_next"),
+        ],
+    ])
+}
+
+/// Direction of a help page change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpNav {
+    Prev,
+    Next,
+}
+
+/// Tracks which help page each user is currently viewing.
+#[derive(Debug, Default)]
+pub struct HelpNavigator {
+    pages: HashMap<u64, usize>,
+}
+
+impl HelpNavigator {
+    /// Reset `user_id` to the first page (e.g. on a fresh /help).
+    pub fn open(&mut self, user_id: u64) -> usize {
+        self.pages.insert(user_id, 0);
+        0
+    }
+
+    /// Move `user_id` one page in `nav`, wrapping around `total` pages.
+    pub fn navigate(&mut self, user_id: u64, nav: HelpNav, total: usize) -> usize {
+        let total = total.max(1);
+        let current = self.pages.get(&user_id).copied().unwrap_or(0) % total;
+        let next = match nav {
+            HelpNav::Next => (current + 1) % total,
+            HelpNav::Prev => (current + total - 1) % total,
+        };
+        self.pages.insert(user_id, next);
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_navigation_wraps() {
+        let mut nav = HelpNavigator::default();
+        assert_eq!(nav.open(7), 0);
+        assert_eq!(nav.navigate(7, HelpNav::Next, 3), 1);
+        assert_eq!(nav.navigate(7, HelpNav::Next, 3), 2);
+        assert_eq!(nav.navigate(7, HelpNav::Next, 3), 0);
+        assert_eq!(nav.navigate(7, HelpNav::Prev, 3), 2);
+
+        // Users are tracked independently, starting from the first page
+        assert_eq!(nav.navigate(8, HelpNav::Prev, 3), 2);
+        assert_eq!(nav.open(7), 0);
+    }
+
+    #[test]
+    fn test_help_pages_respect_limit() {
+        let pages = help_pages(1);
+        assert_eq!(pages.len(), OPTION_HELP.len());
+        assert!(pages.iter().all(|p| p.options.len() <= 1));
+
+        let sections: Vec<HelpSection> = help_pages(10).iter().map(|p| p.section).collect();
+        assert_eq!(sections, HelpSection::ALL.to_vec());
+    }
+
+    #[test]
+    fn test_every_option_appears_on_some_page() {
+        let pages = help_pages(2);
+        for option in OPTION_HELP {
+            assert!(
+                pages
+                    .iter()
+                    .any(|p| p.options.iter().any(|o| o.usage == option.usage)),
+                "{} missing from help",
+                option.usage
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+/// Breach lookups against HaveIBeenPwned's Pwned Passwords range API.
+///
+/// Only built with the `hibp` feature. Uses k-anonymity: just the first five hex
+/// characters of the password's SHA-1 hash are sent, and the matching suffix is
+/// looked for locally among the hundreds the API returns. Neither the password
+/// nor its full hash is sent or logged.
+use crate::encoding::hex_encode;
+use crate::error::{BotError, Result};
+use sha1::{Digest, Sha1};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// The public range endpoint; the hash prefix is appended.
+pub const HIBP_RANGE_URL: &str = "https://api.pwnedpasswords.com/range";
+
+/// Time budget for a single range request.
+const HIBP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Hex characters of the hash that are sent to the API.
+const PREFIX_LEN: usize = 5;
+
+/// Minimal client for the Pwned Passwords range API.
+#[derive(Clone)]
+pub struct HibpClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl HibpClient {
+    pub fn new(base_url: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(HIBP_TIMEOUT)
+            .user_agent(concat!("telegram-password-bot/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| BotError::Hibp(format!("Could not create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http,
+        })
+    }
+
+    /// How many times `password` appears in known breaches; 0 if it doesn't.
+    pub async fn breach_count(&self, password: &str) -> Result<u64> {
+        let hash = sha1_hex(password);
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+
+        // Padding makes every response a similar size, hiding which prefix was asked for
+        let response = self
+            .http
+            .get(format!("{}/{}", self.base_url, prefix))
+            .header("Add-Padding", "true")
+            .send()
+            .await
+            .map_err(|e| {
+                BotError::Hibp(format!("Could not reach HaveIBeenPwned: {}", e.without_url()))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(BotError::Hibp(format!("HaveIBeenPwned returned HTTP {}", status)));
+        }
+        let body = response.text().await.map_err(|e| {
+            BotError::Hibp(format!("Could not read the HaveIBeenPwned reply: {}", e.without_url()))
+        })?;
+        Ok(count_in_range(&body, suffix))
+    }
+}
+
+/// Uppercase hex SHA-1 of `password`, as the range API uses.
+fn sha1_hex(password: &str) -> Zeroizing<String> {
+    let digest = Sha1::digest(password.as_bytes());
+    Zeroizing::new(hex_encode(&digest).to_ascii_uppercase())
+}
+
+/// Breach count for `suffix` in a range response of `SUFFIX:COUNT` lines.
+///
+/// Padding entries have a count of 0, so they read as "not found" like a miss.
+fn count_in_range(body: &str, suffix: &str) -> u64 {
+    body.lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one HTTP request with `status` and `body`, returning the raw request text.
+    async fn mock_hibp(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // A GET has no body, so the request ends with the blank line
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_sha1_hex() {
+        // SHA-1("password"), the example in HaveIBeenPwned's documentation
+        assert_eq!(
+            sha1_hex("password").as_str(),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_count_in_range() {
+        let body = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+                    1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\n\
+                    011053FD0102E94D6AE2F8B83D76FAF94F6:0\r\n";
+        assert_eq!(count_in_range(body, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 9_659_365);
+        assert_eq!(count_in_range(body, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"), 9_659_365);
+        // Padding entries and misses both count as not found
+        assert_eq!(count_in_range(body, "011053FD0102E94D6AE2F8B83D76FAF94F6"), 0);
+        assert_eq!(count_in_range(body, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), 0);
+        assert_eq!(count_in_range("", "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_breach_count_sends_only_the_prefix() {
+        // SHA-1("hunter2") is F3BBBD66A63D4BF1747940578EC3D0103530E21D
+        let (addr, server) =
+            mock_hibp("200 OK", "D66A63D4BF1747940578EC3D0103530E21D:42\r\n").await;
+        let client = HibpClient::new(&addr).unwrap();
+
+        assert_eq!(client.breach_count("hunter2").await.unwrap(), 42);
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /F3BBB HTTP/1.1"));
+        assert!(request.to_ascii_lowercase().contains("add-padding: true"));
+        assert!(!request.contains("D66A63D4"));
+        assert!(!request.contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_breach_count_reports_http_errors() {
+        let (addr, _server) = mock_hibp("503 Service Unavailable", "").await;
+        let client = HibpClient::new(&addr).unwrap();
+
+        let err = client.breach_count("password").await.unwrap_err();
+        assert!(err.to_string().contains("HTTP 503"));
+    }
+}
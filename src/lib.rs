@@ -0,0 +1,51 @@
+//! Password generation core of the Telegram password bot, usable without Telegram.
+//!
+//! The generator, strength estimate and metadata formatting are re-exported at the
+//! crate root; the full configuration (character sets, per-class policies, other
+//! generators) lives in [`password`] and [`policy`].
+//!
+//! ```
+//! use rand::rngs::OsRng;
+//! use telegram_password_bot::{
+//!     estimate_strength, format_metadata, generate_password, PasswordConfig,
+//!     PasswordStrength,
+//! };
+//!
+//! let config = PasswordConfig {
+//!     length: 24,
+//!     ..Default::default()
+//! };
+//! let password = generate_password(&config, &mut OsRng)?;
+//! assert_eq!(password.chars().count(), 24);
+//! assert_eq!(estimate_strength(&config), PasswordStrength::Strong);
+//! println!("{}", format_metadata(&config, estimate_strength(&config)));
+//! # Ok::<(), telegram_password_bot::error::BotError>(())
+//! ```
+//!
+//! The remaining modules make up the bot itself and are public for the
+//! `telegram-password-bot` binary.
+mod audit;
+pub mod bot;
+pub mod config;
+mod dedup;
+mod encoding;
+pub mod error;
+mod help;
+#[cfg(feature = "hibp")]
+mod hibp;
+pub mod locale;
+pub mod metrics;
+pub mod password;
+pub mod policy;
+mod preferences;
+mod qr;
+mod template;
+mod totp;
+pub mod watchdog;
+#[cfg(feature = "vault")]
+mod vault;
+
+pub use password::{
+    estimate_strength, format_metadata, generate_password, PasswordConfig, PasswordConfigBuilder,
+    PasswordMetadata, PasswordStrength,
+};
@@ -0,0 +1,440 @@
+/// User-facing message translations.
+///
+/// English, German and Russian are built in. Operators can add or override
+/// languages by dropping `<lang>.json` files (a flat object of message id → text)
+/// into `LOCALE_DIR`; these are merged over the built-ins at startup. Lookups fall
+/// back from the user's language to its primary subtag, then to English.
+use crate::error::{BotError, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Language used when a message is missing from the user's language.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// Built-in English messages, keyed by message id.
+const BUILTIN_EN: &[(&str, &str)] = &[
+    (
+        "unknown_command",
+        "❓ Unknown command. Type /help to see available commands.",
+    ),
+    (
+        "request_timed_out",
+        "⌛ Request timed out. Try again, or relax some of the options.",
+    ),
+    ("generation_failed", "❌ Failed to generate password: {error}"),
+    ("welcome", "Welcome!"),
+    ("welcome_named", "Welcome, {name}!"),
+    (
+        "start",
+        "🔐 Secure Password Generator Bot\n\n\
+        {welcome} I generate strong, random passwords using cryptographically \
+        secure randomness.\n\n\
+        🔒 Privacy Notice:\n\
+        • Passwords are generated using OS-level secure randomness\n\
+        • Passwords are NOT logged or stored on the server\n\
+        • However, Telegram messages are not end-to-end encrypted\n\
+        • Use this bot as a convenience tool, but be aware of inherent risks\n\n\
+        📝 Quick Start:\n\
+        Use /pass to generate a password with default settings, or customize it:\n\
+        • /pass - Default 16-character password\n\
+        • /pass 24 - 24-character password\n\
+        • /pass 20 --symbols - Include symbols\n\
+        • /pass 16 --no-ambiguous - Exclude ambiguous characters\n\n\
+        Type /help for detailed usage information.",
+    ),
+    (
+        "already_started",
+        "👋 Already started — use /pass to generate a password.",
+    ),
+    ("button_default", "📋 Default (16)"),
+    ("button_strong", "🔒 Strong (24)"),
+    ("button_help", "📖 Help"),
+    (
+        "help_in_english",
+        "ℹ️ The option reference below is in English.",
+    ),
+    (
+        "pass_usage",
+        "❌ Error: {error}\n\nUsage: `/pass [length] [options]`\n\
+        Example: `/pass 20 --symbols --no-ambiguous`\n\n\
+        Type `/help` for detailed usage.",
+    ),
+    (
+        "config_error",
+        "❌ Configuration error: {error}\n\n\
+        Make sure at least one character type is enabled.",
+    ),
+    (
+        "too_many_passwords",
+        "❌ Too many passwords requested. Maximum --count: {max}.",
+    ),
+    (
+        "reply_keyboard_private",
+        "❌ --reply-keyboard only works in a private chat.",
+    ),
+    (
+        "lang_current",
+        "🌐 Language: {language}\nAvailable: {languages}\n\n\
+        Use /lang <code> to change it for this chat, or /lang auto to follow \
+        your Telegram setting.",
+    ),
+    ("lang_set", "🌐 Language set to {language}."),
+    ("lang_auto", "🌐 This chat now follows each user's Telegram language."),
+    (
+        "lang_unsupported",
+        "❌ Unsupported language '{code}'. Available: {languages}",
+    ),
+    (
+        "lang_not_admin",
+        "⛔ Only group administrators can change this chat's language.",
+    ),
+    ("command_disabled", "⛔ This command is disabled."),
+];
+
+/// Built-in German messages.
+const BUILTIN_DE: &[(&str, &str)] = &[
+    (
+        "unknown_command",
+        "❓ Unbekannter Befehl. Mit /help siehst du alle Befehle.",
+    ),
+    (
+        "request_timed_out",
+        "⌛ Zeitüberschreitung. Versuche es erneut oder lockere einige Optionen.",
+    ),
+    (
+        "generation_failed",
+        "❌ Passwort konnte nicht erzeugt werden: {error}",
+    ),
+    ("welcome", "Willkommen!"),
+    ("welcome_named", "Willkommen, {name}!"),
+    (
+        "start",
+        "🔐 Sicherer Passwort-Generator\n\n\
+        {welcome} Ich erzeuge starke Zufallspasswörter mit kryptografisch sicherem Zufall.\n\n\
+        🔒 Datenschutz:\n\
+        • Passwörter werden mit dem sicheren Zufallsgenerator des Betriebssystems erzeugt\n\
+        • Passwörter werden auf dem Server NICHT protokolliert oder gespeichert\n\
+        • Telegram-Nachrichten sind jedoch nicht Ende-zu-Ende-verschlüsselt\n\
+        • Nutze den Bot als Hilfsmittel, aber sei dir der Risiken bewusst\n\n\
+        📝 Schnellstart:\n\
+        Mit /pass erzeugst du ein Passwort mit Standardeinstellungen, oder passe es an:\n\
+        • /pass - Standardpasswort mit 16 Zeichen\n\
+        • /pass 24 - Passwort mit 24 Zeichen\n\
+        • /pass 20 --symbols - Mit Sonderzeichen\n\
+        • /pass 16 --no-ambiguous - Ohne verwechselbare Zeichen\n\n\
+        Mit /help erhältst du eine ausführliche Anleitung.",
+    ),
+    (
+        "already_started",
+        "👋 Schon gestartet — mit /pass erzeugst du ein Passwort.",
+    ),
+    ("button_default", "📋 Standard (16)"),
+    ("button_strong", "🔒 Stark (24)"),
+    ("button_help", "📖 Hilfe"),
+    (
+        "help_in_english",
+        "ℹ️ Die folgende Optionsübersicht ist auf Englisch.",
+    ),
+    (
+        "pass_usage",
+        "❌ Fehler: {error}\n\nVerwendung: `/pass [Länge] [Optionen]`\n\
+        Beispiel: `/pass 20 --symbols --no-ambiguous`\n\n\
+        Mit `/help` erhältst du eine ausführliche Anleitung.",
+    ),
+    (
+        "config_error",
+        "❌ Ungültige Einstellungen: {error}\n\n\
+        Mindestens eine Zeichenart muss aktiviert sein.",
+    ),
+    (
+        "too_many_passwords",
+        "❌ Zu viele Passwörter angefordert. Höchstens --count: {max}.",
+    ),
+    (
+        "reply_keyboard_private",
+        "❌ --reply-keyboard funktioniert nur in einem privaten Chat.",
+    ),
+    (
+        "lang_current",
+        "🌐 Sprache: {language}\nVerfügbar: {languages}\n\n\
+        Mit /lang <Code> änderst du sie für diesen Chat, mit /lang auto gilt \
+        wieder deine Telegram-Einstellung.",
+    ),
+    ("lang_set", "🌐 Sprache auf {language} gesetzt."),
+    (
+        "lang_auto",
+        "🌐 Dieser Chat folgt jetzt der Telegram-Sprache jedes Nutzers.",
+    ),
+    (
+        "lang_unsupported",
+        "❌ Nicht unterstützte Sprache '{code}'. Verfügbar: {languages}",
+    ),
+    (
+        "lang_not_admin",
+        "⛔ Nur Gruppenadministratoren können die Sprache dieses Chats ändern.",
+    ),
+    ("command_disabled", "⛔ Dieser Befehl ist deaktiviert."),
+];
+
+/// Built-in Russian messages.
+const BUILTIN_RU: &[(&str, &str)] = &[
+    (
+        "unknown_command",
+        "❓ Неизвестная команда. Введите /help, чтобы увидеть список команд.",
+    ),
+    (
+        "request_timed_out",
+        "⌛ Время ожидания истекло. Попробуйте ещё раз или ослабьте ограничения.",
+    ),
+    ("generation_failed", "❌ Не удалось создать пароль: {error}"),
+    ("welcome", "Добро пожаловать!"),
+    ("welcome_named", "Добро пожаловать, {name}!"),
+    (
+        "start",
+        "🔐 Генератор надёжных паролей\n\n\
+        {welcome} Я создаю стойкие случайные пароли с помощью криптографически стойкого \
+        генератора.\n\n\
+        🔒 Конфиденциальность:\n\
+        • Пароли создаются системным генератором случайных чисел\n\
+        • Пароли НЕ записываются в журнал и не хранятся на сервере\n\
+        • Однако сообщения Telegram не защищены сквозным шифрованием\n\
+        • Пользуйтесь ботом для удобства, но помните о рисках\n\n\
+        📝 Быстрый старт:\n\
+        /pass создаёт пароль с настройками по умолчанию, их можно изменить:\n\
+        • /pass - Пароль из 16 символов\n\
+        • /pass 24 - Пароль из 24 символов\n\
+        • /pass 20 --symbols - Со спецсимволами\n\
+        • /pass 16 --no-ambiguous - Без похожих символов\n\n\
+        Подробная справка: /help",
+    ),
+    (
+        "already_started",
+        "👋 Бот уже запущен — используйте /pass, чтобы создать пароль.",
+    ),
+    ("button_default", "📋 Обычный (16)"),
+    ("button_strong", "🔒 Надёжный (24)"),
+    ("button_help", "📖 Справка"),
+    (
+        "help_in_english",
+        "ℹ️ Описание параметров ниже приведено на английском.",
+    ),
+    (
+        "pass_usage",
+        "❌ Ошибка: {error}\n\nИспользование: `/pass [длина] [параметры]`\n\
+        Пример: `/pass 20 --symbols --no-ambiguous`\n\n\
+        Подробная справка: `/help`",
+    ),
+    (
+        "config_error",
+        "❌ Ошибка настроек: {error}\n\n\
+        Должен быть включён хотя бы один тип символов.",
+    ),
+    (
+        "too_many_passwords",
+        "❌ Запрошено слишком много паролей. Максимум --count: {max}.",
+    ),
+    (
+        "reply_keyboard_private",
+        "❌ --reply-keyboard работает только в личном чате.",
+    ),
+    (
+        "lang_current",
+        "🌐 Язык: {language}\nДоступные: {languages}\n\n\
+        /lang <код> меняет язык для этого чата, /lang auto возвращает язык \
+        из настроек Telegram.",
+    ),
+    ("lang_set", "🌐 Язык изменён: {language}."),
+    (
+        "lang_auto",
+        "🌐 Теперь в этом чате используется язык Telegram каждого пользователя.",
+    ),
+    (
+        "lang_unsupported",
+        "❌ Язык '{code}' не поддерживается. Доступные: {languages}",
+    ),
+    (
+        "lang_not_admin",
+        "⛔ Только администраторы группы могут менять язык этого чата.",
+    ),
+    ("command_disabled", "⛔ Эта команда отключена."),
+];
+
+/// Built-in catalogs by language code.
+const BUILTIN: &[(&str, &[(&str, &str)])] =
+    &[(FALLBACK_LANGUAGE, BUILTIN_EN), ("de", BUILTIN_DE), ("ru", BUILTIN_RU)];
+
+/// Message catalogs for every known language.
+#[derive(Debug, Clone)]
+pub struct Locales {
+    packs: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for Locales {
+    fn default() -> Self {
+        let packs = BUILTIN
+            .iter()
+            .map(|&(lang, messages)| {
+                let pack = messages
+                    .iter()
+                    .map(|&(key, text)| (key.to_string(), text.to_string()))
+                    .collect();
+                (lang.to_string(), pack)
+            })
+            .collect();
+        Self { packs }
+    }
+}
+
+impl Locales {
+    /// Built-in messages, with any packs in `dir` merged over them.
+    pub fn load(dir: Option<&Path>) -> Result<Self> {
+        let mut locales = Self::default();
+        if let Some(dir) = dir {
+            locales.load_dir(dir)?;
+        }
+        Ok(locales)
+    }
+
+    /// Merge every `<lang>.json` file in `dir` over the current packs.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(lang) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let pack = parse_pack(&fs::read_to_string(&path)?)
+                .map_err(|e| BotError::Config(format!("{}: {}", path.display(), e)))?;
+            self.merge(lang, pack);
+        }
+        Ok(())
+    }
+
+    /// Add `pack` to `lang`, overriding existing messages with the same id.
+    pub fn merge(&mut self, lang: &str, pack: HashMap<String, String>) {
+        self.packs
+            .entry(lang.to_ascii_lowercase())
+            .or_default()
+            .extend(pack);
+    }
+
+    /// Resolve message `key` for `lang`, falling back to English.
+    ///
+    /// Returns the key itself if no language defines it.
+    pub fn get<'a>(&'a self, lang: Option<&str>, key: &'a str) -> &'a str {
+        let lang = lang.unwrap_or(FALLBACK_LANGUAGE).to_ascii_lowercase();
+        let primary = lang.split(['-', '_']).next().unwrap_or_default();
+
+        [lang.as_str(), primary, FALLBACK_LANGUAGE]
+            .iter()
+            .find_map(|l| self.packs.get(*l).and_then(|pack| pack.get(key)))
+            .map(String::as_str)
+            .unwrap_or(key)
+    }
+
+    /// The pack `lang` is served from: itself, its primary subtag, or English.
+    pub fn resolve(&self, lang: Option<&str>) -> String {
+        let lang = lang.unwrap_or(FALLBACK_LANGUAGE).to_ascii_lowercase();
+        let primary = lang.split(['-', '_']).next().unwrap_or_default().to_string();
+
+        [lang, primary]
+            .into_iter()
+            .find(|l| self.packs.contains_key(l))
+            .unwrap_or_else(|| FALLBACK_LANGUAGE.to_string())
+    }
+
+    /// Whether there is a pack for exactly `lang`.
+    pub fn supports(&self, lang: &str) -> bool {
+        self.packs.contains_key(&lang.to_ascii_lowercase())
+    }
+
+    /// Codes of every known language, sorted.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = self.packs.keys().map(String::as_str).collect();
+        languages.sort_unstable();
+        languages
+    }
+}
+
+/// Parse a JSON language pack, rejecting empty messages.
+fn parse_pack(json: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let pack: HashMap<String, String> =
+        serde_json::from_str(json).map_err(|e| format!("invalid language pack: {}", e))?;
+
+    if let Some(key) = pack.keys().find(|k| pack[*k].trim().is_empty()) {
+        return Err(format!("message '{}' is empty", key));
+    }
+
+    Ok(pack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_pack_overrides_and_falls_back() {
+        let dir = std::env::temp_dir().join(format!("locale-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("de.json"),
+            r#"{"unknown_command": "❓ Unbekannter Befehl."}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("README.txt"), "not a pack").unwrap();
+
+        let locales = Locales::load(Some(&dir)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            locales.get(Some("de"), "unknown_command"),
+            "❓ Unbekannter Befehl."
+        );
+        assert_eq!(
+            locales.get(Some("de-AT"), "unknown_command"),
+            "❓ Unbekannter Befehl."
+        );
+        // Missing from the German pack, so English is used
+        assert!(locales
+            .get(Some("de"), "request_timed_out")
+            .starts_with("⌛"));
+        assert!(locales.get(None, "unknown_command").contains("/help"));
+        assert_eq!(locales.get(Some("de"), "no_such_key"), "no_such_key");
+    }
+
+    #[test]
+    fn test_builtin_languages_cover_every_message() {
+        for &(lang, messages) in BUILTIN {
+            assert_eq!(messages.len(), BUILTIN_EN.len(), "{}", lang);
+            for &(key, _) in BUILTIN_EN {
+                assert!(messages.iter().any(|&(k, _)| k == key), "{} lacks {}", lang, key);
+            }
+        }
+
+        let locales = Locales::default();
+        assert_eq!(locales.languages(), ["de", "en", "ru"]);
+        assert!(locales.get(Some("ru-RU"), "start").contains("{welcome}"));
+        assert_eq!(locales.get(Some("de"), "button_help"), "📖 Hilfe");
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_english() {
+        let locales = Locales::default();
+        assert_eq!(locales.resolve(Some("de-CH")), "de");
+        assert_eq!(locales.resolve(Some("RU")), "ru");
+        assert_eq!(locales.resolve(Some("fr")), "en");
+        assert_eq!(locales.resolve(None), "en");
+        assert!(locales.supports("ru"));
+        assert!(!locales.supports("fr"));
+    }
+
+    #[test]
+    fn test_empty_values_rejected() {
+        assert!(parse_pack(r#"{"unknown_command": "  "}"#).is_err());
+        assert!(parse_pack(r#"{"unknown_command": 3}"#).is_err());
+        assert!(parse_pack(r#"{"unknown_command": "ok"}"#).is_ok());
+    }
+}
@@ -9,19 +9,29 @@
 /// - Passwords are NEVER logged or stored server-side
 /// - Rate limiting prevents abuse
 /// - Telegram messages are not end-to-end encrypted
-mod bot;
-mod config;
-mod error;
-mod password;
-
-use bot::{handle_callback, handle_help, handle_password, handle_start, handle_unknown, BotState};
-use config::Config;
-use error::Result;
+///
+/// The generation logic lives in the `telegram_password_bot` library; this binary
+/// only wires it up to Telegram.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use telegram_password_bot::bot::{
+    handle_bench, handle_bulk, handle_callback, handle_check, handle_explain, handle_feedback,
+    handle_help, handle_history, handle_lang, handle_panic, handle_passphrase, handle_password,
+    handle_pin, handle_resetdefault, handle_seed, handle_setdefault, handle_setlimit, handle_start,
+    handle_stats, handle_store, handle_token, handle_totp, handle_unknown, handle_verify,
+    handle_wifi, BotState,
+};
+use telegram_password_bot::config::Config;
+use telegram_password_bot::error::{BotError, Result};
+use telegram_password_bot::locale::Locales;
+use telegram_password_bot::metrics;
+use telegram_password_bot::watchdog::{run_guarded, PanicWatchdog};
 use teloxide::dispatching::UpdateFilterExt;
 use teloxide::prelude::*;
-use teloxide::types::Update;
+use teloxide::types::{BotCommand, Update};
 use teloxide::utils::command::BotCommands;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 /// Telegram bot commands.
@@ -39,6 +49,76 @@ enum Command {
     Pass(String),
     #[command(description = "Generate a secure password")]
     Password(String),
+    #[command(description = "Generate a diceware-style passphrase")]
+    Passphrase(String),
+    #[command(description = "Delete the password messages sent in this chat")]
+    Panic,
+    #[command(description = "Benchmark generation throughput (operators only)")]
+    Bench(String),
+    #[command(description = "Generate a WPA2 Wi-Fi passphrase")]
+    Wifi(String),
+    #[command(description = "Generate a numeric PIN")]
+    Pin(String),
+    #[command(description = "Generate many passwords as a .txt file")]
+    Bulk(String),
+    #[command(description = "Show recent generation metadata for this chat")]
+    History(String),
+    #[command(description = "Generate a password straight into Vault (operators only)")]
+    Store(String),
+    #[command(description = "Set this group's per-minute rate limit (group admins only)")]
+    SetLimit(String),
+    #[command(description = "Compare a passphrase's entropy with a random password's")]
+    Explain(String),
+    #[command(description = "Generate a base32 TOTP secret for 2FA enrollment")]
+    Totp(String),
+    #[command(description = "Check that two copies of a password match")]
+    Verify,
+    #[command(description = "Estimate the strength of a password you already have")]
+    Check(String),
+    #[command(description = "Generate random bytes as hex (--all adds base64 and base58)")]
+    Seed(String),
+    #[command(description = "Generate a random API token (--hex or --base64)")]
+    Token(String),
+    #[command(description = "Show usage counts (operators only)")]
+    Stats,
+    #[command(description = "Save options for a bare /pass in this chat")]
+    SetDefault(String),
+    #[command(description = "Forget this chat's saved /pass options")]
+    ResetDefault,
+    #[command(description = "Choose the language of this chat's replies")]
+    Lang(String),
+    #[command(description = "Send feedback or report a problem to the bot's operators")]
+    Feedback(String),
+}
+
+/// Name `cmd` is counted under in /stats; a fixed set, so the counters stay bounded.
+fn command_name(cmd: &Command) -> &'static str {
+    match cmd {
+        Command::Start => "start",
+        Command::Help => "help",
+        Command::Pass(_) => "pass",
+        Command::Password(_) => "password",
+        Command::Passphrase(_) => "passphrase",
+        Command::Panic => "panic",
+        Command::Bench(_) => "bench",
+        Command::Wifi(_) => "wifi",
+        Command::Pin(_) => "pin",
+        Command::Bulk(_) => "bulk",
+        Command::History(_) => "history",
+        Command::Store(_) => "store",
+        Command::SetLimit(_) => "setlimit",
+        Command::Explain(_) => "explain",
+        Command::Totp(_) => "totp",
+        Command::Verify => "verify",
+        Command::Check(_) => "check",
+        Command::Seed(_) => "seed",
+        Command::Token(_) => "token",
+        Command::Stats => "stats",
+        Command::SetDefault(_) => "setdefault",
+        Command::ResetDefault => "resetdefault",
+        Command::Lang(_) => "lang",
+        Command::Feedback(_) => "feedback",
+    }
 }
 
 /// Main bot message handler.
@@ -48,20 +128,68 @@ async fn handle_command(
     cmd: Command,
     state: BotState,
 ) -> ResponseResult<()> {
+    if state.config.is_command_disabled(command_name(&cmd)) {
+        let lang = state.language(msg.chat.id.0, msg.from()).await;
+        bot.send_message(msg.chat.id, state.locales.get(lang.as_deref(), "command_disabled"))
+            .await?;
+        return Ok(());
+    }
+    state.stats.lock().await.record_command(command_name(&cmd));
     match cmd {
-        Command::Start => handle_start(bot, msg).await,
+        Command::Start => handle_start(bot, msg, state).await,
         Command::Help => handle_help(bot, msg, state).await,
         Command::Pass(args) | Command::Password(args) => {
             handle_password(bot, msg, state, args).await
         }
+        Command::Passphrase(args) => handle_passphrase(bot, msg, state, args).await,
+        Command::Panic => handle_panic(bot, msg, state).await,
+        Command::Bench(args) => handle_bench(bot, msg, state, args).await,
+        Command::Wifi(args) => handle_wifi(bot, msg, state, args).await,
+        Command::Pin(args) => handle_pin(bot, msg, state, args).await,
+        Command::Bulk(args) => handle_bulk(bot, msg, state, args).await,
+        Command::History(args) => handle_history(bot, msg, state, args).await,
+        Command::Store(args) => handle_store(bot, msg, state, args).await,
+        Command::SetLimit(args) => handle_setlimit(bot, msg, state, args).await,
+        Command::Explain(args) => handle_explain(bot, msg, args).await,
+        Command::Totp(args) => handle_totp(bot, msg, state, args).await,
+        Command::Verify => handle_verify(bot, msg, state).await,
+        Command::Check(args) => handle_check(bot, msg, state, args).await,
+        Command::Seed(args) => handle_seed(bot, msg, state, args).await,
+        Command::Token(args) => handle_token(bot, msg, state, args).await,
+        Command::Stats => handle_stats(bot, msg, state).await,
+        Command::SetDefault(args) => handle_setdefault(bot, msg, state, args).await,
+        Command::ResetDefault => handle_resetdefault(bot, msg, state).await,
+        Command::Lang(args) => handle_lang(bot, msg, state, args).await,
+        Command::Feedback(args) => handle_feedback(bot, msg, state, args).await,
+    }
+}
+
+/// Check that every `DISABLED_COMMANDS` entry names a real command, so a typo can't
+/// leave on a command that was meant to be off.
+fn check_disabled_commands(disabled: &[String]) -> Result<()> {
+    let known: Vec<String> = Command::bot_commands()
+        .into_iter()
+        .map(|command| command.command.trim_start_matches('/').to_string())
+        .collect();
+    match disabled.iter().find(|name| !known.contains(name)) {
+        Some(name) => Err(BotError::Config(format!(
+            "DISABLED_COMMANDS names an unknown command: '{}'",
+            name
+        ))),
+        None => Ok(()),
     }
 }
 
 /// Set up the command menu that appears in Telegram.
-async fn set_bot_commands(bot: &Bot) -> Result<()> {
-    use teloxide::types::BotCommand;
+async fn set_bot_commands(bot: &Bot, disabled: &[String]) -> Result<()> {
+    bot.set_my_commands(menu_commands(disabled)).await?;
+    info!("Bot commands menu set successfully");
+    Ok(())
+}
 
-    let commands = vec![
+/// The command menu entries, leaving out the `disabled` commands.
+fn menu_commands(disabled: &[String]) -> Vec<BotCommand> {
+    let mut commands = vec![
         BotCommand {
             command: "start".to_string(),
             description: "Start the bot and see welcome message".to_string(),
@@ -74,36 +202,184 @@ async fn set_bot_commands(bot: &Bot) -> Result<()> {
             command: "pass".to_string(),
             description: "Generate a secure password".to_string(),
         },
+        BotCommand {
+            command: "passphrase".to_string(),
+            description: "Generate a diceware-style passphrase".to_string(),
+        },
+        BotCommand {
+            command: "wifi".to_string(),
+            description: "Generate a WPA2 Wi-Fi passphrase".to_string(),
+        },
+        BotCommand {
+            command: "pin".to_string(),
+            description: "Generate a numeric PIN".to_string(),
+        },
+        BotCommand {
+            command: "totp".to_string(),
+            description: "Generate a base32 TOTP secret for 2FA enrollment".to_string(),
+        },
+        BotCommand {
+            command: "check".to_string(),
+            description: "Estimate the strength of a password you already have".to_string(),
+        },
+        BotCommand {
+            command: "setdefault".to_string(),
+            description: "Save options for a bare /pass in this chat".to_string(),
+        },
+        BotCommand {
+            command: "lang".to_string(),
+            description: "Choose the language of this chat's replies".to_string(),
+        },
+        BotCommand {
+            command: "history".to_string(),
+            description: "Show recent generation metadata for this chat".to_string(),
+        },
+        BotCommand {
+            command: "panic".to_string(),
+            description: "Delete the password messages sent in this chat".to_string(),
+        },
     ];
+    commands.retain(|command| !disabled.contains(&command.command));
+    commands
+}
 
-    bot.set_my_commands(commands).await?;
-    info!("Bot commands menu set successfully");
-    Ok(())
+/// Decide how a command menu failure affects startup.
+///
+/// The menu is a convenience, so by default a failure is logged and startup
+/// continues. When `require_menu` is set the error is propagated instead.
+fn resolve_menu_result(result: Result<()>, require_menu: bool) -> Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if require_menu => {
+            error!("Failed to set bot commands menu: {}", e);
+            Err(e)
+        }
+        Err(e) => {
+            warn!("Failed to set bot commands menu, continuing without it: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Longest wait between two startup attempts.
+const MAX_STARTUP_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Run a startup call to Telegram, retrying up to `retries` times on failure.
+///
+/// The wait starts at `delay` and doubles after each failed attempt, so a
+/// transient network outage at boot does not stop the bot immediately.
+async fn retry_startup<T, F, Fut>(what: &str, retries: u32, delay: Duration, mut op: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let attempts = retries.saturating_add(1);
+    let mut delay = delay;
+    for attempt in 1..=attempts {
+        info!("{}: attempt {}/{}", what, attempt, attempts);
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts => {
+                warn!("{} failed: {}; retrying in {:?}", what, e, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_STARTUP_RETRY_DELAY);
+            }
+            Err(e) => {
+                error!("{} failed after {} attempts: {}", what, attempts, e);
+                return Err(e);
+            }
+        }
+    }
+    unreachable!("at least one attempt is always made")
+}
+
+/// Validate the environment's configuration without connecting to Telegram.
+///
+/// Returns the report to print and the process exit code.
+fn check_config() -> (String, i32) {
+    let config = match Config::from_env()
+        .and_then(|config| check_disabled_commands(&config.disabled_commands).map(|_| config))
+    {
+        Ok(config) => config,
+        Err(e) => return (format!("❌ Configuration error: {}", e), 1),
+    };
+
+    let mut report = config.describe();
+    match Locales::load(config.locale_dir.as_deref()) {
+        Ok(_) => {
+            report.push_str("\n\n✅ Configuration is valid");
+            (report, 0)
+        }
+        Err(e) => {
+            report.push_str(&format!("\n\n❌ Failed to load language packs: {}", e));
+            (report, 1)
+        }
+    }
+}
+
+/// How long shutdown waits for in-flight requests before giving up on them.
+///
+/// Kept under the 10 seconds `docker stop` allows before it kills the container.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Resolves on Ctrl-C (SIGINT), or on SIGTERM as sent by `docker stop` and Kubernetes.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Could not listen for SIGTERM, only Ctrl-C will stop the bot: {}", e),
+        }
+    }
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Could not listen for Ctrl-C: {}", e);
+        std::future::pending::<()>().await;
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing/logging
+    // Load .env file if present (for development), early enough for the logging settings
+    let dotenv = dotenvy::dotenv();
+
+    // Initialize tracing/logging; LOG_FORMAT=json writes one JSON object per line
+    let json_logs = std::env::var("LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "telegram_password_bot=info,teloxide=info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
         .init();
 
+    // `--check-config`: validate and print the effective configuration, then exit
+    if std::env::args().any(|arg| arg == "--check-config") {
+        let (report, code) = check_config();
+        println!("{}", report);
+        std::process::exit(code);
+    }
+
     info!("Starting Telegram Password Generator Bot...");
 
-    // Load .env file if present (for development)
-    if let Err(e) = dotenvy::dotenv() {
+    if let Err(e) = dotenv {
         info!("No .env file found or error loading it: {}", e);
     }
 
     // Load configuration from environment variables
-    let config = Config::from_env().map_err(|e| {
-        error!("Configuration error: {}", e);
-        e
-    })?;
+    let config = Config::from_env()
+        .and_then(|config| check_disabled_commands(&config.disabled_commands).map(|_| config))
+        .map_err(|e| {
+            error!("Configuration error: {}", e);
+            e
+        })?;
 
     info!("Configuration loaded successfully");
     info!(
@@ -115,45 +391,278 @@ async fn main() -> Result<()> {
         config.min_password_length, config.max_password_length
     );
     info!(
-        "Rate limit: {} requests per minute per chat",
-        config.rate_limit_per_minute
+        "Rate limit: {} requests per {}s per chat{} ({} window)",
+        config.rate_limit_per_minute,
+        config.rate_limit_window_secs,
+        config
+            .rate_limit_per_user
+            .map_or(String::new(), |n| format!(", {} per member", n)),
+        config.rate_limit_strategy.name()
     );
 
     // Create bot instance
     let bot = Bot::new(&config.bot_token);
+    let retry_delay = Duration::from_secs(config.startup_retry_delay_secs);
 
-    info!("Bot initialized, starting dispatcher...");
+    // Make sure Telegram is reachable (and the token valid) before going further
+    let me = retry_startup("getMe", config.startup_retries, retry_delay, || async {
+        bot.get_me().await.map_err(BotError::from)
+    })
+    .await
+    .map_err(|e| {
+        error!("Could not reach Telegram, giving up: {}", e);
+        e
+    })?;
+    info!("Bot initialized as @{}, starting dispatcher...", me.username());
 
-    // Set up command menu in Telegram
-    set_bot_commands(&bot).await?;
+    // Set up command menu in Telegram (best-effort unless REQUIRE_COMMAND_MENU is set)
+    let menu = retry_startup("setMyCommands", config.startup_retries, retry_delay, || {
+        set_bot_commands(&bot, &config.disabled_commands)
+    })
+    .await;
+    resolve_menu_result(menu, config.require_command_menu)?;
 
     // Create shared state
-    let state = BotState::new(config);
+    let locales = Locales::load(config.locale_dir.as_deref()).map_err(|e| {
+        error!("Failed to load language packs: {}", e);
+        e
+    })?;
+    let state = BotState::new(config, locales);
+
+    // Save rate limits periodically so a crash loses at most one interval
+    let flusher = state.config.rate_limit_state_path.is_some().then(|| {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let period = Duration::from_secs(state.config.rate_limit_flush_secs);
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = state.persist_rate_limits().await {
+                    warn!("Failed to save rate limit state: {}", e);
+                }
+            }
+        })
+    });
 
-    // Set up command handler
+    // Expose Prometheus metrics if asked to; the default deployment stays a single poller
+    let metrics_server = match state.config.metrics_port {
+        Some(port) => Some(metrics::spawn(port, state.stats.clone())?),
+        None => None,
+    };
+
+    let watchdog = Arc::new(PanicWatchdog::new(
+        state.config.panic_restart_threshold,
+        Duration::from_secs(state.config.panic_window_secs),
+    ));
+
+    // Set up command handler; every handler runs guarded so a panic only loses its update
     let message_handler = Update::filter_message()
-        .branch(
-            dptree::entry()
-                .filter_command::<Command>()
-                .endpoint(handle_command),
-        )
-        .branch(dptree::endpoint(handle_unknown));
+        .branch(dptree::entry().filter_command::<Command>().endpoint(
+            |bot: Bot,
+             msg: Message,
+             cmd: Command,
+             state: BotState,
+             watchdog: Arc<PanicWatchdog>| async move {
+                let context = format!("command in chat {}", msg.chat.id);
+                run_guarded(&watchdog, &context, handle_command(bot, msg, cmd, state)).await
+            },
+        ))
+        .branch(dptree::endpoint(
+            |bot: Bot, msg: Message, state: BotState, watchdog: Arc<PanicWatchdog>| async move {
+                let context = format!("message in chat {}", msg.chat.id);
+                run_guarded(&watchdog, &context, handle_unknown(bot, msg, state)).await
+            },
+        ));
 
-    let callback_handler = Update::filter_callback_query().endpoint(handle_callback);
+    let callback_handler = Update::filter_callback_query().endpoint(
+        |bot: Bot, q: CallbackQuery, state: BotState, watchdog: Arc<PanicWatchdog>| async move {
+            let context = format!("callback from user {}", q.from.id);
+            run_guarded(&watchdog, &context, handle_callback(bot, q, state)).await
+        },
+    );
 
     let handler = dptree::entry()
         .branch(message_handler)
         .branch(callback_handler);
 
-    // Start the dispatcher
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![state])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+    // Start the dispatcher, rebuilding it whenever the watchdog asks for a restart
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let mut dispatcher = Dispatcher::builder(bot.clone(), handler.clone())
+            .dependencies(dptree::deps![state.clone(), watchdog.clone()])
+            .build();
+
+        let token = dispatcher.shutdown_token();
+        let restart_token = token.clone();
+        let signal = watchdog.clone();
+        let trigger = tokio::spawn(async move {
+            signal.restart_requested().await;
+            if let Ok(shutdown) = restart_token.shutdown() {
+                shutdown.await;
+            }
+        });
+
+        let dispatch = dispatcher.dispatch();
+        tokio::pin!(dispatch);
+        let signalled = tokio::select! {
+            () = &mut dispatch => false,
+            () = &mut shutdown => true,
+        };
+        trigger.abort();
+
+        if signalled {
+            // Stop taking updates, but let the handlers already running send their replies
+            info!("Shutdown signal received, finishing in-flight requests");
+            if let Ok(stopped) = token.shutdown() {
+                let drain = async { tokio::join!(&mut dispatch, stopped) };
+                if tokio::time::timeout(SHUTDOWN_GRACE, drain).await.is_err() {
+                    warn!(
+                        "In-flight requests still running after {:?}; stopping anyway",
+                        SHUTDOWN_GRACE
+                    );
+                }
+            }
+            break;
+        }
+        if !watchdog.take_restart() {
+            break;
+        }
+        warn!("Restarting the dispatcher after repeated handler panics");
+    }
+
+    if let Some(flusher) = flusher {
+        flusher.abort();
+    }
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
+    if let Err(e) = state.persist_rate_limits().await {
+        error!("Failed to save rate limit state on shutdown: {}", e);
+    }
+    if let Err(e) = state.persist_preferences().await {
+        error!("Failed to save preferences on shutdown: {}", e);
+    }
+    state.stats.lock().await.log_summary(std::time::Instant::now());
 
     info!("Bot stopped");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Stand-in for Telegram that fails a fixed number of calls, then succeeds.
+    struct FlakyApi {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl FlakyApi {
+        async fn get_me(&self) -> Result<&'static str> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                Err(BotError::Config("network unreachable".to_string()))
+            } else {
+                Ok("password_bot")
+            }
+        }
+    }
+
+    #[test]
+    fn test_menu_failure_is_ignored_by_default() {
+        let failure = Err(BotError::Config("simulated failure".to_string()));
+        assert!(resolve_menu_result(failure, false).is_ok());
+    }
+
+    #[test]
+    fn test_menu_failure_is_fatal_when_required() {
+        let failure = Err(BotError::Config("simulated failure".to_string()));
+        assert!(resolve_menu_result(failure, true).is_err());
+    }
+
+    #[test]
+    fn test_disabled_commands_leave_the_menu() {
+        let all = menu_commands(&[]);
+        assert!(all.iter().any(|command| command.command == "check"));
+
+        let disabled = vec!["check".to_string(), "password".to_string()];
+        let menu = menu_commands(&disabled);
+        assert_eq!(menu.len(), all.len() - 1);
+        assert!(!menu.iter().any(|command| command.command == "check"));
+        // Every disabled command still maps to a handler name, so dispatch stays wired
+        for name in &disabled {
+            assert!(Command::bot_commands()
+                .iter()
+                .any(|command| command.command.trim_start_matches('/') == name));
+        }
+    }
+
+    #[test]
+    fn test_check_disabled_commands() {
+        assert!(check_disabled_commands(&[]).is_ok());
+        let known = vec!["password".to_string(), "setlimit".to_string(), "check".to_string()];
+        assert!(check_disabled_commands(&known).is_ok());
+        let err = check_disabled_commands(&["chek".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("'chek'"), "{}", err);
+    }
+
+    #[test]
+    fn test_menu_success_passes_through() {
+        assert!(resolve_menu_result(Ok(()), false).is_ok());
+        assert!(resolve_menu_result(Ok(()), true).is_ok());
+    }
+
+    #[test]
+    fn test_check_config() {
+        std::env::set_var("TELEGRAM_BOT_TOKEN", "123:secret-token-AAHfiqksKZ8WmR2zSjiQ7_");
+        std::env::set_var("MIN_PASSWORD_LENGTH", "8");
+        let (report, code) = check_config();
+        assert_eq!(code, 0, "{}", report);
+        assert!(report.contains("TELEGRAM_BOT_TOKEN=<redacted>"));
+        assert!(!report.contains("secret-token"));
+        assert!(report.contains("MIN_PASSWORD_LENGTH=8"));
+
+        std::env::set_var("MIN_PASSWORD_LENGTH", "0");
+        let (report, code) = check_config();
+        assert_eq!(code, 1);
+        assert!(report.contains("MIN_PASSWORD_LENGTH must be greater than 0"));
+
+        std::env::remove_var("MIN_PASSWORD_LENGTH");
+        std::env::remove_var("TELEGRAM_BOT_TOKEN");
+        let (_, code) = check_config();
+        assert_eq!(code, 1);
+    }
+
+    #[tokio::test]
+    async fn test_startup_retries_until_success() {
+        let api = FlakyApi {
+            failures: 2,
+            calls: AtomicU32::new(0),
+        };
+        let result = retry_startup("getMe", 3, Duration::ZERO, || api.get_me()).await;
+        assert_eq!(result.unwrap(), "password_bot");
+        assert_eq!(api.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_startup_gives_up_after_retries() {
+        let api = FlakyApi {
+            failures: 10,
+            calls: AtomicU32::new(0),
+        };
+        let result = retry_startup("getMe", 3, Duration::ZERO, || api.get_me()).await;
+        assert!(result.is_err());
+        assert_eq!(api.calls.load(Ordering::SeqCst), 4);
+
+        let api = FlakyApi {
+            failures: 1,
+            calls: AtomicU32::new(0),
+        };
+        assert!(retry_startup("getMe", 0, Duration::ZERO, || api.get_me()).await.is_err());
+        assert_eq!(api.calls.load(Ordering::SeqCst), 1);
+    }
+}
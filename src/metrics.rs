@@ -0,0 +1,98 @@
+/// Optional Prometheus endpoint serving [`UsageStats`] at `/metrics`.
+///
+/// Only started when `METRICS_PORT` is set. The listener speaks plain HTTP/1 and
+/// exposes counts only, the same figures as /stats.
+use crate::bot::UsageStats;
+use crate::error::{BotError, Result};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// Content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Bind `port` on all interfaces and serve `stats` from it in the background.
+///
+/// Binding happens before this returns, so a port that is already taken fails
+/// startup instead of being discovered later.
+pub fn spawn(port: u16, stats: Arc<Mutex<UsageStats>>) -> Result<JoinHandle<()>> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let builder = Server::try_bind(&addr)
+        .map_err(|e| BotError::Config(format!("METRICS_PORT {} is unavailable: {}", port, e)))?;
+    let make_service = make_service_fn(move |_| {
+        let stats = stats.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| respond(req, stats.clone())))
+        }
+    });
+    let server = builder.serve(make_service);
+    info!("Serving metrics on http://{}/metrics", addr);
+
+    Ok(tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Metrics server stopped: {}", e);
+        }
+    }))
+}
+
+/// Answer `GET /metrics`; everything else is a 404.
+async fn respond(
+    req: Request<Body>,
+    stats: Arc<Mutex<UsageStats>>,
+) -> std::result::Result<Response<Body>, Infallible> {
+    let response = if req.method() == Method::GET && req.uri().path() == "/metrics" {
+        let text = stats.lock().await.to_prometheus(Instant::now());
+        Response::builder()
+            .header(CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+            .body(Body::from(text))
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from("Not found\n"))
+    };
+    Ok(response.expect("static status and headers are valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::body::to_bytes;
+
+    async fn request(
+        method: Method,
+        path: &str,
+        stats: &Arc<Mutex<UsageStats>>,
+    ) -> Response<Body> {
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        respond(req, stats.clone()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route() {
+        let stats = Arc::new(Mutex::new(UsageStats::new(Instant::now())));
+        stats.lock().await.record_passwords(3);
+
+        let response = request(Method::GET, "/metrics", &stats).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], PROMETHEUS_CONTENT_TYPE);
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("password_bot_passwords_generated_total 3\n"));
+
+        let missing = request(Method::GET, "/", &stats).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let post = request(Method::POST, "/metrics", &stats).await;
+        assert_eq!(post.status(), StatusCode::NOT_FOUND);
+    }
+}
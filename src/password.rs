@@ -3,9 +3,514 @@
 /// This module provides cryptographically secure password generation using
 /// OS-level randomness (OsRng) and basic password strength estimation.
 use crate::error::{BotError, Result};
+use crate::policy::GenerationPolicy;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 use rand_core::RngCore;
+use serde::Serialize;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Lowercase letters used by default.
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+/// Uppercase letters used by default.
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// Digits used by default.
+pub const DIGITS: &str = "0123456789";
+/// Symbols used by default.
+pub const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.?/";
+/// Emoji added by `--emoji`: 32 of them, so each carries 5 bits. Each is a single
+/// code point that renders as emoji without a variation selector, so one emoji is
+/// one `char`.
+pub const EMOJI: &str = "🐶🐱🐭🐹🐰🦊🐻🐼🐨🐯🦁🐮🐷🐸🐵🐔🍎🍊🍋🍉🍇🍓🍒🍑🚀🌙🌵🍄🎲🎸🔑💎";
+/// Characters removed when ambiguous characters are excluded.
+pub const AMBIGUOUS: &[char] = &['0', 'O', 'o', '1', 'l', 'I'];
+/// Symbol pairs that are easily confused when transcribed.
+///
+/// With `exclude_confusable_symbols` the second member of each pair is removed,
+/// shrinking the default symbol set from 24 to 19 characters (about 0.34 fewer
+/// bits of entropy per symbol drawn).
+pub const CONFUSABLE_SYMBOL_PAIRS: &[(char, char)] = &[
+    (',', '.'),
+    (';', ':'),
+    ('-', '_'),
+    ('(', '['),
+    (')', ']'),
+    ('{', '['),
+    ('}', ']'),
+];
+/// Characters that break naive CSV/TSV exports.
+///
+/// With `exclude_csv_unsafe` these are removed from every set. Only `,` and `;`
+/// occur in the default symbol set, shrinking it from 24 to 22 characters (about
+/// 0.13 fewer bits of entropy per symbol drawn).
+pub const CSV_UNSAFE: &[char] = &[',', '"', ';', '\t', '\r', '\n'];
+
+/// The character sets a password is drawn from.
+///
+/// Defaults to the module constants. Library users can swap out any set with the
+/// chainable `with_*` methods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharSets {
+    pub lowercase: String,
+    pub uppercase: String,
+    pub digits: String,
+    pub symbols: String,
+    pub emoji: String,
+    pub ambiguous: Vec<char>,
+}
+
+impl Default for CharSets {
+    fn default() -> Self {
+        Self {
+            lowercase: LOWERCASE.to_string(),
+            uppercase: UPPERCASE.to_string(),
+            digits: DIGITS.to_string(),
+            symbols: SYMBOLS.to_string(),
+            emoji: EMOJI.to_string(),
+            ambiguous: AMBIGUOUS.to_vec(),
+        }
+    }
+}
+
+// Library API: the bot itself only replaces the symbol set (for /wifi and --symbols=).
+impl CharSets {
+    /// Replace the lowercase set.
+    pub fn with_lowercase(mut self, chars: impl Into<String>) -> Self {
+        self.lowercase = chars.into();
+        self
+    }
+
+    /// Replace the uppercase set.
+    pub fn with_uppercase(mut self, chars: impl Into<String>) -> Self {
+        self.uppercase = chars.into();
+        self
+    }
+
+    /// Replace the digit set.
+    pub fn with_digits(mut self, chars: impl Into<String>) -> Self {
+        self.digits = chars.into();
+        self
+    }
+
+    /// Replace the symbol set.
+    pub fn with_symbols(mut self, chars: impl Into<String>) -> Self {
+        self.symbols = chars.into();
+        self
+    }
+
+    /// Replace the emoji set used by `--emoji`.
+    pub fn with_emoji(mut self, chars: impl Into<String>) -> Self {
+        self.emoji = chars.into();
+        self
+    }
+
+    /// Replace the set of characters considered ambiguous.
+    pub fn with_ambiguous(mut self, chars: impl IntoIterator<Item = char>) -> Self {
+        self.ambiguous = chars.into_iter().collect();
+        self
+    }
+}
+
+/// Check a user-supplied symbol set (`--symbols=<chars>`) and drop repeated characters.
+///
+/// Repeats would be counted twice in the pool and overstate the entropy, and letters
+/// or digits would overlap the other classes, so only distinct symbols are kept.
+pub fn parse_symbol_set(chars: &str) -> Result<String> {
+    if chars.is_empty() {
+        return Err(BotError::PasswordGeneration(
+            "--symbols= needs at least one symbol, e.g. --symbols=!@#$".to_string(),
+        ));
+    }
+    let mut set = String::new();
+    for c in chars.chars() {
+        if c.is_whitespace() || c.is_control() || c.is_alphanumeric() {
+            return Err(BotError::PasswordGeneration(format!(
+                "--symbols= only takes symbols, not {:?}",
+                c
+            )));
+        }
+        if !set.contains(c) {
+            set.push(c);
+        }
+    }
+    Ok(set)
+}
+
+/// Consonants used by `--pronounceable`; 16 of them, so each carries 4 bits.
+pub const PRONOUNCEABLE_CONSONANTS: &str = "bdfghjklmnprstvz";
+/// Vowels used by `--pronounceable`.
+pub const PRONOUNCEABLE_VOWELS: &str = "aeiou";
+/// Letters per hyphen-separated group of a pronounceable password.
+const PRONOUNCEABLE_GROUP: usize = 6;
+/// Digits appended to a pronounceable password when digits are enabled.
+const PRONOUNCEABLE_DIGITS: usize = 2;
+
+/// Shortest passphrase WPA2-PSK accepts.
+pub const WIFI_MIN_LENGTH: usize = 8;
+/// Longest passphrase WPA2-PSK accepts (64 characters would be a raw hex key).
+pub const WIFI_MAX_LENGTH: usize = 63;
+/// Default length for `/wifi`.
+pub const WIFI_DEFAULT_LENGTH: usize = 20;
+/// Printable ASCII symbols minus space, quotes, backtick and backslash, which some
+/// router UIs trim, escape or reject.
+pub const WIFI_SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Configuration for a WPA2 Wi-Fi passphrase of `length` characters.
+///
+/// The 8–63 range is fixed by the standard and applies instead of the bot's
+/// configured min/max length.
+pub fn wifi_config(length: usize) -> Result<PasswordConfig> {
+    if !(WIFI_MIN_LENGTH..=WIFI_MAX_LENGTH).contains(&length) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Wi-Fi passphrases must be between {} and {} characters",
+            WIFI_MIN_LENGTH, WIFI_MAX_LENGTH
+        )));
+    }
+
+    Ok(PasswordConfig {
+        length,
+        charsets: CharSets::default().with_symbols(WIFI_SYMBOLS),
+        // WPA2 passphrases must be printable ASCII
+        strict_ascii: true,
+        ..Default::default()
+    })
+}
+
+/// Default length for `/pin`.
+pub const PIN_DEFAULT_LENGTH: usize = 4;
+
+/// Configuration for a numeric PIN of `length` digits.
+///
+/// PINs are usually 4–6 digits, so `min_length` is the operator's `MIN_PIN_LENGTH`
+/// rather than the password minimum. Every digit is allowed, ambiguous or not.
+pub fn pin_config(length: usize, min_length: usize, max_length: usize) -> Result<PasswordConfig> {
+    if !(min_length..=max_length).contains(&length) {
+        return Err(BotError::PasswordGeneration(format!(
+            "PINs must be between {} and {} digits",
+            min_length, max_length
+        )));
+    }
+
+    Ok(PasswordConfig {
+        length,
+        use_lowercase: false,
+        use_uppercase: false,
+        use_digits: true,
+        use_symbols: false,
+        exclude_ambiguous: false,
+        ..Default::default()
+    })
+}
+
+/// Configuration equivalent to `password`, for judging a password the bot didn't make.
+///
+/// Each class the password uses is enabled with the default character set, so the
+/// pool is what `/pass` would draw from for that mix. Characters outside ASCII
+/// letters and digits count as symbols.
+pub fn config_for_password(password: &str) -> PasswordConfig {
+    let has = |class: fn(&char) -> bool| password.chars().any(|c| class(&c));
+    PasswordConfig {
+        length: password.chars().count(),
+        use_lowercase: has(char::is_ascii_lowercase),
+        use_uppercase: has(char::is_ascii_uppercase),
+        use_digits: has(char::is_ascii_digit),
+        use_symbols: has(|c| !c.is_ascii_alphanumeric()),
+        exclude_ambiguous: false,
+        ..Default::default()
+    }
+}
+
+/// Embedded diceware-style wordlist: 7,776 (6^5) distinct common English words
+/// of 4-8 lowercase letters, one per line.
+const WORDLIST_TEXT: &str = include_str!("wordlist.txt");
+/// Most words a passphrase may have.
+pub const MAX_PASSPHRASE_WORDS: usize = 20;
+/// Word count used when none is given.
+pub const DEFAULT_PASSPHRASE_WORDS: usize = 6;
+/// Separator placed between words by default.
+pub const DEFAULT_PASSPHRASE_SEPARATOR: &str = "-";
+
+/// The embedded wordlist, split once on first use.
+pub fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| WORDLIST_TEXT.lines().filter(|w| !w.is_empty()).collect())
+}
+
+/// Letters `--leet` replaces, and what with.
+pub const LEET_SUBSTITUTIONS: &[(char, char)] =
+    &[('a', '4'), ('e', '3'), ('i', '1'), ('o', '0'), ('s', '5'), ('t', '7')];
+
+/// Spelling changes applied to each passphrase word after it is chosen.
+///
+/// They are applied in a fixed order: title case, then all caps, then leet, so
+/// `--all-caps --leet` turns `forest` into `F0R357`. Each is the same for every
+/// word and can be undone (wordlist words are lowercase letters only, and each
+/// leet digit stands for one letter), so none of them adds or removes entropy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PassphraseTransforms {
+    /// Capitalize the first letter of each word (`--title-case` / `--capitalize`).
+    pub title_case: bool,
+    /// Uppercase every letter (`--all-caps`).
+    pub all_caps: bool,
+    /// Replace letters with look-alike digits per [`LEET_SUBSTITUTIONS`] (`--leet`).
+    pub leet: bool,
+}
+
+impl PassphraseTransforms {
+    /// Apply the enabled transforms to `word`, in the documented order.
+    pub fn apply(&self, word: &str) -> String {
+        let mut word = word.to_string();
+        if self.title_case {
+            let mut chars = word.chars();
+            word = chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default();
+        }
+        if self.all_caps {
+            word = word.to_uppercase();
+        }
+        if self.leet {
+            word = word
+                .chars()
+                .map(|c| {
+                    LEET_SUBSTITUTIONS
+                        .iter()
+                        .find(|&&(letter, _)| letter == c.to_ascii_lowercase())
+                        .map_or(c, |&(_, digit)| digit)
+                })
+                .collect();
+        }
+        word
+    }
+
+    /// The enabled transforms, in the order they are applied, or `None` if none are.
+    pub fn describe(&self) -> Option<String> {
+        let names: Vec<&str> = [
+            (self.title_case, "title case"),
+            (self.all_caps, "all caps"),
+            (self.leet, "leet"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|&(_, name)| name)
+        .collect();
+        (!names.is_empty()).then(|| names.join(", "))
+    }
+}
+
+/// Generate a passphrase of `words` words drawn uniformly from [`wordlist`].
+///
+/// Words are joined by `separator` (any UTF-8 text) after `transforms` are applied
+/// to each. The word choice alone carries the entropy, see [`passphrase_entropy_bits`].
+pub fn generate_passphrase(
+    words: usize,
+    separator: &str,
+    transforms: PassphraseTransforms,
+    rng: &mut impl RngCore,
+) -> Result<String> {
+    if !(1..=MAX_PASSPHRASE_WORDS).contains(&words) {
+        return Err(BotError::PasswordGeneration(format!(
+            "Passphrase must have between 1 and {} words",
+            MAX_PASSPHRASE_WORDS
+        )));
+    }
+
+    let list = wordlist();
+    let chosen: Vec<String> = (0..words)
+        .map(|_| transforms.apply(list[rng.gen_range(0..list.len())]))
+        .collect();
+    Ok(chosen.join(separator))
+}
+
+/// Entropy of a `words`-word passphrase: `words × log2(wordlist length)`.
+pub fn passphrase_entropy_bits(words: usize) -> f64 {
+    (words as f64) * (wordlist().len() as f64).log2()
+}
+
+/// One element of a `--pattern` template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternToken {
+    /// A character copied into the password as-is.
+    Literal(char),
+    /// A word from [`wordlist`] (`{word}`), capitalized for `{Word}`.
+    Word { capitalize: bool },
+    /// One character drawn uniformly from a set (`{digit}`, `{symbol}`, ...).
+    Char(&'static str),
+}
+
+/// Placeholders understood by [`parse_pattern`].
+pub const PATTERN_TOKENS: &[(&str, PatternToken)] = &[
+    ("word", PatternToken::Word { capitalize: false }),
+    ("Word", PatternToken::Word { capitalize: true }),
+    ("digit", PatternToken::Char(DIGITS)),
+    ("symbol", PatternToken::Char(SYMBOLS)),
+    ("upper", PatternToken::Char(UPPERCASE)),
+    ("lower", PatternToken::Char(LOWERCASE)),
+];
+
+/// Split a `--pattern` template such as `{Word}-{word}-{digit}{digit}` into tokens.
+///
+/// Anything outside braces is kept literally. Unknown placeholders, unbalanced
+/// braces and templates without a single placeholder are rejected.
+pub fn parse_pattern(template: &str) -> Result<Vec<PatternToken>> {
+    let known = || {
+        PATTERN_TOKENS
+            .iter()
+            .map(|(name, _)| format!("{{{}}}", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut tokens = Vec::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            return Err(BotError::PasswordGeneration(format!(
+                                "Unclosed '{{' in pattern {:?}",
+                                template
+                            )))
+                        }
+                    }
+                }
+                let token = PATTERN_TOKENS
+                    .iter()
+                    .find(|(known, _)| *known == name)
+                    .map(|&(_, token)| token)
+                    .ok_or_else(|| {
+                        BotError::PasswordGeneration(format!(
+                            "Unknown pattern token {{{}}}; use one of {}",
+                            name,
+                            known()
+                        ))
+                    })?;
+                tokens.push(token);
+            }
+            '}' => {
+                return Err(BotError::PasswordGeneration(format!(
+                    "Unmatched '}}' in pattern {:?}",
+                    template
+                )))
+            }
+            c if c.is_whitespace() || c.is_control() => {
+                return Err(BotError::PasswordGeneration(format!(
+                    "Patterns can't contain {:?}",
+                    c
+                )))
+            }
+            c => tokens.push(PatternToken::Literal(c)),
+        }
+    }
+    if tokens.iter().all(|t| matches!(t, PatternToken::Literal(_))) {
+        return Err(BotError::PasswordGeneration(format!(
+            "A pattern needs at least one placeholder: {}",
+            known()
+        )));
+    }
+    Ok(tokens)
+}
+
+/// Fill in a `--pattern` template, drawing each placeholder independently.
+pub fn generate_from_pattern(template: &str, rng: &mut impl RngCore) -> Result<String> {
+    let list = wordlist();
+    let mut password = String::new();
+    for token in parse_pattern(template)? {
+        match token {
+            PatternToken::Literal(c) => password.push(c),
+            PatternToken::Word { capitalize } => {
+                let word = list[rng.gen_range(0..list.len())];
+                let mut chars = word.chars();
+                if let (true, Some(first)) = (capitalize, chars.next()) {
+                    password.extend(first.to_uppercase());
+                    password.push_str(chars.as_str());
+                } else {
+                    password.push_str(word);
+                }
+            }
+            PatternToken::Char(set) => {
+                let set: Vec<char> = set.chars().collect();
+                password.push(set[rng.gen_range(0..set.len())]);
+            }
+        }
+    }
+    Ok(password)
+}
+
+/// Entropy of a parsed pattern: the sum of log2 of each placeholder's alphabet.
+///
+/// Literals are known to an attacker who knows the template, so they add nothing.
+pub fn pattern_entropy_bits(tokens: &[PatternToken]) -> f64 {
+    tokens
+        .iter()
+        .map(|token| match token {
+            PatternToken::Literal(_) => 0.0,
+            PatternToken::Word { .. } => (wordlist().len() as f64).log2(),
+            PatternToken::Char(set) => (set.chars().count() as f64).log2(),
+        })
+        .sum()
+}
+
+/// Shortest and longest password, in characters, a parsed pattern can produce.
+pub fn pattern_length_range(tokens: &[PatternToken]) -> (usize, usize) {
+    let words = wordlist().iter().map(|w| w.chars().count());
+    let (shortest, longest) = words.fold((usize::MAX, 0), |(lo, hi), n| (lo.min(n), hi.max(n)));
+    tokens.iter().fold((0, 0), |(lo, hi), token| match token {
+        PatternToken::Word { .. } => (lo + shortest, hi + longest),
+        _ => (lo + 1, hi + 1),
+    })
+}
+
+/// A character class that can be switched on or off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digits,
+    Symbols,
+}
+
+impl CharClass {
+    /// Every class, in character pool order.
+    pub const ALL: [CharClass; 4] = [
+        CharClass::Lowercase,
+        CharClass::Uppercase,
+        CharClass::Digits,
+        CharClass::Symbols,
+    ];
+
+    /// Name used for the class in policy files and messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CharClass::Lowercase => "lowercase",
+            CharClass::Uppercase => "uppercase",
+            CharClass::Digits => "digits",
+            CharClass::Symbols => "symbols",
+        }
+    }
+
+    /// One character of the class in messages: "digit", "lowercase character".
+    pub fn singular(&self) -> &'static str {
+        match self {
+            CharClass::Lowercase => "lowercase character",
+            CharClass::Uppercase => "uppercase character",
+            CharClass::Digits => "digit",
+            CharClass::Symbols => "symbol",
+        }
+    }
+
+    /// Look a class up by its [`name`](Self::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.name() == name)
+    }
+}
 
 /// Configuration for password generation.
 #[derive(Debug, Clone)]
@@ -22,6 +527,42 @@ pub struct PasswordConfig {
     pub use_symbols: bool,
     /// Exclude ambiguous characters (0, O, o, 1, l, I).
     pub exclude_ambiguous: bool,
+    /// Keep only one member of each pair in [`CONFUSABLE_SYMBOL_PAIRS`].
+    pub exclude_confusable_symbols: bool,
+    /// Exclude characters in [`CSV_UNSAFE`].
+    pub exclude_csv_unsafe: bool,
+    /// Never use these characters, e.g. ones a site forbids (`--exclude`).
+    pub exclude_chars: Vec<char>,
+    /// Keep digits and symbols away from the first and last position.
+    pub interior_special: bool,
+    /// Never put the same character twice in a row (see [`NoRepeat`]).
+    pub no_repeat: bool,
+    /// Character sets to draw from.
+    pub charsets: CharSets,
+    /// Exact per-class proportions to honor, if any.
+    pub policy: Option<GenerationPolicy>,
+    /// Refuse to produce anything but ASCII, whatever the character sets hold.
+    pub strict_ascii: bool,
+    /// Entropy cutoffs [`estimate_strength`] rates against.
+    pub strength_thresholds: StrengthThresholds,
+    /// Alternate consonants and vowels (see [`PasswordConfig::pronounceable_slots`]).
+    pub pronounceable: bool,
+    /// Add the emoji set to the pool. Emoji aren't a required class: they only
+    /// widen the pool the free positions are drawn from.
+    pub use_emoji: bool,
+    /// At least this many lowercase letters; 0 keeps the usual one when enabled.
+    pub min_lowercase: usize,
+    /// At least this many uppercase letters; 0 keeps the usual one when enabled.
+    pub min_uppercase: usize,
+    /// At least this many digits; 0 keeps the usual one when enabled.
+    pub min_digits: usize,
+    /// At least this many symbols; 0 keeps the usual one when enabled.
+    pub min_symbols: usize,
+    /// At most this many symbols, for sites that cap them; `None` for no cap.
+    pub max_symbols: Option<usize>,
+    /// Fill in this `--pattern` template instead (see [`parse_pattern`]); the length
+    /// and character classes are then ignored.
+    pub pattern: Option<String>,
 }
 
 impl Default for PasswordConfig {
@@ -33,13 +574,55 @@ impl Default for PasswordConfig {
             use_digits: true,
             use_symbols: true,
             exclude_ambiguous: false,
+            exclude_confusable_symbols: false,
+            exclude_csv_unsafe: false,
+            exclude_chars: Vec::new(),
+            interior_special: false,
+            no_repeat: false,
+            charsets: CharSets::default(),
+            policy: None,
+            strict_ascii: false,
+            strength_thresholds: StrengthThresholds::default(),
+            pronounceable: false,
+            use_emoji: false,
+            min_lowercase: 0,
+            min_uppercase: 0,
+            min_digits: 0,
+            min_symbols: 0,
+            max_symbols: None,
+            pattern: None,
         }
     }
 }
 
 impl PasswordConfig {
+    /// Start a [`PasswordConfigBuilder`] from the defaults.
+    pub fn builder() -> PasswordConfigBuilder {
+        PasswordConfigBuilder::default()
+    }
+
     /// Validate that the configuration is sensible.
     pub fn validate(&self) -> Result<()> {
+        if let Some(template) = &self.pattern {
+            let tokens = parse_pattern(template)?;
+            if self.strict_ascii {
+                let non_ascii: String = tokens
+                    .iter()
+                    .filter_map(|token| match *token {
+                        PatternToken::Literal(c) if !c.is_ascii() => Some(c),
+                        _ => None,
+                    })
+                    .collect();
+                if !non_ascii.is_empty() {
+                    return Err(BotError::PasswordGeneration(format!(
+                        "STRICT_ASCII is on, but the pattern includes non-ASCII characters ({})",
+                        non_ascii
+                    )));
+                }
+            }
+            return Ok(());
+        }
+
         if self.length == 0 {
             return Err(BotError::PasswordGeneration(
                 "Password length must be greater than 0".to_string(),
@@ -56,343 +639,3107 @@ impl PasswordConfig {
             ));
         }
 
-        Ok(())
-    }
-
-    /// Build the character pool based on enabled options.
-    pub fn build_char_pool(&self) -> Vec<char> {
-        let mut pool = Vec::new();
-
-        // Define character sets
-        let lowercase = "abcdefghijklmnopqrstuvwxyz";
-        let uppercase = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-        let digits = "0123456789";
-        let symbols = "!@#$%^&*()-_=+[]{};:,.?/";
-
-        // Ambiguous characters to exclude if requested
-        let ambiguous = ['0', 'O', 'o', '1', 'l', 'I'];
-
-        if self.use_lowercase {
-            pool.extend(lowercase.chars().filter(|c| {
-                !self.exclude_ambiguous || !ambiguous.contains(c)
-            }));
+        if self.pronounceable {
+            self.check_pronounceable()?;
         }
 
-        if self.use_uppercase {
-            pool.extend(uppercase.chars().filter(|c| {
-                !self.exclude_ambiguous || !ambiguous.contains(c)
-            }));
+        let classes = [
+            (CharClass::Lowercase, self.use_lowercase),
+            (CharClass::Uppercase, self.use_uppercase),
+            (CharClass::Digits, self.use_digits),
+            (CharClass::Symbols, self.use_symbols),
+        ];
+        if !self.exclude_chars.is_empty() {
+            self.check_exclusions(&classes)?;
+        }
+        if classes.iter().any(|&(class, _)| self.class_minimum(class) > 0) {
+            if self.pronounceable {
+                return Err(BotError::PasswordGeneration(
+                    "Per-class minimums don't apply to pronounceable passwords".to_string(),
+                ));
+            }
+            if let Some(&(class, _)) = classes
+                .iter()
+                .find(|&&(class, on)| !on && self.class_minimum(class) > 0)
+            {
+                return Err(BotError::PasswordGeneration(format!(
+                    "A minimum of {} {} needs {} enabled",
+                    self.class_minimum(class),
+                    class.name(),
+                    class.name()
+                )));
+            }
+            // Checked one by one first, so huge minimums can't wrap the total below
+            if let Some(&(class, _)) = classes
+                .iter()
+                .find(|&&(class, _)| self.class_minimum(class) > self.length)
+            {
+                return Err(BotError::PasswordGeneration(format!(
+                    "A minimum of {} {} is more than the length ({})",
+                    self.class_minimum(class),
+                    class.name(),
+                    self.length
+                )));
+            }
+            match self.required_total() {
+                Some(needed) if needed <= self.length => {}
+                Some(needed) => {
+                    return Err(BotError::PasswordGeneration(format!(
+                        "The per-class minimums need {} characters, more than the length ({})",
+                        needed, self.length
+                    )))
+                }
+                None => {
+                    return Err(BotError::PasswordGeneration(
+                        "The per-class minimums need more characters than any length".to_string(),
+                    ))
+                }
+            }
         }
 
-        if self.use_digits {
-            pool.extend(digits.chars().filter(|c| {
-                !self.exclude_ambiguous || !ambiguous.contains(c)
-            }));
+        if self.no_repeat {
+            self.check_no_repeat()?;
         }
 
-        if self.use_symbols {
-            pool.extend(symbols.chars());
+        if let Some(max) = self.max_symbols {
+            let needed = self.required_count(CharClass::Symbols);
+            if self.use_symbols && !self.pronounceable && needed > max {
+                return Err(BotError::PasswordGeneration(if max == 0 {
+                    "A symbol cap of 0 needs symbols turned off (--no-symbols)".to_string()
+                } else {
+                    format!(
+                        "At most {} symbol{} allowed, but {} are required",
+                        max,
+                        if max == 1 { " is" } else { "s are" },
+                        needed
+                    )
+                }));
+            }
         }
 
-        pool
+        Ok(())
     }
 
-    /// Get the list of required character groups (at least one from each enabled).
-    fn required_chars(&self) -> Vec<Vec<char>> {
-        let mut required = Vec::new();
+    /// Check that `exclude_chars` leaves something to pick from in every enabled class.
+    fn check_exclusions(&self, classes: &[(CharClass, bool)]) -> Result<()> {
+        if self.pronounceable {
+            if self.pronounceable_slots().iter().any(Vec::is_empty) {
+                return Err(BotError::PasswordGeneration(
+                    "--exclude leaves nothing to fill some positions of a pronounceable password"
+                        .to_string(),
+                ));
+            }
+            return Ok(());
+        }
 
-        let lowercase = "abcdefghijklmnopqrstuvwxyz";
-        let uppercase = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-        let digits = "0123456789";
-        let symbols = "!@#$%^&*()-_=+[]{};:,.?/";
-        let ambiguous = ['0', 'O', 'o', '1', 'l', 'I'];
+        let available = self.class_sets();
+        match classes
+            .iter()
+            .find(|&&(class, on)| on && available.iter().all(|&(c, _)| c != class))
+        {
+            Some(&(class, _)) => Err(BotError::PasswordGeneration(format!(
+                "--exclude removes every {}; use --no-{} instead",
+                class.singular(),
+                class.name()
+            ))),
+            None => Ok(()),
+        }
+    }
 
-        if self.use_lowercase {
-            let chars: Vec<char> = lowercase
-                .chars()
-                .filter(|c| !self.exclude_ambiguous || !ambiguous.contains(c))
-                .collect();
-            if !chars.is_empty() {
-                required.push(chars);
-            }
+    /// Reject options that pronounceable passwords would otherwise ignore.
+    ///
+    /// Syllables are lowercase letters and the digits go at the end, so neither
+    /// `--no-lowercase` nor `--interior-special` could be honoured.
+    fn check_pronounceable(&self) -> Result<()> {
+        if !self.use_lowercase {
+            return Err(BotError::PasswordGeneration(
+                "Pronounceable passwords are built from lowercase syllables; drop --no-lowercase"
+                    .to_string(),
+            ));
+        }
+        if self.interior_special {
+            return Err(BotError::PasswordGeneration(
+                "--interior-special doesn't apply to pronounceable passwords".to_string(),
+            ));
         }
+        Ok(())
+    }
 
-        if self.use_uppercase {
-            let chars: Vec<char> = uppercase
-                .chars()
-                .filter(|c| !self.exclude_ambiguous || !ambiguous.contains(c))
-                .collect();
-            if !chars.is_empty() {
-                required.push(chars);
+    /// Check that every repeat [`NoRepeat`] finds can be redrawn.
+    ///
+    /// A repeat is redrawn from its own type, avoiding both neighbours, so every
+    /// type needs at least [`NO_REPEAT_MIN_SET`] characters. Checking up front keeps
+    /// tiny sets from failing on some draws only.
+    fn check_no_repeat(&self) -> Result<()> {
+        if self.pronounceable {
+            return Err(BotError::PasswordGeneration(
+                "--no-repeat doesn't apply to pronounceable passwords".to_string(),
+            ));
+        }
+        let emoji = self.emoji_set();
+        let sets = self
+            .class_sets()
+            .into_iter()
+            .map(|(class, chars)| (class.name(), chars))
+            .chain((!emoji.is_empty()).then_some(("emoji", emoji)));
+        for (name, chars) in sets {
+            if chars.len() < NO_REPEAT_MIN_SET {
+                return Err(BotError::PasswordGeneration(format!(
+                    "--no-repeat needs at least {} {} to choose from, but only {} {} left",
+                    NO_REPEAT_MIN_SET,
+                    name,
+                    chars.len(),
+                    if chars.len() == 1 { "is" } else { "are" }
+                )));
             }
         }
+        Ok(())
+    }
 
-        if self.use_digits {
-            let chars: Vec<char> = digits
-                .chars()
-                .filter(|c| !self.exclude_ambiguous || !ambiguous.contains(c))
-                .collect();
-            if !chars.is_empty() {
-                required.push(chars);
-            }
+    /// The configured minimum for `class` (0 if none was set).
+    pub fn class_minimum(&self, class: CharClass) -> usize {
+        match class {
+            CharClass::Lowercase => self.min_lowercase,
+            CharClass::Uppercase => self.min_uppercase,
+            CharClass::Digits => self.min_digits,
+            CharClass::Symbols => self.min_symbols,
         }
+    }
 
-        if self.use_symbols {
-            required.push(symbols.chars().collect());
+    /// Mutable access to the minimum for `class`, for option parsers.
+    pub fn class_minimum_mut(&mut self, class: CharClass) -> &mut usize {
+        match class {
+            CharClass::Lowercase => &mut self.min_lowercase,
+            CharClass::Uppercase => &mut self.min_uppercase,
+            CharClass::Digits => &mut self.min_digits,
+            CharClass::Symbols => &mut self.min_symbols,
         }
+    }
 
-        required
+    /// How many characters of an enabled `class` every password gets: its minimum,
+    /// and never fewer than one.
+    pub fn required_count(&self, class: CharClass) -> usize {
+        self.class_minimum(class).max(1)
     }
-}
 
-/// Generate a cryptographically secure random password.
-///
-/// # Arguments
-/// * `config` - Password configuration specifying length and character types.
-/// * `rng` - A cryptographically secure random number generator (e.g., OsRng).
-///
-/// # Returns
-/// A randomly generated password string.
-///
-/// # Security
-/// This function uses the provided RNG to select characters uniformly at random
-/// from the allowed character pool. It ensures at least one character from each
-/// enabled category appears in the password.
-pub fn generate_password(config: &PasswordConfig, rng: &mut impl RngCore) -> Result<String> {
-    config.validate()?;
+    /// Characters the enabled classes' [`required_count`](Self::required_count)s
+    /// take up together, or `None` if the total overflows.
+    pub fn required_total(&self) -> Option<usize> {
+        self.class_sets()
+            .iter()
+            .try_fold(0usize, |total, &(class, _)| total.checked_add(self.required_count(class)))
+    }
 
-    let char_pool = config.build_char_pool();
-    if char_pool.is_empty() {
-        return Err(BotError::PasswordGeneration(
-            "Character pool is empty".to_string(),
-        ));
+    /// Build the character pool based on enabled options.
+    pub fn build_char_pool(&self) -> Vec<char> {
+        let mut pool: Vec<char> = self.required_chars().into_iter().flatten().collect();
+        pool.extend(self.emoji_set());
+        pool
     }
 
-    let required_groups = config.required_chars();
-    let required_count = required_groups.len();
+    /// The emoji added to the pool, or nothing without `use_emoji`.
+    pub fn emoji_set(&self) -> Vec<char> {
+        if self.use_emoji {
+            self.filter_set(&self.charsets.emoji)
+        } else {
+            Vec::new()
+        }
+    }
 
-    if config.length < required_count {
-        return Err(BotError::PasswordGeneration(format!(
-            "Password length ({}) is too short for the required character types ({})",
-            config.length, required_count
-        )));
+    /// Letters available for the edges when `interior_special` is set.
+    fn edge_letters(&self) -> Vec<char> {
+        let sets = &self.charsets;
+        [
+            (self.use_lowercase, &sets.lowercase),
+            (self.use_uppercase, &sets.uppercase),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .flat_map(|(_, set)| self.filter_set(set))
+        .collect()
     }
 
-    let mut password_chars = Vec::with_capacity(config.length);
+    /// The characters allowed at each position of a pronounceable password.
+    ///
+    /// Letters alternate consonant, vowel in groups of six (three syllables) joined
+    /// by hyphens, e.g. `bakudo-fepi`; with digits enabled and room for them, the last
+    /// two positions are digits. Case and symbol options don't apply. Entropy is the
+    /// sum of `log2` of each slot's size, so it reflects the small syllable alphabets
+    /// rather than the full pool; the fixed hyphens contribute nothing.
+    pub fn pronounceable_slots(&self) -> Vec<Vec<char>> {
+        let consonants = self.filter_set(PRONOUNCEABLE_CONSONANTS);
+        let vowels = self.filter_set(PRONOUNCEABLE_VOWELS);
+        let suffix = if self.use_digits && self.length >= PRONOUNCEABLE_GROUP {
+            PRONOUNCEABLE_DIGITS
+        } else {
+            0
+        };
+        let letters = self.length - suffix;
 
-    // First, ensure at least one character from each required group
-    for group in &required_groups {
-        let idx = rng.gen_range(0..group.len());
-        password_chars.push(group[idx]);
+        let mut slots = Vec::with_capacity(self.length);
+        let mut in_group = 0;
+        let mut consonant = true;
+        while slots.len() < letters {
+            // Never end on a hyphen
+            if in_group == PRONOUNCEABLE_GROUP && slots.len() + 1 < letters {
+                slots.push(vec!['-']);
+                in_group = 0;
+                consonant = true;
+                continue;
+            }
+            slots.push(if consonant { consonants.clone() } else { vowels.clone() });
+            consonant = !consonant;
+            in_group += 1;
+        }
+        let digits = self.filter_set(&self.charsets.digits);
+        slots.extend(std::iter::repeat_n(digits, suffix));
+        slots
     }
 
-    // Fill the rest with random characters from the full pool
-    for _ in required_count..config.length {
-        let idx = rng.gen_range(0..char_pool.len());
-        password_chars.push(char_pool[idx]);
+    /// Whether this config produces numeric PINs (digits only).
+    pub fn is_pin(&self) -> bool {
+        self.use_digits && !self.use_lowercase && !self.use_uppercase && !self.use_symbols
     }
 
-    // Shuffle to avoid predictable patterns (required chars at the start)
-    password_chars.shuffle(rng);
+    /// Characters of `set`, minus ambiguous ones if they are excluded and any in
+    /// `exclude_chars`.
+    fn filter_set(&self, set: &str) -> Vec<char> {
+        set.chars()
+            .filter(|c| !self.exclude_ambiguous || !self.charsets.ambiguous.contains(c))
+            .filter(|c| !self.exclude_csv_unsafe || !CSV_UNSAFE.contains(c))
+            .filter(|c| !self.exclude_chars.contains(c))
+            .collect()
+    }
 
-    Ok(password_chars.into_iter().collect())
-}
+    /// Get the list of required character groups (at least one from each enabled).
+    ///
+    /// The groups are listed in the same order they make up the character pool.
+    fn required_chars(&self) -> Vec<Vec<char>> {
+        self.class_sets().into_iter().map(|(_, chars)| chars).collect()
+    }
 
-/// Password strength category based on entropy.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PasswordStrength {
-    Weak,
-    Medium,
-    Strong,
-}
+    /// The enabled, non-empty character classes and their filtered characters.
+    pub fn class_sets(&self) -> Vec<(CharClass, Vec<char>)> {
+        let sets = &self.charsets;
+        let symbols: String = if self.exclude_confusable_symbols {
+            sets.symbols
+                .chars()
+                .filter(|c| !CONFUSABLE_SYMBOL_PAIRS.iter().any(|&(_, drop)| drop == *c))
+                .collect()
+        } else {
+            sets.symbols.clone()
+        };
 
-impl PasswordStrength {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            PasswordStrength::Weak => "Weak",
-            PasswordStrength::Medium => "Medium",
-            PasswordStrength::Strong => "Strong",
-        }
+        let enabled = [
+            (CharClass::Lowercase, self.use_lowercase, &sets.lowercase),
+            (CharClass::Uppercase, self.use_uppercase, &sets.uppercase),
+            (CharClass::Digits, self.use_digits, &sets.digits),
+            (CharClass::Symbols, self.use_symbols, &symbols),
+        ];
+
+        enabled
+            .iter()
+            .filter(|(_, on, _)| *on)
+            .map(|(class, _, set)| (*class, self.filter_set(set)))
+            .filter(|(_, chars)| !chars.is_empty())
+            .collect()
     }
 }
 
-/// Estimate password strength based on entropy.
+/// Chainable construction of a [`PasswordConfig`], starting from the defaults.
 ///
-/// Entropy is calculated as: length × log2(pool_size).
+/// ```
+/// use telegram_password_bot::password::PasswordConfig;
 ///
-/// Strength categories:
-/// - Weak: < 50 bits
-/// - Medium: 50-80 bits
-/// - Strong: >= 80 bits
-pub fn estimate_strength(config: &PasswordConfig) -> PasswordStrength {
-    let pool_size = config.build_char_pool().len();
-    if pool_size == 0 {
-        return PasswordStrength::Weak;
+/// let config = PasswordConfig::builder()
+///     .length(24)
+///     .symbols(false)
+///     .exclude_ambiguous(true)
+///     .build()?;
+/// assert_eq!(config.length, 24);
+/// # Ok::<(), telegram_password_bot::error::BotError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PasswordConfigBuilder {
+    config: PasswordConfig,
+}
+
+impl PasswordConfigBuilder {
+    /// Set the password length.
+    pub fn length(mut self, length: usize) -> Self {
+        self.config.length = length;
+        self
     }
 
-    // Calculate entropy in bits
-    let entropy = (config.length as f64) * (pool_size as f64).log2();
+    /// Include or exclude lowercase letters.
+    pub fn lowercase(mut self, on: bool) -> Self {
+        self.config.use_lowercase = on;
+        self
+    }
 
-    if entropy < 50.0 {
-        PasswordStrength::Weak
-    } else if entropy < 80.0 {
-        PasswordStrength::Medium
-    } else {
-        PasswordStrength::Strong
+    /// Include or exclude uppercase letters.
+    pub fn uppercase(mut self, on: bool) -> Self {
+        self.config.use_uppercase = on;
+        self
     }
-}
 
-/// Format password metadata for display (without revealing the password in logs).
-pub fn format_metadata(config: &PasswordConfig, strength: PasswordStrength) -> String {
-    let pool_size = config.build_char_pool().len();
-    let entropy = (config.length as f64) * (pool_size as f64).log2();
+    /// Include or exclude digits.
+    pub fn digits(mut self, on: bool) -> Self {
+        self.config.use_digits = on;
+        self
+    }
 
-    let mut char_types = Vec::new();
-    if config.use_lowercase {
-        char_types.push("lowercase");
+    /// Include or exclude symbols.
+    pub fn symbols(mut self, on: bool) -> Self {
+        self.config.use_symbols = on;
+        self
     }
-    if config.use_uppercase {
-        char_types.push("uppercase");
+
+    /// Leave out the ambiguous characters of the character sets.
+    pub fn exclude_ambiguous(mut self, on: bool) -> Self {
+        self.config.exclude_ambiguous = on;
+        self
     }
-    if config.use_digits {
-        char_types.push("digits");
+
+    /// Keep only one symbol of each look-alike pair.
+    pub fn exclude_confusable_symbols(mut self, on: bool) -> Self {
+        self.config.exclude_confusable_symbols = on;
+        self
     }
-    if config.use_symbols {
-        char_types.push("symbols");
+
+    /// Leave out characters that break CSV/TSV imports.
+    pub fn exclude_csv_unsafe(mut self, on: bool) -> Self {
+        self.config.exclude_csv_unsafe = on;
+        self
     }
 
-    format!(
-        "Length: {} | Types: {} | Pool size: {} | Entropy: {:.1} bits | Strength: {}",
-        config.length,
-        char_types.join(", "),
-        pool_size,
-        entropy,
-        strength.as_str()
-    )
-}
+    /// Keep digits and symbols away from the first and last position.
+    pub fn interior_special(mut self, on: bool) -> Self {
+        self.config.interior_special = on;
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::OsRng;
+    /// Never put the same character twice in a row.
+    pub fn no_repeat(mut self, on: bool) -> Self {
+        self.config.no_repeat = on;
+        self
+    }
 
-    #[test]
-    fn test_default_config() {
-        let config = PasswordConfig::default();
-        assert!(config.validate().is_ok());
+    /// Draw from these character sets.
+    pub fn charsets(mut self, charsets: CharSets) -> Self {
+        self.config.charsets = charsets;
+        self
+    }
+
+    /// Honor exact per-class proportions.
+    pub fn policy(mut self, policy: GenerationPolicy) -> Self {
+        self.config.policy = Some(policy);
+        self
+    }
+
+    /// Refuse to produce anything but ASCII.
+    pub fn strict_ascii(mut self, on: bool) -> Self {
+        self.config.strict_ascii = on;
+        self
+    }
+
+    /// Alternate consonants and vowels.
+    pub fn pronounceable(mut self, on: bool) -> Self {
+        self.config.pronounceable = on;
+        self
+    }
+
+    /// Add the emoji set to the pool.
+    pub fn emoji(mut self, on: bool) -> Self {
+        self.config.use_emoji = on;
+        self
+    }
+
+    /// Require at least `count` characters of `class`.
+    pub fn min_count(mut self, class: CharClass, count: usize) -> Self {
+        *self.config.class_minimum_mut(class) = count;
+        self
+    }
+
+    /// Allow at most `max` symbols.
+    pub fn max_symbols(mut self, max: usize) -> Self {
+        self.config.max_symbols = Some(max);
+        self
+    }
+
+    /// Never use any of `chars`.
+    pub fn exclude_chars(mut self, chars: &str) -> Self {
+        self.config.exclude_chars = chars.chars().collect();
+        self
+    }
+
+    /// Fill in a template instead (see [`parse_pattern`]).
+    pub fn pattern(mut self, template: impl Into<String>) -> Self {
+        self.config.pattern = Some(template.into());
+        self
+    }
+
+    /// Finish the config, rejecting it if [`PasswordConfig::validate`] does.
+    pub fn build(self) -> Result<PasswordConfig> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Generate a cryptographically secure random password.
+///
+/// # Arguments
+/// * `config` - Password configuration specifying length and character types.
+/// * `rng` - A cryptographically secure random number generator (e.g., OsRng).
+///
+/// # Returns
+/// A randomly generated password string.
+///
+/// # Security
+/// This function uses the provided RNG to select characters uniformly at random
+/// from the allowed character pool. It ensures at least one character from each
+/// enabled category appears in the password.
+pub fn generate_password(config: &PasswordConfig, rng: &mut impl RngCore) -> Result<String> {
+    if let Some(template) = &config.pattern {
+        // Patterns bypass the generator, so repeat its ASCII safety net here
+        let password = generate_from_pattern(template, rng)?;
+        if config.strict_ascii && !password.is_ascii() {
+            return Err(BotError::PasswordGeneration(
+                "STRICT_ASCII is on, but a non-ASCII password was produced".to_string(),
+            ));
+        }
+        return Ok(password);
+    }
+    if config.pronounceable {
+        return generate_pronounceable(config, rng);
+    }
+    PasswordGenerator::new(config, rng)?.generate()
+}
+
+/// Generate a password from a `seed`, reproducibly: the same seed and config always
+/// give the same password.
+///
+/// **For tests only, never for real passwords.** Anyone who knows or guesses the
+/// seed can recreate the password, and a `u64` seed caps the entropy at 64 bits
+/// whatever the config says. Production code passes `OsRng` to
+/// [`generate_password`]. The output for a given seed may also change between
+/// versions of this crate or of `rand`.
+pub fn generate_password_seeded(config: &PasswordConfig, seed: u64) -> Result<String> {
+    generate_password(config, &mut StdRng::seed_from_u64(seed))
+}
+
+/// Generate a pronounceable password, one uniform draw per slot of
+/// [`PasswordConfig::pronounceable_slots`].
+pub fn generate_pronounceable(config: &PasswordConfig, rng: &mut impl RngCore) -> Result<String> {
+    config.validate()?;
+    let slots = config.pronounceable_slots();
+    if slots.iter().any(|slot| slot.is_empty()) {
+        return Err(BotError::PasswordGeneration(
+            "Character exclusions leave no letters for a pronounceable password".to_string(),
+        ));
+    }
+    Ok(slots
+        .iter()
+        .map(|slot| slot[rng.gen_range(0..slot.len())])
+        .collect())
+}
+
+/// Generate `count` independent passwords with the same configuration.
+pub fn generate_passwords(
+    config: &PasswordConfig,
+    count: usize,
+    rng: &mut impl RngCore,
+) -> Result<Vec<String>> {
+    if let Some(template) = &config.pattern {
+        return (0..count).map(|_| generate_from_pattern(template, rng)).collect();
+    }
+    if config.pronounceable {
+        return (0..count).map(|_| generate_pronounceable(config, rng)).collect();
+    }
+    PasswordGenerator::new(config, rng)?.take(count).collect()
+}
+
+/// Lazily generates passwords from a fixed configuration.
+///
+/// The config is validated and the character pools are built once, so large
+/// batches can be streamed (e.g. straight into a file) without materializing
+/// every password in memory. The iterator is infinite; bound it with `take`.
+pub struct PasswordGenerator<R: RngCore> {
+    length: usize,
+    /// Pool the characters not covered by `seeds` are drawn from.
+    char_pool: Vec<char>,
+    /// Groups and how many characters must come from each.
+    seeds: Vec<(Vec<char>, usize)>,
+    /// Letters forced into the password so both edges can hold one.
+    edge_letters: Option<(Vec<char>, usize)>,
+    transforms: TransformPipeline,
+    /// Check every output is ASCII before returning it.
+    strict_ascii: bool,
+    rng: R,
+}
+
+impl<R: RngCore> PasswordGenerator<R> {
+    /// Validate `config` and prepare its character pools.
+    pub fn new(config: &PasswordConfig, rng: R) -> Result<Self> {
+        config.validate()?;
+
+        let char_pool = config.build_char_pool();
+        if char_pool.is_empty() {
+            return Err(BotError::PasswordGeneration(
+                "Character pool is empty".to_string(),
+            ));
+        }
+
+        let required_groups = config.required_chars();
+        if config.length < required_groups.len() {
+            return Err(BotError::PasswordGeneration(format!(
+                "Password length ({}) is too short for the required character types ({})",
+                config.length,
+                required_groups.len()
+            )));
+        }
+
+        let mut transforms = TransformPipeline::default();
+        let edge_letters = if config.interior_special {
+            let letters = config.edge_letters();
+            if letters.is_empty() {
+                return Err(BotError::PasswordGeneration(
+                    "Interior specials need letters enabled for the edges".to_string(),
+                ));
+            }
+            // Each enabled letter group already contributes at least one required letter.
+            // A policy fixes the class counts, so no letters are added on top of it.
+            let letter_groups = required_groups
+                .iter()
+                .filter(|g| g.iter().all(|c| letters.contains(c)))
+                .count();
+            let extra = if config.policy.is_some() {
+                0
+            } else {
+                config.length.min(2).saturating_sub(letter_groups)
+            };
+            let seeded = config.required_total().unwrap_or(usize::MAX);
+            if config.length < seeded.saturating_add(extra) {
+                return Err(BotError::PasswordGeneration(format!(
+                    "Password length ({}) is too short to keep digits and symbols off both edges",
+                    config.length
+                )));
+            }
+            transforms.push(InteriorSpecial {
+                letters: letters.clone(),
+            });
+            Some((letters, extra))
+        } else {
+            None
+        };
+
+        let (seeds, char_pool) = match &config.policy {
+            Some(policy) => {
+                let plan = policy.seed_plan(config)?;
+                (plan.seeds, plan.fill_pool)
+            }
+            None => {
+                let seeds = config
+                    .class_sets()
+                    .into_iter()
+                    .map(|(class, group)| (group, config.required_count(class)))
+                    .collect();
+                (seeds, char_pool)
+            }
+        };
+
+        // Cap symbols before the edges are fixed, so the swaps there see the final mix
+        if let Some(max) = config.max_symbols {
+            transforms.insert(0, MaxSymbols::new(config, max, &seeds, &char_pool, &edge_letters)?);
+        }
+
+        if config.strict_ascii {
+            let mut non_ascii: Vec<char> = Vec::new();
+            for &c in seeds.iter().flat_map(|(group, _)| group).chain(&char_pool) {
+                if !c.is_ascii() && !non_ascii.contains(&c) {
+                    non_ascii.push(c);
+                }
+            }
+            if !non_ascii.is_empty() {
+                return Err(BotError::PasswordGeneration(format!(
+                    "STRICT_ASCII is on, but the character set includes non-ASCII characters ({})",
+                    non_ascii.into_iter().collect::<String>()
+                )));
+            }
+        }
+
+        // Last, so the edges and symbol cap are final; redraws keep each character's type
+        if config.no_repeat {
+            let emoji = config.emoji_set();
+            let groups = config
+                .class_sets()
+                .into_iter()
+                .map(|(_, chars)| chars)
+                .chain((!emoji.is_empty()).then_some(emoji))
+                .collect();
+            transforms.push(NoRepeat { groups });
+        }
+
+        Ok(Self {
+            length: config.length,
+            char_pool,
+            seeds,
+            edge_letters,
+            transforms,
+            strict_ascii: config.strict_ascii,
+            rng,
+        })
+    }
+
+    /// Generate the next password.
+    ///
+    /// Each required group gets its minimum (one unless raised, or a policy's exact
+    /// count per class), the rest comes from the full pool, and the whole buffer is
+    /// then shuffled. The Fisher-Yates shuffle makes every arrangement equally
+    /// likely, so the order in which groups are seeded (and whether it matches the
+    /// pool's composition order) can't bias which class ends up at which position.
+    pub fn generate(&mut self) -> Result<String> {
+        let mut password_chars = Vec::with_capacity(self.length);
+
+        // First, ensure each required group's minimum
+        for (group, count) in &self.seeds {
+            for _ in 0..*count {
+                let idx = self.rng.gen_range(0..group.len());
+                password_chars.push(group[idx]);
+            }
+        }
+
+        // Make sure there are enough letters to occupy both edges
+        if let Some((letters, extra)) = &self.edge_letters {
+            for _ in 0..*extra {
+                password_chars.push(letters[self.rng.gen_range(0..letters.len())]);
+            }
+        }
+
+        // Fill the rest with random characters from the full pool
+        for _ in password_chars.len()..self.length {
+            let idx = self.rng.gen_range(0..self.char_pool.len());
+            password_chars.push(self.char_pool[idx]);
+        }
+
+        // Shuffle to avoid predictable patterns (required chars at the start)
+        password_chars.shuffle(&mut self.rng);
+
+        let password = self
+            .transforms
+            .apply(password_chars.into_iter().collect(), &mut self.rng)?;
+
+        // Safety net in case a transform introduced something outside the pools
+        if self.strict_ascii && !password.is_ascii() {
+            return Err(BotError::PasswordGeneration(
+                "STRICT_ASCII is on, but a non-ASCII password was produced".to_string(),
+            ));
+        }
+        Ok(password)
+    }
+}
+
+/// A post-processing step applied to each freshly generated password.
+pub trait PasswordTransform {
+    fn apply(&self, pw: String, rng: &mut dyn RngCore) -> Result<String>;
+}
+
+/// An ordered list of transforms, each fed the previous one's output.
+#[derive(Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Box<dyn PasswordTransform>>,
+}
+
+impl TransformPipeline {
+    /// Append `transform` to the end of the pipeline.
+    pub fn push(&mut self, transform: impl PasswordTransform + 'static) {
+        self.transforms.push(Box::new(transform));
+    }
+
+    /// Put `transform` at `index`, ahead of the ones already there.
+    pub fn insert(&mut self, index: usize, transform: impl PasswordTransform + 'static) {
+        self.transforms.insert(index, Box::new(transform));
+    }
+
+    /// Run every transform in order, stopping at the first error.
+    pub fn apply(&self, pw: String, rng: &mut dyn RngCore) -> Result<String> {
+        self.transforms
+            .iter()
+            .try_fold(pw, |pw, transform| transform.apply(pw, rng))
+    }
+}
+
+/// Replaces symbols beyond `--max-symbols` with other characters of the fill pool.
+pub struct MaxSymbols {
+    pub max: usize,
+    pub symbols: Vec<char>,
+    /// The fill pool without symbols. Under a policy that pool only holds the free
+    /// classes, so the policy's exact counts are left alone.
+    pub replacements: Vec<char>,
+}
+
+impl MaxSymbols {
+    /// Prepare the cap for a generator with these `seeds` and fill `pool`, failing
+    /// if no password could meet it.
+    fn new(
+        config: &PasswordConfig,
+        max: usize,
+        seeds: &[(Vec<char>, usize)],
+        pool: &[char],
+        edge_letters: &Option<(Vec<char>, usize)>,
+    ) -> Result<Self> {
+        let symbols = config
+            .class_sets()
+            .into_iter()
+            .find(|(class, _)| *class == CharClass::Symbols)
+            .map(|(_, set)| set)
+            .unwrap_or_default();
+        let is_symbol_group = |group: &[char]| group.iter().all(|c| symbols.contains(c));
+        let seeded_symbols = seeds
+            .iter()
+            .filter(|(group, _)| !symbols.is_empty() && is_symbol_group(group))
+            .fold(0usize, |total, (_, count)| total.saturating_add(*count));
+        if seeded_symbols > max {
+            return Err(BotError::PasswordGeneration(format!(
+                "At most {} symbols allowed, but {} are required",
+                max, seeded_symbols
+            )));
+        }
+
+        let replacements: Vec<char> =
+            pool.iter().copied().filter(|c| !symbols.contains(c)).collect();
+        let seeded = seeds.iter().fold(0usize, |total, (_, count)| total.saturating_add(*count));
+        let extra = edge_letters.as_ref().map_or(0, |(_, extra)| *extra);
+        let filled = config.length.saturating_sub(seeded.saturating_add(extra));
+        if replacements.is_empty() && seeded_symbols + filled > max {
+            return Err(BotError::PasswordGeneration(format!(
+                "At most {} symbols allowed, but only symbols are left to fill the password",
+                max
+            )));
+        }
+        Ok(Self {
+            max,
+            symbols,
+            replacements,
+        })
+    }
+}
+
+impl PasswordTransform for MaxSymbols {
+    fn apply(&self, pw: String, rng: &mut dyn RngCore) -> Result<String> {
+        let mut chars: Vec<char> = pw.chars().collect();
+        let mut positions: Vec<usize> = (0..chars.len())
+            .filter(|&i| self.symbols.contains(&chars[i]))
+            .collect();
+        // Symbols are interchangeable and the cap covers the required ones, so any can go
+        while positions.len() > self.max && !self.replacements.is_empty() {
+            let i = positions.swap_remove(rng.gen_range(0..positions.len()));
+            chars[i] = self.replacements[rng.gen_range(0..self.replacements.len())];
+        }
+
+        let count = chars.iter().filter(|c| self.symbols.contains(c)).count();
+        if count > self.max {
+            return Err(BotError::PasswordGeneration(format!(
+                "Could not keep the password to {} symbols",
+                self.max
+            )));
+        }
+        Ok(chars.into_iter().collect())
+    }
+}
+
+/// Keeps digits and symbols off the edges; see [`fix_edges`].
+pub struct InteriorSpecial {
+    pub letters: Vec<char>,
+}
+
+impl PasswordTransform for InteriorSpecial {
+    fn apply(&self, pw: String, _rng: &mut dyn RngCore) -> Result<String> {
+        let mut chars: Vec<char> = pw.chars().collect();
+        fix_edges(&mut chars, &self.letters);
+        Ok(chars.into_iter().collect())
+    }
+}
+
+/// Fewest characters each type needs for `--no-repeat`: a repeat is redrawn
+/// avoiding its two neighbours, so one choice must be left over.
+pub const NO_REPEAT_MIN_SET: usize = 3;
+
+/// Redraws characters equal to the one before them (`--no-repeat`).
+///
+/// Each repeat is replaced by a uniform draw from its own character type, minus
+/// both neighbours, so class counts, a policy's proportions, letter edges and the
+/// symbol cap all still hold. Every position after the first then has one
+/// character fewer to choose from, which [`entropy_bits`] accounts for.
+pub struct NoRepeat {
+    /// The character types, one set each.
+    pub groups: Vec<Vec<char>>,
+}
+
+impl PasswordTransform for NoRepeat {
+    fn apply(&self, pw: String, rng: &mut dyn RngCore) -> Result<String> {
+        let mut chars: Vec<char> = pw.chars().collect();
+        for i in 1..chars.len() {
+            if chars[i] != chars[i - 1] {
+                continue;
+            }
+            let (current, next) = (chars[i], chars.get(i + 1).copied());
+            let candidates: Vec<char> = self
+                .groups
+                .iter()
+                .find(|group| group.contains(&current))
+                .into_iter()
+                .flatten()
+                .copied()
+                .filter(|&c| c != current && Some(c) != next)
+                .collect();
+            if candidates.is_empty() {
+                return Err(BotError::PasswordGeneration(format!(
+                    "--no-repeat found no replacement for a repeated '{}'",
+                    current
+                )));
+            }
+            chars[i] = candidates[rng.gen_range(0..candidates.len())];
+        }
+        Ok(chars.into_iter().collect())
+    }
+}
+
+/// Move digits and symbols off the first and last position.
+///
+/// Each edge that doesn't hold one of `letters` is swapped with the nearest interior
+/// letter. Edges are left as they are if the interior has no letters to spare.
+pub fn fix_edges(chars: &mut [char], letters: &[char]) {
+    let n = chars.len();
+    if n < 2 {
+        return;
+    }
+
+    for edge in [0, n - 1] {
+        if letters.contains(&chars[edge]) {
+            continue;
+        }
+        let interior = 1..n - 1;
+        let nearest = if edge == 0 {
+            interior.clone().find(|&i| letters.contains(&chars[i]))
+        } else {
+            interior.rev().find(|&i| letters.contains(&chars[i]))
+        };
+        if let Some(i) = nearest {
+            chars.swap(edge, i);
+        }
+    }
+}
+
+impl<R: RngCore> Iterator for PasswordGenerator<R> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.generate())
+    }
+}
+
+/// Maximum number of attempts made to avoid rejected outputs before giving up.
+pub const MAX_REGENERATION_ATTEMPTS: usize = 100;
+
+/// PINs that appear near the top of leaked-PIN frequency lists.
+pub const COMMON_PINS: &[&str] = &[
+    "1004", "2580", "6969", "1122", "1313", "0852", "5683", "7410", "1470", "2468", "8520",
+    "112233", "123123", "159753", "147258", "696969",
+];
+
+/// Return the first forbidden substring contained in `password`, if any.
+///
+/// The comparison is case-insensitive.
+pub fn find_forbidden_substring<'a>(password: &str, forbidden: &'a [String]) -> Option<&'a str> {
+    let lowered = password.to_lowercase();
+    forbidden
+        .iter()
+        .find(|f| !f.is_empty() && lowered.contains(&f.to_lowercase()))
+        .map(String::as_str)
+}
+
+/// Check whether a numeric PIN follows an obvious, easily guessed pattern.
+///
+/// Flags repeated digits (`0000`), ascending or descending runs (`1234`, `4321`),
+/// a repeated two-digit unit (`1212`), 4-digit years from 1900 to 2099, and
+/// entries from [`COMMON_PINS`].
+pub fn is_weak_pin(pin: &str) -> bool {
+    let digits: Vec<u32> = match pin.chars().map(|c| c.to_digit(10)).collect() {
+        Some(digits) => digits,
+        None => return false,
+    };
+
+    if digits.len() < 2 {
+        return false;
+    }
+
+    let all_same = digits.iter().all(|&d| d == digits[0]);
+    let steps: Vec<i64> = digits
+        .windows(2)
+        .map(|w| w[1] as i64 - w[0] as i64)
+        .collect();
+    let sequential = digits.len() >= 3
+        && (steps.iter().all(|&s| s == 1) || steps.iter().all(|&s| s == -1));
+    let repeated_pair =
+        digits.len() >= 4 && digits.iter().enumerate().all(|(i, &d)| d == digits[i % 2]);
+    let year = digits.len() == 4 && (1900..=2099).contains(&pin.parse::<u32>().unwrap_or(0));
+
+    all_same || sequential || repeated_pair || year || COMMON_PINS.contains(&pin)
+}
+
+/// Generate a password that is not rejected by the output filters.
+///
+/// Outputs containing any `forbidden` substring are rejected, and so are weak PINs
+/// (see [`is_weak_pin`]) when the config is digits-only. Passwords are regenerated
+/// up to [`MAX_REGENERATION_ATTEMPTS`] times; if every attempt is rejected, the
+/// error names the last reason.
+pub fn generate_password_avoiding(
+    config: &PasswordConfig,
+    forbidden: &[String],
+    rng: &mut impl RngCore,
+) -> Result<String> {
+    let mut rejection = String::new();
+
+    for _ in 0..MAX_REGENERATION_ATTEMPTS {
+        let password = generate_password(config, rng)?;
+        if let Some(found) = find_forbidden_substring(&password, forbidden) {
+            rejection = format!("forbidden substring '{}'", found);
+        } else if config.is_pin() && is_weak_pin(&password) {
+            rejection = "weak PIN patterns".to_string();
+        } else {
+            return Ok(password);
+        }
+    }
+
+    Err(BotError::PasswordGeneration(format!(
+        "Could not avoid {} after {} attempts",
+        rejection, MAX_REGENERATION_ATTEMPTS
+    )))
+}
+
+/// Split `password` into groups of `size` characters joined by `separator`.
+///
+/// Grouping is display-only: removing the separators yields the original password.
+pub fn group_password(password: &str, size: usize, separator: &str) -> String {
+    let chars: Vec<char> = password.chars().collect();
+    chars
+        .chunks(size.max(1))
+        .map(|group| group.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Spell a character out loud using the NATO phonetic alphabet.
+///
+/// Uppercase letters are spelled in capitals and lowercase letters in lowercase,
+/// so the readout preserves case.
+pub fn phonetic(c: char) -> String {
+    const NATO: [&str; 26] = [
+        "alfa", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india",
+        "juliett", "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo",
+        "sierra", "tango", "uniform", "victor", "whiskey", "x-ray", "yankee", "zulu",
+    ];
+    const DIGITS: [&str; 10] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
+
+    if c.is_ascii_lowercase() {
+        NATO[(c as u8 - b'a') as usize].to_string()
+    } else if c.is_ascii_uppercase() {
+        NATO[(c as u8 - b'A') as usize].to_uppercase()
+    } else if let Some(d) = c.to_digit(10) {
+        DIGITS[d as usize].to_string()
+    } else {
+        let name = match c {
+            '!' => "exclamation",
+            '@' => "at",
+            '#' => "hash",
+            '$' => "dollar",
+            '%' => "percent",
+            '^' => "caret",
+            '&' => "ampersand",
+            '*' => "asterisk",
+            '(' => "open-paren",
+            ')' => "close-paren",
+            '-' => "dash",
+            '_' => "underscore",
+            '=' => "equals",
+            '+' => "plus",
+            '[' => "open-bracket",
+            ']' => "close-bracket",
+            '{' => "open-brace",
+            '}' => "close-brace",
+            ';' => "semicolon",
+            ':' => "colon",
+            ',' => "comma",
+            '.' => "period",
+            '?' => "question",
+            '/' => "slash",
+            _ => return format!("'{}'", c),
+        };
+        name.to_string()
+    }
+}
+
+/// Password strength category based on entropy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum PasswordStrength {
+    Weak,
+    Medium,
+    Strong,
+}
+
+impl PasswordStrength {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PasswordStrength::Weak => "Weak",
+            PasswordStrength::Medium => "Medium",
+            PasswordStrength::Strong => "Strong",
+        }
+    }
+}
+
+/// Default upper bound, in bits, of Weak (`WEAK_ENTROPY_MAX`).
+pub const DEFAULT_WEAK_ENTROPY_MAX: f64 = 50.0;
+
+/// Default upper bound, in bits, of Medium (`MEDIUM_ENTROPY_MAX`).
+pub const DEFAULT_MEDIUM_ENTROPY_MAX: f64 = 80.0;
+
+/// Highest cutoff, in bits, a deployment may set; well past any real password.
+pub const MAX_ENTROPY_THRESHOLD: f64 = 256.0;
+
+/// Entropy cutoffs between the strength categories.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrengthThresholds {
+    /// Below this many bits a password is Weak.
+    pub weak_max: f64,
+    /// Below this many bits it is Medium, and Strong from here up.
+    pub medium_max: f64,
+}
+
+impl Default for StrengthThresholds {
+    fn default() -> Self {
+        Self {
+            weak_max: DEFAULT_WEAK_ENTROPY_MAX,
+            medium_max: DEFAULT_MEDIUM_ENTROPY_MAX,
+        }
+    }
+}
+
+/// Estimated entropy of `config` in bits: `length × log2(pool_size)`.
+///
+/// With `no_repeat`, every character after the first can't be the one before it:
+/// `log2(pool_size) + (length - 1) × log2(pool_size - 1)`.
+pub fn entropy_bits(config: &PasswordConfig) -> f64 {
+    if let Some(template) = &config.pattern {
+        return parse_pattern(template).map_or(0.0, |tokens| pattern_entropy_bits(&tokens));
+    }
+    if config.pronounceable {
+        return config
+            .pronounceable_slots()
+            .iter()
+            .map(|slot| (slot.len() as f64).log2())
+            .sum();
+    }
+    let pool = config.build_char_pool().len() as f64;
+    if config.no_repeat && config.length > 0 && pool > 1.0 {
+        return pool.log2() + (config.length - 1) as f64 * (pool - 1.0).log2();
+    }
+    (config.length as f64) * pool.log2()
+}
+
+/// `log2(n!)`.
+fn log2_factorial(n: usize) -> f64 {
+    (2..=n).map(|k| (k as f64).log2()).sum()
+}
+
+/// Share of the `total^length` strings over a pool that contain at least one
+/// character from each class in `classes` (sizes), by inclusion-exclusion.
+fn share_with_every_class(classes: &[usize], total: usize, length: usize) -> f64 {
+    (0..1usize << classes.len())
+        .map(|subset: usize| {
+            let excluded: usize = classes
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| subset & (1 << i) != 0)
+                .map(|(_, &size)| size)
+                .sum();
+            let term = ((total - excluded) as f64 / total as f64).powi(length as i32);
+            // Subsets with an odd number of missing classes are subtracted
+            if subset.count_ones() & 1 == 1 {
+                -term
+            } else {
+                term
+            }
+        })
+        .sum::<f64>()
+        .max(0.0)
+}
+
+/// Share of the `total^length` strings over a pool with at least `min` characters
+/// from each class in `classes` (size, min).
+///
+/// Falls back to [`share_with_every_class`] when every minimum is one. Otherwise
+/// the multinomial probability is summed class by class in log space, since the
+/// binomial coefficients of long passwords overflow `f64`.
+fn share_meeting_minimums(classes: &[(usize, usize)], total: usize, length: usize) -> f64 {
+    if classes.iter().all(|&(_, min)| min <= 1) {
+        let sizes: Vec<usize> = classes
+            .iter()
+            .filter(|&&(_, min)| min == 1)
+            .map(|&(size, _)| size)
+            .collect();
+        return share_with_every_class(&sizes, total, length);
+    }
+
+    // log2_ways[t]: log2 of the share of length-t strings over the classes seen so far
+    // (each weighted by its share of the pool) that meet their minimums
+    let log2_fact: Vec<f64> = (0..=length)
+        .scan(0.0, |sum, n| {
+            *sum += (n.max(1) as f64).log2();
+            Some(*sum)
+        })
+        .collect();
+    let mut log2_ways = vec![f64::NEG_INFINITY; length + 1];
+    log2_ways[0] = 0.0;
+    for &(size, min) in classes {
+        let log2_p = (size as f64 / total as f64).log2();
+        let mut next = vec![f64::NEG_INFINITY; length + 1];
+        for (t, slot) in next.iter_mut().enumerate() {
+            let terms: Vec<f64> = (min..=t)
+                .filter(|&k| log2_ways[t - k].is_finite())
+                .map(|k| {
+                    log2_fact[t] - log2_fact[k] - log2_fact[t - k] + k as f64 * log2_p
+                        + log2_ways[t - k]
+                })
+                .collect();
+            let Some(max) = terms.iter().copied().reduce(f64::max) else {
+                continue;
+            };
+            *slot = max + terms.iter().map(|x| (x - max).exp2()).sum::<f64>().log2();
+        }
+        log2_ways = next;
+    }
+    log2_ways[length].exp2().min(1.0)
+}
+
+/// Entropy of `config` for an attacker who knows the exact generation rules.
+///
+/// [`entropy_bits`] counts every string over the pool, but only strings that meet
+/// the rules can come out: each enabled class's minimum (at least one), a policy's
+/// exact class counts, and (approximately) letters on both edges with
+/// `--interior-special` and no adjacent repeats with `--no-repeat`. This counts
+/// just those, so it is never above
+/// [`entropy_bits`].
+pub fn policy_aware_entropy(config: &PasswordConfig) -> f64 {
+    // Patterns and pronounceable slots already encode every rule
+    if config.pronounceable || config.pattern.is_some() {
+        return entropy_bits(config);
+    }
+    let length = config.length;
+    let classes = config.class_sets();
+    let emoji = config.emoji_set().len();
+    let pool: usize = classes.iter().map(|(_, chars)| chars.len()).sum::<usize>() + emoji;
+    if length == 0 || pool == 0 {
+        return 0.0;
+    }
+
+    let plan = config.policy.as_ref().and_then(|p| p.seed_plan(config).ok());
+    let mut bits = match plan {
+        Some(plan) => {
+            // Choose the positions of each exact class, then its characters
+            let exact = &plan.seeds[..plan.exact_seeds];
+            let rest = length - exact.iter().map(|(_, count)| count).sum::<usize>();
+            let free: Vec<(usize, usize)> = plan.seeds[plan.exact_seeds..]
+                .iter()
+                .map(|(group, count)| (group.len(), *count))
+                .collect();
+
+            let mut bits = log2_factorial(length) - log2_factorial(rest);
+            for (group, count) in exact {
+                bits += *count as f64 * (group.len() as f64).log2() - log2_factorial(*count);
+            }
+            if rest > 0 {
+                let fill = plan.fill_pool.len();
+                bits += rest as f64 * (fill as f64).log2()
+                    + share_meeting_minimums(&free, fill, rest).log2();
+            }
+            bits
+        }
+        None => {
+            // Emoji are in the pool but never required
+            let sizes: Vec<(usize, usize)> = classes
+                .iter()
+                .map(|(class, chars)| (chars.len(), config.required_count(*class)))
+                .chain((emoji > 0).then_some((emoji, 0)))
+                .collect();
+            length as f64 * (pool as f64).log2()
+                + share_meeting_minimums(&sizes, pool, length).log2()
+        }
+    };
+
+    // Both edges are drawn from the letters only
+    if config.interior_special && length >= 2 {
+        let letters = config.edge_letters().len();
+        if letters > 0 {
+            bits -= 2.0 * ((pool as f64).log2() - (letters as f64).log2());
+        }
+    }
+
+    // Every character after the first has its predecessor ruled out
+    if config.no_repeat && pool > 1 {
+        bits -= (length - 1) as f64 * ((pool as f64).log2() - ((pool - 1) as f64).log2());
+    }
+
+    bits.clamp(0.0, entropy_bits(config))
+}
+
+/// Estimate password strength based on entropy.
+///
+/// The rating uses [`policy_aware_entropy`], not the `length × log2(pool_size)`
+/// of [`entropy_bits`]: the generator forces at least one character of each
+/// enabled class, so only the strings that contain one can come out. By
+/// inclusion-exclusion over the classes, those number
+/// `Σ_S (-1)^|S| × (pool - size(S))^length` for every subset `S` of classes left
+/// out, which is what the estimate takes the log of. The difference is small for
+/// long passwords, but rating on it never overstates the strength of short ones.
+///
+/// Strength categories, with `config.strength_thresholds` at their defaults:
+/// - Weak: < 50 bits
+/// - Medium: 50-80 bits
+/// - Strong: >= 80 bits
+pub fn estimate_strength(config: &PasswordConfig) -> PasswordStrength {
+    let pool_size = config.build_char_pool().len();
+    if pool_size == 0 {
+        return PasswordStrength::Weak;
+    }
+
+    strength_for_entropy(policy_aware_entropy(config), config.strength_thresholds)
+}
+
+/// Strength category for `bits` of entropy, for passwords and passphrases alike.
+pub fn strength_for_entropy(bits: f64, thresholds: StrengthThresholds) -> PasswordStrength {
+    if bits < thresholds.weak_max {
+        PasswordStrength::Weak
+    } else if bits < thresholds.medium_max {
+        PasswordStrength::Medium
+    } else {
+        PasswordStrength::Strong
+    }
+}
+
+/// Shortest length from `config.length` up to `max_length` at which `config`
+/// reaches at least `target` strength, or `None` if no such length exists.
+pub fn min_length_for_strength(
+    config: &PasswordConfig,
+    target: PasswordStrength,
+    max_length: usize,
+) -> Option<usize> {
+    (config.length..=max_length).find(|&length| {
+        let candidate = PasswordConfig {
+            length,
+            ..config.clone()
+        };
+        estimate_strength(&candidate) >= target
+    })
+}
+
+/// Longest length [`length_for_entropy`] considers for pronounceable passwords.
+const MAX_ENTROPY_SEARCH_LENGTH: usize = 10_000;
+
+/// Shortest length at which `config` reaches `target_bits` of entropy.
+///
+/// Inverts `length × log2(pool size)`, rounding up. Pronounceable passwords don't
+/// follow that formula, so their lengths are searched instead. Saturates at
+/// `usize::MAX` (or the search bound) when the target can't be reached at all,
+/// e.g. with a one-character pool; callers cap the result anyway.
+pub fn length_for_entropy(config: &PasswordConfig, target_bits: f64) -> usize {
+    if config.pronounceable || config.no_repeat {
+        return (1..=MAX_ENTROPY_SEARCH_LENGTH)
+            .find(|&length| {
+                let candidate = PasswordConfig {
+                    length,
+                    ..config.clone()
+                };
+                entropy_bits(&candidate) >= target_bits
+            })
+            .unwrap_or(MAX_ENTROPY_SEARCH_LENGTH);
+    }
+
+    let per_char = (config.build_char_pool().len() as f64).log2();
+    if per_char <= 0.0 {
+        return usize::MAX;
+    }
+    // The small tolerance keeps exact multiples (64 bits over 16 symbols) from
+    // rounding up an extra character on floating-point noise
+    ((target_bits / per_char) - 1e-9).ceil().max(1.0) as usize
+}
+
+/// Enabling a character class is weighed like adding this many characters when
+/// picking the cheapest improvement: it changes what the user has to type.
+const CLASS_CHANGE_COST: usize = 2;
+
+/// Most extra `{word}` placeholders [`improvement_suggestion`] will propose.
+const MAX_SUGGESTED_WORDS: usize = 32;
+
+/// How far past the current length [`improvement_suggestion`] looks.
+const MAX_SUGGESTED_EXTRA_LENGTH: usize = 256;
+
+/// Suggest the cheapest single change that moves `config` up one strength tier.
+///
+/// Compares lengthening the password against enabling one more character class.
+/// A Weak config also hears what Strong would take, so a short PIN shows how far
+/// off it is. Returns `None` if the config is already Strong or nothing helps.
+pub fn improvement_suggestion(config: &PasswordConfig) -> Option<String> {
+    let target = match estimate_strength(config) {
+        PasswordStrength::Weak => PasswordStrength::Medium,
+        PasswordStrength::Medium => PasswordStrength::Strong,
+        PasswordStrength::Strong => return None,
+    };
+
+    if config.pattern.is_some() {
+        let entropy = entropy_bits(config);
+        let thresholds = config.strength_thresholds;
+        let words = (1..=MAX_SUGGESTED_WORDS).find(|&n| {
+            strength_for_entropy(entropy + passphrase_entropy_bits(n), thresholds) >= target
+        })?;
+        return Some(format!(
+            "Add {} more {{word}} placeholder{} to reach {}.",
+            words,
+            if words == 1 { "" } else { "s" },
+            target.as_str()
+        ));
+    }
+
+    // (cost, suggestion) candidates
+    let mut candidates = Vec::new();
+
+    let max_length = config.length + MAX_SUGGESTED_EXTRA_LENGTH;
+    if let Some(length) = min_length_for_strength(config, target, max_length) {
+        let extra = length - config.length;
+        candidates.push((
+            extra,
+            format!(
+                "Add {} more character{} to reach {}.",
+                extra,
+                if extra == 1 { "" } else { "s" },
+                target.as_str()
+            ),
+        ));
+    }
+
+    for name in ["symbols", "digits", "uppercase letters", "lowercase letters"] {
+        let mut candidate = config.clone();
+        let enabled = match name {
+            "symbols" => &mut candidate.use_symbols,
+            "digits" => &mut candidate.use_digits,
+            "uppercase letters" => &mut candidate.use_uppercase,
+            _ => &mut candidate.use_lowercase,
+        };
+        if *enabled {
+            continue;
+        }
+        *enabled = true;
+        if estimate_strength(&candidate) >= target {
+            candidates.push((
+                CLASS_CHANGE_COST,
+                format!("Enable {} to reach {}.", name, target.as_str()),
+            ));
+        }
+    }
+
+    // Stable sort keeps the length suggestion first on ties
+    candidates.sort_by_key(|(cost, _)| *cost);
+    let tip = candidates.into_iter().next().map(|(_, suggestion)| suggestion);
+    if target != PasswordStrength::Medium {
+        return tip;
+    }
+    match (tip, strong_suggestion(config)) {
+        (Some(tip), Some(strong)) => Some(format!("{} {}", tip, strong)),
+        (tip, strong) => tip.or(strong),
+    }
+}
+
+/// What it would take a Weak `config` to reach Strong: the length that gets there
+/// with the current pool, and more character types if any are turned off.
+fn strong_suggestion(config: &PasswordConfig) -> Option<String> {
+    let max_length = config.length + MAX_SUGGESTED_EXTRA_LENGTH;
+    let length = min_length_for_strength(config, PasswordStrength::Strong, max_length);
+    let all_types = [
+        config.use_lowercase,
+        config.use_uppercase,
+        config.use_digits,
+        config.use_symbols,
+    ];
+    let types_off = !config.pronounceable && all_types.contains(&false);
+    let how = match (types_off, length) {
+        (true, Some(length)) => {
+            format!("enable more character types or use a length of {}", length)
+        }
+        (false, Some(length)) => format!("use a length of {}", length),
+        (true, None) => "enable more character types".to_string(),
+        (false, None) => return None,
+    };
+    Some(format!(
+        "To reach Strong ({} bits), {}.",
+        config.strength_thresholds.medium_max, how
+    ))
+}
+
+/// Estimate how hard `password` is to guess using zxcvbn.
+///
+/// Returns log10 of the estimated number of guesses. This is supplementary to the
+/// entropy estimate: entropy describes the generator, zxcvbn the concrete output.
+#[cfg(feature = "zxcvbn")]
+pub fn zxcvbn_guesses_log10(password: &str) -> f64 {
+    zxcvbn::zxcvbn(password, &[]).guesses_log10()
+}
+
+/// What [`format_metadata`] reports about a config, also serialized for `--json`.
+///
+/// Built from the settings alone, so it never holds a password.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PasswordMetadata {
+    /// Password length; `None` for a pattern whose words make it vary.
+    pub length: Option<usize>,
+    pub entropy_bits: f64,
+    pub strength: PasswordStrength,
+    /// Distinct characters drawn from; `None` for a pattern, whose positions each
+    /// draw from their own set.
+    pub pool_size: Option<usize>,
+    /// The character types, or the template of a pattern.
+    #[serde(skip)]
+    types: String,
+}
+
+impl PasswordMetadata {
+    pub fn new(config: &PasswordConfig, strength: PasswordStrength) -> Self {
+        // The figure the rating is based on, see [`estimate_strength`]
+        let entropy_bits = policy_aware_entropy(config);
+
+        if let Some(template) = &config.pattern {
+            let (shortest, longest) =
+                parse_pattern(template).map_or((0, 0), |tokens| pattern_length_range(&tokens));
+            return Self {
+                length: (shortest == longest).then_some(longest),
+                entropy_bits,
+                strength,
+                pool_size: None,
+                types: template.clone(),
+            };
+        }
+
+        if config.pronounceable {
+            let slots = config.pronounceable_slots();
+            let mut pool: Vec<char> =
+                slots.iter().flatten().copied().filter(|&c| c != '-').collect();
+            pool.sort_unstable();
+            pool.dedup();
+            let types = if slots.iter().any(|slot| slot.iter().any(char::is_ascii_digit)) {
+                "pronounceable syllables, digits"
+            } else {
+                "pronounceable syllables"
+            };
+            return Self {
+                length: Some(config.length),
+                entropy_bits,
+                strength,
+                pool_size: Some(pool.len()),
+                types: types.to_string(),
+            };
+        }
+
+        let mut char_types = Vec::new();
+        if config.use_lowercase {
+            char_types.push("lowercase");
+        }
+        if config.use_uppercase {
+            char_types.push("uppercase");
+        }
+        if config.use_digits {
+            char_types.push("digits");
+        }
+        if config.use_symbols {
+            char_types.push("symbols");
+        }
+        if config.use_emoji {
+            char_types.push("emoji");
+        }
+
+        let mut types = char_types.join(", ");
+        if !config.exclude_chars.is_empty() {
+            let excluded: String = config.exclude_chars.iter().collect();
+            types.push_str(&format!(" (excluding {})", excluded));
+        }
+
+        Self {
+            length: Some(config.length),
+            entropy_bits,
+            strength,
+            pool_size: Some(config.build_char_pool().len()),
+            types,
+        }
+    }
+}
+
+impl fmt::Display for PasswordMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.length, self.pool_size) {
+            (Some(length), Some(pool_size)) => write!(
+                f,
+                "Length: {} | Types: {} | Pool size: {} | Entropy: {:.1} bits | Strength: {}",
+                length,
+                self.types,
+                pool_size,
+                self.entropy_bits,
+                self.strength.as_str()
+            ),
+            _ => write!(
+                f,
+                "Pattern: {} | Entropy: {:.1} bits | Strength: {}",
+                self.types,
+                self.entropy_bits,
+                self.strength.as_str()
+            ),
+        }
+    }
+}
+
+/// Format password metadata for display (without revealing the password in logs).
+pub fn format_metadata(config: &PasswordConfig, strength: PasswordStrength) -> String {
+    PasswordMetadata::new(config, strength).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::{OsRng, StdRng};
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_default_config() {
+        let config = PasswordConfig::default();
+        assert!(config.validate().is_ok());
         assert!(config.length > 0);
         assert!(config.use_lowercase || config.use_uppercase || config.use_digits || config.use_symbols);
     }
 
     #[test]
-    fn test_password_length() {
+    fn test_password_length() {
+        let config = PasswordConfig {
+            length: 20,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        let password = generate_password(&config, &mut rng).unwrap();
+        assert_eq!(password.chars().count(), 20);
+    }
+
+    #[test]
+    fn test_multibyte_symbols_count_as_one_character() {
+        let config = PasswordConfig {
+            length: 20,
+            charsets: CharSets::default().with_symbols("§±€¥£"),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(278);
+        for password in generate_passwords(&config, 50, &mut rng).unwrap() {
+            assert_eq!(password.chars().count(), 20);
+            assert!(password.len() > 20, "at least one symbol is two or more bytes");
+        }
+        assert!(format_metadata(&config, estimate_strength(&config)).starts_with("Length: 20 |"));
+        assert_eq!(config_for_password("ab€1").length, 4);
+    }
+
+    #[test]
+    fn test_password_contains_required_types() {
+        let config = PasswordConfig {
+            length: 16,
+            use_lowercase: true,
+            use_uppercase: true,
+            use_digits: true,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        let password = generate_password(&config, &mut rng).unwrap();
+
+        let has_lowercase = password.chars().any(|c| c.is_ascii_lowercase());
+        let has_uppercase = password.chars().any(|c| c.is_ascii_uppercase());
+        let has_digit = password.chars().any(|c| c.is_ascii_digit());
+
+        assert!(has_lowercase, "Password should contain lowercase");
+        assert!(has_uppercase, "Password should contain uppercase");
+        assert!(has_digit, "Password should contain digit");
+    }
+
+    #[test]
+    fn test_no_ambiguous_characters() {
+        let config = PasswordConfig {
+            length: 20,
+            use_lowercase: true,
+            use_uppercase: true,
+            use_digits: true,
+            use_symbols: false,
+            exclude_ambiguous: true,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        let password = generate_password(&config, &mut rng).unwrap();
+
+        for c in password.chars() {
+            assert!(!AMBIGUOUS.contains(&c), "Found ambiguous character: {}", c);
+        }
+    }
+
+    #[test]
+    fn test_invalid_config_no_char_types() {
+        let config = PasswordConfig {
+            length: 16,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_invalid_config_zero_length() {
+        let config = PasswordConfig {
+            length: 0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_strength_estimation() {
+        // Strong password
+        let strong_config = PasswordConfig {
+            length: 20,
+            ..Default::default()
+        };
+        assert_eq!(estimate_strength(&strong_config), PasswordStrength::Strong);
+
+        // Weak password
+        let weak_config = PasswordConfig {
+            length: 6,
+            use_lowercase: true,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        assert_eq!(estimate_strength(&weak_config), PasswordStrength::Weak);
+    }
+
+    #[test]
+    fn test_custom_strength_thresholds() {
+        // About 103 bits: Strong by default
+        let config = PasswordConfig {
+            length: 16,
+            ..Default::default()
+        };
+        assert_eq!(estimate_strength(&config), PasswordStrength::Strong);
+
+        let strict = PasswordConfig {
+            strength_thresholds: StrengthThresholds {
+                weak_max: 80.0,
+                medium_max: 128.0,
+            },
+            ..config
+        };
+        assert_eq!(estimate_strength(&strict), PasswordStrength::Medium);
+        // Lengthening follows the stricter cutoffs too
+        let length = min_length_for_strength(&strict, PasswordStrength::Strong, 64).unwrap();
+        assert!(policy_aware_entropy(&PasswordConfig { length, ..strict.clone() }) >= 128.0);
+        assert!(improvement_suggestion(&strict).unwrap().contains("Strong"));
+
+        let thresholds = strict.strength_thresholds;
+        assert_eq!(strength_for_entropy(79.9, thresholds), PasswordStrength::Weak);
+        assert_eq!(strength_for_entropy(80.0, thresholds), PasswordStrength::Medium);
+        assert_eq!(strength_for_entropy(128.0, thresholds), PasswordStrength::Strong);
+    }
+
+    #[test]
+    fn test_char_pool_building() {
+        let config = PasswordConfig {
+            length: 10,
+            use_lowercase: true,
+            use_uppercase: false,
+            use_digits: true,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        let pool = config.build_char_pool();
+        assert!(!pool.is_empty());
+        assert!(pool.iter().any(|c| c.is_ascii_lowercase()));
+        assert!(pool.iter().any(|c| c.is_ascii_digit()));
+        assert!(!pool.iter().any(|c| c.is_ascii_uppercase()));
+    }
+
+    #[test]
+    fn test_find_forbidden_substring_is_case_insensitive() {
+        let forbidden = vec!["admin".to_string()];
+        assert_eq!(find_forbidden_substring("xxADMINxx", &forbidden), Some("admin"));
+        assert_eq!(find_forbidden_substring("xxadm1nxx", &forbidden), None);
+    }
+
+    #[test]
+    fn test_forbidden_substring_avoidable() {
+        // A single lowercase letter shows up often but is easy to avoid with retries
+        let config = PasswordConfig {
+            length: 8,
+            use_lowercase: true,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        let forbidden = vec!["A".to_string()];
+        let mut rng = OsRng;
+        for _ in 0..20 {
+            let password = generate_password_avoiding(&config, &forbidden, &mut rng).unwrap();
+            assert!(!password.contains('a'));
+        }
+    }
+
+    #[test]
+    fn test_forbidden_substring_impossible() {
+        // Every digit is forbidden, so a digits-only password can never pass
+        let config = PasswordConfig {
+            length: 8,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: true,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        let forbidden: Vec<String> = (0..10).map(|d| d.to_string()).collect();
+        let mut rng = OsRng;
+        let err = generate_password_avoiding(&config, &forbidden, &mut rng).unwrap_err();
+        assert!(err.to_string().contains("forbidden substring '"));
+    }
+
+    #[cfg(feature = "zxcvbn")]
+    #[test]
+    fn test_zxcvbn_guesses_log10_for_random_password() {
+        let config = PasswordConfig {
+            length: 20,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        let password = generate_password(&config, &mut rng).unwrap();
+        assert!(zxcvbn_guesses_log10(&password) > 15.0);
+        assert!(zxcvbn_guesses_log10("password") < 3.0);
+    }
+
+    #[test]
+    fn test_custom_charsets() {
+        let charsets = CharSets::default()
+            .with_lowercase("abc")
+            .with_uppercase("XYZ")
+            .with_digits("78")
+            .with_symbols("!");
+        let config = PasswordConfig {
+            length: 32,
+            charsets,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        let password = generate_password(&config, &mut rng).unwrap();
+
+        assert_eq!(config.build_char_pool().len(), 9);
+        assert!(password.chars().all(|c| "abcXYZ78!".contains(c)));
+        assert!(password.contains('!'));
+    }
+
+    #[test]
+    fn test_custom_ambiguous_set() {
+        let config = PasswordConfig {
+            length: 10,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
+            exclude_ambiguous: true,
+            charsets: CharSets::default().with_ambiguous(['a', 'b']),
+            ..Default::default()
+        };
+        let pool = config.build_char_pool();
+        assert!(!pool.contains(&'a') && !pool.contains(&'b'));
+        assert!(pool.contains(&'o') && pool.contains(&'l'));
+    }
+
+    #[test]
+    fn test_weak_pins_rejected() {
+        for pin in ["0000", "1111", "1234", "4321", "1212", "1990", "2024", "123456", "6969"] {
+            assert!(is_weak_pin(pin), "{} should be weak", pin);
+        }
+    }
+
+    #[test]
+    fn test_strong_looking_pin_passes() {
+        for pin in ["3819", "7052", "480613"] {
+            assert!(!is_weak_pin(pin), "{} should not be weak", pin);
+        }
+    }
+
+    #[test]
+    fn test_generated_pins_skip_weak_patterns() {
+        let config = PasswordConfig {
+            length: 4,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: true,
+            use_symbols: false,
+            exclude_ambiguous: false,
+            ..Default::default()
+        };
+        assert!(config.is_pin());
+
+        let mut rng = OsRng;
+        for _ in 0..200 {
+            let pin = generate_password_avoiding(&config, &[], &mut rng).unwrap();
+            assert!(!is_weak_pin(&pin), "generated weak PIN {}", pin);
+        }
+    }
+
+    #[test]
+    fn test_group_password_roundtrip() {
+        let password = "xK4f9pLm2Qvz!";
+        let grouped = group_password(password, 4, " ");
+        assert_eq!(grouped, "xK4f 9pLm 2Qvz !");
+        assert_eq!(grouped.replace(' ', ""), password);
+    }
+
+    #[test]
+    fn test_phonetic() {
+        assert_eq!(phonetic('x'), "x-ray");
+        assert_eq!(phonetic('K'), "KILO");
+        assert_eq!(phonetic('4'), "four");
+        assert_eq!(phonetic('#'), "hash");
+    }
+
+    #[test]
+    fn test_no_confusable_symbols() {
+        let config = PasswordConfig {
+            length: 32,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: true,
+            exclude_confusable_symbols: true,
+            ..Default::default()
+        };
+        let pool = config.build_char_pool();
+        assert_eq!(pool.len(), 19);
+
+        let mut rng = OsRng;
+        for _ in 0..20 {
+            let password = generate_password(&config, &mut rng).unwrap();
+            for &(a, b) in CONFUSABLE_SYMBOL_PAIRS {
+                assert!(
+                    !(password.contains(a) && password.contains(b)),
+                    "both {} and {} appeared",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_passwords_batch() {
+        let config = PasswordConfig::default();
+        let mut rng = OsRng;
+        let passwords = generate_passwords(&config, 5, &mut rng).unwrap();
+        assert_eq!(passwords.len(), 5);
+        assert!(passwords.iter().all(|p| p.chars().count() == config.length));
+    }
+
+    #[test]
+    fn test_password_generator_streams_valid_passwords() {
+        let config = PasswordConfig {
+            length: 12,
+            use_symbols: false,
+            exclude_ambiguous: true,
+            ..Default::default()
+        };
+        let generator = PasswordGenerator::new(&config, OsRng).unwrap();
+
+        let mut count = 0;
+        for password in generator.take(200) {
+            let password = password.unwrap();
+            assert_eq!(password.chars().count(), 12);
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+            assert!(!password.chars().any(|c| AMBIGUOUS.contains(&c)));
+            count += 1;
+        }
+        assert_eq!(count, 200);
+    }
+
+    #[test]
+    fn test_password_generator_rejects_invalid_config() {
+        let config = PasswordConfig {
+            length: 0,
+            ..Default::default()
+        };
+        assert!(PasswordGenerator::new(&config, OsRng).is_err());
+    }
+
+    #[test]
+    fn test_csv_safe_round_trip() {
+        let config = PasswordConfig {
+            length: 32,
+            exclude_csv_unsafe: true,
+            ..Default::default()
+        };
+        assert_eq!(config.build_char_pool().len(), 26 + 26 + 10 + 22);
+
+        let mut rng = OsRng;
+        let passwords = generate_passwords(&config, 50, &mut rng).unwrap();
+        for password in &passwords {
+            assert!(!password.contains(CSV_UNSAFE), "{} is not CSV-safe", password);
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for password in &passwords {
+            writer.write_record([password.as_str(), "note"]).unwrap();
+        }
+        let data = writer.into_inner().unwrap();
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_slice());
+        let read: Vec<String> = reader
+            .records()
+            .map(|r| r.unwrap()[0].to_string())
+            .collect();
+        assert_eq!(read, passwords);
+    }
+
+    #[test]
+    fn test_csv_safe_composes_with_confusable() {
+        let config = PasswordConfig {
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: false,
+            exclude_confusable_symbols: true,
+            exclude_csv_unsafe: true,
+            ..Default::default()
+        };
+        // 19 after dropping look-alikes, minus the kept `,` and `;`
+        assert_eq!(config.build_char_pool().len(), 17);
+    }
+
+    #[test]
+    fn test_exclude_chars_shrinks_pool() {
+        let config = PasswordConfig {
+            length: 40,
+            exclude_chars: vec!['<', '>', '"', 'a', '7'],
+            ..Default::default()
+        };
+        let full = PasswordConfig {
+            length: 40,
+            ..Default::default()
+        };
+        // `<`, `>` and `"` aren't in the default symbols, so only `a` and `7` go
+        assert_eq!(config.build_char_pool().len(), full.build_char_pool().len() - 2);
+        assert!(entropy_bits(&config) < entropy_bits(&full));
+
+        let mut rng = OsRng;
+        for password in generate_passwords(&config, 50, &mut rng).unwrap() {
+            assert!(!password.contains(['<', '>', 'a', '7']), "{}", password);
+        }
+
+        let metadata = format_metadata(&config, estimate_strength(&config));
+        assert!(
+            metadata.contains("Types: lowercase, uppercase, digits, symbols (excluding <>\"a7)")
+        );
+    }
+
+    #[test]
+    fn test_exclude_chars_cannot_empty_a_class() {
+        let config = PasswordConfig {
+            exclude_chars: DIGITS.chars().collect(),
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("--exclude removes every digit; use --no-digits"));
+
+        let config = PasswordConfig {
+            use_digits: false,
+            ..config
+        };
+        assert!(config.validate().is_ok());
+
+        let config = PasswordConfig {
+            pronounceable: true,
+            exclude_chars: PRONOUNCEABLE_VOWELS.chars().collect(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_fix_edges() {
+        let letters: Vec<char> = LOWERCASE.chars().collect();
+        let mut chars: Vec<char> = "1ab#".chars().collect();
+        fix_edges(&mut chars, &letters);
+        assert_eq!(chars.iter().collect::<String>(), "a1#b");
+
+        // Nothing to swap with: left unchanged
+        let mut chars: Vec<char> = "1#".chars().collect();
+        fix_edges(&mut chars, &letters);
+        assert_eq!(chars.iter().collect::<String>(), "1#");
+    }
+
+    #[test]
+    fn test_interior_special_edges_are_letters() {
+        let config = PasswordConfig {
+            length: 4,
+            use_uppercase: false,
+            interior_special: true,
+            ..Default::default()
+        };
+        let mut rng = OsRng;
+        for password in generate_passwords(&config, 200, &mut rng).unwrap() {
+            let first = password.chars().next().unwrap();
+            let last = password.chars().last().unwrap();
+            assert!(first.is_ascii_lowercase(), "{} starts with a special", password);
+            assert!(last.is_ascii_lowercase(), "{} ends with a special", password);
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+            assert!(password.chars().any(|c| SYMBOLS.contains(c)));
+        }
+    }
+
+    #[test]
+    fn test_interior_special_feasibility() {
+        let no_letters = PasswordConfig {
+            use_lowercase: false,
+            use_uppercase: false,
+            interior_special: true,
+            ..Default::default()
+        };
+        assert!(generate_password(&no_letters, &mut OsRng).is_err());
+
+        // Lowercase, digit and symbol plus a second edge letter need 4 characters
+        let too_short = PasswordConfig {
+            length: 3,
+            use_uppercase: false,
+            interior_special: true,
+            ..Default::default()
+        };
+        assert!(generate_password(&too_short, &mut OsRng).is_err());
+    }
+
+    #[test]
+    fn test_no_repeat_keeps_neighbours_distinct() {
+        // Few letters and a long password make adjacent repeats near-certain without the flag
+        let config = PasswordConfig {
+            length: 40,
+            use_uppercase: false,
+            use_lowercase: false,
+            charsets: CharSets::default().with_symbols("!@#"),
+            min_digits: 30,
+            no_repeat: true,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(293);
+        for password in generate_passwords(&config, 200, &mut rng).unwrap() {
+            let chars: Vec<char> = password.chars().collect();
+            assert!(chars.windows(2).all(|w| w[0] != w[1]), "{} repeats", password);
+            // Redraws stay within a character's type, so the minimum still holds
+            assert!(chars.iter().filter(|c| c.is_ascii_digit()).count() >= 30);
+        }
+    }
+
+    #[test]
+    fn test_no_repeat_validation() {
+        let tiny = PasswordConfig {
+            use_symbols: true,
+            charsets: CharSets::default().with_symbols("!@"),
+            no_repeat: true,
+            ..Default::default()
+        };
+        let err = tiny.validate().unwrap_err().to_string();
+        assert!(err.contains("symbols"), "{}", err);
+
+        let pronounceable = PasswordConfig {
+            pronounceable: true,
+            no_repeat: true,
+            ..Default::default()
+        };
+        assert!(pronounceable.validate().is_err());
+        assert!(PasswordConfig::builder().no_repeat(true).build().is_ok());
+    }
+
+    #[test]
+    fn test_no_repeat_entropy() {
+        let plain = PasswordConfig::default();
+        let config = PasswordConfig {
+            no_repeat: true,
+            ..Default::default()
+        };
+        let pool = plain.build_char_pool().len() as f64;
+        let expected = pool.log2() + 15.0 * (pool - 1.0).log2();
+        assert!((entropy_bits(&config) - expected).abs() < 1e-9);
+        assert!(entropy_bits(&config) < entropy_bits(&plain));
+        assert!(policy_aware_entropy(&config) < policy_aware_entropy(&plain));
+        let length = length_for_entropy(&config, 100.0);
+        assert!(entropy_bits(&PasswordConfig { length, ..config.clone() }) >= 100.0);
+        assert!(entropy_bits(&PasswordConfig { length: length - 1, ..config }) < 100.0);
+    }
+
+    struct Suffix(&'static str);
+
+    impl PasswordTransform for Suffix {
+        fn apply(&self, pw: String, _rng: &mut dyn RngCore) -> Result<String> {
+            Ok(pw + self.0)
+        }
+    }
+
+    struct RejectLongerThan(usize);
+
+    impl PasswordTransform for RejectLongerThan {
+        fn apply(&self, pw: String, _rng: &mut dyn RngCore) -> Result<String> {
+            if pw.chars().count() > self.0 {
+                return Err(BotError::PasswordGeneration("too long".to_string()));
+            }
+            Ok(pw)
+        }
+    }
+
+    #[test]
+    fn test_transform_pipeline_composes_in_order() {
+        let mut pipeline = TransformPipeline::default();
+        pipeline.push(Suffix("1"));
+        pipeline.push(InteriorSpecial {
+            letters: LOWERCASE.chars().collect(),
+        });
+
+        let out = pipeline.apply("ab".to_string(), &mut OsRng).unwrap();
+        assert_eq!(out, "a1b");
+    }
+
+    #[test]
+    fn test_transform_pipeline_stops_on_error() {
+        let mut pipeline = TransformPipeline::default();
+        pipeline.push(Suffix("!!"));
+        pipeline.push(RejectLongerThan(3));
+        pipeline.push(Suffix("unreachable"));
+
+        assert!(pipeline.apply("ab".to_string(), &mut OsRng).is_err());
+        assert_eq!(pipeline.apply("a".to_string(), &mut OsRng).unwrap(), "a!!unreachable");
+    }
+
+    #[test]
+    fn test_parse_symbol_set() {
+        assert_eq!(parse_symbol_set("!@#$!").unwrap(), "!@#$");
+        assert!(parse_symbol_set("").is_err());
+        assert!(parse_symbol_set("! @").is_err());
+        assert!(parse_symbol_set("!a").is_err());
+        assert!(parse_symbol_set("!7").is_err());
+
+        // The pool and entropy follow the custom set
+        let config = PasswordConfig {
+            use_lowercase: false,
+            use_uppercase: false,
+            use_digits: false,
+            charsets: CharSets::default().with_symbols(parse_symbol_set("!@#$").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(config.build_char_pool().len(), 4);
+        assert!((entropy_bits(&config) - 2.0 * config.length as f64).abs() < 1e-9);
+        let password = generate_password(&config, &mut OsRng).unwrap();
+        assert!(password.chars().all(|c| "!@#$".contains(c)));
+    }
+
+    #[test]
+    fn test_pronounceable_passwords() {
+        let config = PasswordConfig {
+            length: 13,
+            pronounceable: true,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(5);
+        for password in generate_passwords(&config, 20, &mut rng).unwrap() {
+            let (letters, digits) = password.split_at(11);
+            assert_eq!(letters.chars().nth(6), Some('-'));
+            for (i, c) in letters.replace('-', "").chars().enumerate() {
+                let expected = if i & 1 == 0 {
+                    PRONOUNCEABLE_CONSONANTS
+                } else {
+                    PRONOUNCEABLE_VOWELS
+                };
+                assert!(expected.contains(c), "{} in {}", c, password);
+            }
+            assert!(digits.chars().all(|c| c.is_ascii_digit()));
+        }
+
+        // Never ends on a hyphen, and is exactly as long as requested
+        for length in 1..30 {
+            let config = PasswordConfig {
+                length,
+                use_digits: false,
+                pronounceable: true,
+                ..Default::default()
+            };
+            let password = generate_password(&config, &mut rng).unwrap();
+            assert_eq!(password.chars().count(), length);
+            assert!(!password.ends_with('-'));
+        }
+    }
+
+    #[test]
+    fn test_pronounceable_rejects_ignored_options() {
+        let config = PasswordConfig {
+            length: 20,
+            pronounceable: true,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let interior = PasswordConfig {
+            interior_special: true,
+            ..config.clone()
+        };
+        let err = interior.validate().unwrap_err();
+        assert!(err.to_string().contains("--interior-special"), "{}", err);
+
+        let no_lowercase = PasswordConfig {
+            use_lowercase: false,
+            ..config
+        };
+        let err = no_lowercase.validate().unwrap_err();
+        assert!(err.to_string().contains("--no-lowercase"), "{}", err);
+    }
+
+    #[test]
+    fn test_pronounceable_entropy_uses_syllable_alphabets() {
+        let config = PasswordConfig {
+            length: 13,
+            pronounceable: true,
+            ..Default::default()
+        };
+        // 5 consonants × 4 bits + 5 vowels × log2(5) + 2 digits × log2(10)
+        let expected = 5.0 * 4.0 + 5.0 * 5f64.log2() + 2.0 * 10f64.log2();
+        assert!((entropy_bits(&config) - expected).abs() < 1e-9);
+        // 38 bits: Weak, though 13 fully random characters would be Strong
+        assert_eq!(estimate_strength(&config), PasswordStrength::Weak);
+        let random = PasswordConfig {
+            pronounceable: false,
+            ..config.clone()
+        };
+        assert_eq!(estimate_strength(&random), PasswordStrength::Strong);
+        assert!(format_metadata(&config, PasswordStrength::Weak).contains("pronounceable"));
+    }
+
+    #[test]
+    fn test_length_for_entropy() {
+        // 94 characters: 6.55 bits each, so 128 bits needs 19.5 → 20
+        let config = PasswordConfig::default();
+        assert_eq!(length_for_entropy(&config, 128.0), 20);
+        let reached = PasswordConfig {
+            length: 20,
+            ..config.clone()
+        };
+        assert!(entropy_bits(&reached) >= 128.0);
+
+        // Exact multiples don't round up
+        let hex_like = PasswordConfig {
+            use_uppercase: false,
+            use_symbols: false,
+            charsets: CharSets::default().with_lowercase("abcdef"),
+            ..Default::default()
+        };
+        assert_eq!(length_for_entropy(&hex_like, 64.0), 16);
+
+        let single = PasswordConfig {
+            use_lowercase: false,
+            use_uppercase: false,
+            use_symbols: false,
+            charsets: CharSets::default().with_digits("7"),
+            ..Default::default()
+        };
+        assert_eq!(length_for_entropy(&single, 10.0), usize::MAX);
+
+        let pronounceable = PasswordConfig {
+            pronounceable: true,
+            ..Default::default()
+        };
+        let length = length_for_entropy(&pronounceable, 60.0);
+        let at = |length| {
+            entropy_bits(&PasswordConfig {
+                length,
+                ..pronounceable.clone()
+            })
+        };
+        assert!(at(length) >= 60.0 && at(length - 1) < 60.0);
+    }
+
+    #[test]
+    fn test_config_for_password() {
+        let config = config_for_password("hunter2");
+        assert_eq!(config.length, 7);
+        assert!(config.use_lowercase && config.use_digits);
+        assert!(!config.use_uppercase && !config.use_symbols);
+        assert_eq!(config.build_char_pool().len(), 36);
+
+        // Non-ASCII letters fall under symbols, and length counts characters
+        let config = config_for_password("Pässwörd");
+        assert_eq!(config.length, 8);
+        assert!(config.use_uppercase && config.use_symbols && !config.use_digits);
+    }
+
+    #[test]
+    fn test_pin_config() {
+        assert!(pin_config(3, 4, 12).is_err());
+        assert!(pin_config(13, 4, 12).is_err());
+
+        let config = pin_config(PIN_DEFAULT_LENGTH, 4, 12).unwrap();
+        let mut rng = OsRng;
+        for pin in generate_passwords(&config, 50, &mut rng).unwrap() {
+            assert_eq!(pin.chars().count(), 4);
+            assert!(pin.chars().all(|c| c.is_ascii_digit()));
+        }
+
+        // 4 digits is about 13 bits: fine for a PIN, but Weak as a password
+        assert_eq!(estimate_strength(&config), PasswordStrength::Weak);
+        assert!(format_metadata(&config, PasswordStrength::Weak).contains("13.3 bits"));
+    }
+
+    #[test]
+    fn test_wifi_length_bounds() {
+        assert!(wifi_config(WIFI_MIN_LENGTH - 1).is_err());
+        assert!(wifi_config(WIFI_MAX_LENGTH + 1).is_err());
+        assert!(wifi_config(WIFI_MIN_LENGTH).is_ok());
+        assert_eq!(wifi_config(WIFI_MAX_LENGTH).unwrap().length, 63);
+    }
+
+    #[test]
+    fn test_wifi_excludes_problem_symbols() {
+        let config = wifi_config(WIFI_MAX_LENGTH).unwrap();
+        let mut rng = OsRng;
+        for password in generate_passwords(&config, 50, &mut rng).unwrap() {
+            assert_eq!(password.len(), WIFI_MAX_LENGTH);
+            assert!(password.chars().all(|c| c.is_ascii_graphic()));
+            assert!(!password.contains(['\\', '"', '\'', '`', ' ']));
+        }
+    }
+
+    #[test]
+    fn test_min_length_for_strength() {
+        let config = PasswordConfig {
+            length: 6,
+            ..Default::default()
+        };
+        // 86-character pool: ~6.43 bits per character, Medium from 50 bits
+        assert_eq!(
+            min_length_for_strength(&config, PasswordStrength::Medium, 64),
+            Some(8)
+        );
+        assert_eq!(
+            min_length_for_strength(&config, PasswordStrength::Weak, 64),
+            Some(6)
+        );
+        assert_eq!(
+            min_length_for_strength(&config, PasswordStrength::Strong, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn test_improvement_suggestion_weak_suggests_length() {
+        // 8 lowercase letters: ~37.6 bits, and no single class reaches 50 bits
         let config = PasswordConfig {
-            length: 20,
+            length: 8,
+            use_uppercase: false,
+            use_digits: false,
+            use_symbols: false,
             ..Default::default()
         };
-        let mut rng = OsRng;
-        let password = generate_password(&config, &mut rng).unwrap();
-        assert_eq!(password.len(), 20);
+        assert_eq!(estimate_strength(&config), PasswordStrength::Weak);
+        assert_eq!(
+            improvement_suggestion(&config).unwrap(),
+            "Add 3 more characters to reach Medium. To reach Strong (80 bits), enable more \
+             character types or use a length of 18."
+        );
+
+        // Four digits: about 13 bits, and Strong needs 25 of them
+        let pin = PasswordConfig {
+            length: 4,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_symbols: false,
+            ..Default::default()
+        };
+        assert!(improvement_suggestion(&pin).unwrap().ends_with(
+            "To reach Strong (80 bits), enable more character types or use a length of 25."
+        ));
+
+        // With every type on, only the length is worth mentioning
+        let short = PasswordConfig {
+            length: 6,
+            ..Default::default()
+        };
+        assert_eq!(estimate_strength(&short), PasswordStrength::Weak);
+        let tip = improvement_suggestion(&short).unwrap();
+        assert!(tip.ends_with("To reach Strong (80 bits), use a length of 13."), "{}", tip);
+        assert!(!tip.contains("character types"), "{}", tip);
     }
 
     #[test]
-    fn test_password_contains_required_types() {
-        let config = PasswordConfig {
-            length: 16,
-            use_lowercase: true,
-            use_uppercase: true,
-            use_digits: true,
+    fn test_improvement_suggestion_medium() {
+        // 12 characters from every class: ~77 bits, one more character is enough
+        let all_classes = PasswordConfig {
+            length: 12,
+            ..Default::default()
+        };
+        assert_eq!(estimate_strength(&all_classes), PasswordStrength::Medium);
+        assert_eq!(
+            improvement_suggestion(&all_classes).unwrap(),
+            "Add 1 more character to reach Strong."
+        );
+
+        // 15 lowercase letters: ~70.5 bits; enabling symbols beats three more characters
+        let lowercase = PasswordConfig {
+            length: 15,
+            use_uppercase: false,
+            use_digits: false,
             use_symbols: false,
-            exclude_ambiguous: false,
+            ..Default::default()
         };
-        let mut rng = OsRng;
-        let password = generate_password(&config, &mut rng).unwrap();
+        assert_eq!(estimate_strength(&lowercase), PasswordStrength::Medium);
+        assert_eq!(
+            improvement_suggestion(&lowercase).unwrap(),
+            "Enable symbols to reach Strong."
+        );
 
-        let has_lowercase = password.chars().any(|c| c.is_ascii_lowercase());
-        let has_uppercase = password.chars().any(|c| c.is_ascii_uppercase());
-        let has_digit = password.chars().any(|c| c.is_ascii_digit());
+        let strong = PasswordConfig {
+            length: 20,
+            ..Default::default()
+        };
+        assert!(improvement_suggestion(&strong).is_none());
+    }
 
-        assert!(has_lowercase, "Password should contain lowercase");
-        assert!(has_uppercase, "Password should contain uppercase");
-        assert!(has_digit, "Password should contain digit");
+    #[test]
+    fn test_improvement_suggestion_pattern_search_is_bounded() {
+        let pattern = PasswordConfig {
+            pattern: Some("{word}{digit}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            improvement_suggestion(&pattern).unwrap(),
+            "Add 3 more {word} placeholders to reach Medium."
+        );
+
+        // Out of reach of any sensible pattern: no suggestion rather than an endless search
+        let unreachable = PasswordConfig {
+            strength_thresholds: StrengthThresholds {
+                weak_max: 1e14,
+                medium_max: 1e15,
+            },
+            ..pattern
+        };
+        assert!(improvement_suggestion(&unreachable).is_none());
     }
 
     #[test]
-    fn test_no_ambiguous_characters() {
+    fn test_required_chars_match_pool_order() {
         let config = PasswordConfig {
-            length: 20,
-            use_lowercase: true,
-            use_uppercase: true,
-            use_digits: true,
-            use_symbols: false,
             exclude_ambiguous: true,
+            exclude_confusable_symbols: true,
+            ..Default::default()
         };
-        let mut rng = OsRng;
-        let password = generate_password(&config, &mut rng).unwrap();
+        let flattened: Vec<char> = config.required_chars().into_iter().flatten().collect();
+        assert_eq!(flattened, config.build_char_pool());
+    }
 
-        let ambiguous = ['0', 'O', 'o', '1', 'l', 'I'];
-        for c in password.chars() {
-            assert!(!ambiguous.contains(&c), "Found ambiguous character: {}", c);
+    #[test]
+    fn test_class_positions_are_unbiased() {
+        const RUNS: usize = 20_000;
+        const LENGTH: usize = 6;
+
+        let config = PasswordConfig {
+            length: LENGTH,
+            ..Default::default()
+        };
+        let class_of = |c: char| {
+            if c.is_ascii_lowercase() {
+                0
+            } else if c.is_ascii_uppercase() {
+                1
+            } else if c.is_ascii_digit() {
+                2
+            } else {
+                3
+            }
+        };
+
+        // counts[position][class]
+        let mut counts = [[0usize; 4]; LENGTH];
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for password in generate_passwords(&config, RUNS, &mut rng).unwrap() {
+            for (position, c) in password.chars().enumerate() {
+                counts[position][class_of(c)] += 1;
+            }
+        }
+
+        // Every position should see each class about as often as the positions
+        // do on average; a seeding-order bias would skew the first few positions.
+        for class in 0..4 {
+            let mean = counts.iter().map(|p| p[class]).sum::<usize>() as f64 / LENGTH as f64;
+            for (position, per_class) in counts.iter().enumerate() {
+                let deviation = (per_class[class] as f64 - mean).abs() / RUNS as f64;
+                assert!(
+                    deviation < 0.02,
+                    "class {} at position {} deviates by {:.3}",
+                    class,
+                    position,
+                    deviation
+                );
+            }
         }
     }
 
     #[test]
-    fn test_invalid_config_no_char_types() {
+    fn test_strict_ascii_rejects_non_ascii_sets() {
+        // Stand-in for an emoji option: a custom symbol set with emoji in it
+        let emoji = PasswordConfig {
+            charsets: CharSets::default().with_symbols("🔥✨!?"),
+            strict_ascii: true,
+            ..Default::default()
+        };
+        let err = generate_password(&emoji, &mut OsRng).unwrap_err();
+        assert!(err.to_string().contains("STRICT_ASCII"), "{}", err);
+        assert!(err.to_string().contains("(🔥✨)"), "{}", err);
+
+        let lenient = PasswordConfig {
+            strict_ascii: false,
+            ..emoji.clone()
+        };
+        assert!(generate_password(&lenient, &mut OsRng).is_ok());
+
+        let ascii = PasswordConfig {
+            strict_ascii: true,
+            ..Default::default()
+        };
+        assert!(generate_password(&ascii, &mut OsRng).unwrap().is_ascii());
+    }
+
+    #[test]
+    fn test_strict_ascii_rejects_non_ascii_pattern_literals() {
         let config = PasswordConfig {
-            length: 16,
-            use_lowercase: false,
+            pattern: Some("é{word}{digit}{digit}".to_string()),
+            strict_ascii: true,
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("(é)"), "{}", err);
+        let err = generate_password(&config, &mut OsRng).unwrap_err();
+        assert!(err.to_string().contains("STRICT_ASCII"), "{}", err);
+
+        let lenient = PasswordConfig {
+            strict_ascii: false,
+            ..config.clone()
+        };
+        assert!(lenient.validate().is_ok());
+        assert!(generate_password(&lenient, &mut OsRng).unwrap().starts_with('é'));
+
+        let ascii = PasswordConfig {
+            pattern: Some("{Word}-{digit}".to_string()),
+            ..config
+        };
+        assert!(ascii.validate().is_ok());
+        assert!(generate_password(&ascii, &mut OsRng).unwrap().is_ascii());
+    }
+
+    #[test]
+    fn test_policy_aware_entropy_counts_constraints() {
+        // A single class has no "one of each" rule, so nothing is lost
+        let lowercase = PasswordConfig {
+            length: 12,
             use_uppercase: false,
             use_digits: false,
             use_symbols: false,
-            exclude_ambiguous: false,
+            ..Default::default()
         };
-        assert!(config.validate().is_err());
+        assert!((policy_aware_entropy(&lowercase) - entropy_bits(&lowercase)).abs() < 1e-9);
+
+        // Requiring every class removes the strings missing one
+        let short = PasswordConfig {
+            length: 6,
+            ..Default::default()
+        };
+        assert!(policy_aware_entropy(&short) < entropy_bits(&short) - 0.5);
+
+        let interior = PasswordConfig {
+            interior_special: true,
+            ..short.clone()
+        };
+        assert!(policy_aware_entropy(&interior) < policy_aware_entropy(&short));
+
+        // Exact class counts shrink the space much further
+        let policy = crate::policy::GenerationPolicy::parse(
+            r#"{"proportions": {"digits": 0.5, "symbols": 0}}"#,
+        )
+        .unwrap();
+        let unconstrained = PasswordConfig {
+            length: 16,
+            ..Default::default()
+        };
+        let constrained = PasswordConfig {
+            policy: Some(policy),
+            ..unconstrained.clone()
+        };
+        assert!(policy_aware_entropy(&constrained) < policy_aware_entropy(&unconstrained) - 5.0);
+        assert!(policy_aware_entropy(&constrained) > 0.0);
     }
 
     #[test]
-    fn test_invalid_config_zero_length() {
+    fn test_policy_aware_entropy_matches_enumeration() {
+        // Pool {a, b, C, 1}: count the strings with a lowercase, an uppercase and a digit
         let config = PasswordConfig {
-            length: 0,
+            length: 4,
+            use_symbols: false,
+            charsets: CharSets::default()
+                .with_lowercase("ab")
+                .with_uppercase("C")
+                .with_digits("1"),
             ..Default::default()
         };
-        assert!(config.validate().is_err());
+        let pool = config.build_char_pool();
+        assert_eq!(pool.len(), 4);
+        let valid = (0..4usize.pow(4))
+            .filter(|&n| {
+                let chars: Vec<char> = (0..4).map(|i| pool[n / 4usize.pow(i) % 4]).collect();
+                chars.iter().any(char::is_ascii_lowercase)
+                    && chars.iter().any(char::is_ascii_uppercase)
+                    && chars.iter().any(char::is_ascii_digit)
+            })
+            .count();
+        // 4^4 - (3^4 + 3^4 + 2^4) + (2^4 + 1 + 1) - 0 = 96
+        assert_eq!(valid, 96);
+        assert!((policy_aware_entropy(&config) - (valid as f64).log2()).abs() < 1e-9);
+        assert_eq!(entropy_bits(&config), 8.0);
     }
 
     #[test]
-    fn test_strength_estimation() {
-        // Strong password
-        let strong_config = PasswordConfig {
-            length: 20,
+    fn test_strength_uses_constrained_entropy() {
+        // 25 characters over a 4-character pool is exactly 50 bits before the
+        // one-of-each rule, and just under once the strings it rules out are dropped
+        let config = PasswordConfig {
+            length: 25,
+            use_symbols: false,
+            charsets: CharSets::default()
+                .with_lowercase("ab")
+                .with_uppercase("C")
+                .with_digits("1"),
             ..Default::default()
         };
-        assert_eq!(estimate_strength(&strong_config), PasswordStrength::Strong);
-
-        // Weak password
-        let weak_config = PasswordConfig {
+        assert_eq!(entropy_bits(&config), 50.0);
+        let naive = strength_for_entropy(entropy_bits(&config), StrengthThresholds::default());
+        assert_eq!(naive, PasswordStrength::Medium);
+        assert_eq!(estimate_strength(&config), PasswordStrength::Weak);
+        // and the metadata shows the figure it was rated on
+        let metadata = PasswordMetadata::new(&config, estimate_strength(&config));
+        assert_eq!(metadata.entropy_bits, policy_aware_entropy(&config));
+        let short = PasswordConfig {
             length: 6,
-            use_lowercase: true,
-            use_uppercase: false,
-            use_digits: false,
+            ..Default::default()
+        };
+        let shown = format_metadata(&short, estimate_strength(&short));
+        let aware = format!("Entropy: {:.1} bits", policy_aware_entropy(&short));
+        assert!(shown.contains(&aware), "{}", shown);
+        let naive = format!("Entropy: {:.1} bits", entropy_bits(&short));
+        assert!(!shown.contains(&naive), "{}", shown);
+        assert_eq!(
+            min_length_for_strength(&config, PasswordStrength::Medium, 64),
+            Some(26)
+        );
+    }
+
+    #[test]
+    fn test_share_with_every_class() {
+        // Two classes of one character each, length 2: only "ab" and "ba" of 4 qualify
+        assert!((share_with_every_class(&[1, 1], 2, 2) - 0.5).abs() < 1e-12);
+        assert!((share_with_every_class(&[], 10, 3) - 1.0).abs() < 1e-12);
+        assert!(share_with_every_class(&[1, 1, 1], 3, 2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_share_meeting_minimums() {
+        // Pool {a, b, X}, length 3, at least two of a/b and one X: 3 × 2² = 12 of 27
+        let share = share_meeting_minimums(&[(2, 2), (1, 1)], 3, 3);
+        assert!((share - 12.0 / 27.0).abs() < 1e-9, "{}", share);
+        // Minimums of one defer to inclusion-exclusion
+        assert_eq!(
+            share_meeting_minimums(&[(1, 1), (1, 1)], 2, 2),
+            share_with_every_class(&[1, 1], 2, 2)
+        );
+        // Long passwords stay finite
+        let share = share_meeting_minimums(&[(26, 2), (10, 40)], 36, 1000);
+        assert!(share > 0.0 && share <= 1.0);
+    }
+
+    #[test]
+    fn test_class_minimums_are_met() {
+        let config = PasswordConfig {
+            length: 10,
+            min_digits: 3,
+            min_symbols: 2,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(266);
+        for password in generate_passwords(&config, 200, &mut rng).unwrap() {
+            assert_eq!(password.chars().count(), 10);
+            assert!(password.chars().filter(|c| DIGITS.contains(*c)).count() >= 3);
+            assert!(password.chars().filter(|c| SYMBOLS.contains(*c)).count() >= 2);
+            assert!(password.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        }
+
+        // The minimums rule strings out, which the rules-aware estimate reflects
+        let unconstrained = PasswordConfig {
+            min_digits: 0,
+            min_symbols: 0,
+            ..config.clone()
+        };
+        assert!(policy_aware_entropy(&config) < policy_aware_entropy(&unconstrained));
+        assert_eq!(entropy_bits(&config), entropy_bits(&unconstrained));
+    }
+
+    #[test]
+    fn test_invalid_class_minimums() {
+        // 4 digits + 4 symbols + one each of the letters is 10 > 8
+        let crowded = PasswordConfig {
+            length: 8,
+            min_digits: 4,
+            min_symbols: 4,
+            ..Default::default()
+        };
+        let err = crowded.validate().unwrap_err().to_string();
+        assert!(err.contains("need 10 characters"), "{}", err);
+
+        // Minimums that would wrap around when added up are refused, not generated
+        let huge = PasswordConfig {
+            min_digits: usize::MAX,
+            min_symbols: 2,
+            ..Default::default()
+        };
+        let err = huge.validate().unwrap_err().to_string();
+        assert!(err.contains("is more than the length (16)"), "{}", err);
+        assert!(generate_password(&huge, &mut OsRng).is_err());
+        let wrapping = PasswordConfig {
+            length: usize::MAX,
+            min_lowercase: usize::MAX,
+            min_digits: usize::MAX,
+            ..Default::default()
+        };
+        assert_eq!(wrapping.required_total(), None);
+        let err = wrapping.validate().unwrap_err().to_string();
+        assert!(err.contains("more characters than any length"), "{}", err);
+
+        let disabled = PasswordConfig {
             use_symbols: false,
-            exclude_ambiguous: false,
+            min_symbols: 2,
+            ..Default::default()
         };
-        assert_eq!(estimate_strength(&weak_config), PasswordStrength::Weak);
+        assert!(disabled.validate().is_err());
+
+        let pronounceable = PasswordConfig {
+            pronounceable: true,
+            min_digits: 2,
+            ..Default::default()
+        };
+        assert!(pronounceable.validate().is_err());
     }
 
     #[test]
-    fn test_char_pool_building() {
+    fn test_max_symbols_is_honored() {
+        // Half the pool is symbols, so most draws overshoot the cap before it applies
         let config = PasswordConfig {
+            length: 30,
+            use_lowercase: false,
+            use_uppercase: false,
+            min_digits: 3,
+            max_symbols: Some(2),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(276);
+        for password in generate_passwords(&config, 200, &mut rng).unwrap() {
+            assert_eq!(password.chars().count(), 30);
+            let symbols = password.chars().filter(|c| SYMBOLS.contains(*c)).count();
+            assert!((1..=2).contains(&symbols), "{}", password);
+            assert!(password.chars().filter(|c| DIGITS.contains(*c)).count() >= 3);
+        }
+
+        let interior = PasswordConfig {
+            length: 12,
+            interior_special: true,
+            max_symbols: Some(1),
+            ..Default::default()
+        };
+        for password in generate_passwords(&interior, 100, &mut rng).unwrap() {
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.iter().filter(|c| SYMBOLS.contains(**c)).count(), 1);
+            assert!(chars[0].is_ascii_alphabetic() && chars[11].is_ascii_alphabetic());
+        }
+    }
+
+    #[test]
+    fn test_impossible_max_symbols() {
+        let below_minimum = PasswordConfig {
+            min_symbols: 3,
+            max_symbols: Some(2),
+            ..Default::default()
+        };
+        assert!(below_minimum.validate().is_err());
+        let zero = PasswordConfig {
+            max_symbols: Some(0),
+            ..Default::default()
+        };
+        assert!(zero.validate().unwrap_err().to_string().contains("--no-symbols"));
+        assert!(PasswordConfig { use_symbols: false, ..zero }.validate().is_ok());
+
+        // Nothing but symbols to fill with
+        let symbols_only = PasswordConfig {
             length: 10,
-            use_lowercase: true,
+            use_lowercase: false,
             use_uppercase: false,
-            use_digits: true,
+            use_digits: false,
+            max_symbols: Some(4),
+            ..Default::default()
+        };
+        assert!(generate_password(&symbols_only, &mut OsRng).is_err());
+    }
+
+    #[test]
+    fn test_emoji_pool() {
+        assert_eq!(EMOJI.chars().count(), 32);
+        let config = PasswordConfig {
+            length: 20,
+            use_emoji: true,
+            ..Default::default()
+        };
+        let plain = PasswordConfig::default();
+        assert_eq!(config.build_char_pool().len(), plain.build_char_pool().len() + 32);
+        let expected = 20.0 * ((plain.build_char_pool().len() + 32) as f64).log2();
+        assert!((entropy_bits(&config) - expected).abs() < 1e-9);
+        assert!(policy_aware_entropy(&config) <= entropy_bits(&config));
+        assert!(format_metadata(&config, PasswordStrength::Strong).contains("emoji"));
+
+        let mut rng = StdRng::seed_from_u64(277);
+        let passwords = generate_passwords(&config, 50, &mut rng).unwrap();
+        for password in &passwords {
+            // One emoji is one char, so the requested length holds
+            assert_eq!(password.chars().count(), 20);
+            assert!(password.len() >= 20);
+        }
+        assert!(passwords.iter().any(|p| p.chars().any(|c| EMOJI.contains(c))));
+
+        let strict = PasswordConfig {
+            strict_ascii: true,
+            ..config
+        };
+        assert!(generate_password(&strict, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_wordlist_is_distinct_words() {
+        let words = wordlist();
+        assert_eq!(words.len(), 7776);
+        let unique: std::collections::HashSet<&str> = words.iter().copied().collect();
+        assert_eq!(unique.len(), words.len());
+        assert!(words.iter().all(|w| w.chars().all(|c| c.is_ascii_lowercase())));
+    }
+
+    #[test]
+    fn test_generate_passphrase() {
+        let plain = PassphraseTransforms::default();
+        let phrase = generate_passphrase(5, "-", plain, &mut OsRng).unwrap();
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| wordlist().contains(w)));
+
+        let title_case = PassphraseTransforms {
+            title_case: true,
+            ..plain
+        };
+        let capitalized = generate_passphrase(3, ".", title_case, &mut OsRng).unwrap();
+        assert!(capitalized
+            .split('.')
+            .all(|w| w.chars().next().is_some_and(|c| c.is_ascii_uppercase())));
+
+        assert!(generate_passphrase(0, "-", plain, &mut OsRng).is_err());
+        assert!(generate_passphrase(MAX_PASSPHRASE_WORDS + 1, "-", plain, &mut OsRng).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_transforms_apply_in_order() {
+        let transforms = |title_case, all_caps, leet| PassphraseTransforms {
+            title_case,
+            all_caps,
+            leet,
+        };
+        assert_eq!(transforms(false, false, false).apply("forest"), "forest");
+        assert_eq!(transforms(true, false, false).apply("forest"), "Forest");
+        assert_eq!(transforms(false, true, false).apply("forest"), "FOREST");
+        assert_eq!(transforms(false, false, true).apply("forest"), "f0r357");
+        assert_eq!(transforms(true, false, true).apply("steady"), "5734dy");
+        assert_eq!(transforms(true, true, true).apply("forest"), "F0R357");
+
+        assert_eq!(transforms(false, false, false).describe(), None);
+        assert_eq!(
+            transforms(true, false, true).describe().as_deref(),
+            Some("title case, leet")
+        );
+    }
+
+    #[test]
+    fn test_leet_keeps_words_distinguishable() {
+        // Each word still maps to exactly one spelling, so entropy is unchanged
+        let leet = PassphraseTransforms {
+            all_caps: true,
+            leet: true,
+            ..Default::default()
+        };
+        let spelled: std::collections::HashSet<String> =
+            wordlist().iter().map(|w| leet.apply(w)).collect();
+        assert_eq!(spelled.len(), wordlist().len());
+    }
+
+    #[test]
+    fn test_passphrase_multibyte_separator() {
+        let phrase =
+            generate_passphrase(4, "·🔹", PassphraseTransforms::default(), &mut OsRng).unwrap();
+        let words: Vec<&str> = phrase.split("·🔹").collect();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| wordlist().contains(w)));
+    }
+
+    #[test]
+    fn test_passphrase_strength() {
+        let per_word = (7776f64).log2();
+        assert!((passphrase_entropy_bits(6) - 6.0 * per_word).abs() < 1e-9);
+        let strength =
+            |words| strength_for_entropy(passphrase_entropy_bits(words), Default::default());
+        assert_eq!(strength(3), PasswordStrength::Weak);
+        assert_eq!(strength(5), PasswordStrength::Medium);
+        assert_eq!(strength(7), PasswordStrength::Strong);
+    }
+
+    #[test]
+    fn test_generate_from_pattern() {
+        let password = generate_from_pattern("{Word}-{word}-{digit}{symbol}", &mut OsRng).unwrap();
+        let parts: Vec<&str> = password.splitn(3, '-').collect();
+        assert!(parts[0].chars().next().is_some_and(|c| c.is_ascii_uppercase()));
+        assert!(wordlist().contains(&parts[0].to_lowercase().as_str()));
+        assert!(wordlist().contains(&parts[1]));
+        let mut tail = parts[2].chars();
+        assert!(tail.next().is_some_and(|c| c.is_ascii_digit()));
+        assert!(tail.next().is_some_and(|c| SYMBOLS.contains(c)));
+        assert_eq!(tail.next(), None);
+
+        let config = PasswordConfig {
+            pattern: Some("x{upper}{lower}".to_string()),
+            ..Default::default()
+        };
+        let password = generate_password(&config, &mut OsRng).unwrap();
+        assert!(password.starts_with('x'));
+        assert_eq!(password.chars().count(), 3);
+    }
+
+    #[test]
+    fn test_pattern_entropy_and_errors() {
+        let tokens = parse_pattern("{word}-{digit}{digit}").unwrap();
+        let expected = (7776f64).log2() + 2.0 * 10f64.log2();
+        assert!((pattern_entropy_bits(&tokens) - expected).abs() < 1e-9);
+        assert_eq!(pattern_length_range(&tokens), (4 + 3, 8 + 3));
+
+        let config = PasswordConfig {
+            pattern: Some("{Word}{Word}{Word}{Word}".to_string()),
+            ..Default::default()
+        };
+        assert!((entropy_bits(&config) - passphrase_entropy_bits(4)).abs() < 1e-9);
+        assert_eq!(policy_aware_entropy(&config), entropy_bits(&config));
+        assert!(format_metadata(&config, estimate_strength(&config)).contains("Pattern:"));
+        assert_eq!(
+            improvement_suggestion(&config).unwrap(),
+            "Add 3 more {word} placeholders to reach Strong."
+        );
+
+        let unknown = parse_pattern("{Word}-{animal}").unwrap_err().to_string();
+        assert!(unknown.contains("{animal}"));
+        assert!(parse_pattern("{word").is_err());
+        assert!(parse_pattern("word}").is_err());
+        assert!(parse_pattern("static").is_err());
+        assert!(parse_pattern("").is_err());
+    }
+
+    #[test]
+    fn test_generate_password_seeded() {
+        let config = PasswordConfig::default();
+        let first = generate_password_seeded(&config, 42).unwrap();
+        assert_eq!(generate_password_seeded(&config, 42).unwrap(), first);
+        assert_eq!(
+            first,
+            generate_password(&config, &mut StdRng::seed_from_u64(42)).unwrap()
+        );
+        assert_ne!(generate_password_seeded(&config, 43).unwrap(), first);
+        assert_eq!(first.chars().count(), config.length);
+    }
+
+    #[test]
+    fn test_builder_matches_struct_update() {
+        let built = PasswordConfig::builder()
+            .length(24)
+            .symbols(false)
+            .exclude_ambiguous(true)
+            .min_count(CharClass::Digits, 3)
+            .build()
+            .unwrap();
+        let manual = PasswordConfig {
+            length: 24,
             use_symbols: false,
-            exclude_ambiguous: false,
+            exclude_ambiguous: true,
+            min_digits: 3,
+            ..Default::default()
         };
-        let pool = config.build_char_pool();
-        assert!(pool.len() > 0);
-        assert!(pool.iter().any(|c| c.is_ascii_lowercase()));
-        assert!(pool.iter().any(|c| c.is_ascii_digit()));
-        assert!(!pool.iter().any(|c| c.is_ascii_uppercase()));
+        assert_eq!(format!("{:?}", built), format!("{:?}", manual));
+
+        let default = PasswordConfig::builder().build().unwrap();
+        assert_eq!(format!("{:?}", default), format!("{:?}", PasswordConfig::default()));
+    }
+
+    #[test]
+    fn test_builder_validates() {
+        assert!(PasswordConfig::builder().length(0).build().is_err());
+        assert!(PasswordConfig::builder()
+            .lowercase(false)
+            .uppercase(false)
+            .digits(false)
+            .symbols(false)
+            .build()
+            .is_err());
+        assert!(PasswordConfig::builder()
+            .symbols(false)
+            .min_count(CharClass::Symbols, 1)
+            .build()
+            .is_err());
+        assert!(PasswordConfig::builder().pattern("{animal}").build().is_err());
     }
 }
@@ -0,0 +1,407 @@
+/// Declarative generation policies loaded from a JSON file.
+///
+/// A policy pins the share of the password drawn from individual character
+/// classes (e.g. "exactly 40% digits") and optionally bounds the length:
+///
+/// ```json
+/// { "min_length": 12, "max_length": 64, "proportions": { "digits": 0.4, "symbols": 0.1 } }
+/// ```
+///
+/// Classes with a proportion get exactly that many characters (rounded); the rest
+/// of the password is drawn from the enabled classes without one. A proportion of
+/// `0` keeps a class out entirely.
+use crate::error::{BotError, Result};
+use crate::password::{CharClass, PasswordConfig};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Slack allowed when proportions must add up to exactly 1.
+const PROPORTION_EPSILON: f64 = 0.01;
+
+/// Per-class target proportions and length bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationPolicy {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub proportions: Vec<(CharClass, f64)>,
+}
+
+/// How a policy splits one password: exact class counts plus a free remainder.
+#[derive(Debug)]
+pub struct SeedPlan {
+    /// Character groups and how many characters to draw from each.
+    ///
+    /// The first `exact_seeds` entries are the policy's exact counts; the rest are
+    /// the free classes, seeded with their minimum (at least once) so every one of
+    /// them appears.
+    pub seeds: Vec<(Vec<char>, usize)>,
+    pub exact_seeds: usize,
+    /// Pool the remaining characters are drawn from.
+    pub fill_pool: Vec<char>,
+}
+
+impl GenerationPolicy {
+    /// Read and validate the policy in `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| BotError::Config(format!("POLICY_FILE {}: {}", path.display(), e)))?;
+        Self::parse(&json)
+            .map_err(|e| BotError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parse and validate a policy from JSON.
+    pub fn parse(json: &str) -> Result<Self> {
+        let invalid = |msg: String| BotError::Config(format!("invalid policy: {}", msg));
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        let Value::Object(fields) = value else {
+            return Err(invalid("expected a JSON object".to_string()));
+        };
+
+        let mut policy = GenerationPolicy {
+            min_length: None,
+            max_length: None,
+            proportions: Vec::new(),
+        };
+        for (key, value) in &fields {
+            match key.as_str() {
+                "min_length" | "max_length" => {
+                    let n = value
+                        .as_u64()
+                        .ok_or_else(|| invalid(format!("{} must be a positive integer", key)))?;
+                    if key == "min_length" {
+                        policy.min_length = Some(n as usize);
+                    } else {
+                        policy.max_length = Some(n as usize);
+                    }
+                }
+                "proportions" => {
+                    let Value::Object(classes) = value else {
+                        return Err(invalid("proportions must be an object".to_string()));
+                    };
+                    for (name, share) in classes {
+                        let class = CharClass::from_name(name)
+                            .ok_or_else(|| invalid(format!("unknown class '{}'", name)))?;
+                        let share = share.as_f64().ok_or_else(|| {
+                            invalid(format!("proportion of {} must be a number", name))
+                        })?;
+                        policy.proportions.push((class, share));
+                    }
+                }
+                other => return Err(invalid(format!("unknown field '{}'", other))),
+            }
+        }
+
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Check the policy is consistent on its own.
+    pub fn validate(&self) -> Result<()> {
+        if let Some((class, share)) = self
+            .proportions
+            .iter()
+            .find(|(_, share)| !(0.0..=1.0).contains(share))
+        {
+            return Err(BotError::Config(format!(
+                "policy proportion of {} ({}) must be between 0 and 1",
+                class.name(),
+                share
+            )));
+        }
+
+        let total: f64 = self.proportions.iter().map(|(_, share)| share).sum();
+        if total > 1.0 + PROPORTION_EPSILON {
+            return Err(BotError::Config(format!(
+                "policy proportions add up to {:.2}, more than 1",
+                total
+            )));
+        }
+
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length) {
+            if min > max {
+                return Err(BotError::Config(format!(
+                    "policy min_length ({}) must be <= max_length ({})",
+                    min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn share_of(&self, class: CharClass) -> Option<f64> {
+        self.proportions
+            .iter()
+            .find(|(c, _)| *c == class)
+            .map(|&(_, share)| share)
+    }
+
+    /// Work out exact class counts for `config`, or explain why it is infeasible.
+    pub fn seed_plan(&self, config: &PasswordConfig) -> Result<SeedPlan> {
+        let length = config.length;
+        let infeasible = |msg: String| BotError::PasswordGeneration(msg);
+
+        if self.min_length.is_some_and(|min| length < min)
+            || self.max_length.is_some_and(|max| length > max)
+        {
+            return Err(infeasible(format!(
+                "The password policy allows lengths {}-{}",
+                self.min_length.unwrap_or(1),
+                self.max_length.map_or("∞".to_string(), |m| m.to_string())
+            )));
+        }
+
+        let classes = config.class_sets();
+        if let Some((class, _)) = self
+            .proportions
+            .iter()
+            .find(|(class, share)| *share > 0.0 && !classes.iter().any(|(c, _)| c == class))
+        {
+            return Err(infeasible(format!(
+                "The password policy requires {}, which are disabled",
+                class.name()
+            )));
+        }
+
+        if let Some((class, _)) = self.proportions.iter().find(|(class, share)| {
+            *share == 0.0 && config.class_minimum(*class) > 0
+        }) {
+            return Err(infeasible(format!(
+                "The password policy excludes {}, so no minimum can be met",
+                class.name()
+            )));
+        }
+
+        let mut targeted = Vec::new();
+        let mut free = Vec::new();
+        for (class, chars) in classes {
+            match self.share_of(class) {
+                Some(share) if share > 0.0 => targeted.push((class, chars, share)),
+                Some(_) => {}
+                None => free.push((chars, config.required_count(class))),
+            }
+        }
+        // Emoji only fill free positions, so they join the free classes without a minimum
+        let emoji = config.emoji_set();
+        if !free.is_empty() && !emoji.is_empty() {
+            free.push((emoji, 0));
+        }
+
+        let mut counts: Vec<usize> = targeted
+            .iter()
+            .map(|(_, _, share)| (share * length as f64).round() as usize)
+            .collect();
+        if free.is_empty() {
+            let total: f64 = targeted.iter().map(|(_, _, share)| share).sum();
+            if (total - 1.0).abs() > PROPORTION_EPSILON {
+                return Err(infeasible(format!(
+                    "The password policy's proportions add up to {:.2}, but no other \
+                     character type is enabled to fill the rest",
+                    total
+                )));
+            }
+            let shares: Vec<f64> = targeted.iter().map(|(_, _, share)| *share).collect();
+            distribute_rounding(&mut counts, &shares, length);
+        }
+
+        let seeded = counts
+            .iter()
+            .chain(free.iter().map(|(_, min)| min))
+            .fold(0usize, |total, count| total.saturating_add(*count));
+        if counts.contains(&0) || seeded > length {
+            return Err(infeasible(format!(
+                "Length {} is too short to meet the password policy's proportions",
+                length
+            )));
+        }
+
+        if let Some(((class, _, _), count)) = targeted
+            .iter()
+            .zip(&counts)
+            .find(|((class, _, _), count)| **count < config.class_minimum(*class))
+        {
+            return Err(infeasible(format!(
+                "The password policy gives {} {} at this length, fewer than the minimum of {}",
+                count,
+                class.name(),
+                config.class_minimum(*class)
+            )));
+        }
+
+        let fill_pool = free.iter().flat_map(|(chars, _)| chars).copied().collect();
+        let exact_seeds = targeted.len();
+        let seeds = targeted
+            .into_iter()
+            .map(|(_, chars, _)| chars)
+            .zip(counts)
+            .chain(free)
+            .collect();
+        Ok(SeedPlan {
+            seeds,
+            exact_seeds,
+            fill_pool,
+        })
+    }
+}
+
+/// Adjust rounded `counts` so they sum to `length`, favouring the largest remainders.
+fn distribute_rounding(counts: &mut [usize], shares: &[f64], length: usize) {
+    let exact: Vec<f64> = shares.iter().map(|share| share * length as f64).collect();
+    let mut order: Vec<usize> = (0..counts.len()).collect();
+
+    while counts.iter().sum::<usize>() < length {
+        order.sort_by(|&a, &b| {
+            let gap = |i: usize| exact[i] - counts[i] as f64;
+            gap(b).total_cmp(&gap(a))
+        });
+        counts[order[0]] += 1;
+    }
+    while counts.iter().sum::<usize>() > length {
+        order.sort_by(|&a, &b| {
+            let excess = |i: usize| counts[i] as f64 - exact[i];
+            excess(b).total_cmp(&excess(a))
+        });
+        counts[order[0]] -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::password::{generate_passwords, DIGITS, SYMBOLS};
+    use rand::rngs::OsRng;
+
+    fn forty_percent_digits() -> GenerationPolicy {
+        GenerationPolicy::parse(
+            r#"{"min_length": 10, "max_length": 40, "proportions": {"digits": 0.4, "symbols": 0}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = forty_percent_digits();
+        assert_eq!(policy.min_length, Some(10));
+        assert_eq!(policy.max_length, Some(40));
+        assert_eq!(policy.share_of(CharClass::Digits), Some(0.4));
+        assert_eq!(policy.share_of(CharClass::Symbols), Some(0.0));
+        assert_eq!(policy.share_of(CharClass::Lowercase), None);
+
+        assert!(GenerationPolicy::parse(r#"{"proportions": {"digits": 1.5}}"#).is_err());
+        assert!(GenerationPolicy::parse(
+            r#"{"proportions": {"digits": 0.7, "symbols": 0.5}}"#
+        )
+        .is_err());
+        assert!(GenerationPolicy::parse(r#"{"proportions": {"emoji": 0.1}}"#).is_err());
+        assert!(GenerationPolicy::parse(r#"{"min_length": 20, "max_length": 10}"#).is_err());
+        assert!(GenerationPolicy::parse(r#"{"ratio": 0.4}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_names_the_file() {
+        let missing =
+            std::env::temp_dir().join(format!("policy-missing-{}.json", std::process::id()));
+        let err = GenerationPolicy::load(&missing).unwrap_err().to_string();
+        assert!(err.contains("POLICY_FILE"), "{}", err);
+        assert!(err.contains(&missing.display().to_string()), "{}", err);
+    }
+
+    #[test]
+    fn test_batch_matches_proportions() {
+        let config = PasswordConfig {
+            length: 20,
+            policy: Some(forty_percent_digits()),
+            ..Default::default()
+        };
+        let batch = generate_passwords(&config, 200, &mut OsRng).unwrap();
+
+        let mut digits = 0;
+        for password in &batch {
+            assert_eq!(password.chars().count(), 20);
+            assert!(!password.chars().any(|c| SYMBOLS.contains(c)));
+            digits += password.chars().filter(|c| DIGITS.contains(*c)).count();
+        }
+        let share = digits as f64 / (20.0 * batch.len() as f64);
+        assert!((share - 0.4).abs() < 0.01, "digit share was {}", share);
+    }
+
+    #[test]
+    fn test_proportions_covering_every_class() {
+        let json = r#"{"proportions":
+            {"lowercase": 0.34, "uppercase": 0.33, "digits": 0.33, "symbols": 0}}"#;
+        let policy = GenerationPolicy::parse(json).unwrap();
+        let config = PasswordConfig {
+            length: 10,
+            policy: Some(policy),
+            ..Default::default()
+        };
+        let plan = config.policy.as_ref().unwrap().seed_plan(&config).unwrap();
+        let counts: Vec<usize> = plan.seeds.iter().map(|(_, n)| *n).collect();
+        assert_eq!(counts.iter().sum::<usize>(), 10);
+        assert!(plan.fill_pool.is_empty());
+    }
+
+    #[test]
+    fn test_infeasible_policies() {
+        let policy = forty_percent_digits();
+        let too_long = PasswordConfig {
+            length: 41,
+            ..Default::default()
+        };
+        assert!(policy.seed_plan(&too_long).is_err());
+
+        let no_digits = PasswordConfig {
+            length: 20,
+            use_digits: false,
+            ..Default::default()
+        };
+        assert!(policy.seed_plan(&no_digits).is_err());
+
+        // Nothing left to fill the other 60% with
+        let digits_only = PasswordConfig {
+            length: 20,
+            use_lowercase: false,
+            use_uppercase: false,
+            use_symbols: false,
+            ..Default::default()
+        };
+        assert!(policy.seed_plan(&digits_only).is_err());
+
+        // 1% of 10 characters rounds to none
+        let tiny = GenerationPolicy::parse(r#"{"proportions": {"symbols": 0.01}}"#).unwrap();
+        let short = PasswordConfig {
+            length: 10,
+            ..Default::default()
+        };
+        assert!(tiny.seed_plan(&short).is_err());
+    }
+
+    #[test]
+    fn test_policy_with_class_minimums() {
+        // Free classes are seeded with their minimum
+        let config = PasswordConfig {
+            length: 20,
+            min_uppercase: 3,
+            policy: Some(forty_percent_digits()),
+            ..Default::default()
+        };
+        let plan = config.policy.as_ref().unwrap().seed_plan(&config).unwrap();
+        let counts: Vec<usize> = plan.seeds.iter().map(|(_, n)| *n).collect();
+        assert_eq!(counts, vec![8, 1, 3]);
+
+        // 40% of 10 is 4 digits, short of 5
+        let short = PasswordConfig {
+            length: 10,
+            min_digits: 5,
+            ..config.clone()
+        };
+        assert!(short.policy.as_ref().unwrap().seed_plan(&short).is_err());
+
+        // The policy keeps symbols out entirely
+        let excluded = PasswordConfig {
+            min_symbols: 1,
+            ..config.clone()
+        };
+        assert!(excluded.policy.as_ref().unwrap().seed_plan(&excluded).is_err());
+    }
+}
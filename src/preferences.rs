@@ -0,0 +1,118 @@
+/// Per-chat default `/pass` options saved with `/setdefault`.
+///
+/// Only the option text is kept (for example `24 --no-ambiguous`), never a
+/// generated password. It is parsed again on each use, on top of the operator's
+/// current defaults, so it follows later changes to `AMBIGUOUS_CHARS` and friends.
+use crate::error::{BotError, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tracing::warn;
+
+/// Saved `/pass` options per chat.
+#[derive(Debug, Default)]
+pub struct UserPreferences {
+    options: HashMap<i64, String>,
+}
+
+impl UserPreferences {
+    /// The options saved for `chat_id`, if any.
+    pub fn get(&self, chat_id: i64) -> Option<&str> {
+        self.options.get(&chat_id).map(String::as_str)
+    }
+
+    /// Save `options` for `chat_id`, with runs of whitespace collapsed.
+    pub fn set(&mut self, chat_id: i64, options: &str) {
+        let options = options.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.options.insert(chat_id, options);
+    }
+
+    /// Forget `chat_id`'s options; returns whether there were any.
+    pub fn clear(&mut self, chat_id: i64) -> bool {
+        self.options.remove(&chat_id).is_some()
+    }
+
+    /// Serialize for `PREFERENCES_PATH`.
+    pub fn to_json(&self) -> Value {
+        let chats: Vec<Value> = self
+            .options
+            .iter()
+            .map(|(chat_id, options)| json!({ "chat_id": chat_id, "options": options }))
+            .collect();
+        json!({ "chats": chats })
+    }
+
+    /// Restore preferences saved by [`to_json`](Self::to_json).
+    pub fn from_json(json: &str) -> Result<Self> {
+        let invalid = |msg: &str| BotError::Config(format!("invalid preferences: {}", msg));
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(&e.to_string()))?;
+        let chats = match &value["chats"] {
+            Value::Null => return Ok(Self::default()),
+            Value::Array(chats) => chats,
+            _ => return Err(invalid("\"chats\" must be an array")),
+        };
+
+        let mut preferences = Self::default();
+        for entry in chats {
+            let chat_id = entry["chat_id"].as_i64().ok_or_else(|| invalid("bad chat_id"))?;
+            let options = entry["options"].as_str().ok_or_else(|| invalid("bad options"))?;
+            preferences.set(chat_id, options);
+        }
+        Ok(preferences)
+    }
+
+    /// Load the preferences saved at `path`, or start empty.
+    ///
+    /// As with the rate limit state, a missing file is normal and an unreadable one
+    /// is logged rather than keeping the bot from starting.
+    pub fn load(path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return Self::default();
+        };
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Could not read preferences {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        Self::from_json(&json).unwrap_or_else(|e| {
+            warn!("Ignoring preferences {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_get_clear() {
+        let mut preferences = UserPreferences::default();
+        preferences.set(7, "  24   --no-ambiguous ");
+        assert_eq!(preferences.get(7), Some("24 --no-ambiguous"));
+        assert_eq!(preferences.get(8), None);
+        assert!(preferences.clear(7));
+        assert!(!preferences.clear(7));
+        assert_eq!(preferences.get(7), None);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut preferences = UserPreferences::default();
+        preferences.set(-100123, "20 --no-symbols");
+        preferences.set(42, "--pattern {Word}-{digit}{digit}");
+        let restored = UserPreferences::from_json(&preferences.to_json().to_string()).unwrap();
+        assert_eq!(restored.get(-100123), Some("20 --no-symbols"));
+        assert_eq!(restored.get(42), Some("--pattern {Word}-{digit}{digit}"));
+
+        assert!(UserPreferences::from_json("{}").unwrap().get(42).is_none());
+        assert!(UserPreferences::from_json("{\"chats\": 3}").is_err());
+        assert!(UserPreferences::from_json("{\"chats\": [{\"chat_id\": 1}]}").is_err());
+        assert!(UserPreferences::from_json("not json").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+/// QR code rendering for `/pass --qr`, so a password can be scanned onto another device.
+///
+/// The image is a plain black-on-white grayscale PNG, encoded in memory; nothing
+/// touches the disk.
+use crate::error::{BotError, Result};
+use qrcode::{Color, QrCode};
+
+/// Pixels per QR module; large enough for phone cameras at arm's length.
+const MODULE_PIXELS: usize = 8;
+
+/// Light modules around the code, as the QR specification requires for reliable scanning.
+const QUIET_ZONE: usize = 4;
+
+/// Render `text` as a PNG QR code.
+///
+/// Fails if `text` is too long to fit in a QR code.
+pub fn render_png(text: &str) -> Result<Vec<u8>> {
+    let code = QrCode::new(text.as_bytes()).map_err(|e| BotError::Qr(e.to_string()))?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + 2 * QUIET_ZONE) * MODULE_PIXELS;
+
+    let mut pixels = vec![u8::MAX; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (i % modules + QUIET_ZONE) * MODULE_PIXELS;
+        let y0 = (i / modules + QUIET_ZONE) * MODULE_PIXELS;
+        for y in y0..y0 + MODULE_PIXELS {
+            pixels[y * side + x0..y * side + x0 + MODULE_PIXELS].fill(0);
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| BotError::Qr(e.to_string()))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_png() {
+        let png = render_png("x7#Kq9!mP2vL").unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        // A 12-byte password fits a version 1 code: 21 modules plus the quiet zone
+        assert_eq!(info.width as usize, (21 + 2 * QUIET_ZONE) * MODULE_PIXELS);
+        assert_eq!(info.width, info.height);
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+    }
+
+    #[test]
+    fn test_render_png_rejects_oversized_text() {
+        assert!(matches!(render_png(&"x".repeat(4000)), Err(BotError::Qr(_))));
+    }
+}
@@ -0,0 +1,123 @@
+/// Operator-configurable layout for the password reply (`REPLY_TEMPLATE`).
+///
+/// Templates are plain text with `{name}` placeholders; see [`PLACEHOLDERS`].
+/// Unknown placeholders are rejected when the configuration is loaded, so
+/// rendering itself can't fail.
+use crate::error::{BotError, Result};
+use crate::password::PasswordStrength;
+
+/// Placeholders a reply template may use.
+pub const PLACEHOLDERS: &[&str] = &["password", "strength", "entropy", "metadata"];
+
+/// Values substituted into a reply template.
+#[derive(Debug, Clone)]
+pub struct ReplyContext<'a> {
+    pub password: &'a str,
+    pub strength: PasswordStrength,
+    /// Estimated entropy in bits.
+    pub entropy: f64,
+    pub metadata: &'a str,
+}
+
+impl ReplyContext<'_> {
+    fn value(&self, name: &str) -> Option<String> {
+        match name {
+            "password" => Some(self.password.to_string()),
+            "strength" => Some(self.strength.as_str().to_string()),
+            "entropy" => Some(format!("{:.1}", self.entropy)),
+            "metadata" => Some(self.metadata.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Split `tmpl` into literal text and `{placeholder}` names.
+fn segments(tmpl: &str) -> Vec<(bool, &str)> {
+    let mut segments = Vec::new();
+    let mut rest = tmpl;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        segments.push((false, &rest[..start]));
+        segments.push((true, &rest[start + 1..start + len]));
+        rest = &rest[start + len + 1..];
+    }
+    segments.push((false, rest));
+    segments
+}
+
+/// Check that `tmpl` only uses known placeholders and shows the password.
+pub fn validate_template(tmpl: &str) -> Result<()> {
+    let names: Vec<&str> = segments(tmpl)
+        .into_iter()
+        .filter(|(is_placeholder, _)| *is_placeholder)
+        .map(|(_, name)| name)
+        .collect();
+
+    if let Some(unknown) = names.iter().find(|name| !PLACEHOLDERS.contains(name)) {
+        return Err(BotError::Config(format!(
+            "REPLY_TEMPLATE uses unknown placeholder {{{}}} (known: {})",
+            unknown,
+            PLACEHOLDERS.join(", ")
+        )));
+    }
+    if !names.contains(&"password") {
+        return Err(BotError::Config(
+            "REPLY_TEMPLATE must include {password}".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Substitute `ctx` into a template accepted by [`validate_template`].
+pub fn render_template(tmpl: &str, ctx: &ReplyContext) -> String {
+    segments(tmpl)
+        .into_iter()
+        .map(|(is_placeholder, text)| match is_placeholder {
+            true => ctx.value(text).unwrap_or_default(),
+            false => text.to_string(),
+        })
+        .collect()
+}
+
+/// Byte offsets in `tmpl` at which `{password}` starts.
+pub fn password_positions(tmpl: &str) -> Vec<usize> {
+    tmpl.match_indices("{password}").map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> ReplyContext<'static> {
+        ReplyContext {
+            password: "xK4f9pLm",
+            strength: PasswordStrength::Medium,
+            entropy: 51.4,
+            metadata: "Length: 8",
+        }
+    }
+
+    #[test]
+    fn test_render_template_substitutes_placeholders() {
+        let tmpl = "🔑 {password}\n{strength} ({entropy} bits) — {metadata}";
+        assert!(validate_template(tmpl).is_ok());
+        assert_eq!(
+            render_template(tmpl, &context()),
+            "🔑 xK4f9pLm\nMedium (51.4 bits) — Length: 8"
+        );
+        assert_eq!(
+            render_template("{password}{password} {", &context()),
+            "xK4f9pLmxK4f9pLm {"
+        );
+        assert_eq!(password_positions("a {password} b {password}"), vec![2, 15]);
+    }
+
+    #[test]
+    fn test_unknown_placeholder_is_rejected() {
+        let err = validate_template("{password} {colour}").unwrap_err();
+        assert!(err.to_string().contains("{colour}"));
+        assert!(validate_template("{strength} only").is_err());
+    }
+}
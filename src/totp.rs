@@ -0,0 +1,99 @@
+/// TOTP secrets for manual two-factor enrollment.
+///
+/// Secrets are random bytes encoded as unpadded base32 (RFC 4648), the format
+/// authenticator apps expect when a key is typed in or read from an
+/// `otpauth://` URI.
+use rand_core::RngCore;
+
+/// Secret size in bytes; 160 bits, as recommended by RFC 4226.
+pub const TOTP_SECRET_BYTES: usize = 20;
+/// Issuer shown by authenticator apps for URIs built by the bot.
+pub const TOTP_ISSUER: &str = "PasswordBot";
+
+/// RFC 4648 base32 alphabet.
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode `bytes` as base32 without `=` padding.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Generate a random [`TOTP_SECRET_BYTES`]-byte secret, base32-encoded.
+pub fn generate_totp_secret(rng: &mut impl RngCore) -> String {
+    let mut secret = [0u8; TOTP_SECRET_BYTES];
+    rng.fill_bytes(&mut secret);
+    base32_encode(&secret)
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Build an `otpauth://totp/` enrollment URI for `secret` and `account`.
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}",
+        percent_encode(TOTP_ISSUER),
+        percent_encode(account),
+        secret,
+        percent_encode(TOTP_ISSUER)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_base32_rfc4648_vectors() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"fo"), "MZXQ");
+        assert_eq!(base32_encode(b"foo"), "MZXW6");
+        assert_eq!(base32_encode(b"foob"), "MZXW6YQ");
+        assert_eq!(base32_encode(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_totp_secret_is_base32() {
+        let secret = generate_totp_secret(&mut OsRng);
+        // 160 bits is exactly 32 base32 characters, so there is never padding
+        assert_eq!(secret.len(), 32);
+        assert!(secret.bytes().all(|b| BASE32_ALPHABET.contains(&b)));
+        assert_ne!(secret, generate_totp_secret(&mut OsRng));
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let uri = otpauth_uri("MZXW6YTBOI", "me@example.com");
+        assert_eq!(
+            uri,
+            "otpauth://totp/PasswordBot:me%40example.com?secret=MZXW6YTBOI&issuer=PasswordBot"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+/// Writing generated passwords to HashiCorp Vault (KV version 2).
+///
+/// Only built with the `vault` feature. The password is sent in the request body
+/// and never logged; errors describe the failure without echoing the secret.
+use crate::config::Config;
+use crate::error::{BotError, Result};
+use std::time::Duration;
+
+/// Time budget for a single Vault request.
+const VAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Minimal client for Vault's KV v2 write endpoint.
+pub struct VaultClient {
+    addr: String,
+    token: String,
+    mount: String,
+    http: reqwest::Client,
+}
+
+impl VaultClient {
+    pub fn new(addr: &str, token: &str, mount: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(VAULT_TIMEOUT)
+            .build()
+            .map_err(|e| BotError::Vault(format!("Could not create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            mount: mount.trim_matches('/').to_string(),
+            http,
+        })
+    }
+
+    /// Client for the Vault configured via `VAULT_ADDR`/`VAULT_TOKEN`/`VAULT_MOUNT`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match (&config.vault_addr, &config.vault_token) {
+            (Some(addr), Some(token)) => Self::new(addr, token, &config.vault_mount),
+            _ => Err(BotError::Vault(
+                "Vault is not configured (set VAULT_ADDR and VAULT_TOKEN)".to_string(),
+            )),
+        }
+    }
+
+    /// Store `password` under the `password` key of the secret at `path`.
+    pub async fn write_secret(&self, path: &str, password: &str) -> Result<()> {
+        validate_path(path)?;
+        let url = format!("{}/v1/{}/data/{}", self.addr, self.mount, path);
+        let body = serde_json::json!({ "data": { "password": password } });
+
+        let response = self
+            .http
+            .post(&url)
+            .header("X-Vault-Token", &self.token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| BotError::Vault(format!("Could not reach Vault: {}", e.without_url())))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        Err(BotError::Vault(match status.as_u16() {
+            401 | 403 => "Vault rejected the token or its policy forbids this path".to_string(),
+            _ => format!("Vault returned HTTP {}", status),
+        }))
+    }
+}
+
+/// Check that `path` is a plain relative secret path.
+pub fn validate_path(path: &str) -> Result<()> {
+    let valid = !path.is_empty()
+        && path
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+        && path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.'));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(BotError::Vault(format!(
+            "Invalid secret path '{}': use letters, digits, '-', '_', '.' and '/'",
+            path
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one HTTP request with `status`, returning the raw request text.
+    async fn mock_vault(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read until the JSON body has arrived
+            while !request.ends_with(b"}}") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        (addr, handle)
+    }
+
+    #[tokio::test]
+    async fn test_write_secret_posts_kv2_payload() {
+        let (addr, server) = mock_vault("204 No Content").await;
+        let client = VaultClient::new(&addr, "s.token", "secret").unwrap();
+
+        client.write_secret("apps/db", "p@ss-123").await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/secret/data/apps/db HTTP/1.1"));
+        assert!(request.to_ascii_lowercase().contains("x-vault-token: s.token"));
+        assert!(request.ends_with(r#"{"data":{"password":"p@ss-123"}}"#));
+    }
+
+    #[tokio::test]
+    async fn test_write_secret_reports_auth_failure() {
+        let (addr, _server) = mock_vault("403 Forbidden").await;
+        let client = VaultClient::new(&addr, "bad", "secret").unwrap();
+
+        let err = client.write_secret("apps/db", "p@ss-123").await.unwrap_err();
+        assert!(err.to_string().contains("rejected the token"));
+        assert!(!err.to_string().contains("p@ss-123"));
+    }
+
+    #[tokio::test]
+    async fn test_write_secret_reports_unreachable_vault() {
+        // Bind and drop a listener to get a port nothing is listening on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client = VaultClient::new(&format!("http://127.0.0.1:{}", port), "t", "secret").unwrap();
+
+        let err = client.write_secret("apps/db", "p@ss-123").await.unwrap_err();
+        assert!(err.to_string().contains("Could not reach Vault"));
+    }
+
+    #[test]
+    fn test_validate_path() {
+        assert!(validate_path("apps/db/root").is_ok());
+        assert!(validate_path("").is_err());
+        assert!(validate_path("../sys/policy").is_err());
+        assert!(validate_path("apps//db").is_err());
+        assert!(validate_path("apps/db?version=1").is_err());
+    }
+}
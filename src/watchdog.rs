@@ -0,0 +1,152 @@
+/// Handler panic isolation and the dispatcher restart watchdog.
+///
+/// Each handler runs in its own task, so a panic is caught at the task boundary,
+/// logged with the handler's context (never message text, which may hold a
+/// password) and the bot keeps serving other updates. When panics pile up past
+/// `PANIC_RESTART_THRESHOLD` within `PANIC_WINDOW_SECS`, a restart is requested
+/// and `main` rebuilds the dispatcher.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use teloxide::prelude::ResponseResult;
+use tokio::sync::Notify;
+use tracing::{error, warn};
+
+/// Counts handler panics in a sliding window and signals restarts.
+#[derive(Debug)]
+pub struct PanicWatchdog {
+    /// Panics within `window` that trigger a restart; 0 never restarts.
+    threshold: usize,
+    window: Duration,
+    panics: Mutex<VecDeque<Instant>>,
+    restart_pending: AtomicBool,
+    restart: Notify,
+}
+
+impl PanicWatchdog {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            panics: Mutex::new(VecDeque::new()),
+            restart_pending: AtomicBool::new(false),
+            restart: Notify::new(),
+        }
+    }
+
+    /// Record a panic at `now`; returns whether the threshold has been reached.
+    pub fn record_panic(&self, now: Instant) -> bool {
+        let mut panics = self.panics.lock().unwrap_or_else(|e| e.into_inner());
+        while panics
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= self.window)
+        {
+            panics.pop_front();
+        }
+        panics.push_back(now);
+
+        if self.threshold > 0 && panics.len() >= self.threshold {
+            panics.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Ask the dispatcher loop to restart.
+    pub fn request_restart(&self) {
+        self.restart_pending.store(true, Ordering::SeqCst);
+        self.restart.notify_one();
+    }
+
+    /// Wait until a restart is requested.
+    pub async fn restart_requested(&self) {
+        self.restart.notified().await;
+    }
+
+    /// Whether a restart was requested, clearing the request.
+    pub fn take_restart(&self) -> bool {
+        self.restart_pending.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Run `handler` in its own task, turning a panic into a logged, counted error.
+///
+/// `context` names the handler and chat for the log; it must not contain
+/// message text.
+pub async fn run_guarded<F>(
+    watchdog: &PanicWatchdog,
+    context: &str,
+    handler: F,
+) -> ResponseResult<()>
+where
+    F: Future<Output = ResponseResult<()>> + Send + 'static,
+{
+    match tokio::spawn(handler).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => {
+            error!("Handler panicked ({}); continuing with other updates", context);
+            if watchdog.record_panic(Instant::now()) {
+                warn!("Too many handler panics, requesting a dispatcher restart");
+                watchdog.request_restart();
+            }
+            Ok(())
+        }
+        Err(e) => {
+            error!("Handler task failed ({}): {}", context, e);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn panicking_handler() -> ResponseResult<()> {
+        panic!("simulated handler bug");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_does_not_stop_the_loop() {
+        let watchdog = PanicWatchdog::new(0, Duration::from_secs(60));
+        let mut handled = 0;
+
+        // Stand-in for the dispatcher loop: every update gets its own guarded handler
+        for buggy in [true, false, true, false] {
+            let result = if buggy {
+                run_guarded(&watchdog, "test", panicking_handler()).await
+            } else {
+                run_guarded(&watchdog, "test", async { Ok(()) }).await
+            };
+            assert!(result.is_ok());
+            handled += 1;
+        }
+        assert_eq!(handled, 4);
+        assert!(!watchdog.take_restart());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_panics_request_restart() {
+        let watchdog = PanicWatchdog::new(2, Duration::from_secs(60));
+        run_guarded(&watchdog, "test", panicking_handler()).await.unwrap();
+        assert!(!watchdog.take_restart());
+        run_guarded(&watchdog, "test", panicking_handler()).await.unwrap();
+        assert!(watchdog.take_restart());
+        // The request is consumed once taken
+        assert!(!watchdog.take_restart());
+    }
+
+    #[test]
+    fn test_panics_outside_window_are_forgotten() {
+        let watchdog = PanicWatchdog::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(!watchdog.record_panic(start));
+        assert!(!watchdog.record_panic(start + Duration::from_secs(10)));
+        // The first panic has aged out, so this is only the second in the window
+        assert!(!watchdog.record_panic(start + Duration::from_secs(65)));
+        assert!(watchdog.record_panic(start + Duration::from_secs(68)));
+    }
+}